@@ -0,0 +1,113 @@
+//! Benchmarks of reading the documents from `tests/documents`.
+//!
+//! Run them with `cargo bench`; arguments which are not options select benchmarks by
+//! a part of their name, e.g. `cargo bench -- sample_1`. Every benchmark repeats parsing of
+//! a document for about a second and reports the time of one iteration and the throughput.
+
+#![forbid(unsafe_code)]
+
+extern crate xml;
+
+use std::env;
+use std::fs;
+use std::hint::black_box;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use xml::reader::{BorrowedReader, EventReader, ParserConfig, XmlEvent};
+
+const TARGET_DURATION: Duration = Duration::from_secs(1);
+const MIN_ITERATIONS: u32 = 10;
+
+/// Reads all events of a document, returning their number.
+fn read_events(document: &[u8], config: &ParserConfig) -> usize {
+    let mut reader = EventReader::new_with_config(document, config.clone());
+    let mut count = 0;
+    loop {
+        count += 1;
+        match reader.next() {
+            Ok(XmlEvent::EndDocument) | Err(_) => return count,
+            Ok(event) => { black_box(event); }
+        }
+    }
+}
+
+/// Reads all events of a document with `BorrowedReader`, returning their number.
+fn read_borrowed_events(document: &str, config: &ParserConfig) -> usize {
+    let mut reader = BorrowedReader::new_with_config(document, config.clone());
+    let mut count = 0;
+    loop {
+        count += 1;
+        match reader.next() {
+            Ok(xml::reader::borrowed::XmlEvent::EndDocument) | Err(_) => return count,
+            Ok(event) => { black_box(event); }
+        }
+    }
+}
+
+/// A large document with all kinds of content, to measure throughput without the setup
+/// costs of parsing small documents.
+fn generated_document() -> String {
+    let mut document = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<catalog xmlns:x=\"urn:x\">\n");
+    for i in 0..20_000 {
+        document.push_str(&format!(
+            "  <item id=\"{}\" x:kind='plain text'>\n    <title>Item number {} &amp; some longer text</title>\n    \
+             <!-- a comment about the item -->\n    <data><![CDATA[<raw> data]]></data>\n    <?pi some data?>\n  </item>\n",
+            i, i));
+    }
+    document.push_str("</catalog>\n");
+    document
+}
+
+fn bench<F: FnMut() -> usize>(name: &str, filters: &[String], bytes: usize, mut f: F) {
+    if !filters.is_empty() && !filters.iter().any(|filter| name.contains(&filter[..])) {
+        return;
+    }
+
+    let events = f();  // warm up
+    let start = Instant::now();
+    let mut iterations = 0;
+    while iterations < MIN_ITERATIONS || start.elapsed() < TARGET_DURATION {
+        black_box(f());
+        iterations += 1;
+    }
+    let elapsed = start.elapsed();
+
+    let per_iteration = elapsed / iterations;
+    let throughput = bytes as f64 * iterations as f64 / elapsed.as_secs_f64() / (1024.0 * 1024.0);
+    println!("{:<40} {:>12} ns/iter {:>10.2} MiB/s {:>8} events",
+             name, per_iteration.as_nanos(), throughput, events);
+}
+
+fn main() {
+    // `cargo bench` passes `--bench`, and other options may be given as well
+    let filters: Vec<String> = env::args().skip(1).filter(|a| !a.starts_with('-')).collect();
+
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("documents");
+    let mut paths: Vec<_> = fs::read_dir(&directory).unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().map_or(false, |e| e == "xml"))
+        .collect();
+    paths.sort();
+
+    let mut documents: Vec<(String, Vec<u8>)> = paths.iter()
+        .map(|path| (path.file_stem().unwrap().to_string_lossy().into_owned(), fs::read(path).unwrap()))
+        .collect();
+    documents.push(("generated".into(), generated_document().into_bytes()));
+
+    let configs = [
+        ("default", ParserConfig::new()),
+        ("coalesced", ParserConfig::new().trim_whitespace(true).cdata_to_characters(true)),
+    ];
+
+    for &(ref name, ref document) in &documents {
+        for &(config_name, ref config) in &configs {
+            bench(&format!("event_reader/{}/{}", config_name, name), &filters, document.len(),
+                  || read_events(document, config));
+        }
+        if let Ok(text) = std::str::from_utf8(document) {
+            bench(&format!("borrowed_reader/default/{}", name), &filters, document.len(),
+                  || read_borrowed_events(text, &configs[0].1));
+        }
+    }
+}
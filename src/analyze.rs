@@ -58,6 +58,8 @@ fn main() {
                         "XML document version {}, encoded in {}, {}standalone",
                         version, encoding, if standalone.unwrap_or(false) { "" } else { "not " }
                     ),
+                XmlEvent::Doctype(doctype) =>
+                    println!("Document type {}, {} declarations", doctype.name, doctype.declarations.len()),
                 XmlEvent::EndDocument => println!("Document finished"),
                 XmlEvent::ProcessingInstruction { .. } => processing_instructions += 1,
                 XmlEvent::Whitespace(_) => {}  // can't happen due to configuration
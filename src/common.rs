@@ -1,6 +1,7 @@
 //! Contains common types and functions used throughout the library.
 
 use std::fmt;
+use std::str;
 
 /// Represents a position inside some textual document.
 #[derive(Copy, Clone, PartialEq, Eq)]
@@ -93,6 +94,84 @@ impl fmt::Debug for XmlVersion {
     }
 }
 
+/// Character encoding of an XML document.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Encoding {
+    /// UTF-8, the default encoding of XML documents.
+    Utf8,
+
+    /// UTF-16, little endian.
+    Utf16Le,
+
+    /// UTF-16, big endian.
+    Utf16Be,
+
+    /// ISO-8859-1, also known as Latin-1.
+    Latin1,
+
+    /// Windows-1252, a superset of ISO-8859-1 which uses the range 0x80-0x9F for printable
+    /// characters.
+    Windows1252,
+
+    /// US-ASCII, which only contains characters in the range 0x00-0x7F.
+    Ascii,
+}
+
+impl Encoding {
+    /// Returns `true` if all ASCII characters are encoded as single bytes with the same values
+    /// as in ASCII.
+    ///
+    /// A document in such an encoding can be read as ASCII up to its encoding declaration.
+    #[inline]
+    pub fn is_ascii_compatible(self) -> bool {
+        match self {
+            Encoding::Utf16Le | Encoding::Utf16Be => false,
+            Encoding::Utf8 | Encoding::Latin1 | Encoding::Windows1252 | Encoding::Ascii => true,
+        }
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf16Le => "UTF-16LE",
+            Encoding::Utf16Be => "UTF-16BE",
+            Encoding::Latin1 => "ISO-8859-1",
+            Encoding::Windows1252 => "windows-1252",
+            Encoding::Ascii => "US-ASCII",
+        })
+    }
+}
+
+impl str::FromStr for Encoding {
+    type Err = ();
+
+    /// Parses an encoding name as it is written in XML declarations. Names are matched
+    /// case-insensitively, and common aliases are recognized.
+    ///
+    /// `UTF-16` without an explicit byte order means big endian.
+    fn from_str(s: &str) -> Result<Encoding, ()> {
+        match &s.to_ascii_lowercase()[..] {
+            "utf-8" | "utf8" => Ok(Encoding::Utf8),
+            "utf-16" | "utf16" | "utf-16be" => Ok(Encoding::Utf16Be),
+            "utf-16le" => Ok(Encoding::Utf16Le),
+            "iso-8859-1" | "iso8859-1" | "iso_8859-1" | "latin1" | "l1" | "cp819" => Ok(Encoding::Latin1),
+            "windows-1252" | "cp1252" | "x-cp1252" => Ok(Encoding::Windows1252),
+            "us-ascii" | "ascii" | "iso646-us" => Ok(Encoding::Ascii),
+            _ => Err(())
+        }
+    }
+}
+
+/// Checks whether the given character is a character (`Char`)
+/// as is defined by XML 1.0 specification, [section 2.2][1].
+///
+/// [1]: http://www.w3.org/TR/2008/REC-xml-20081126/#charsets
+pub fn is_xml_char(c: char) -> bool {
+    matches!(c, '\x09' | '\x0a' | '\x0d' | '\u{20}'..='\u{D7FF}' | '\u{E000}'..='\u{FFFD}' | '\u{10000}'..='\u{10FFFF}')
+}
+
 /// Checks whether the given character is a white space character (`S`)
 /// as is defined by XML 1.1 specification, [section 2.3][1].
 ///
@@ -0,0 +1,843 @@
+//! Contains a serde `Deserializer` which reads values from an `EventReader`.
+//!
+//! This module is only available with the `serde` feature.
+//!
+//! Values are mapped onto XML as follows:
+//!
+//! * a struct or a map is read from an element. Its keys are names of attributes and child
+//!   elements of the element; a field named `@name` only matches an attribute and a field named
+//!   `$value` receives text content and child elements which do not match any other field;
+//! * a sequence is read from consecutive sibling elements with the same name, or, for a `$value`
+//!   field, from all consecutive content which does not match other fields. Values of attributes
+//!   are split into sequence items at whitespace;
+//! * an enum is read from an element whose name selects the variant. A struct field which is an
+//!   enum is read from the element named after the field, which wraps either the element of the
+//!   variant or, for unit variants, the name of the variant as text;
+//! * `Option` fields are `None` when there is no matching attribute or element;
+//! * strings, numbers, booleans and unit variants are read from text content of elements and from
+//!   values of attributes. Numbers and booleans may be surrounded with whitespace.
+//!
+//! Prefixes of element and attribute names are ignored. Comments, processing instructions and
+//! whitespace-only text are skipped.
+//!
+//! ```rust
+//! # extern crate xml;
+//! # #[macro_use] extern crate serde_derive;
+//! use xml::EventReader;
+//!
+//! #[derive(Deserialize, PartialEq, Debug)]
+//! struct Catalog {
+//!     #[serde(rename = "@name")]
+//!     name: String,
+//!     book: Vec<Book>,
+//! }
+//!
+//! #[derive(Deserialize, PartialEq, Debug)]
+//! struct Book {
+//!     year: u32,
+//!     title: String,
+//!     note: Option<String>,
+//! }
+//!
+//! # fn main() {
+//! let catalog: Catalog = xml::de::from_reader(EventReader::from_str(r#"
+//!     <catalog name="classics">
+//!       <book year="1965"><title>Dune</title></book>
+//!       <book year="1951"><title>Foundation</title><note>First of a series</note></book>
+//!     </catalog>
+//! "#)).unwrap();
+//!
+//! assert_eq!(catalog.name, "classics");
+//! assert_eq!(catalog.book[1], Book { year: 1951, title: "Foundation".into(), note: Some("First of a series".into()) });
+//!
+//! let error = xml::de::from_reader::<_, Book>(EventReader::from_str("<book year='x'/>")).unwrap_err();
+//! assert_eq!(error.to_string(), "1:1 Value 'x' is not a valid u32");
+//! # }
+//! ```
+
+use std::error;
+use std::fmt;
+use std::io::Read;
+use std::str::FromStr;
+use std::vec;
+
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, Visitor};
+
+use attribute::OwnedAttribute;
+use common::{Position, TextPosition};
+use reader::{self, EventReader, XmlEvent};
+
+/// An error of deserialization.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Error {
+    pos: Option<TextPosition>,
+    kind: ErrorKind,
+}
+
+/// The kind of a deserialization error.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ErrorKind {
+    /// The document is not well-formed.
+    Reader(reader::Error),
+
+    /// The document does not match the deserialized type.
+    Data(String),
+}
+
+impl Error {
+    fn new<M: Into<String>>(pos: TextPosition, msg: M) -> Error {
+        Error { pos: Some(pos), kind: ErrorKind::Data(msg.into()) }
+    }
+
+    /// Returns the kind of this error.
+    #[inline]
+    pub fn kind(&self) -> &ErrorKind { &self.kind }
+
+    /// Returns a message describing this error.
+    pub fn msg(&self) -> &str {
+        match self.kind {
+            ErrorKind::Reader(ref e) => e.msg(),
+            ErrorKind::Data(ref msg) => msg,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.position(), self.msg())
+    }
+}
+
+impl Position for Error {
+    /// Returns the position of the event which caused the error.
+    #[inline]
+    fn position(&self) -> TextPosition {
+        self.pos.unwrap_or_else(TextPosition::new)
+    }
+}
+
+impl error::Error for Error {
+    #[inline]
+    fn description(&self) -> &str { self.msg() }
+}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Error {
+        Error { pos: None, kind: ErrorKind::Data(msg.to_string()) }
+    }
+}
+
+impl From<reader::Error> for Error {
+    #[inline]
+    fn from(e: reader::Error) -> Error {
+        Error { pos: Some(e.position()), kind: ErrorKind::Reader(e) }
+    }
+}
+
+/// A result type of deserialization.
+pub type Result<T> = ::std::result::Result<T, Error>;
+
+/// Attaches the given position to an error which does not have one yet.
+fn locate<T>(pos: TextPosition, result: Result<T>) -> Result<T> {
+    result.map_err(|mut e| {
+        if e.pos.is_none() {
+            e.pos = Some(pos);
+        }
+        e
+    })
+}
+
+/// Deserializes a value from the document read by the given reader.
+#[inline]
+pub fn from_reader<R: Read, T: DeserializeOwned>(reader: EventReader<R>) -> Result<T> {
+    T::deserialize(&mut Deserializer::new(reader))
+}
+
+/// Deserializes a value from a document in a string.
+#[inline]
+pub fn from_str<T: DeserializeOwned>(s: &str) -> Result<T> {
+    from_reader(EventReader::from_str(s))
+}
+
+/// A deserializer which reads values from events of an `EventReader`.
+///
+/// The root element of the document is deserialized as a value of the requested type.
+pub struct Deserializer<R: Read> {
+    reader: EventReader<R>,
+    peeked: Option<XmlEvent>,
+    peeked_pos: TextPosition,
+    /// Position of the last consumed event.
+    pos: TextPosition,
+}
+
+impl<R: Read> Deserializer<R> {
+    /// Creates a new deserializer reading from the given reader.
+    #[inline]
+    pub fn new(reader: EventReader<R>) -> Deserializer<R> {
+        Deserializer {
+            reader,
+            peeked: None,
+            peeked_pos: TextPosition::new(),
+            pos: TextPosition::new(),
+        }
+    }
+
+    /// Returns the underlying reader.
+    #[inline]
+    pub fn into_inner(self) -> EventReader<R> {
+        self.reader
+    }
+
+    fn peek(&mut self) -> Result<&XmlEvent> {
+        while self.peeked.is_none() {
+            match self.reader.next()? {
+                XmlEvent::StartDocument { .. } | XmlEvent::Doctype(_) | XmlEvent::ProcessingInstruction { .. } |
+                XmlEvent::Comment(_) | XmlEvent::Whitespace(_) => {}
+                event => {
+                    self.peeked = Some(event);
+                    self.peeked_pos = self.reader.position();
+                }
+            }
+        }
+        Ok(self.peeked.as_ref().unwrap())
+    }
+
+    fn next(&mut self) -> Result<XmlEvent> {
+        self.peek()?;
+        self.pos = self.peeked_pos;
+        Ok(self.peeked.take().unwrap())
+    }
+
+    /// Returns an error about the peeked event.
+    fn unexpected(&self) -> Error {
+        let msg = match self.peeked {
+            Some(XmlEvent::StartElement { ref name, .. }) => format!("Unexpected element {}", name),
+            Some(XmlEvent::EndElement { ref name }) => format!("Unexpected end of element {}", name),
+            Some(XmlEvent::EndDocument) => "Unexpected end of document".into(),
+            _ => "Unexpected text".into(),
+        };
+        Error::new(self.peeked_pos, msg)
+    }
+
+    fn start_element(&mut self) -> Result<Vec<OwnedAttribute>> {
+        match *self.peek()? {
+            XmlEvent::StartElement { .. } => match self.next()? {
+                XmlEvent::StartElement { attributes, .. } => Ok(attributes),
+                _ => unreachable!(),
+            },
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    fn end_element(&mut self) -> Result<()> {
+        match *self.peek()? {
+            XmlEvent::EndElement { .. } => self.next().map(|_| ()),
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    /// Reads consecutive text and CDATA events.
+    fn text(&mut self) -> Result<String> {
+        let mut text = String::new();
+        loop {
+            match *self.peek()? {
+                XmlEvent::Characters(_) | XmlEvent::CData(_) => {}
+                _ => return Ok(text),
+            }
+            match self.next()? {
+                XmlEvent::Characters(data) | XmlEvent::CData(data) => text.push_str(&data),
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    /// Reads text content of an element or text at the current position, and returns it
+    /// together with its position.
+    fn read_text(&mut self) -> Result<(String, TextPosition)> {
+        let pos = self.peeked_pos;
+        match *self.peek()? {
+            XmlEvent::StartElement { .. } => {
+                self.start_element()?;
+                let pos = match *self.peek()? {
+                    XmlEvent::EndElement { .. } => pos,
+                    _ => self.peeked_pos,
+                };
+                let text = self.text()?;
+                self.end_element()?;
+                Ok((text, pos))
+            }
+            XmlEvent::Characters(_) | XmlEvent::CData(_) => self.text().map(|text| (text, pos)),
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    fn parse<T: FromStr>(&mut self, what: &str) -> Result<T> {
+        let (text, pos) = self.read_text()?;
+        parse(&text, what).map_err(|msg| Error::new(pos, msg))
+    }
+
+    fn parse_char(&mut self) -> Result<char> {
+        let (text, pos) = self.read_text()?;
+        parse_char(&text).map_err(|msg| Error::new(pos, msg))
+    }
+
+    fn locate<T>(&self, result: Result<T>) -> Result<T> {
+        locate(self.pos, result)
+    }
+
+    /// Skips the element or text at the current position.
+    fn skip(&mut self) -> Result<()> {
+        match *self.peek()? {
+            XmlEvent::StartElement { .. } => {
+                let mut depth = 0;
+                loop {
+                    match self.next()? {
+                        XmlEvent::StartElement { .. } => depth += 1,
+                        XmlEvent::EndElement { .. } => {
+                            depth -= 1;
+                            if depth == 0 {
+                                return Ok(());
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            XmlEvent::Characters(_) | XmlEvent::CData(_) => self.text().map(|_| ()),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn parse<T: FromStr>(text: &str, what: &str) -> ::std::result::Result<T, String> {
+    text.trim().parse().map_err(|_| format!("Value '{}' is not a valid {}", text, what))
+}
+
+fn parse_bool(text: &str) -> ::std::result::Result<bool, String> {
+    match text.trim() {
+        "true" | "1" => Ok(true),
+        "false" | "0" => Ok(false),
+        _ => Err(format!("Value '{}' is not a valid bool", text)),
+    }
+}
+
+fn parse_char(text: &str) -> ::std::result::Result<char, String> {
+    let mut chars = text.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(c),
+        _ => Err(format!("Value '{}' is not a valid char", text)),
+    }
+}
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident($what:expr)),*) => {$(
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+            let value = self.parse($what)?;
+            self.locate(visitor.$visit(value))
+        }
+    )*}
+}
+
+macro_rules! deserialize_primitives {
+    () => {
+        deserialize_parsed! {
+            deserialize_i8 => visit_i8("i8"),
+            deserialize_i16 => visit_i16("i16"),
+            deserialize_i32 => visit_i32("i32"),
+            deserialize_i64 => visit_i64("i64"),
+            deserialize_u8 => visit_u8("u8"),
+            deserialize_u16 => visit_u16("u16"),
+            deserialize_u32 => visit_u32("u32"),
+            deserialize_u64 => visit_u64("u64"),
+            deserialize_f32 => visit_f32("f32"),
+            deserialize_f64 => visit_f64("f64")
+        }
+
+        fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+            let value = self.parse_bool()?;
+            self.locate(visitor.visit_bool(value))
+        }
+
+        fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+            let value = self.parse_char()?;
+            self.locate(visitor.visit_char(value))
+        }
+    }
+}
+
+impl<R: Read> Deserializer<R> {
+    fn parse_bool(&mut self) -> Result<bool> {
+        let (text, pos) = self.read_text()?;
+        parse_bool(&text).map_err(|msg| Error::new(pos, msg))
+    }
+}
+
+impl<'de, 'a, R: Read> de::Deserializer<'de> for &'a mut Deserializer<R> {
+    type Error = Error;
+
+    deserialize_primitives!();
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let has_attributes = match *self.peek()? {
+            XmlEvent::StartElement { ref attributes, .. } => !attributes.is_empty(),
+            XmlEvent::Characters(_) | XmlEvent::CData(_) => return self.deserialize_string(visitor),
+            _ => return Err(self.unexpected()),
+        };
+        let attributes = self.start_element()?;
+        let has_children = match *self.peek()? {
+            XmlEvent::StartElement { .. } => true,
+            _ => false,
+        };
+        if has_attributes || has_children {
+            let result = visitor.visit_map(ElementMap::new(self, attributes, &[]));
+            self.locate(result)
+        } else {
+            let text = self.text()?;
+            self.end_element()?;
+            self.locate(visitor.visit_string(text))
+        }
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let (text, _) = self.read_text()?;
+        self.locate(visitor.visit_string(text))
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_byte_buf(visitor)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let (text, _) = self.read_text()?;
+        self.locate(visitor.visit_byte_buf(text.into_bytes()))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match *self.peek()? {
+            XmlEvent::EndElement { .. } | XmlEvent::EndDocument => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.skip()?;
+        self.locate(visitor.visit_unit())
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let name = match *self.peek()? {
+            XmlEvent::StartElement { ref name, .. } => name.local_name.clone(),
+            _ => return Err(self.unexpected()),
+        };
+        let result = visitor.visit_seq(Elements { de: self, kind: Sequence::Named(name), item: true });
+        self.locate(result)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(self, _name: &'static str, _len: usize,
+                                                 visitor: V) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_struct("", &[], visitor)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(self, _name: &'static str, fields: &'static [&'static str],
+                                           visitor: V) -> Result<V::Value> {
+        let attributes = self.start_element()?;
+        let result = visitor.visit_map(ElementMap::new(self, attributes, fields));
+        self.locate(result)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(self, _name: &'static str, _variants: &'static [&'static str],
+                                         visitor: V) -> Result<V::Value> {
+        match *self.peek()? {
+            XmlEvent::StartElement { .. } => {
+                let result = visitor.visit_enum(ElementEnum { de: self });
+                self.locate(result)
+            }
+            XmlEvent::Characters(_) | XmlEvent::CData(_) => {
+                let pos = self.peeked_pos;
+                let text = self.text()?;
+                locate(pos, visitor.visit_enum(text.into_deserializer()))
+            }
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_unit(visitor)
+    }
+}
+
+/// A value of a struct or map key which is read next.
+enum Value {
+    Attribute(String),
+    Element(String),
+    Content,
+}
+
+/// Access to attributes and content of an element as map entries.
+struct ElementMap<'a, R: Read + 'a> {
+    de: &'a mut Deserializer<R>,
+    attributes: vec::IntoIter<OwnedAttribute>,
+    /// Position of the element start.
+    pos: TextPosition,
+    fields: &'static [&'static str],
+    value: Option<Value>,
+}
+
+impl<'a, R: Read> ElementMap<'a, R> {
+    fn new(de: &'a mut Deserializer<R>, attributes: Vec<OwnedAttribute>,
+           fields: &'static [&'static str]) -> ElementMap<'a, R> {
+        ElementMap { pos: de.pos, de, attributes: attributes.into_iter(), fields, value: None }
+    }
+
+}
+
+fn has_field(fields: &[&str], name: &str) -> bool {
+    fields.contains(&name)
+}
+
+impl<'de, 'a, R: Read> de::MapAccess<'de> for ElementMap<'a, R> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        if let Some(attribute) = self.attributes.next() {
+            let prefixed = format!("@{}", attribute.name.local_name);
+            let key = if has_field(self.fields, &prefixed) { prefixed } else { attribute.name.local_name };
+            self.value = Some(Value::Attribute(attribute.value));
+            return locate(self.pos, seed.deserialize(key.into_deserializer())).map(Some);
+        }
+
+        let fields = self.fields;
+        let (key, value) = match *self.de.peek()? {
+            XmlEvent::StartElement { ref name, .. } =>
+                if has_field(fields, &name.local_name) || !has_field(fields, "$value") {
+                    (name.local_name.clone(), Value::Element(name.local_name.clone()))
+                } else {
+                    ("$value".to_string(), Value::Content)
+                },
+            XmlEvent::Characters(_) | XmlEvent::CData(_) => ("$value".to_string(), Value::Content),
+            XmlEvent::EndElement { .. } => {
+                self.de.next()?;
+                return Ok(None);
+            }
+            _ => return Err(self.de.unexpected()),
+        };
+        self.value = Some(value);
+        locate(self.de.peeked_pos, seed.deserialize(key.into_deserializer())).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        match self.value.take() {
+            Some(Value::Attribute(value)) =>
+                locate(self.pos, seed.deserialize(AttributeDeserializer { value })),
+            Some(Value::Element(name)) =>
+                seed.deserialize(FieldDeserializer { de: &mut *self.de, kind: Sequence::Named(name) }),
+            Some(Value::Content) =>
+                seed.deserialize(FieldDeserializer { de: &mut *self.de, kind: Sequence::Content(self.fields) }),
+            None => Err(de::Error::custom("A map value is requested before its key")),
+        }
+    }
+}
+
+/// Consecutive nodes which form a sequence.
+#[derive(Clone)]
+enum Sequence {
+    /// Elements with the given name.
+    Named(String),
+    /// Text and elements whose names are not among the given struct fields.
+    Content(&'static [&'static str]),
+}
+
+impl Sequence {
+    fn includes(&self, event: &XmlEvent) -> bool {
+        match (self, event) {
+            (&Sequence::Named(ref expected), &XmlEvent::StartElement { ref name, .. }) => name.local_name == *expected,
+            (&Sequence::Content(fields), &XmlEvent::StartElement { ref name, .. }) =>
+                !has_field(fields, &name.local_name),
+            (&Sequence::Content(_), &XmlEvent::Characters(_)) | (&Sequence::Content(_), &XmlEvent::CData(_)) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Access to consecutive nodes as items of a sequence.
+struct Elements<'a, R: Read + 'a> {
+    de: &'a mut Deserializer<R>,
+    kind: Sequence,
+    /// Whether items are values of a struct field, which are read as `FieldDeserializer` does.
+    item: bool,
+}
+
+impl<'de, 'a, R: Read> de::SeqAccess<'de> for Elements<'a, R> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        if !self.kind.includes(self.de.peek()?) {
+            return Ok(None);
+        }
+        if self.item {
+            seed.deserialize(FieldDeserializer { de: &mut *self.de, kind: self.kind.clone() }.single()).map(Some)
+        } else {
+            seed.deserialize(&mut *self.de).map(Some)
+        }
+    }
+}
+
+/// A deserializer of a struct field value which is read from elements or content.
+///
+/// Sequences are read from all consecutive matching nodes, and enums of named fields are read
+/// from the content of the field element.
+struct FieldDeserializer<'a, R: Read + 'a> {
+    de: &'a mut Deserializer<R>,
+    kind: Sequence,
+}
+
+impl<'a, R: Read> FieldDeserializer<'a, R> {
+    /// Returns a deserializer of a single sequence item.
+    fn single(self) -> SingleField<'a, R> {
+        SingleField(self)
+    }
+}
+
+/// A deserializer of a single element which is a struct field value, an item of a sequence or
+/// the content of an enum variant.
+///
+/// Sequences are read from the content of the element, and enums from the element wrapped by it
+/// or from its text.
+struct SingleField<'a, R: Read + 'a>(FieldDeserializer<'a, R>);
+
+macro_rules! forward_to_element {
+    ($($method:ident($($arg:ident: $ty:ty),*)),*) => {$(
+        #[inline]
+        fn $method<V: Visitor<'de>>(self, $($arg: $ty,)* visitor: V) -> Result<V::Value> {
+            de::Deserializer::$method(self.deserializer(), $($arg,)* visitor)
+        }
+    )*}
+}
+
+macro_rules! field_deserializer {
+    ($($ty:ident),*) => {$(
+        impl<'de, 'a, R: Read> de::Deserializer<'de> for $ty<'a, R> {
+            type Error = Error;
+
+            forward_to_element! {
+                deserialize_any(), deserialize_bool(), deserialize_char(), deserialize_str(), deserialize_string(),
+                deserialize_i8(), deserialize_i16(), deserialize_i32(), deserialize_i64(),
+                deserialize_u8(), deserialize_u16(), deserialize_u32(), deserialize_u64(),
+                deserialize_f32(), deserialize_f64(), deserialize_bytes(), deserialize_byte_buf(),
+                deserialize_unit(), deserialize_unit_struct(name: &'static str),
+                deserialize_map(), deserialize_struct(name: &'static str, fields: &'static [&'static str]),
+                deserialize_identifier(), deserialize_ignored_any()
+            }
+
+            fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+                visitor.visit_some(self)
+            }
+
+            fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value> {
+                visitor.visit_newtype_struct(self)
+            }
+
+            fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+                self.seq(visitor)
+            }
+
+            fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+                self.seq(visitor)
+            }
+
+            fn deserialize_tuple_struct<V: Visitor<'de>>(self, _name: &'static str, _len: usize,
+                                                         visitor: V) -> Result<V::Value> {
+                self.seq(visitor)
+            }
+
+            fn deserialize_enum<V: Visitor<'de>>(self, name: &'static str, variants: &'static [&'static str],
+                                                 visitor: V) -> Result<V::Value> {
+                self.enumeration(name, variants, visitor)
+            }
+        }
+    )*}
+}
+
+field_deserializer!(FieldDeserializer, SingleField);
+
+impl<'a, R: Read> FieldDeserializer<'a, R> {
+    fn deserializer(self) -> &'a mut Deserializer<R> {
+        self.de
+    }
+
+    fn seq<'de, V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let result = visitor.visit_seq(Elements { de: &mut *self.de, kind: self.kind, item: true });
+        self.de.locate(result)
+    }
+
+    fn enumeration<'de, V: Visitor<'de>>(self, name: &'static str, variants: &'static [&'static str],
+                                         visitor: V) -> Result<V::Value> {
+        self.single().enumeration(name, variants, visitor)
+    }
+}
+
+impl<'a, R: Read> SingleField<'a, R> {
+    fn deserializer(self) -> &'a mut Deserializer<R> {
+        self.0.de
+    }
+
+    /// Reads a sequence from the content of the element.
+    fn seq<'de, V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let de = self.0.de;
+        de.start_element()?;
+        let value = visitor.visit_seq(Elements { de: &mut *de, kind: Sequence::Content(&[]), item: false });
+        let value = de.locate(value)?;
+        de.end_element()?;
+        Ok(value)
+    }
+
+    fn enumeration<'de, V: Visitor<'de>>(self, name: &'static str, variants: &'static [&'static str],
+                                         visitor: V) -> Result<V::Value> {
+        let de = self.0.de;
+        if let Sequence::Content(_) = self.0.kind {
+            return de::Deserializer::deserialize_enum(de, name, variants, visitor);
+        }
+        de.start_element()?;
+        let value = match *de.peek()? {
+            XmlEvent::StartElement { .. } | XmlEvent::Characters(_) | XmlEvent::CData(_) =>
+                de::Deserializer::deserialize_enum(&mut *de, name, variants, visitor)?,
+            _ => return Err(de.unexpected()),
+        };
+        de.end_element()?;
+        Ok(value)
+    }
+}
+
+/// Access to an element as an enum variant named after the element.
+struct ElementEnum<'a, R: Read + 'a> {
+    de: &'a mut Deserializer<R>,
+}
+
+impl<'de, 'a, R: Read> de::EnumAccess<'de> for ElementEnum<'a, R> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self)> {
+        let name = match *self.de.peek()? {
+            XmlEvent::StartElement { ref name, .. } => name.local_name.clone(),
+            _ => return Err(self.de.unexpected()),
+        };
+        let variant = locate(self.de.peeked_pos, seed.deserialize(name.into_deserializer()))?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de, 'a, R: Read> de::VariantAccess<'de> for ElementEnum<'a, R> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        self.de.skip()
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        seed.deserialize(FieldDeserializer { de: self.de, kind: Sequence::Named(String::new()) }.single())
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        FieldDeserializer { de: self.de, kind: Sequence::Named(String::new()) }.single().seq(visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value> {
+        de::Deserializer::deserialize_struct(self.de, "", fields, visitor)
+    }
+}
+
+/// A deserializer of an attribute value.
+struct AttributeDeserializer {
+    value: String,
+}
+
+impl AttributeDeserializer {
+    fn parse<T: FromStr>(&self, what: &str) -> Result<T> {
+        parse(&self.value, what).map_err(de::Error::custom)
+    }
+
+    fn parse_bool(&self) -> Result<bool> {
+        parse_bool(&self.value).map_err(de::Error::custom)
+    }
+
+    fn parse_char(&self) -> Result<char> {
+        parse_char(&self.value).map_err(de::Error::custom)
+    }
+
+    #[inline]
+    fn locate<T>(&self, result: Result<T>) -> Result<T> {
+        result
+    }
+}
+
+impl<'de> de::Deserializer<'de> for AttributeDeserializer {
+    type Error = Error;
+
+    deserialize_primitives!();
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_string(self.value)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let words: Vec<_> = self.value.split_whitespace().map(|w| AttributeDeserializer { value: w.into() }).collect();
+        visitor.visit_seq(de::value::SeqDeserializer::new(words.into_iter()))
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(self, _name: &'static str, _variants: &'static [&'static str],
+                                         visitor: V) -> Result<V::Value> {
+        visitor.visit_enum(self.value.into_deserializer())
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        str string bytes byte_buf unit_struct tuple tuple_struct map struct identifier
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for AttributeDeserializer {
+    type Deserializer = Self;
+
+    #[inline]
+    fn into_deserializer(self) -> Self { self }
+}
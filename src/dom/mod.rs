@@ -0,0 +1,864 @@
+//! Contains a DOM-like tree representation of XML documents.
+//!
+//! A `Document` owns all of its nodes; nodes are referred to by `NodeId` handles which are only
+//! meaningful for the document they were obtained from. A document can be built from any
+//! `EventReader` and serialized back through an `EventWriter`:
+//!
+//! ```rust
+//! use xml::{EventReader, EventWriter, ParserConfig};
+//! use xml::dom::{Document, Node};
+//!
+//! let source = r#"<catalog xmlns="urn:catalog"><book id="1">Dune</book><!-- more --></catalog>"#;
+//! let config = ParserConfig::new().ignore_comments(false);
+//! let document = Document::from_reader(EventReader::new_with_config(source.as_bytes(), config)).unwrap();
+//!
+//! let catalog = document.root_element().unwrap();
+//! assert_eq!(document.element(catalog).unwrap().name.local_name, "catalog");
+//!
+//! let children = document.children(catalog);
+//! assert_eq!(children.len(), 2);
+//! assert_eq!(*document.node(children[1]), Node::Comment(" more ".into()));
+//!
+//! let mut output = Vec::new();
+//! document.write_to(&mut EventWriter::new(&mut output)).unwrap();
+//! assert_eq!(
+//!     String::from_utf8(output).unwrap(),
+//!     r#"<?xml version="1.0" encoding="UTF-8"?><catalog xmlns="urn:catalog"><book id="1">Dune</book><!-- more --></catalog>"#
+//! );
+//! ```
+
+use std::borrow::Cow;
+use std::io::{Read, Write};
+use std::slice;
+
+use common::XmlVersion;
+use name::{Name, OwnedName};
+use attribute::OwnedAttribute;
+use namespace::{Namespace, NamespaceStack, NS_NO_PREFIX, NS_EMPTY_URI};
+use dtd::Doctype;
+use reader::{self, EventReader};
+use writer::{self, EventWriter};
+
+/// A handle of a node in a `Document`.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct NodeId(usize);
+
+/// An XML element.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Element {
+    /// Qualified name of the element.
+    pub name: OwnedName,
+
+    /// A list of attributes of the element, excluding namespace declarations.
+    pub attributes: Vec<OwnedAttribute>,
+
+    /// Namespace mappings in scope of the element.
+    ///
+    /// Mappings which are not already in scope of the parent element are written as
+    /// namespace declarations when the element is serialized.
+    pub namespace: Namespace,
+}
+
+impl Element {
+    /// Creates a new element with the given name, no attributes and an empty namespace.
+    #[inline]
+    pub fn new(name: OwnedName) -> Element {
+        Element {
+            name,
+            attributes: Vec::new(),
+            namespace: Namespace::empty(),
+        }
+    }
+
+    /// Returns the value of the attribute with the given name.
+    ///
+    /// Attribute names are matched by their local names and namespace URIs; prefixes
+    /// are ignored.
+    pub fn attribute<'n, N>(&self, name: N) -> Option<&str> where N: Into<Name<'n>> {
+        let name = name.into();
+        self.attributes.iter().find(|a| name_matches(&a.name, name)).map(|a| &a.value[..])
+    }
+}
+
+fn name_matches(name: &OwnedName, pattern: Name) -> bool {
+    name.local_name == pattern.local_name && name.namespace.as_ref().map(|s| &s[..]) == pattern.namespace
+}
+
+/// A node of a document tree.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Node {
+    /// The document node, which is the root of the tree.
+    ///
+    /// Its children are the root element and comments, processing instructions and whitespace
+    /// outside of it.
+    Document,
+
+    /// An element.
+    Element(Element),
+
+    /// Character data, including whitespace.
+    Text(String),
+
+    /// A CDATA section.
+    CData(String),
+
+    /// A comment.
+    Comment(String),
+
+    /// A processing instruction.
+    ProcessingInstruction {
+        /// Processing instruction target.
+        name: String,
+
+        /// Processing instruction content.
+        data: Option<String>,
+    },
+}
+
+impl Node {
+    /// Returns the element if this node is an element.
+    #[inline]
+    pub fn as_element(&self) -> Option<&Element> {
+        match *self {
+            Node::Element(ref e) => Some(e),
+            _ => None
+        }
+    }
+
+    /// Returns the element if this node is an element.
+    #[inline]
+    pub fn as_element_mut(&mut self) -> Option<&mut Element> {
+        match *self {
+            Node::Element(ref mut e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct NodeData {
+    node: Node,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    removed: bool,  // whether the slot is free for reuse
+}
+
+/// An XML document represented as a tree of nodes.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Document {
+    /// XML version of the document.
+    pub version: XmlVersion,
+
+    /// Encoding of the document. It is used when the document is written.
+    pub encoding: String,
+
+    /// Standalone declaration of the document.
+    pub standalone: Option<bool>,
+
+    /// The document type declaration, if the document has one.
+    ///
+    /// It is not written when the document is serialized.
+    pub doctype: Option<Doctype>,
+
+    nodes: Vec<NodeData>,
+    free: Vec<NodeId>,
+}
+
+impl Document {
+    /// Creates a new document which consists only of the document node.
+    pub fn new() -> Document {
+        Document {
+            version: XmlVersion::Version10,
+            encoding: "UTF-8".into(),
+            standalone: None,
+            doctype: None,
+            nodes: vec![NodeData { node: Node::Document, parent: None, children: Vec::new(), removed: false }],
+            free: Vec::new(),
+        }
+    }
+
+    /// Builds a document from all events of the given reader.
+    ///
+    /// Whitespace events become text nodes; the reader configuration determines which of them
+    /// are reported.
+    pub fn from_reader<R: Read>(mut reader: EventReader<R>) -> reader::Result<Document> {
+        let mut document = Document::new();
+        let mut stack = vec![document.root()];
+        loop {
+            let parent = *stack.last().unwrap();
+            let node = match reader.next()? {
+                reader::XmlEvent::StartDocument { version, encoding, standalone } => {
+                    document.version = version;
+                    document.encoding = encoding;
+                    document.standalone = standalone;
+                    continue;
+                }
+                reader::XmlEvent::Doctype(doctype) => {
+                    document.doctype = Some(doctype);
+                    continue;
+                }
+                reader::XmlEvent::EndDocument => return Ok(document),
+                reader::XmlEvent::StartElement { name, attributes, namespace } => {
+                    let id = document.push(parent, Node::Element(Element { name, attributes, namespace }));
+                    stack.push(id);
+                    continue;
+                }
+                reader::XmlEvent::EndElement { .. } => {
+                    stack.pop();
+                    continue;
+                }
+                reader::XmlEvent::ProcessingInstruction { name, data } =>
+                    Node::ProcessingInstruction { name, data },
+                reader::XmlEvent::CData(data) => Node::CData(data),
+                reader::XmlEvent::Comment(data) => Node::Comment(data),
+                reader::XmlEvent::Characters(data) | reader::XmlEvent::Whitespace(data) =>
+                    Node::Text(data),
+            };
+            document.push(parent, node);
+        }
+    }
+
+    /// Writes the document through the given writer.
+    ///
+    /// The XML declaration is written with the version, encoding and standalone declaration
+    /// of this document. Namespace declarations are written for mappings of each element
+    /// which are not in scope of its parent.
+    pub fn write_to<W: Write>(&self, writer: &mut EventWriter<W>) -> writer::Result<()> {
+        writer.write(writer::XmlEvent::StartDocument {
+            version: self.version,
+            encoding: Some(&self.encoding),
+            standalone: self.standalone,
+        })?;
+
+        // elements are written without recursion, so that deep trees do not exhaust the stack
+        enum Step { Enter(NodeId), Leave }
+        let mut steps: Vec<_> = self.children(self.root()).iter().rev().map(|&id| Step::Enter(id)).collect();
+        while let Some(step) = steps.pop() {
+            let id = match step {
+                Step::Enter(id) => id,
+                Step::Leave => {
+                    writer.write(writer::XmlEvent::end_element())?;
+                    continue;
+                }
+            };
+            match *self.node(id) {
+                Node::Document => {}
+                Node::Element(ref element) => {
+                    let attributes: Vec<_> = element.attributes.iter().map(|a| a.borrow()).collect();
+                    writer.write(writer::XmlEvent::StartElement {
+                        name: element.name.borrow(),
+                        attributes: Cow::Owned(attributes),
+                        namespace: Cow::Borrowed(&element.namespace),
+                    })?;
+                    steps.push(Step::Leave);
+                    steps.extend(self.children(id).iter().rev().map(|&id| Step::Enter(id)));
+                }
+                Node::Text(ref data) => writer.write(writer::XmlEvent::Characters(data))?,
+                Node::CData(ref data) => writer.write(writer::XmlEvent::CData(data))?,
+                Node::Comment(ref data) => writer.write(writer::XmlEvent::Comment(data))?,
+                Node::ProcessingInstruction { ref name, ref data } =>
+                    writer.write(writer::XmlEvent::ProcessingInstruction {
+                        name,
+                        data: data.as_ref().map(|s| &s[..]),
+                    })?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the document node.
+    #[inline]
+    pub fn root(&self) -> NodeId { NodeId(0) }
+
+    /// Returns the root element of the document, if there is one.
+    pub fn root_element(&self) -> Option<NodeId> {
+        self.children(self.root()).iter().cloned().find(|&id| self.element(id).is_some())
+    }
+
+    /// Returns the node with the given id.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the id does not belong to this document.
+    #[inline]
+    pub fn node(&self, id: NodeId) -> &Node { &self.nodes[id.0].node }
+
+    /// Returns the node with the given id.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the id does not belong to this document.
+    #[inline]
+    pub fn node_mut(&mut self, id: NodeId) -> &mut Node { &mut self.nodes[id.0].node }
+
+    /// Returns the node with the given id if it is an element.
+    #[inline]
+    pub fn element(&self, id: NodeId) -> Option<&Element> { self.node(id).as_element() }
+
+    /// Returns the node with the given id if it is an element.
+    #[inline]
+    pub fn element_mut(&mut self, id: NodeId) -> Option<&mut Element> { self.node_mut(id).as_element_mut() }
+
+    /// Returns the children of the given node in document order.
+    #[inline]
+    pub fn children(&self, id: NodeId) -> &[NodeId] { &self.nodes[id.0].children }
+
+    /// Returns the parent of the given node, or `None` for the document node and detached nodes.
+    #[inline]
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> { self.nodes[id.0].parent }
+
+    /// Returns the first child of the given node.
+    #[inline]
+    pub fn first_child(&self, id: NodeId) -> Option<NodeId> { self.children(id).first().cloned() }
+
+    /// Returns the last child of the given node.
+    #[inline]
+    pub fn last_child(&self, id: NodeId) -> Option<NodeId> { self.children(id).last().cloned() }
+
+    /// Returns the sibling which follows the given node.
+    pub fn next_sibling(&self, id: NodeId) -> Option<NodeId> {
+        let parent = self.parent(id)?;
+        let siblings = self.children(parent);
+        siblings.get(self.index_of(id) + 1).cloned()
+    }
+
+    /// Returns the sibling which precedes the given node.
+    pub fn previous_sibling(&self, id: NodeId) -> Option<NodeId> {
+        match self.parent(id) {
+            Some(parent) => match self.index_of(id) {
+                0 => None,
+                i => Some(self.children(parent)[i - 1])
+            },
+            None => None
+        }
+    }
+
+    /// Returns an iterator over ancestors of the given node, starting with its parent.
+    #[inline]
+    pub fn ancestors(&self, id: NodeId) -> Ancestors<'_> {
+        Ancestors { document: self, next: self.parent(id) }
+    }
+
+    /// Returns an iterator over descendants of the given node in document order.
+    #[inline]
+    pub fn descendants(&self, id: NodeId) -> Descendants<'_> {
+        Descendants { document: self, stack: vec![self.children(id).iter()] }
+    }
+
+    /// Returns an iterator over child elements of the given node.
+    #[inline]
+    pub fn child_elements(&self, id: NodeId) -> ChildElements<'_, 'static> {
+        ChildElements { document: self, children: self.children(id).iter(), name: None }
+    }
+
+    /// Returns an iterator over child elements of the given node which have the given name.
+    ///
+    /// Element names are matched by their local names and namespace URIs; prefixes are
+    /// ignored, and a name without a namespace only matches elements without a namespace.
+    #[inline]
+    pub fn children_named<'n, N>(&self, id: NodeId, name: N) -> ChildElements<'_, 'n>
+        where N: Into<Name<'n>>
+    {
+        ChildElements { document: self, children: self.children(id).iter(), name: Some(name.into()) }
+    }
+
+    /// Returns the first child element of the given node which has the given name.
+    ///
+    /// Names are matched as in `children_named()`.
+    #[inline]
+    pub fn child_named<'n, N>(&self, id: NodeId, name: N) -> Option<NodeId> where N: Into<Name<'n>> {
+        self.children_named(id, name).next()
+    }
+
+    /// Creates a new detached node and returns its id.
+    ///
+    /// The node becomes a part of the document tree when it is inserted with `append_child()`
+    /// or a similar method.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the node is `Node::Document`.
+    pub fn create(&mut self, node: Node) -> NodeId {
+        if let Node::Document = node {
+            panic!("Cannot create another document node");
+        }
+        let data = NodeData { node, parent: None, children: Vec::new(), removed: false };
+        match self.free.pop() {
+            Some(id) => {
+                self.nodes[id.0] = data;
+                id
+            }
+            None => {
+                self.nodes.push(data);
+                NodeId(self.nodes.len() - 1)
+            }
+        }
+    }
+
+    /// Inserts a node as the last child of the given parent.
+    ///
+    /// If the node is already in the tree, it is moved together with its descendants.
+    /// Namespace mappings of moved and new elements are updated so that they stay bound to the
+    /// same namespace URIs: mappings in scope of the new parent are inherited, unless they
+    /// conflict with mappings which the elements already had or with the prefixes and namespaces
+    /// of their names.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the parent is neither an element nor the document node, if the node is
+    /// the document node, or if the node is the parent itself or one of its ancestors.
+    pub fn append_child(&mut self, parent: NodeId, child: NodeId) {
+        self.check_insertion(parent, child);
+        self.detach(child);
+        let index = self.children(parent).len();
+        self.attach(parent, index, child);
+    }
+
+    /// Inserts a node as a child of the given parent at the given position.
+    ///
+    /// If the node is a child of the same parent already, the index refers to the list of
+    /// children without this node. See `append_child()` for the rest of the details.
+    ///
+    /// # Panics
+    ///
+    /// Panics in the same cases as `append_child()`, and also if the index is greater than
+    /// the number of children.
+    pub fn insert_child(&mut self, parent: NodeId, index: usize, child: NodeId) {
+        self.check_insertion(parent, child);
+        self.detach(child);
+        assert!(index <= self.children(parent).len(), "Child index is out of bounds");
+        self.attach(parent, index, child);
+    }
+
+    /// Inserts a node before the given sibling.
+    ///
+    /// See `append_child()` for the details.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the sibling has no parent, and in the same cases as `append_child()`.
+    pub fn insert_before(&mut self, sibling: NodeId, child: NodeId) {
+        self.insert_next_to(sibling, child, 0);
+    }
+
+    /// Inserts a node after the given sibling.
+    ///
+    /// See `append_child()` for the details.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the sibling has no parent, and in the same cases as `append_child()`.
+    pub fn insert_after(&mut self, sibling: NodeId, child: NodeId) {
+        self.insert_next_to(sibling, child, 1);
+    }
+
+    /// Detaches a node with its descendants from its parent.
+    ///
+    /// The node can be inserted into the tree again later. Detaching the document node or
+    /// a node which is already detached has no effect.
+    pub fn detach(&mut self, id: NodeId) {
+        if let Some(parent) = self.parent(id) {
+            let index = self.index_of(id);
+            self.nodes[parent.0].children.remove(index);
+            self.nodes[id.0].parent = None;
+        }
+    }
+
+    /// Removes a node with its descendants from the document.
+    ///
+    /// Ids of the removed nodes must not be used afterwards: they may be reused for nodes
+    /// created later. Removing a node which has already been removed has no effect.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the node is the document node.
+    pub fn remove(&mut self, id: NodeId) {
+        assert!(id != self.root(), "Cannot remove the document node");
+        if self.nodes[id.0].removed {
+            return;
+        }
+        self.detach(id);
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            let data = ::std::mem::replace(&mut self.nodes[id.0], NodeData {
+                node: Node::Text(String::new()),
+                parent: None,
+                children: Vec::new(),
+                removed: true,
+            });
+            stack.extend(data.children);
+            self.free.push(id);
+        }
+    }
+
+    fn insert_next_to(&mut self, sibling: NodeId, child: NodeId, offset: usize) {
+        let parent = self.parent(sibling).expect("Sibling node has no parent");
+        self.check_insertion(parent, child);
+        if sibling != child {
+            self.detach(child);
+            let index = self.index_of(sibling) + offset;
+            self.attach(parent, index, child);
+        }
+    }
+
+    fn check_insertion(&self, parent: NodeId, child: NodeId) {
+        match *self.node(parent) {
+            Node::Document | Node::Element(_) => {}
+            _ => panic!("Only elements and the document node can have children")
+        }
+        assert!(child != self.root(), "Cannot insert the document node");
+        assert!(parent != child && self.ancestors(parent).all(|a| a != child),
+                "Cannot insert a node into itself or its descendant");
+    }
+
+    fn attach(&mut self, parent: NodeId, index: usize, child: NodeId) {
+        self.nodes[parent.0].children.insert(index, child);
+        self.nodes[child.0].parent = Some(parent);
+        self.adopt_namespaces(child);
+    }
+
+    /// Updates namespace mappings of elements in a subtree after it is inserted into
+    /// a new parent.
+    fn adopt_namespaces(&mut self, id: NodeId) {
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            let mut namespace = match self.parent(id).and_then(|p| self.element(p)) {
+                Some(parent) => parent.namespace.clone(),
+                None => NamespaceStack::default().squash()
+            };
+            if let Node::Element(ref mut element) = self.nodes[id.0].node {
+                namespace.0.extend(element.namespace.0.iter().map(|(k, v)| (k.clone(), v.clone())));
+                bind_prefix(&mut namespace, &element.name, true);
+                for attribute in &element.attributes {
+                    bind_prefix(&mut namespace, &attribute.name, false);
+                }
+                element.namespace = namespace;
+            }
+            stack.extend(self.children(id).iter().cloned());
+        }
+    }
+
+    fn index_of(&self, id: NodeId) -> usize {
+        match self.parent(id) {
+            Some(parent) => self.children(parent).iter().position(|&c| c == id).unwrap(),
+            None => 0
+        }
+    }
+
+    fn push(&mut self, parent: NodeId, node: Node) -> NodeId {
+        let id = NodeId(self.nodes.len());
+        self.nodes.push(NodeData { node, parent: Some(parent), children: Vec::new(), removed: false });
+        self.nodes[parent.0].children.push(id);
+        id
+    }
+}
+
+impl Default for Document {
+    #[inline]
+    fn default() -> Document { Document::new() }
+}
+
+/// Makes the prefix of a name bound to the namespace URI of the name.
+fn bind_prefix(namespace: &mut Namespace, name: &OwnedName, is_element: bool) {
+    let prefix = match (&name.prefix, is_element) {
+        (Some(prefix), _) => &prefix[..],
+        // unprefixed attributes are never in a namespace, unlike unprefixed elements
+        (None, true) => NS_NO_PREFIX,
+        (None, false) => return
+    };
+    let uri = match name.namespace {
+        Some(ref uri) => &uri[..],
+        None if prefix == NS_NO_PREFIX => NS_EMPTY_URI,
+        None => return
+    };
+    let bound = namespace.get(prefix).unwrap_or(NS_EMPTY_URI);
+    if bound != uri {
+        namespace.force_put(prefix, uri);
+    }
+}
+
+/// An iterator over ancestors of a node.
+///
+/// It is returned by `Document::ancestors()`.
+pub struct Ancestors<'a> {
+    document: &'a Document,
+    next: Option<NodeId>,
+}
+
+impl<'a> Iterator for Ancestors<'a> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        let id = self.next?;
+        self.next = self.document.parent(id);
+        Some(id)
+    }
+}
+
+/// An iterator over descendants of a node in document order.
+///
+/// It is returned by `Document::descendants()`.
+pub struct Descendants<'a> {
+    document: &'a Document,
+    stack: Vec<slice::Iter<'a, NodeId>>,
+}
+
+impl<'a> Iterator for Descendants<'a> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        loop {
+            match self.stack.last_mut()?.next() {
+                Some(&id) => {
+                    self.stack.push(self.document.children(id).iter());
+                    return Some(id);
+                }
+                None => { self.stack.pop(); }
+            }
+        }
+    }
+}
+
+/// An iterator over child elements of a node, optionally filtered by name.
+///
+/// It is returned by `Document::child_elements()` and `Document::children_named()`.
+pub struct ChildElements<'a, 'n> {
+    document: &'a Document,
+    children: slice::Iter<'a, NodeId>,
+    name: Option<Name<'n>>,
+}
+
+impl<'a, 'n> Iterator for ChildElements<'a, 'n> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        let document = self.document;
+        let name = self.name;
+        self.children.by_ref().cloned().find(|&id| match document.element(id) {
+            Some(element) => match name {
+                Some(name) => name_matches(&element.name, name),
+                None => true
+            },
+            None => false
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Document, Node, Element, NodeId};
+
+    use name::{Name, OwnedName};
+
+    use reader::{EventReader, ParserConfig};
+    use writer::{EventWriter, EmitterConfig};
+
+    fn parse(input: &str) -> Document {
+        Document::from_reader(EventReader::from_str(input)).unwrap()
+    }
+
+    fn write(document: &Document) -> String {
+        let mut output = Vec::new();
+        let config = EmitterConfig::new().write_document_declaration(false).pad_self_closing(false);
+        document.write_to(&mut EventWriter::new_with_config(&mut output, config)).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        output.trim_start_matches(r#"<?xml version="1.0" encoding="UTF-8"?>"#).into()
+    }
+
+    fn names(document: &Document, ids: &[NodeId]) -> Vec<String> {
+        ids.iter().map(|&id| match *document.node(id) {
+            Node::Element(ref e) => e.name.to_string(),
+            Node::Text(ref t) => format!("'{}'", t),
+            ref other => format!("{:?}", other)
+        }).collect()
+    }
+
+    fn round_trip(input: &str, config: EmitterConfig) -> String {
+        let document = Document::from_reader(EventReader::from_str(input)).unwrap();
+        let mut output = Vec::new();
+        document.write_to(&mut EventWriter::new_with_config(&mut output, config)).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn build_tree() {
+        let document = Document::from_reader(EventReader::new_with_config(
+            r#"<?xml version="1.1" standalone="yes"?><!DOCTYPE a><?pi data?><a><![CDATA[x]]> <b/></a>"#.as_bytes(),
+            ParserConfig::new().cdata_to_characters(false)
+        )).unwrap();
+        assert_eq!(document.version.to_string(), "1.1");
+        assert_eq!(document.standalone, Some(true));
+        assert_eq!(document.doctype.as_ref().unwrap().name, "a");
+
+        let top = document.children(document.root());
+        assert_eq!(top.len(), 2);
+        assert_eq!(*document.node(top[0]), Node::ProcessingInstruction { name: "pi".into(), data: Some("data".into()) });
+        assert_eq!(document.root_element(), Some(top[1]));
+
+        let children = document.children(top[1]);
+        assert_eq!(children.len(), 3);
+        assert_eq!(*document.node(children[0]), Node::CData("x".into()));
+        assert_eq!(*document.node(children[1]), Node::Text(" ".into()));
+        assert_eq!(document.element(children[2]).unwrap().name.local_name, "b");
+    }
+
+    #[test]
+    fn namespaces_round_trip() {
+        let input = r#"<a:x xmlns:a="urn:a"><y xmlns="urn:y" a:attr="1"><a:z/></y></a:x>"#;
+        assert_eq!(
+            round_trip(input, EmitterConfig::new().pad_self_closing(false)),
+            format!(r#"<?xml version="1.0" encoding="UTF-8"?>{}"#, input)
+        );
+    }
+
+
+    #[test]
+    fn navigation() {
+        let document = parse(r#"<r xmlns:p="urn:p"><a/>t<p:b x="1" p:x="2"/><b xmlns="urn:p"/><b/></r>"#);
+        let r = document.root_element().unwrap();
+        let children = document.children(r).to_vec();
+        assert_eq!(document.parent(r), Some(document.root()));
+        assert_eq!(document.first_child(r), Some(children[0]));
+        assert_eq!(document.last_child(r), Some(children[4]));
+        assert_eq!(document.next_sibling(children[0]), Some(children[1]));
+        assert_eq!(document.next_sibling(children[4]), None);
+        assert_eq!(document.previous_sibling(children[1]), Some(children[0]));
+        assert_eq!(document.previous_sibling(children[0]), None);
+        assert_eq!(document.next_sibling(r), None);
+
+        assert_eq!(document.ancestors(children[2]).collect::<Vec<_>>(), vec![r, document.root()]);
+        assert_eq!(names(&document, &document.descendants(document.root()).collect::<Vec<_>>()),
+                   vec!["r", "a", "'t'", "{urn:p}p:b", "{urn:p}b", "b"]);
+        assert_eq!(document.child_elements(r).count(), 4);
+
+        let p_b = Name::qualified("b", "urn:p", None);
+        assert_eq!(document.children_named(r, p_b).collect::<Vec<_>>(), vec![children[2], children[3]]);
+        assert_eq!(document.child_named(r, "b"), Some(children[4]));
+        assert_eq!(document.child_named(r, "c"), None);
+
+        let b = document.element(children[2]).unwrap();
+        assert_eq!(b.attribute("x"), Some("1"));
+        assert_eq!(b.attribute(Name::qualified("x", "urn:p", Some("q"))), Some("2"));
+        assert_eq!(b.attribute("y"), None);
+    }
+
+    #[test]
+    fn moving_subtrees() {
+        let mut document = parse(concat!(
+            r#"<r xmlns="urn:d" xmlns:p="urn:p1">"#,
+            r#"<from><p:moved p:a="1"><child/></p:moved></from>"#,
+            r#"<to xmlns:p="urn:p2"><p:other/></to>"#,
+            r#"</r>"#
+        ));
+        let r = document.root_element().unwrap();
+        let from = document.child_named(r, Name::qualified("from", "urn:d", None)).unwrap();
+        let to = document.child_named(r, Name::qualified("to", "urn:d", None)).unwrap();
+        let moved = document.first_child(from).unwrap();
+
+        document.append_child(to, moved);
+        assert_eq!(document.children(from), &[]);
+        assert_eq!(document.parent(moved), Some(to));
+        assert_eq!(document.element(moved).unwrap().namespace.get("p"), Some("urn:p1"));
+        assert_eq!(write(&document), concat!(
+            r#"<r xmlns="urn:d" xmlns:p="urn:p1">"#,
+            r#"<from/>"#,
+            r#"<to xmlns:p="urn:p2"><p:other/><p:moved xmlns:p="urn:p1" p:a="1"><child/></p:moved></to>"#,
+            r#"</r>"#
+        ));
+
+        // moving within the same parent
+        let other = document.first_child(to).unwrap();
+        document.insert_after(other, moved);
+        assert_eq!(document.children(to), &[other, moved]);
+        document.insert_before(other, moved);
+        assert_eq!(document.children(to), &[moved, other]);
+        document.insert_child(to, 1, moved);
+        assert_eq!(document.children(to), &[other, moved]);
+
+        // a detached subtree can be inserted elsewhere later
+        document.detach(to);
+        assert_eq!(document.parent(to), None);
+        assert_eq!(document.child_elements(r).count(), 1);
+        document.insert_before(from, to);
+        assert_eq!(document.child_elements(r).collect::<Vec<_>>(), vec![to, from]);
+    }
+
+    #[test]
+    fn new_elements_namespaces() {
+        let mut document = parse(r#"<r xmlns="urn:d" xmlns:p="urn:p"/>"#);
+        let r = document.root_element().unwrap();
+
+        let mut element = Element::new(OwnedName::qualified("x", "urn:q", Some("p")));
+        element.attributes.push(::attribute::OwnedAttribute::new(OwnedName::qualified("a", "urn:p", Some("p2")), "v"));
+        let x = document.create(Node::Element(element));
+        document.append_child(r, x);
+        let unqualified = document.create(Node::Element(Element::new(OwnedName::local("y"))));
+        document.append_child(x, unqualified);
+        let text = document.create(Node::Text("z".into()));
+        document.append_child(unqualified, text);
+        let default = document.create(Node::Element(Element::new(OwnedName::qualified("d", "urn:d", None::<&str>))));
+        document.append_child(unqualified, default);
+
+        assert_eq!(write(&document), concat!(
+            r#"<r xmlns="urn:d" xmlns:p="urn:p">"#,
+            r#"<p:x xmlns:p="urn:q" xmlns:p2="urn:p" p2:a="v"><y xmlns="">z<d xmlns="urn:d"/></y></p:x>"#,
+            r#"</r>"#
+        ));
+    }
+
+    #[test]
+    fn removing_nodes() {
+        let mut document = parse("<r><a><b/></a><c/></r>");
+        let r = document.root_element().unwrap();
+        let a = document.first_child(r).unwrap();
+        let b = document.first_child(a).unwrap();
+        document.remove(a);
+        assert_eq!(names(&document, document.children(r)), vec!["c"]);
+        assert_eq!(write(&document), "<r><c/></r>");
+
+        // slots of removed nodes are reused
+        let d = document.create(Node::Comment("d".into()));
+        let e = document.create(Node::Comment("e".into()));
+        let mut reused = vec![d.0, e.0];
+        reused.sort();
+        assert_eq!(reused, vec![a.0, b.0]);
+    }
+
+    #[test]
+    fn removing_nodes_twice() {
+        let mut document = parse("<r><a><b/></a><c/></r>");
+        let r = document.root_element().unwrap();
+        let a = document.first_child(r).unwrap();
+        document.remove(a);
+        document.remove(a);
+
+        // each slot is reused once
+        let d = document.create(Node::Comment("d".into()));
+        let e = document.create(Node::Comment("e".into()));
+        let f = document.create(Node::Comment("f".into()));
+        assert!(d != e && d != f && e != f);
+        document.append_child(r, d);
+        document.append_child(r, e);
+        document.append_child(r, f);
+        assert_eq!(write(&document), "<r><c/><!-- d --><!-- e --><!-- f --></r>");
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot insert a node into itself or its descendant")]
+    fn inserting_into_descendant() {
+        let mut document = parse("<r><a><b/></a></r>");
+        let r = document.root_element().unwrap();
+        let a = document.first_child(r).unwrap();
+        let b = document.first_child(a).unwrap();
+        document.append_child(b, r);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only elements and the document node can have children")]
+    fn inserting_into_text() {
+        let mut document = parse("<r>t</r>");
+        let t = document.first_child(document.root_element().unwrap()).unwrap();
+        let c = document.create(Node::Comment("c".into()));
+        document.append_child(t, c);
+    }
+}
@@ -0,0 +1,347 @@
+//! Contains types describing document type declarations (DTDs).
+//!
+//! These types are produced by the reader when it encounters a `<!DOCTYPE>` declaration;
+//! see `reader::XmlEvent::Doctype`. Names in a DTD are not namespace-aware, so they are
+//! represented as plain strings which may contain a prefix, e.g. `"xsl:template"`.
+
+use std::fmt;
+
+/// A parsed document type declaration.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Doctype {
+    /// Name of the document type, which must match the name of the root element.
+    pub name: String,
+
+    /// External identifier of the external DTD subset, if it is present.
+    ///
+    /// The external subset itself is not read by the parser.
+    pub external_id: Option<ExternalId>,
+
+    /// Markup declarations of the internal subset, in document order.
+    ///
+    /// Declarations contained in internal parameter entities referenced between
+    /// declarations are expanded in place. Comments and processing instructions inside
+    /// the internal subset are skipped.
+    pub declarations: Vec<MarkupDeclaration>,
+}
+
+impl Doctype {
+    /// Returns a new document type declaration with the given name and no declarations.
+    #[inline]
+    pub fn new<S: Into<String>>(name: S) -> Doctype {
+        Doctype {
+            name: name.into(),
+            external_id: None,
+            declarations: Vec::new(),
+        }
+    }
+
+    /// Looks up a general entity declaration by its name.
+    ///
+    /// If an entity is declared more than once, the first declaration is returned,
+    /// as required by the XML specification.
+    pub fn entity(&self, name: &str) -> Option<&EntityDecl> {
+        self.entities().find(|e| !e.parameter && e.name == name)
+    }
+
+    /// Looks up a parameter entity declaration by its name.
+    ///
+    /// If an entity is declared more than once, the first declaration is returned.
+    pub fn parameter_entity(&self, name: &str) -> Option<&EntityDecl> {
+        self.entities().find(|e| e.parameter && e.name == name)
+    }
+
+    /// Looks up an element type declaration by the element name.
+    pub fn element(&self, name: &str) -> Option<&ElementDecl> {
+        self.declarations.iter().filter_map(|d| match *d {
+            MarkupDeclaration::Element(ref e) => Some(e),
+            _ => None
+        }).find(|e| e.name == name)
+    }
+
+    /// Looks up an attribute definition for the given element and attribute names.
+    ///
+    /// Attribute-list declarations for the same element are merged; when an attribute is
+    /// defined more than once, the first definition is returned.
+    pub fn attribute(&self, element: &str, name: &str) -> Option<&AttributeDef> {
+        self.declarations.iter().filter_map(|d| match *d {
+            MarkupDeclaration::AttList(ref l) if l.element == element => l.attributes.iter().find(|a| a.name == name),
+            _ => None
+        }).next()
+    }
+
+    /// Returns an iterator over all attribute definitions for the given element, in
+    /// declaration order.
+    pub fn attributes<'a>(&'a self, element: &'a str) -> impl Iterator<Item=&'a AttributeDef> + 'a {
+        self.declarations.iter().filter_map(move |d| match *d {
+            MarkupDeclaration::AttList(ref l) if l.element == element => Some(l.attributes.iter()),
+            _ => None
+        }).flatten()
+    }
+
+    /// Looks up a notation declaration by its name.
+    pub fn notation(&self, name: &str) -> Option<&NotationDecl> {
+        self.declarations.iter().filter_map(|d| match *d {
+            MarkupDeclaration::Notation(ref n) => Some(n),
+            _ => None
+        }).find(|n| n.name == name)
+    }
+
+    fn entities(&self) -> impl Iterator<Item=&EntityDecl> {
+        self.declarations.iter().filter_map(|d| match *d {
+            MarkupDeclaration::Entity(ref e) => Some(e),
+            _ => None
+        })
+    }
+}
+
+/// An external identifier of an entity or of the external DTD subset.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ExternalId {
+    /// `SYSTEM "system-literal"`
+    System(String),
+
+    /// `PUBLIC "public-id" "system-literal"`
+    Public(String, String),
+}
+
+impl ExternalId {
+    /// Returns the system literal of this identifier.
+    #[inline]
+    pub fn system_id(&self) -> &str {
+        match *self {
+            ExternalId::System(ref s) | ExternalId::Public(_, ref s) => s,
+        }
+    }
+
+    /// Returns the public identifier, if it is present.
+    #[inline]
+    pub fn public_id(&self) -> Option<&str> {
+        match *self {
+            ExternalId::System(_) => None,
+            ExternalId::Public(ref p, _) => Some(p),
+        }
+    }
+}
+
+impl fmt::Display for ExternalId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ExternalId::System(ref s) => write!(f, "SYSTEM \"{}\"", s),
+            ExternalId::Public(ref p, ref s) => write!(f, "PUBLIC \"{}\" \"{}\"", p, s),
+        }
+    }
+}
+
+/// A single markup declaration from the internal subset.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum MarkupDeclaration {
+    /// `<!ELEMENT ...>`
+    Element(ElementDecl),
+
+    /// `<!ATTLIST ...>`
+    AttList(AttListDecl),
+
+    /// `<!ENTITY ...>`
+    Entity(EntityDecl),
+
+    /// `<!NOTATION ...>`
+    Notation(NotationDecl),
+}
+
+/// An element type declaration, `<!ELEMENT name contentspec>`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ElementDecl {
+    /// Name of the element type.
+    pub name: String,
+
+    /// Allowed content of the element.
+    pub content: ContentSpec,
+}
+
+/// A content specification of an element type declaration.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ContentSpec {
+    /// `EMPTY`: the element must not have any content.
+    Empty,
+
+    /// `ANY`: the element may contain any declared elements and character data.
+    Any,
+
+    /// `(#PCDATA | a | b)*`: character data optionally mixed with the listed elements.
+    ///
+    /// The vector is empty for `(#PCDATA)`.
+    Mixed(Vec<String>),
+
+    /// Element content described by a content model.
+    Children(ContentParticle),
+}
+
+/// A content particle of an element content model.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ContentParticle {
+    /// The particle itself.
+    pub kind: ParticleKind,
+
+    /// How many times the particle may occur.
+    pub repetition: Repetition,
+}
+
+/// The kind of a content particle.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ParticleKind {
+    /// A single element name.
+    Name(String),
+
+    /// `(a | b | c)`
+    Choice(Vec<ContentParticle>),
+
+    /// `(a, b, c)`
+    Sequence(Vec<ContentParticle>),
+}
+
+/// An occurrence indicator of a content particle.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Repetition {
+    /// No indicator: exactly once.
+    Once,
+
+    /// `?`: zero or one time.
+    Optional,
+
+    /// `*`: zero or more times.
+    ZeroOrMore,
+
+    /// `+`: one or more times.
+    OneOrMore,
+}
+
+/// An attribute-list declaration, `<!ATTLIST element ...>`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct AttListDecl {
+    /// Name of the element type the attributes belong to.
+    pub element: String,
+
+    /// Attribute definitions, in declaration order.
+    pub attributes: Vec<AttributeDef>,
+}
+
+/// A single attribute definition of an attribute-list declaration.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct AttributeDef {
+    /// Name of the attribute.
+    pub name: String,
+
+    /// Type of the attribute.
+    pub attribute_type: AttributeType,
+
+    /// Default value declaration of the attribute.
+    pub default: DefaultDecl,
+}
+
+/// A type of an attribute.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum AttributeType {
+    /// `CDATA`
+    CData,
+    /// `ID`
+    Id,
+    /// `IDREF`
+    IdRef,
+    /// `IDREFS`
+    IdRefs,
+    /// `ENTITY`
+    Entity,
+    /// `ENTITIES`
+    Entities,
+    /// `NMTOKEN`
+    NmToken,
+    /// `NMTOKENS`
+    NmTokens,
+    /// `NOTATION (a | b)`
+    Notation(Vec<String>),
+    /// `(a | b)`
+    Enumeration(Vec<String>),
+}
+
+impl AttributeType {
+    /// Returns `true` if this is the `CDATA` type.
+    ///
+    /// Values of attributes of all other types are tokenized.
+    #[inline]
+    pub fn is_cdata(&self) -> bool {
+        *self == AttributeType::CData
+    }
+}
+
+/// A default value declaration of an attribute.
+///
+/// Default values are stored as they are written in the declaration, without any
+/// references expanded.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum DefaultDecl {
+    /// `#REQUIRED`
+    Required,
+    /// `#IMPLIED`
+    Implied,
+    /// `#FIXED "value"`
+    Fixed(String),
+    /// `"value"`
+    Default(String),
+}
+
+impl DefaultDecl {
+    /// Returns the default value, if there is one.
+    #[inline]
+    pub fn value(&self) -> Option<&str> {
+        match *self {
+            DefaultDecl::Fixed(ref v) | DefaultDecl::Default(ref v) => Some(v),
+            DefaultDecl::Required | DefaultDecl::Implied => None,
+        }
+    }
+}
+
+/// An entity declaration, `<!ENTITY name ...>` or `<!ENTITY % name ...>`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct EntityDecl {
+    /// Name of the entity.
+    pub name: String,
+
+    /// Whether this is a parameter entity.
+    pub parameter: bool,
+
+    /// Definition of the entity.
+    pub value: EntityValue,
+}
+
+/// A definition of an entity.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum EntityValue {
+    /// An internal entity with its replacement text.
+    ///
+    /// Character references in the literal are already replaced with the characters
+    /// they refer to; general entity references are kept as they are.
+    Internal(String),
+
+    /// An external entity.
+    External {
+        /// Location of the entity.
+        external_id: ExternalId,
+
+        /// Notation name of an unparsed entity, i.e. the `NDATA` part.
+        notation: Option<String>,
+    },
+}
+
+/// A notation declaration, `<!NOTATION name ...>`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct NotationDecl {
+    /// Name of the notation.
+    pub name: String,
+
+    /// Public identifier of the notation, if present.
+    pub public_id: Option<String>,
+
+    /// System identifier of the notation, if present.
+    pub system_id: Option<String>,
+}
@@ -13,6 +13,14 @@ extern crate doc_comment;
 #[cfg(doctest)]
 doctest!("../Readme.md");
 
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde;
+#[cfg(feature = "async")]
+extern crate tokio;
+#[cfg(feature = "async")]
+extern crate futures_core;
+
 pub use reader::EventReader;
 pub use reader::ParserConfig;
 pub use writer::EventWriter;
@@ -24,6 +32,16 @@ pub mod attribute;
 pub mod common;
 pub mod escape;
 pub mod namespace;
+pub mod dtd;
 pub mod reader;
 pub mod writer;
+pub mod schema;
+pub mod dom;
+pub mod xpath;
+pub mod selector;
+pub mod sax;
+#[cfg(feature = "serde")]
+pub mod de;
+#[cfg(feature = "serde")]
+pub mod ser;
 mod util;
@@ -255,6 +255,8 @@ impl NamespaceStack {
     /// Returns a boolean flag indicating whether the insertion has completed successfully.
     /// Note that both key and value are matched and the mapping is inserted if either
     /// namespace prefix is not already mapped, or if it is mapped, but to a different URI.
+    /// Only the innermost mapping of the prefix is considered, so a prefix which is bound to
+    /// the URI in an outer namespace but rebound in an inner one is mapped again.
     ///
     /// # Parameters
     /// * `prefix` --- namespace prefix;
@@ -267,7 +269,7 @@ impl NamespaceStack {
         where P: Into<String> + AsRef<str>,
               U: Into<String> + AsRef<str>
     {
-        if self.0.iter().any(|ns| ns.get(&prefix) == Some(uri.as_ref())) {
+        if self.get(&prefix) == Some(uri.as_ref()) {
             false
         } else {
             self.put(prefix, uri);
@@ -483,3 +485,24 @@ impl<'a, 'b> Extend<UriMapping<'b>> for CheckedTarget<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::NamespaceStack;
+
+    #[test]
+    fn put_checked_considers_innermost_mapping() {
+        let mut nst = NamespaceStack::empty();
+        nst.push_empty();
+        assert!(nst.put_checked("p", "urn:a"));
+        nst.push_empty();
+        assert!(!nst.put_checked("p", "urn:a"));
+        assert!(nst.put_checked("p", "urn:b"));
+
+        // `p` is bound to `urn:a` in the outer namespace, but it is rebound in between
+        nst.push_empty();
+        assert!(nst.put_checked("p", "urn:a"));
+        assert_eq!(nst.get("p"), Some("urn:a"));
+        assert_eq!(nst.peek().get("p"), Some("urn:a"));
+    }
+}
@@ -0,0 +1,224 @@
+//! Contains an asynchronous counterpart of `EventReader` for `tokio::io::AsyncRead` sources.
+//!
+//! This module is only available when the `async` feature is enabled.
+
+use std::future::Future;
+use std::io::{self, Read};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+use tokio::io::{AsyncRead, ReadBuf};
+
+use common::{Position, TextPosition};
+
+use super::parser::PullParser;
+use super::{ParserConfig, Result, XmlEvent};
+
+/// Size of the buffer which the source is read into.
+const BUFFER_SIZE: usize = 8 * 1024;
+
+/// A wrapper around a `tokio::io::AsyncRead` instance which provides pull-based XML parsing
+/// without blocking.
+///
+/// It uses the same parser as `EventReader` and produces exactly the same events, but whenever
+/// the source has no data available, it yields to the executor instead of blocking the thread.
+/// Since every `AsyncBufRead` is an `AsyncRead` as well, both kinds of sources are supported;
+/// the source is read in blocks, so it does not need to be buffered.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let mut reader = AsyncEventReader::new(tcp_stream);
+/// loop {
+///     match reader.next().await? {
+///         XmlEvent::EndDocument => break,
+///         event => println!("{:?}", event),
+///     }
+/// }
+/// ```
+pub struct AsyncEventReader<R> {
+    source: R,
+    parser: PullParser,
+    buf: Vec<u8>,             // data read from the source, allocated on the first read
+    pos: usize,               // offset of the first byte in `buf` not passed to the parser yet
+    end: usize,               // offset right after the last byte read into `buf`
+    eof: bool,                // whether the end of the source was reached
+    error: Option<io::Error>  // an error of the source which is not reported by the parser yet
+}
+
+impl<R: AsyncRead + Unpin> AsyncEventReader<R> {
+    /// Creates a new reader, consuming the given stream.
+    #[inline]
+    pub fn new(source: R) -> AsyncEventReader<R> {
+        AsyncEventReader::new_with_config(source, ParserConfig::new())
+    }
+
+    /// Creates a new reader with the provded configuration, consuming the given stream.
+    #[inline]
+    pub fn new_with_config(source: R, config: ParserConfig) -> AsyncEventReader<R> {
+        AsyncEventReader {
+            source,
+            parser: PullParser::new(config),
+            buf: Vec::new(),
+            pos: 0,
+            end: 0,
+            eof: false,
+            error: None
+        }
+    }
+
+    /// Returns a future which resolves to the next XML event from the stream.
+    ///
+    /// If returned event is an error or `XmlEvent::EndDocument`, then further calls to this
+    /// method will return this event again, just like with `EventReader::next()`.
+    #[inline]
+    pub fn next(&mut self) -> Next<'_, R> {
+        Next { reader: self }
+    }
+
+    /// Attempts to parse the next XML event, reading the stream as long as it has data.
+    ///
+    /// Returns `Poll::Pending` if the stream has no data available; in this case the current
+    /// task is scheduled to be woken up when it has.
+    pub fn poll_next_event(&mut self, cx: &mut Context) -> Poll<Result<XmlEvent>> {
+        loop {
+            let result = {
+                let mut input = Input {
+                    data: &self.buf[self.pos..self.end],
+                    eof: self.eof,
+                    error: &mut self.error
+                };
+                let result = self.parser.next(&mut input);
+                self.pos = self.end - input.data.len();
+                result
+            };
+            match result {
+                // all data read so far has been parsed
+                Err(ref e) if e.is_would_block() => {}
+                result => return Poll::Ready(result)
+            }
+
+            if self.buf.is_empty() {
+                self.buf.resize(BUFFER_SIZE, 0);
+            }
+            let mut read_buf = ReadBuf::new(&mut self.buf);
+            match Pin::new(&mut self.source).poll_read(cx, &mut read_buf) {
+                Poll::Ready(Ok(())) => {
+                    self.eof = read_buf.filled().is_empty();
+                    self.pos = 0;
+                    self.end = read_buf.filled().len();
+                }
+                // the error is returned by the parser, so it is handled as with `EventReader`
+                Poll::Ready(Err(e)) => self.error = Some(e),
+                Poll::Pending => return Poll::Pending
+            }
+        }
+    }
+
+    pub fn source(&self) -> &R { &self.source }
+    pub fn source_mut(&mut self) -> &mut R { &mut self.source }
+
+    /// Unwraps this `AsyncEventReader`, returning the underlying reader.
+    ///
+    /// Note that the data which has been read from the stream but not parsed yet is lost.
+    pub fn into_inner(self) -> R {
+        self.source
+    }
+
+    /// Converts this reader into a `Stream` of events.
+    #[inline]
+    pub fn into_stream(self) -> Events<R> {
+        Events { reader: self, finished: false }
+    }
+}
+
+impl<R> Position for AsyncEventReader<R> {
+    /// Returns the position of the last event produced by the reader.
+    #[inline]
+    fn position(&self) -> TextPosition {
+        self.parser.position()
+    }
+}
+
+/// Passes the data read from the source to the parser, reporting errors of the `WouldBlock`
+/// kind when more data has to be read.
+struct Input<'a> {
+    data: &'a [u8],
+    eof: bool,
+    error: &'a mut Option<io::Error>
+}
+
+impl<'a> Read for Input<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+        if self.data.is_empty() && !self.eof {
+            return Err(io::Error::new(io::ErrorKind::WouldBlock, "no data is available yet"));
+        }
+        self.data.read(buf)
+    }
+}
+
+/// A future which resolves to the next event of an `AsyncEventReader`.
+///
+/// It is returned by `AsyncEventReader::next()`.
+pub struct Next<'a, R: 'a> {
+    reader: &'a mut AsyncEventReader<R>
+}
+
+impl<'a, R: AsyncRead + Unpin> Future for Next<'a, R> {
+    type Output = Result<XmlEvent>;
+
+    #[inline]
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<XmlEvent>> {
+        self.get_mut().reader.poll_next_event(cx)
+    }
+}
+
+/// A stream of XML events read from some type implementing `AsyncRead`.
+///
+/// When the next event is an error or `XmlEvent::EndDocument`, it is returned by the stream
+/// once, and then the stream ends.
+pub struct Events<R> {
+    reader: AsyncEventReader<R>,
+    finished: bool
+}
+
+impl<R> Events<R> {
+    /// Unwraps the stream, returning the internal `AsyncEventReader`.
+    #[inline]
+    pub fn into_inner(self) -> AsyncEventReader<R> {
+        self.reader
+    }
+
+    pub fn source(&self) -> &R { &self.reader.source }
+    pub fn source_mut(&mut self) -> &mut R { &mut self.reader.source }
+}
+
+impl<R> Position for Events<R> {
+    /// Returns the position of the last event produced by the stream.
+    #[inline]
+    fn position(&self) -> TextPosition {
+        self.reader.position()
+    }
+}
+
+impl<R: AsyncRead + Unpin> Stream for Events<R> {
+    type Item = Result<XmlEvent>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Result<XmlEvent>>> {
+        let this = self.get_mut();
+        if this.finished && !this.reader.parser.is_ignoring_end_of_stream() {
+            return Poll::Ready(None);
+        }
+        this.reader.poll_next_event(cx).map(|ev| {
+            match ev {
+                Ok(XmlEvent::EndDocument) | Err(_) => this.finished = true,
+                _ => {}
+            }
+            Some(ev)
+        })
+    }
+}
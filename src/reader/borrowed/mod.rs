@@ -0,0 +1,627 @@
+//! Contains a pull parser which produces events borrowing from an in-memory document.
+//!
+//! `EventReader` owns all strings in the events it produces, even when the whole document is
+//! already in memory. `BorrowedReader` works on a document in memory instead and yields events
+//! whose names, namespace URIs, attribute values and text are slices of the document whenever
+//! they appear in it literally.
+//!
+//! Documents in UTF-8 are scanned for events directly, and only strings which differ from the
+//! document, like text with references or attribute values with normalized whitespace, are
+//! allocated. Documents with a document type declaration or carriage returns, documents in
+//! other encodings and documents which are validated against their DTD are read by the parser
+//! of `EventReader` instead, from the point where the reader finds out about it. Their strings
+//! are borrowed only after the parser has produced them, so reading them is not faster than
+//! with `EventReader`. Documents in encodings other than UTF-8 and US-ASCII are supported, but
+//! all their strings are allocated.
+//!
+//! Either way, the reader supports all options of `ParserConfig` and produces the same events
+//! and errors at the same positions as `EventReader`.
+//!
+//! ```rust
+//! use std::borrow::Cow;
+//! use xml::reader::borrowed::{BorrowedReader, XmlEvent};
+//!
+//! let document = "<a href='x.html'>Fish &amp; chips</a>";
+//! let mut reader = BorrowedReader::new(document);
+//!
+//! assert!(match reader.next().unwrap() { XmlEvent::StartDocument { .. } => true, _ => false });
+//! match reader.next().unwrap() {
+//!     XmlEvent::StartElement { name, attributes, .. } => {
+//!         assert_eq!(name.local_name, "a");
+//!         assert!(match attributes[0].value { Cow::Borrowed("x.html") => true, _ => false });
+//!     }
+//!     _ => unreachable!(),
+//! }
+//! match reader.next().unwrap() {
+//!     XmlEvent::Characters(text) => assert!(match text { Cow::Owned(ref s) => s == "Fish & chips", _ => false }),
+//!     _ => unreachable!(),
+//! }
+//! ```
+
+use std::borrow::Cow;
+use std::fmt;
+use std::str;
+
+use attribute::{self, OwnedAttribute};
+use common::{Encoding, Position, TextPosition, XmlVersion, is_name_char, is_name_start_char, is_whitespace_char};
+use dtd::Doctype;
+use name::{self, OwnedName};
+use namespace::{Namespace, NS_NO_PREFIX, NS_XMLNS_PREFIX, NS_XMLNS_URI, NS_XML_PREFIX, NS_XML_URI};
+
+use super::parser::PullParser;
+use super::{events, ParserConfig, Result};
+
+use self::scanner::Scanner;
+
+mod scanner;
+
+/// A qualified name which borrows from the document when it appears in it literally.
+///
+/// See `name::Name` for the meaning of the fields.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Name<'a> {
+    /// A local name, e.g. `string` in `xsi:string`.
+    pub local_name: Cow<'a, str>,
+
+    /// A namespace URI, e.g. `http://www.w3.org/2000/xmlns/`.
+    pub namespace: Option<Cow<'a, str>>,
+
+    /// A name prefix, e.g. `xsi` in `xsi:string`.
+    pub prefix: Option<Cow<'a, str>>,
+}
+
+impl<'a> Name<'a> {
+    /// Returns a `name::Name` borrowing from this one, e.g. to pass it to the writer.
+    #[inline]
+    pub fn borrow(&self) -> name::Name<'_> {
+        name::Name {
+            local_name: &self.local_name,
+            namespace: self.namespace.as_ref().map(|n| &n[..]),
+            prefix: self.prefix.as_ref().map(|p| &p[..]),
+        }
+    }
+
+    /// Creates an owned name out of this borrowed one.
+    #[inline]
+    pub fn to_owned(&self) -> OwnedName {
+        self.borrow().to_owned()
+    }
+}
+
+impl<'a> fmt::Display for Name<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.borrow().fmt(f)
+    }
+}
+
+/// An attribute whose value borrows from the document when it does not need unescaping.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Attribute<'a> {
+    /// Attribute name.
+    pub name: Name<'a>,
+
+    /// Attribute value.
+    pub value: Cow<'a, str>,
+}
+
+impl<'a> Attribute<'a> {
+    /// Returns an `attribute::Attribute` borrowing from this one, e.g. to pass it to the writer.
+    #[inline]
+    pub fn borrow(&self) -> attribute::Attribute<'_> {
+        attribute::Attribute::new(self.name.borrow(), &self.value)
+    }
+
+    /// Creates an owned attribute out of this borrowed one.
+    #[inline]
+    pub fn to_owned(&self) -> OwnedAttribute {
+        OwnedAttribute::new(self.name.to_owned(), &*self.value)
+    }
+}
+
+/// An element of an XML input stream borrowing from the document.
+///
+/// Events correspond to those of `reader::XmlEvent`, see its documentation for details.
+#[derive(Clone, PartialEq, Debug)]
+pub enum XmlEvent<'a> {
+    /// Corresponds to XML document declaration.
+    StartDocument {
+        /// XML version.
+        version: XmlVersion,
+
+        /// XML document encoding as declared in the document, or `UTF-8`.
+        encoding: Cow<'a, str>,
+
+        /// XML standalone declaration.
+        standalone: Option<bool>
+    },
+
+    /// Denotes the document type declaration.
+    Doctype(Doctype),
+
+    /// Denotes the end of the document stream.
+    EndDocument,
+
+    /// Denotes an XML processing instruction.
+    ProcessingInstruction {
+        /// Processing instruction target.
+        name: Cow<'a, str>,
+
+        /// Processing instruction content.
+        data: Option<Cow<'a, str>>
+    },
+
+    /// Denotes a beginning of an XML element.
+    StartElement {
+        /// Qualified name of the element.
+        name: Name<'a>,
+
+        /// A list of attributes associated with the element.
+        attributes: Vec<Attribute<'a>>,
+
+        /// Namespace declarations of this element as pairs of prefixes and URIs; the default
+        /// namespace has an empty prefix.
+        namespaces: Vec<(Cow<'a, str>, Cow<'a, str>)>
+    },
+
+    /// Denotes an end of an XML element.
+    EndElement {
+        /// Qualified name of the element.
+        name: Name<'a>
+    },
+
+    /// Denotes CDATA content.
+    CData(Cow<'a, str>),
+
+    /// Denotes a comment.
+    Comment(Cow<'a, str>),
+
+    /// Denotes character data outside of tags.
+    Characters(Cow<'a, str>),
+
+    /// Denotes a chunk of whitespace outside of tags.
+    Whitespace(Cow<'a, str>),
+}
+
+/// An element which is open at the current point of the document.
+struct OpenElement<'a> {
+    name: Name<'a>,
+    bindings: usize,
+}
+
+/// A pull parser over a document in memory which yields events borrowing from it.
+pub struct BorrowedReader<'a> {
+    source: &'a [u8],
+    input: &'a [u8],  // the part of `source` which has not been passed to the parser yet
+    text: &'a str,    // the longest prefix of `source` which is valid UTF-8
+    parser: PullParser,
+    trim_whitespace: bool,
+    elements: Vec<OpenElement<'a>>,
+    bindings: Vec<(Cow<'a, str>, Cow<'a, str>)>,
+    cursor: (usize, TextPosition),  // an offset in `text` and its position
+    scanner: Option<Scanner<'a>>,  // reads events until it leaves the document to the parser
+}
+
+impl<'a> BorrowedReader<'a> {
+    /// Creates a new reader over the given document.
+    #[inline]
+    pub fn new(source: &'a str) -> BorrowedReader<'a> {
+        BorrowedReader::new_with_config(source, ParserConfig::new())
+    }
+
+    /// Creates a new reader over the given document with the provided configuration.
+    #[inline]
+    pub fn new_with_config(source: &'a str, config: ParserConfig) -> BorrowedReader<'a> {
+        BorrowedReader::from_bytes_with_config(source.as_bytes(), config)
+    }
+
+    /// Creates a new reader over an encoded document.
+    #[inline]
+    pub fn from_bytes(source: &'a [u8]) -> BorrowedReader<'a> {
+        BorrowedReader::from_bytes_with_config(source, ParserConfig::new())
+    }
+
+    /// Creates a new reader over an encoded document with the provided configuration.
+    ///
+    /// The encoding is detected in the same way as by `EventReader`. Invalid byte sequences
+    /// are reported as errors when the parser reaches them.
+    pub fn from_bytes_with_config(source: &'a [u8], config: ParserConfig) -> BorrowedReader<'a> {
+        let text = match str::from_utf8(source) {
+            Ok(text) => text,
+            Err(e) => str::from_utf8(&source[..e.valid_up_to()]).unwrap(),
+        };
+        // The byte order mark is not counted in positions
+        let start = if text.starts_with('\u{feff}') { '\u{feff}'.len_utf8() } else { 0 };
+        BorrowedReader {
+            source: source,
+            input: source,
+            text: text,
+            trim_whitespace: config.trim_whitespace,
+            scanner: if text.len() == source.len() { Scanner::new(text, &config) } else { None },
+            parser: PullParser::new(config),
+            elements: Vec::new(),
+            bindings: Vec::new(),
+            cursor: (start, TextPosition::new()),
+        }
+    }
+
+    /// Pulls and returns next XML event from the document.
+    ///
+    /// If returned event is an error or `XmlEvent::EndDocument`, then further calls to this
+    /// method will return this event again.
+    pub fn next(&mut self) -> Result<XmlEvent<'a>> {
+        if let Some(ref mut scanner) = self.scanner {
+            if let Some(event) = scanner.next(&self.parser) {
+                return Ok(event);
+            }
+        }
+        if let Some(scanner) = self.scanner.take() {
+            // The parser reads the document from the beginning, skipping the events which
+            // have been returned by the scanner already
+            for _ in 0..scanner.emitted() {
+                self.parse_next()?;
+            }
+        }
+        self.parse_next()
+    }
+
+    /// Returns the document this reader is reading.
+    #[inline]
+    pub fn source(&self) -> &'a [u8] { self.source }
+
+    fn parse_next(&mut self) -> Result<XmlEvent<'a>> {
+        let event = self.parser.next(&mut self.input)?;
+        let offset = match self.parser.encoding() {
+            Encoding::Utf8 | Encoding::Ascii => {
+                let pos = self.parser.position();
+                self.offset_of(pos)
+            }
+            _ => None
+        };
+        Ok(self.borrow_event(event, offset))
+    }
+
+    /// Converts an event of the parser, borrowing its strings from the document at the given
+    /// offset where possible.
+    fn borrow_event(&mut self, event: events::XmlEvent, offset: Option<usize>) -> XmlEvent<'a> {
+        match event {
+            events::XmlEvent::StartDocument { version, encoding, standalone } => {
+                // The encoding is the only string of the declaration
+                let declaration = offset.map(|o| &self.text[o..])
+                    .filter(|rest| rest.starts_with("<?xml"))
+                    .and_then(|rest| rest.find("?>").map(|end| &rest[..end]));
+                let encoding = match declaration.and_then(|d| d.find(&*encoding).map(|i| &d[i..i + encoding.len()])) {
+                    Some(slice) => Cow::Borrowed(slice),
+                    None => Cow::Owned(encoding),
+                };
+                XmlEvent::StartDocument { version: version, encoding: encoding, standalone: standalone }
+            }
+            events::XmlEvent::Doctype(doctype) => XmlEvent::Doctype(doctype),
+            events::XmlEvent::EndDocument => XmlEvent::EndDocument,
+            events::XmlEvent::ProcessingInstruction { name, data } => {
+                let name_offset = offset.filter(|&o| self.text[o..].starts_with("<?")).map(|o| o + 2);
+                let data_offset = name_offset.map(|o| {
+                    let rest = &self.text[o + name.len()..];
+                    self.text.len() - rest.trim_start_matches(is_whitespace_char).len()
+                });
+                XmlEvent::ProcessingInstruction {
+                    data: data.map(|data| self.slice(data_offset, data)),
+                    name: self.slice(name_offset, name),
+                }
+            }
+            events::XmlEvent::StartElement { name, attributes, namespace } =>
+                self.start_element(offset, name, attributes, namespace),
+            events::XmlEvent::EndElement { .. } => {
+                let element = self.elements.pop().expect("the parser checks that elements are balanced");
+                self.bindings.truncate(element.bindings);
+                XmlEvent::EndElement { name: element.name }
+            }
+            events::XmlEvent::CData(data) => XmlEvent::CData(self.text_slice(offset, data)),
+            events::XmlEvent::Comment(data) => {
+                let offset = offset.filter(|&o| self.text[o..].starts_with("<!--")).map(|o| o + 4);
+                XmlEvent::Comment(self.slice(offset, data))
+            }
+            events::XmlEvent::Characters(data) => XmlEvent::Characters(self.text_slice(offset, data)),
+            events::XmlEvent::Whitespace(data) => XmlEvent::Whitespace(self.text_slice(offset, data)),
+        }
+    }
+
+    fn start_element(&mut self, offset: Option<usize>, name: OwnedName, attributes: Vec<OwnedAttribute>,
+                     namespace: Namespace) -> XmlEvent<'a> {
+        // The parser has read the document up to the end of the tag, and attribute values
+        // cannot contain `<`, so the tag starts at the last `<` before that
+        let end = self.source.len() - self.input.len();
+        let tag = offset.and(self.text.get(..end))
+            .and_then(|read| read.rfind('<').map(|start| &read[start..]))
+            .and_then(parse_tag)
+            .filter(|t| t.name == name.borrow().to_repr());
+
+        // Declarations of the element are taken from its tag; elements from the replacement
+        // text of entities declare the mappings which differ from those of their parents
+        let bindings = self.bindings.len();
+        let namespaces: Vec<_> = match tag {
+            Some(ref tag) => tag.attributes.iter().filter_map(|&(attr, value)| {
+                let prefix = match split_name(attr) {
+                    (None, NS_XMLNS_PREFIX) => NS_NO_PREFIX,
+                    (Some(NS_XMLNS_PREFIX), prefix) => prefix,
+                    _ => return None,
+                };
+                let uri = namespace.get(prefix).unwrap_or("");
+                Some((Cow::Borrowed(prefix), borrow_if_equal(value, uri)))
+            }).collect(),
+            None => namespace.into_iter()
+                .filter(|&(prefix, uri)| prefix != NS_XML_PREFIX && prefix != NS_XMLNS_PREFIX &&
+                                          self.lookup(prefix).map_or(!uri.is_empty(), |u| u != uri))
+                .map(|(prefix, uri)| (Cow::Owned(prefix.into()), Cow::Owned(uri.into())))
+                .collect(),
+        };
+        self.bindings.extend(namespaces.iter().cloned());
+
+        let name = self.name(tag.as_ref().map(|t| t.name), name);
+        let attributes = attributes.into_iter().map(|a| {
+            let qualified_name = a.name.borrow().to_repr();
+            let raw = tag.as_ref().and_then(|t| t.attributes.iter().find(|r| r.0 == qualified_name));
+            Attribute {
+                name: self.name(raw.map(|r| r.0), a.name),
+                value: match raw {
+                    Some(&(_, value)) if value == a.value => Cow::Borrowed(value),
+                    _ => Cow::Owned(a.value),
+                },
+            }
+        }).collect();
+
+        self.elements.push(OpenElement { name: name.clone(), bindings: bindings });
+        XmlEvent::StartElement { name: name, attributes: attributes, namespaces: namespaces }
+    }
+
+    /// Converts a name, borrowing its parts from the qualified name in the document.
+    fn name(&self, raw: Option<&'a str>, name: OwnedName) -> Name<'a> {
+        let (raw_prefix, raw_local_name) = match raw {
+            Some(raw) => split_name(raw),
+            None => (None, ""),
+        };
+        let prefix = name.prefix.as_ref().map_or(NS_NO_PREFIX, |p| &p[..]);
+        let namespace = name.namespace.map(|uri| match prefix {
+            NS_XML_PREFIX if uri == NS_XML_URI => Cow::Borrowed(NS_XML_URI),
+            NS_XMLNS_PREFIX if uri == NS_XMLNS_URI => Cow::Borrowed(NS_XMLNS_URI),
+            prefix => match self.bindings.iter().rev().find(|b| b.0 == prefix) {
+                Some(&(_, Cow::Borrowed(bound))) if bound == uri => Cow::Borrowed(bound),
+                _ => Cow::Owned(uri),
+            },
+        });
+        Name {
+            local_name: borrow_if_equal(raw_local_name, name.local_name),
+            namespace: namespace,
+            prefix: name.prefix.map(|prefix| match raw_prefix {
+                Some(raw) if raw == prefix => Cow::Borrowed(raw),
+                _ => Cow::Owned(prefix),
+            }),
+        }
+    }
+
+    /// Returns the namespace URI bound to the prefix by the open elements.
+    fn lookup(&self, prefix: &str) -> Option<&str> {
+        self.bindings.iter().rev().find(|b| b.0 == prefix).map(|b| &b.1[..])
+    }
+
+    /// Borrows the string from the document if it is found at the given offset.
+    fn slice(&self, offset: Option<usize>, s: String) -> Cow<'a, str> {
+        match offset.map(|o| &self.text[o..]) {
+            Some(rest) if rest.starts_with(&*s) => Cow::Borrowed(&rest[..s.len()]),
+            _ => Cow::Owned(s),
+        }
+    }
+
+    /// Borrows character data, which starts at the given offset, possibly after the start of
+    /// a CDATA section or whitespace which has been trimmed.
+    ///
+    /// Text which starts with a reference is positioned after it, so the text found there is
+    /// only used when it ends where character data may end.
+    fn text_slice(&self, offset: Option<usize>, s: String) -> Cow<'a, str> {
+        let offset = offset.map(|o| if self.text[o..].starts_with("<![CDATA[") { o + 9 } else { o });
+        let offset = offset.map(|o| if self.trim_whitespace && !self.text[o..].starts_with(&*s) {
+            self.text.len() - self.text[o..].trim_start_matches(is_whitespace_char).len()
+        } else {
+            o
+        });
+        let offset = offset.filter(|&o| match self.text[o..].get(s.len()..).and_then(|rest| rest.chars().next()) {
+            None | Some('<') | Some('&') | Some(']') => true,
+            Some(c) => self.trim_whitespace && is_whitespace_char(c),
+        });
+        self.slice(offset, s)
+    }
+
+    /// Returns the offset in the document of the given position, counting characters and line
+    /// breaks in the same way as the parser does.
+    ///
+    /// Positions of events only grow, so the search continues from the previous one.
+    fn offset_of(&mut self, pos: TextPosition) -> Option<usize> {
+        let (mut offset, mut current) = self.cursor;
+        if (pos.row, pos.column) < (current.row, current.column) {
+            offset = if self.text.starts_with('\u{feff}') { '\u{feff}'.len_utf8() } else { 0 };
+            current = TextPosition::new();
+        }
+        let bytes = self.text.as_bytes();
+        let mut chars = self.text[offset..].chars();
+        loop {
+            // A line feed after a carriage return is a part of the same line break
+            let inside_line_break = offset > 0 && bytes[offset - 1] == b'\r' && bytes.get(offset) == Some(&b'\n');
+            if current == pos && !inside_line_break {
+                self.cursor = (offset, current);
+                return Some(offset);
+            }
+            let c = chars.next()?;
+            match c {
+                '\n' if inside_line_break => {}
+                '\r' | '\n' => current.new_line(),
+                _ => current.advance(1),
+            }
+            offset += c.len_utf8();
+        }
+    }
+}
+
+impl<'a> Position for BorrowedReader<'a> {
+    /// Returns the position of the last event produced by the reader.
+    #[inline]
+    fn position(&self) -> TextPosition {
+        match self.scanner {
+            Some(ref scanner) => scanner.position(),
+            None => self.parser.position(),
+        }
+    }
+}
+
+impl<'a> IntoIterator for BorrowedReader<'a> {
+    type Item = Result<XmlEvent<'a>>;
+    type IntoIter = BorrowedEvents<'a>;
+
+    fn into_iter(self) -> BorrowedEvents<'a> {
+        BorrowedEvents { reader: self, finished: false }
+    }
+}
+
+/// An iterator over events of a `BorrowedReader`.
+///
+/// When the next event is an error or `XmlEvent::EndDocument`, then it will be returned by the
+/// iterator once, and then it will stop producing events.
+pub struct BorrowedEvents<'a> {
+    reader: BorrowedReader<'a>,
+    finished: bool
+}
+
+impl<'a> BorrowedEvents<'a> {
+    /// Unwraps the iterator, returning the internal `BorrowedReader`.
+    #[inline]
+    pub fn into_inner(self) -> BorrowedReader<'a> {
+        self.reader
+    }
+}
+
+impl<'a> Position for BorrowedEvents<'a> {
+    /// Returns the position of the last event produced by the iterator.
+    #[inline]
+    fn position(&self) -> TextPosition {
+        self.reader.position()
+    }
+}
+
+impl<'a> Iterator for BorrowedEvents<'a> {
+    type Item = Result<XmlEvent<'a>>;
+
+    fn next(&mut self) -> Option<Result<XmlEvent<'a>>> {
+        if self.finished {
+            return None;
+        }
+        let ev = self.reader.next();
+        match ev {
+            Ok(XmlEvent::EndDocument) | Err(_) => self.finished = true,
+            _ => {}
+        }
+        Some(ev)
+    }
+}
+
+/// A start tag as it is written in the document.
+struct Tag<'a> {
+    name: &'a str,
+    attributes: Vec<(&'a str, &'a str)>,
+}
+
+/// Splits the start tag which makes up the whole text into the name and raw attributes.
+///
+/// The tag has been checked by the parser already, but the text before the end of an event
+/// may also be something else when the event comes from the replacement text of an entity,
+/// so `None` is returned for anything which is not a complete tag.
+fn parse_tag<'a>(text: &'a str) -> Option<Tag<'a>> {
+    fn name(text: &str) -> Option<(&str, &str)> {
+        let len = text.char_indices()
+            .find(|&(i, c)| if i == 0 { !is_name_start_char(c) } else { !is_name_char(c) })
+            .map_or(text.len(), |(i, _)| i);
+        if len == 0 { None } else { Some((&text[..len], &text[len..])) }
+    }
+    fn skip_whitespace(text: &str) -> &str {
+        text.trim_start_matches(is_whitespace_char)
+    }
+
+    let (tag_name, mut rest) = name(text.strip_prefix('<')?)?;
+    let mut attributes = Vec::new();
+    loop {
+        rest = skip_whitespace(rest);
+        if rest == ">" || rest == "/>" {
+            return Some(Tag { name: tag_name, attributes: attributes });
+        }
+        let (attr, after_name) = name(rest)?;
+        let after_equals = skip_whitespace(skip_whitespace(after_name).strip_prefix('=')?);
+        let quote = after_equals.chars().next().filter(|&c| c == '"' || c == '\'')?;
+        let value_start = &after_equals[1..];
+        let end = value_start.find(quote)?;
+        attributes.push((attr, &value_start[..end]));
+        rest = &value_start[end + 1..];
+    }
+}
+
+/// Splits a qualified name into its prefix and local name.
+fn split_name(name: &str) -> (Option<&str>, &str) {
+    match name.find(':') {
+        Some(i) => (Some(&name[..i]), &name[i + 1..]),
+        None => (None, name),
+    }
+}
+
+/// Borrows `raw` if it is equal to `s`.
+fn borrow_if_equal<'a, S: AsRef<str> + Into<String>>(raw: &'a str, s: S) -> Cow<'a, str> {
+    if raw == s.as_ref() { Cow::Borrowed(raw) } else { Cow::Owned(s.into()) }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use common::Position;
+    use reader::{ErrorKind, EventReader};
+
+    use super::{BorrowedReader, XmlEvent};
+
+    #[test]
+    fn borrowed_and_owned_text() {
+        let mut reader = BorrowedReader::new("<a x='1&amp;2' y='\t3'>plain<![CDATA[raw]]>a&lt;b\r\nc</a>");
+        reader.next().unwrap();
+        match reader.next().unwrap() {
+            XmlEvent::StartElement { attributes, .. } => {
+                assert_eq!(attributes[0].value, Cow::Owned::<str>("1&2".into()));
+                assert_eq!(attributes[1].value, " 3");
+            }
+            e => panic!("Unexpected event: {:?}", e),
+        }
+        match reader.next().unwrap() {
+            XmlEvent::Characters(Cow::Borrowed(text)) => assert_eq!(text, "plain"),
+            e => panic!("Unexpected event: {:?}", e),
+        }
+        match reader.next().unwrap() {
+            XmlEvent::CData(Cow::Borrowed(text)) => assert_eq!(text, "raw"),
+            e => panic!("Unexpected event: {:?}", e),
+        }
+        match reader.next().unwrap() {
+            XmlEvent::Characters(Cow::Owned(text)) => assert_eq!(text, "a<b\nc"),
+            e => panic!("Unexpected event: {:?}", e),
+        }
+        assert_eq!(reader.position().to_string(), "1:43");
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let document = b"<a>\n  \xff</a>";
+        let error = BorrowedReader::from_bytes(document).into_iter().last().unwrap().unwrap_err();
+        let mut expected = EventReader::new(&document[..]);
+        let expected = loop {
+            if let Err(e) = expected.next() {
+                break e;
+            }
+        };
+        assert_eq!(error, expected);
+        match *error.kind() {
+            ErrorKind::Utf8(ref e) => assert_eq!(e.valid_up_to(), 0),
+            ref other => panic!("Unexpected error kind: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,723 @@
+//! Contains the scanner which reads events of `BorrowedReader` directly from slices of a
+//! document.
+//!
+//! The scanner follows the same rules as the parser, including the positions of events, for
+//! documents in UTF-8 without a document type declaration and carriage returns. When it finds
+//! anything else, including any error, it gives up and the rest of the document is read by the
+//! parser.
+
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::mem;
+
+use common::{Encoding, TextPosition, XmlVersion, is_name_char, is_name_start_char, is_whitespace_char, is_whitespace_str};
+use namespace::{NS_NO_PREFIX, NS_XMLNS_PREFIX, NS_XMLNS_URI, NS_XML_PREFIX, NS_XML_URI};
+use reader::ParserConfig;
+use reader::parser::PullParser;
+
+use super::{Attribute, Name, XmlEvent};
+
+/// Character data of the next event, which is kept as a range of the document while it is
+/// a single slice of it.
+enum Data {
+    Empty,
+    Slice(usize, usize),
+    Owned(String),
+}
+
+impl Data {
+    fn is_empty(&self) -> bool {
+        match *self {
+            Data::Empty => true,
+            Data::Slice(start, end) => start == end,
+            Data::Owned(ref s) => s.is_empty(),
+        }
+    }
+
+    fn push_slice(&mut self, text: &str, start: usize, end: usize) {
+        if start == end {
+            return;
+        }
+        match *self {
+            Data::Empty => *self = Data::Slice(start, end),
+            Data::Slice(s, e) if e == start => *self = Data::Slice(s, end),
+            Data::Slice(s, e) => *self = Data::Owned(format!("{}{}", &text[s..e], &text[start..end])),
+            Data::Owned(ref mut buf) => buf.push_str(&text[start..end]),
+        }
+    }
+
+    fn push_str(&mut self, text: &str, s: &str) {
+        if s.is_empty() {
+            return;
+        }
+        match *self {
+            Data::Empty => *self = Data::Owned(s.into()),
+            Data::Slice(start, end) => *self = Data::Owned(format!("{}{}", &text[start..end], s)),
+            Data::Owned(ref mut buf) => buf.push_str(s),
+        }
+    }
+
+    fn take<'a>(&mut self, text: &'a str) -> Cow<'a, str> {
+        match mem::replace(self, Data::Empty) {
+            Data::Empty => Cow::Borrowed(""),
+            Data::Slice(start, end) => Cow::Borrowed(&text[start..end]),
+            Data::Owned(s) => Cow::Owned(s),
+        }
+    }
+}
+
+/// An element which is open at the current point of the document.
+struct OpenElement<'a> {
+    qualified_name: &'a str,
+    name: Name<'a>,
+    bindings: usize,
+}
+
+/// Reads events from a document in UTF-8 until it finds something it leaves to the parser.
+pub struct Scanner<'a> {
+    text: &'a str,
+    offset: usize,
+    config: ParserConfig,
+    data: Data,
+    inside_whitespace: bool,
+    pos: Vec<TextPosition>,  // positions of the events to come, in the same way as in the parser
+    cursor: (usize, TextPosition),  // an offset in `text` and its position
+    elements: Vec<OpenElement<'a>>,
+    bindings: Vec<(Cow<'a, str>, Cow<'a, str>)>,
+    encountered_element: bool,
+    parsed_declaration: bool,
+    events: VecDeque<(XmlEvent<'a>, Option<usize>)>,  // events with the offsets of their last tokens
+    emitted: usize,
+    finished: bool,
+    failed: bool,
+}
+
+impl<'a> Scanner<'a> {
+    /// Creates a scanner for the document if it can read it with the given configuration.
+    pub fn new(text: &'a str, config: &ParserConfig) -> Option<Scanner<'a>> {
+        let other_encoding = config.override_encoding.iter().any(|&e| e != Encoding::Utf8);
+        // Documents which may be in UTF-16 are left to the parser, just as line breaks
+        // which it normalizes
+        if other_encoding || config.validate_dtd || text.starts_with("\0<") || text.starts_with("<\0") ||
+           text.as_bytes().contains(&b'\r') {
+            return None;
+        }
+        let start = if text.starts_with('\u{feff}') { '\u{feff}'.len_utf8() } else { 0 };
+        Some(Scanner {
+            text,
+            offset: start,
+            config: config.clone(),
+            data: Data::Empty,
+            inside_whitespace: true,
+            pos: vec![TextPosition::new()],
+            cursor: (start, TextPosition::new()),
+            elements: Vec::new(),
+            bindings: Vec::new(),
+            encountered_element: false,
+            parsed_declaration: false,
+            events: VecDeque::new(),
+            emitted: 0,
+            finished: false,
+            failed: false,
+        })
+    }
+
+    /// Returns the next event, or `None` if the rest of the document should be read by the
+    /// parser.
+    pub fn next(&mut self, parser: &PullParser) -> Option<XmlEvent<'a>> {
+        loop {
+            if let Some((event, token)) = self.events.pop_front() {
+                if let Some(offset) = token {
+                    self.next_pos(offset);
+                }
+                self.emitted += 1;
+                return Some(event);
+            }
+            if self.finished {
+                return Some(XmlEvent::EndDocument);
+            }
+            if self.failed || self.step(parser).is_none() {
+                self.failed = true;
+                return None;
+            }
+        }
+    }
+
+    /// Returns the number of events returned so far.
+    #[inline]
+    pub fn emitted(&self) -> usize { self.emitted }
+
+    /// Returns the position of the last event.
+    #[inline]
+    pub fn position(&self) -> TextPosition { self.pos[0] }
+
+    fn position_at(&mut self, offset: usize) -> TextPosition {
+        let (mut current_offset, mut current) = self.cursor;
+        if offset < current_offset {
+            current_offset = if self.text.starts_with('\u{feff}') { '\u{feff}'.len_utf8() } else { 0 };
+            current = TextPosition::new();
+        }
+        for &b in &self.text.as_bytes()[current_offset..offset] {
+            match b {
+                b'\n' => current.new_line(),
+                // continuation bytes of multibyte characters
+                0x80..=0xbf => {}
+                _ => current.advance(1),
+            }
+        }
+        self.cursor = (offset, current);
+        current
+    }
+
+    fn push_pos(&mut self, offset: usize) {
+        let pos = self.position_at(offset);
+        self.pos.push(pos);
+    }
+
+    /// Moves to the position of the next event; when there is none, the position of the last
+    /// token of the event is used, like the parser does with the position of its lexer.
+    fn next_pos(&mut self, token: usize) {
+        if self.pos.len() > 1 {
+            self.pos.remove(0);
+        } else {
+            self.pos[0] = self.position_at(token);
+        }
+    }
+
+    fn emit(&mut self, event: Option<XmlEvent<'a>>, token: Option<usize>) {
+        if let Some(event) = event {
+            self.events.push_back((event, token));
+        }
+    }
+
+    /// Reads the next piece of the document, or returns `None` when it should be left to
+    /// the parser.
+    fn step(&mut self, parser: &PullParser) -> Option<()> {
+        let bytes = self.text.as_bytes();
+        let start = self.offset;
+        match bytes.get(start) {
+            None => {
+                if !self.elements.is_empty() || !self.encountered_element {
+                    return None;
+                }
+                let end = self.text.len();
+                self.emit(Some(XmlEvent::EndDocument), Some(end));
+                self.finished = true;
+                Some(())
+            }
+            Some(&b'<') => self.markup(parser, start),
+            Some(&b'&') => {
+                if self.elements.is_empty() {
+                    return None;
+                }
+                let (value, end) = self.reference(parser, start)?;
+                if !is_whitespace_str(&value) {
+                    self.inside_whitespace = false;
+                }
+                self.data.push_str(self.text, &value);
+                self.offset = end;
+                Some(())
+            }
+            Some(&b) if is_whitespace_byte(b) => {
+                let end = skip_whitespace(self.text, start);
+                if !(self.elements.is_empty() && self.config.ignore_root_level_whitespace ||
+                     self.config.trim_whitespace && self.data.is_empty()) {
+                    if self.data.is_empty() {
+                        self.push_pos(start);
+                    }
+                    self.data.push_slice(self.text, start, end);
+                }
+                self.offset = end;
+                Some(())
+            }
+            Some(&b';') if !self.elements.is_empty() => {
+                // the end of a reference is text which does not start an event
+                self.inside_whitespace = false;
+                self.data.push_slice(self.text, start, start + 1);
+                self.offset = start + 1;
+                Some(())
+            }
+            Some(_) => {
+                if self.elements.is_empty() || is_text_error(&bytes[start..]) {
+                    return None;
+                }
+                if self.data.is_empty() {
+                    self.push_pos(start);
+                }
+                self.inside_whitespace = false;
+                let mut end = start + 1;
+                while end < bytes.len() && bytes[end] != b'<' && bytes[end] != b'&' && !is_text_error(&bytes[end..]) {
+                    end += 1;
+                }
+                self.data.push_slice(self.text, start, end);
+                self.offset = end;
+                Some(())
+            }
+        }
+    }
+
+    fn markup(&mut self, parser: &PullParser, start: usize) -> Option<()> {
+        let rest = &self.text[start..];
+        if rest.starts_with("<?") {
+            self.processing_instruction(start)
+        } else if rest.starts_with("</") {
+            self.end_element(start)
+        } else if rest.starts_with("<!--") {
+            let content = start + 4;
+            let dashes = self.text[content..].find("--")?;
+            let end = content + dashes;
+            // `<!--` inside of a comment is a token which swallows the dashes
+            if !self.text[end..].starts_with("-->") || self.text[content..end].ends_with("<!") {
+                return None;
+            }
+            self.offset = end + 3;
+            if !(self.config.coalesce_characters && self.config.ignore_comments) {
+                let event = self.flush(start);
+                self.emit(event, Some(start));
+                if !self.config.ignore_comments {
+                    let comment = XmlEvent::Comment(Cow::Borrowed(&self.text[content..end]));
+                    self.emit(Some(comment), Some(end));
+                }
+            }
+            Some(())
+        } else if rest.starts_with("<![CDATA[") {
+            let content = start + 9;
+            let end = content + self.text[content..].find("]]>")?;
+            let data = &self.text[content..end];
+            // the lexer places the end of sections which end with `]` one character later
+            let token = if data.ends_with(']') { end + 1 } else { end };
+            self.offset = end + 3;
+            if self.config.coalesce_characters && self.config.cdata_to_characters {
+                if self.data.is_empty() {
+                    self.push_pos(start);
+                }
+            } else {
+                let event = self.flush(start);
+                self.emit(event, Some(start));
+            }
+            if !is_whitespace_str(data) {
+                self.inside_whitespace = false;
+            }
+            if self.config.cdata_to_characters {
+                self.data.push_slice(self.text, content, end);
+            } else {
+                self.emit(Some(XmlEvent::CData(Cow::Borrowed(data))), Some(token));
+            }
+            Some(())
+        } else {
+            match rest[1..].chars().next() {
+                Some(c) if is_name_start_char(c) && c != ':' => self.start_element(parser, start),
+                _ => None,
+            }
+        }
+    }
+
+    /// Flushes character data before markup which starts at the given offset.
+    fn flush(&mut self, start: usize) -> Option<XmlEvent<'a>> {
+        let event = if self.data.is_empty() {
+            None
+        } else {
+            let data = self.data.take(self.text);
+            if self.inside_whitespace && self.config.trim_whitespace {
+                None
+            } else if self.inside_whitespace && !self.config.whitespace_to_characters {
+                Some(XmlEvent::Whitespace(data))
+            } else if self.config.trim_whitespace {
+                Some(XmlEvent::Characters(match data {
+                    Cow::Borrowed(s) => Cow::Borrowed(s.trim_matches(is_whitespace_char)),
+                    Cow::Owned(s) => Cow::Owned(s.trim_matches(is_whitespace_char).into()),
+                }))
+            } else {
+                Some(XmlEvent::Characters(data))
+            }
+        };
+        self.inside_whitespace = true;
+        self.push_pos(start);
+        event
+    }
+
+    fn processing_instruction(&mut self, start: usize) -> Option<()> {
+        let name_start = start + 2;
+        let mut name_end = name_start;
+        for c in self.text[name_start..].chars() {
+            if !(if name_end == name_start { is_name_start_char(c) } else { is_name_char(c) }) {
+                break;
+            }
+            name_end += c.len_utf8();
+        }
+        let name = &self.text[name_start..name_end];
+        if name.is_empty() || name.contains("--") {
+            return None;
+        }
+        let xml = name.eq_ignore_ascii_case("xml");
+        let has_data = match self.text.as_bytes().get(name_end) {
+            Some(&b) if is_whitespace_byte(b) => true,
+            Some(&b'?') if self.text[name_end..].starts_with("?>") => false,
+            _ => return None,
+        };
+        if xml && (!has_data || self.encountered_element || self.parsed_declaration) {
+            return None;
+        }
+
+        if name == "xml" {
+            let (declaration, end) = self.declaration(name_end)?;
+            self.offset = end + 2;
+            self.parsed_declaration = true;
+            let event = self.flush(start);
+            self.emit(event, Some(start));
+            self.emit(Some(declaration), Some(end));
+            return Some(());
+        }
+
+        let (data, end) = if has_data {
+            let data_start = name_end + 1;
+            let end = data_start + self.text[data_start..].find("?>")?;
+            // `<?` inside of the data is a token which swallows the question mark
+            if self.text[..end].ends_with('<') {
+                return None;
+            }
+            (Some(Cow::Borrowed(&self.text[data_start..end])), end)
+        } else {
+            (None, name_end)
+        };
+        self.offset = end + 2;
+        let event = self.flush(start);
+        self.emit(event, Some(start));
+        let pi = XmlEvent::ProcessingInstruction { name: Cow::Borrowed(name), data };
+        self.emit(Some(pi), Some(end));
+        Some(())
+    }
+
+    /// Reads the XML declaration after its name, returning the event and the offset of its end.
+    fn declaration(&self, start: usize) -> Option<(XmlEvent<'a>, usize)> {
+        let text = self.text;
+        let mut i = skip_whitespace(text, start);
+
+        i = keyword(text, i, "version")?;
+        let (version, end) = declaration_value(text, i)?;
+        let version = match version {
+            "1.0" => XmlVersion::Version10,
+            "1.1" => XmlVersion::Version11,
+            _ => return None,
+        };
+        i = skip_whitespace(text, end);
+
+        let mut encoding = None;
+        if let Some(after) = keyword(text, i, "encoding") {
+            let (value, end) = declaration_value(text, after)?;
+            if self.config.override_encoding.is_none() {
+                match value.parse() {
+                    Ok(Encoding::Utf8) => {}
+                    Err(_) if !self.config.reject_unsupported_encoding => {}
+                    _ => return None,
+                }
+            }
+            encoding = Some(value);
+            i = skip_whitespace(text, end);
+        }
+
+        let mut standalone = None;
+        if let Some(after) = keyword(text, i, "standalone") {
+            let (value, end) = declaration_value(text, after)?;
+            standalone = match value {
+                "yes" => Some(true),
+                "no" => Some(false),
+                _ => return None,
+            };
+            i = skip_whitespace(text, end);
+        }
+
+        if !text[i..].starts_with("?>") {
+            return None;
+        }
+        Some((XmlEvent::StartDocument {
+            version,
+            encoding: Cow::Borrowed(encoding.unwrap_or("UTF-8")),
+            standalone,
+        }, i))
+    }
+
+    fn start_element(&mut self, parser: &PullParser, start: usize) -> Option<()> {
+        let text = self.text;
+        let bytes = text.as_bytes();
+        let name_start = start + 1;
+        let (name_end, colon) = qualified_name(text, name_start)?;
+
+        // raw names of attributes with their values
+        let mut raw_attributes: Vec<(usize, usize, Option<usize>, Cow<'a, str>)> = Vec::new();
+        let mut i = name_end;
+        let (end, empty) = loop {
+            let after = skip_whitespace(text, i);
+            match bytes.get(after) {
+                Some(&b'>') => break (after, false),
+                Some(&b'/') if bytes.get(after + 1) == Some(&b'>') => break (after, true),
+                _ if after == name_end => return None,
+                _ => {}
+            }
+            let (attr_end, attr_colon) = qualified_name(text, after)?;
+            let equals = skip_whitespace(text, attr_end);
+            if bytes.get(equals) != Some(&b'=') {
+                return None;
+            }
+            let quote = skip_whitespace(text, equals + 1);
+            let (value, value_end) = match bytes.get(quote) {
+                Some(&q) if q == b'"' || q == b'\'' => self.attribute_value(parser, quote + 1, q)?,
+                _ => return None,
+            };
+            raw_attributes.push((after, attr_end, attr_colon, value));
+            i = value_end;
+        };
+
+        let (prefix, local_name) = split_name(text, name_start, name_end, colon);
+        if prefix == Some(NS_XML_PREFIX) || prefix == Some(NS_XMLNS_PREFIX) {
+            return None;
+        }
+
+        let bindings = self.bindings.len();
+        let mut namespaces = Vec::new();
+        let mut attributes = Vec::new();
+        for (k, (attr_start, attr_end, attr_colon, value)) in raw_attributes.iter().cloned().enumerate() {
+            let raw = &text[attr_start..attr_end];
+            if raw_attributes[..k].iter().any(|a| &text[a.0..a.1] == raw) {
+                return None;
+            }
+            match split_name(text, attr_start, attr_end, attr_colon) {
+                (Some(NS_XMLNS_PREFIX), prefix) => {
+                    if prefix == NS_XMLNS_PREFIX || prefix == NS_XML_PREFIX && value != NS_XML_URI || value.is_empty() {
+                        return None;
+                    }
+                    namespaces.push((Cow::Borrowed(prefix), value));
+                }
+                (None, NS_XMLNS_PREFIX) => {
+                    if value == NS_XMLNS_PREFIX || value == NS_XML_PREFIX {
+                        return None;
+                    }
+                    namespaces.push((Cow::Borrowed(NS_NO_PREFIX), value));
+                }
+                (prefix, local_name) => attributes.push((prefix, local_name, value)),
+            }
+        }
+        self.bindings.extend(namespaces.iter().cloned());
+
+        let name = Name {
+            local_name: Cow::Borrowed(local_name),
+            namespace: self.lookup(prefix)?,
+            prefix: prefix.map(Cow::Borrowed),
+        };
+        let mut resolved: Vec<Attribute<'a>> = Vec::with_capacity(attributes.len());
+        for (prefix, local_name, value) in attributes {
+            let namespace = match prefix {
+                Some(_) => self.lookup(prefix)?,
+                None => None,
+            };
+            if namespace.is_some() &&
+               resolved.iter().any(|a| a.name.namespace == namespace && a.name.local_name == local_name) {
+                return None;
+            }
+            resolved.push(Attribute {
+                name: Name { local_name: Cow::Borrowed(local_name), namespace, prefix: prefix.map(Cow::Borrowed) },
+                value,
+            });
+        }
+
+        self.offset = if empty { end + 2 } else { end + 1 };
+        let mut event = self.flush(start);
+        if !self.parsed_declaration {
+            self.parsed_declaration = true;
+            event = Some(XmlEvent::StartDocument {
+                version: XmlVersion::Version10,
+                encoding: Cow::Borrowed("UTF-8"),
+                standalone: None,
+            });
+            self.push_pos(start);
+        }
+        self.encountered_element = true;
+        self.emit(event, Some(start));
+        self.emit(Some(XmlEvent::StartElement {
+            name: name.clone(),
+            attributes: resolved,
+            namespaces,
+        }), Some(end));
+        if empty {
+            self.bindings.truncate(bindings);
+            self.emit(Some(XmlEvent::EndElement { name }), None);
+        } else {
+            self.elements.push(OpenElement {
+                qualified_name: &text[name_start..name_end],
+                name,
+                bindings,
+            });
+        }
+        Some(())
+    }
+
+    fn end_element(&mut self, start: usize) -> Option<()> {
+        let name_start = start + 2;
+        let (name_end, _) = qualified_name(self.text, name_start)?;
+        let end = skip_whitespace(self.text, name_end);
+        if self.text.as_bytes().get(end) != Some(&b'>') {
+            return None;
+        }
+        match self.elements.last() {
+            Some(element) if element.qualified_name == &self.text[name_start..name_end] => {}
+            _ => return None,
+        }
+        let element = self.elements.pop().unwrap();
+        self.bindings.truncate(element.bindings);
+        self.offset = end + 1;
+        let event = self.flush(start);
+        self.emit(event, Some(start));
+        self.emit(Some(XmlEvent::EndElement { name: element.name }), Some(end));
+        Some(())
+    }
+
+    /// Returns the namespace of the prefix as it is put into names of events, or `None` if it
+    /// is not bound.
+    fn lookup(&self, prefix: Option<&str>) -> Option<Option<Cow<'a, str>>> {
+        let prefix = prefix.unwrap_or(NS_NO_PREFIX);
+        let uri = match self.bindings.iter().rev().find(|b| b.0 == prefix) {
+            Some(binding) => binding.1.clone(),
+            None => match prefix {
+                NS_NO_PREFIX => Cow::Borrowed(""),
+                NS_XML_PREFIX => Cow::Borrowed(NS_XML_URI),
+                NS_XMLNS_PREFIX => Cow::Borrowed(NS_XMLNS_URI),
+                _ => return None,
+            },
+        };
+        Some(if uri.is_empty() { None } else { Some(uri) })
+    }
+
+    /// Reads an attribute value which starts at the given offset, returning it and the offset
+    /// after its closing quote.
+    fn attribute_value(&self, parser: &PullParser, start: usize, quote: u8) -> Option<(Cow<'a, str>, usize)> {
+        let bytes = self.text.as_bytes();
+        let mut data = Data::Empty;
+        let mut run = start;
+        let mut i = start;
+        loop {
+            match *bytes.get(i)? {
+                b if b == quote => {
+                    data.push_slice(self.text, run, i);
+                    return Some((data.take(self.text), i + 1));
+                }
+                b'<' => return None,
+                b'&' => {
+                    data.push_slice(self.text, run, i);
+                    let (value, end) = self.reference(parser, i)?;
+                    data.push_str(self.text, &value);
+                    i = end;
+                    run = end;
+                }
+                b'\t' | b'\n' => {
+                    data.push_slice(self.text, run, i);
+                    data.push_str(self.text, " ");
+                    i += 1;
+                    run = i;
+                }
+                _ => i += 1,
+            }
+        }
+    }
+
+    /// Resolves the reference which starts at the given offset, returning its replacement
+    /// text and the offset after it.
+    fn reference(&self, parser: &PullParser, start: usize) -> Option<(String, usize)> {
+        let rest = &self.text[start + 1..];
+        let mut len = 0;
+        for c in rest.chars() {
+            if !(if len == 0 { is_name_start_char(c) || c == '#' } else { is_name_char(c) }) {
+                break;
+            }
+            len += c.len_utf8();
+        }
+        let name = &rest[..len];
+        if !rest[len..].starts_with(';') || name.contains("--") {
+            return None;
+        }
+        parser.resolve_reference(name).ok().map(|value| (value, start + len + 2))
+    }
+}
+
+fn is_whitespace_byte(b: u8) -> bool {
+    b == b' ' || b == b'\t' || b == b'\n'
+}
+
+/// Checks whether the text starts with a token which the parser does not accept in text.
+fn is_text_error(bytes: &[u8]) -> bool {
+    bytes.starts_with(b"]]>") || bytes.starts_with(b"/>") || bytes.starts_with(b"?>")
+}
+
+fn skip_whitespace(text: &str, start: usize) -> usize {
+    let bytes = text.as_bytes();
+    let mut i = start;
+    while i < bytes.len() && is_whitespace_byte(bytes[i]) {
+        i += 1;
+    }
+    i
+}
+
+/// Returns the offset after the keyword at the given offset, if it is followed by whitespace
+/// or an equals sign.
+fn keyword(text: &str, start: usize, keyword: &str) -> Option<usize> {
+    let end = start + keyword.len();
+    if !text[start..].starts_with(keyword) {
+        return None;
+    }
+    match text.as_bytes().get(end) {
+        Some(&b) if b == b'=' || is_whitespace_byte(b) => Some(end),
+        _ => None,
+    }
+}
+
+/// Reads `= 'value'` of a pseudo-attribute of the XML declaration, returning the value and the
+/// offset after it.
+fn declaration_value(text: &str, start: usize) -> Option<(&str, usize)> {
+    let bytes = text.as_bytes();
+    let equals = skip_whitespace(text, start);
+    if bytes.get(equals) != Some(&b'=') {
+        return None;
+    }
+    let quote_start = skip_whitespace(text, equals + 1);
+    let quote = match bytes.get(quote_start) {
+        Some(&q) if q == b'"' || q == b'\'' => q as char,
+        _ => return None,
+    };
+    let value_start = quote_start + 1;
+    let value = &text[value_start..value_start + text[value_start..].find(quote)?];
+    if value.contains(|c| c == '&' || c == '<' || is_whitespace_char(c)) {
+        return None;
+    }
+    Some((value, value_start + value.len() + 1))
+}
+
+/// Reads the qualified name which starts at the given offset, returning the offset after it
+/// and the offset of its colon.
+///
+/// Names which the parser does not accept, or which contain double dashes that the lexer
+/// reads as a separate token, are not read.
+fn qualified_name(text: &str, start: usize) -> Option<(usize, Option<usize>)> {
+    let mut end = start;
+    let mut colon = None;
+    for c in text[start..].chars() {
+        match c {
+            ':' if end > start && colon.is_none() => colon = Some(end),
+            ':' => return None,
+            _ if end == start && !is_name_start_char(c) => return None,
+            _ if !is_name_char(c) => break,
+            _ => {}
+        }
+        end += c.len_utf8();
+    }
+    if end == start || colon == Some(end - 1) || text[start..end].contains("--") {
+        return None;
+    }
+    Some((end, colon))
+}
+
+/// Splits the qualified name between the given offsets into its prefix and local name.
+fn split_name(text: &str, start: usize, end: usize, colon: Option<usize>) -> (Option<&str>, &str) {
+    match colon {
+        Some(colon) => (Some(&text[start..colon]), &text[colon + 1..end]),
+        None => (None, &text[start..end]),
+    }
+}
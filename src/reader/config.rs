@@ -2,6 +2,7 @@
 use std::io::Read;
 use std::collections::HashMap;
 
+use common::Encoding;
 use reader::EventReader;
 
 /// Parser configuration structure.
@@ -60,8 +61,10 @@ pub struct ParserConfig {
     ///
     /// By default the XML parser recognizes the entities defined in the XML spec. Sometimes,
     /// however, it is convenient to make the parser recognize additional entities which
-    /// are also not available through the DTD definitions (especially given that at the moment
-    /// DTD parsing is not supported).
+    /// are also not available through the DTD definitions, for example, entities declared
+    /// in an external DTD subset, which is not read by the parser.
+    ///
+    /// These entities take precedence over entities declared in the DTD.
     pub extra_entities: HashMap<String, String>,
 
     /// Whether or not the parser should ignore the end of stream. Default is false.
@@ -74,6 +77,7 @@ pub struct ParserConfig {
     ///
     /// Note that support for this functionality is incomplete; for example, the parser will fail if
     /// the premature end of stream happens inside PCDATA. Therefore, use this option at your own risk.
+    /// Documents which arrive in chunks can be parsed with `PushReader` instead.
     pub ignore_end_of_stream: bool,
 
     /// Whether or not non-unicode entity references get replaced with the replacement character
@@ -88,6 +92,58 @@ pub struct ParserConfig {
     /// By default any whitespace that is not enclosed within at least one level of elements will be
     /// ignored. Setting this value to false will cause root level whitespace events to be emitted.
     pub ignore_root_level_whitespace: bool,
+
+    /// Maximum nesting depth of entity references during expansion of entities declared
+    /// in the DTD. Default is 16.
+    ///
+    /// An entity whose replacement text contains references to other entities is expanded
+    /// recursively; a reference which would exceed this depth results in an
+    /// `ErrorKind::EntityExpansionLimit` error.
+    pub max_entity_expansion_depth: usize,
+
+    /// Maximum total length, in bytes, of text produced by expansion of entities declared in
+    /// the DTD over the whole document. Default is 1000000.
+    ///
+    /// A reference which would exceed this length results in an
+    /// `ErrorKind::EntityExpansionLimit` error.
+    pub max_entity_expansion_length: usize,
+
+    /// Maximum ratio of the total length of text produced by expansion of entities declared
+    /// in the DTD to the length of the input read so far. Default is 100.
+    ///
+    /// This protects against documents which are small but expand into huge amounts of
+    /// text, like the "billion laughs" attack. A reference which would exceed this ratio
+    /// results in an `ErrorKind::EntityExpansionLimit` error.
+    pub max_entity_expansion_ratio: usize,
+
+    /// Encoding of the input which overrides the encoding specified in the document.
+    /// Default is `None`.
+    ///
+    /// By default the encoding is detected from the byte order mark at the beginning of the
+    /// document and from the `encoding` pseudo-attribute of its XML declaration, defaulting to
+    /// UTF-8. When this option is set, the document is always decoded with the given encoding
+    /// and its encoding declaration is ignored. This is useful for documents which declare
+    /// a wrong encoding.
+    pub override_encoding: Option<Encoding>,
+
+    /// Whether or not an encoding declaration with an unsupported encoding is reported
+    /// as an error. Default is false.
+    ///
+    /// By default such a declaration is ignored, and the document is decoded with the encoding
+    /// detected from its first bytes, or as UTF-8 if nothing was detected. When this option
+    /// is set, the parser reports an error instead. The option has no effect when
+    /// `override_encoding` is set.
+    pub reject_unsupported_encoding: bool,
+
+    /// Whether or not the document is validated against its DTD. Default is false.
+    ///
+    /// When this option is set, the parser checks that the document is valid according to the
+    /// declarations from the internal subset of its `<!DOCTYPE>` declaration: elements must be
+    /// declared and match their content models, attributes must be declared and have values
+    /// allowed by their types and default declarations, ID values must be unique and IDREF
+    /// values must refer to existing IDs. Violations result in an `ErrorKind::Validation`
+    /// error. Documents without a `<!DOCTYPE>` declaration are invalid in this mode.
+    pub validate_dtd: bool,
 }
 
 impl ParserConfig {
@@ -114,6 +170,12 @@ impl ParserConfig {
             ignore_end_of_stream: false,
             replace_unknown_entity_references: false,
             ignore_root_level_whitespace: true,
+            max_entity_expansion_depth: 16,
+            max_entity_expansion_length: 1_000_000,
+            max_entity_expansion_ratio: 100,
+            override_encoding: None,
+            reject_unsupported_encoding: false,
+            validate_dtd: false,
         }
     }
 
@@ -177,5 +239,11 @@ gen_setters! { ParserConfig,
     coalesce_characters: val bool,
     ignore_end_of_stream: val bool,
     replace_unknown_entity_references: val bool,
-    ignore_root_level_whitespace: val bool
+    ignore_root_level_whitespace: val bool,
+    max_entity_expansion_depth: val usize,
+    max_entity_expansion_length: val usize,
+    max_entity_expansion_ratio: val usize,
+    override_encoding: into Option<Encoding>,
+    reject_unsupported_encoding: val bool,
+    validate_dtd: val bool
 }
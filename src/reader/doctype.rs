@@ -0,0 +1,727 @@
+//! Contains a parser for the contents of `<!DOCTYPE>` declarations.
+//!
+//! This module is for internal use. The pull parser collects the whole declaration as text
+//! and then parses it here into the structures from the `dtd` module.
+
+use std::borrow::Cow;
+use std::char;
+use std::result;
+
+use common::{is_name_start_char, is_name_char, is_whitespace_char};
+use dtd::{
+    Doctype, ExternalId, MarkupDeclaration, ElementDecl, ContentSpec, ContentParticle,
+    ParticleKind, Repetition, AttListDecl, AttributeDef, AttributeType, DefaultDecl,
+    EntityDecl, EntityValue, NotationDecl
+};
+
+/// An error which occurred while parsing a doctype declaration.
+#[derive(Debug)]
+pub struct DoctypeError {
+    /// Byte offset of the error inside the parsed text.
+    pub offset: usize,
+    /// Error message.
+    pub msg: Cow<'static, str>,
+}
+
+pub type Result<T> = result::Result<T, DoctypeError>;
+
+/// Parses the contents of a doctype declaration, that is, everything between `<!DOCTYPE`
+/// and the closing `>`.
+pub fn parse_doctype(text: &str) -> Result<Doctype> {
+    DoctypeParser::new(text).doctype()
+}
+
+struct DoctypeParser<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl<'a> DoctypeParser<'a> {
+    fn new(src: &'a str) -> DoctypeParser<'a> {
+        DoctypeParser { src, pos: 0 }
+    }
+
+    #[inline]
+    fn rest(&self) -> &'a str {
+        &self.src[self.pos..]
+    }
+
+    #[inline]
+    fn at_end(&self) -> bool {
+        self.pos == self.src.len()
+    }
+
+    #[inline]
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    #[inline]
+    fn error<T, M: Into<Cow<'static, str>>>(&self, msg: M) -> Result<T> {
+        self.error_at(self.pos, msg)
+    }
+
+    #[inline]
+    fn error_at<T, M: Into<Cow<'static, str>>>(&self, offset: usize, msg: M) -> Result<T> {
+        Err(DoctypeError { offset, msg: msg.into() })
+    }
+
+    fn unexpected<T>(&self, context: &str) -> Result<T> {
+        match self.peek() {
+            Some(c) => self.error(format!("Unexpected character inside {}: {}", context, c)),
+            None => self.error(format!("Unexpected end of {}", context)),
+        }
+    }
+
+    /// Consumes the given string if the input starts with it.
+    fn eat(&mut self, s: &str) -> bool {
+        if self.rest().starts_with(s) {
+            self.pos += s.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, s: &str, context: &str) -> Result<()> {
+        if self.eat(s) { Ok(()) } else { self.unexpected(context) }
+    }
+
+    /// Skips whitespace, returning `true` if there was any.
+    fn skip_whitespace(&mut self) -> bool {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if !is_whitespace_char(c) { break; }
+            self.pos += c.len_utf8();
+        }
+        self.pos > start
+    }
+
+    fn require_whitespace(&mut self, context: &str) -> Result<()> {
+        if self.skip_whitespace() { Ok(()) } else { self.unexpected(context) }
+    }
+
+    fn name(&mut self, context: &str) -> Result<String> {
+        match self.peek() {
+            Some(c) if is_name_start_char(c) => {},
+            _ => return self.unexpected(context),
+        }
+        Ok(self.name_chars().into())
+    }
+
+    fn nmtoken(&mut self, context: &str) -> Result<String> {
+        match self.name_chars() {
+            "" => self.unexpected(context),
+            s => Ok(s.into()),
+        }
+    }
+
+    fn name_chars(&mut self) -> &'a str {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if !is_name_char(c) { break; }
+            self.pos += c.len_utf8();
+        }
+        &self.src[start..self.pos]
+    }
+
+    /// Reads a quoted literal and returns its contents, calling `check` for every character.
+    fn literal<F>(&mut self, context: &str, mut check: F) -> Result<&'a str>
+        where F: FnMut(&Self, char) -> Result<()>
+    {
+        let quote = match self.peek() {
+            Some(q @ '"') | Some(q @ '\'') => q,
+            _ => return self.unexpected(context),
+        };
+        self.pos += 1;
+        let start = self.pos;
+        loop {
+            match self.peek() {
+                Some(c) if c == quote => break,
+                Some(c) => {
+                    check(self, c)?;
+                    self.pos += c.len_utf8();
+                }
+                None => return self.unexpected(context),
+            }
+        }
+        let value = &self.src[start..self.pos];
+        self.pos += 1;
+        Ok(value)
+    }
+
+    fn system_literal(&mut self) -> Result<String> {
+        self.literal("system literal", |_, _| Ok(())).map(From::from)
+    }
+
+    fn pubid_literal(&mut self) -> Result<String> {
+        self.literal("public identifier", |this, c| match c {
+            '\x20' | '\x0d' | '\x0a' | 'a'..='z' | 'A'..='Z' | '0'..='9' |
+            '-' | '\'' | '(' | ')' | '+' | ',' | '.' | '/' | ':' | '=' | '?' |
+            ';' | '!' | '*' | '#' | '@' | '$' | '_' | '%' => Ok(()),
+            _ => this.error(format!("Invalid character in public identifier: {}", c)),
+        }).map(From::from)
+    }
+
+    fn external_id(&mut self, context: &str) -> Result<ExternalId> {
+        if self.eat("SYSTEM") {
+            self.require_whitespace(context)?;
+            Ok(ExternalId::System(self.system_literal()?))
+        } else if self.eat("PUBLIC") {
+            self.require_whitespace(context)?;
+            let public_id = self.pubid_literal()?;
+            self.require_whitespace(context)?;
+            Ok(ExternalId::Public(public_id, self.system_literal()?))
+        } else {
+            self.unexpected(context)
+        }
+    }
+
+    fn doctype(&mut self) -> Result<Doctype> {
+        self.require_whitespace("DOCTYPE")?;
+        let mut doctype = Doctype::new(self.name("DOCTYPE")?);
+        if self.skip_whitespace() && !self.rest().starts_with('[') && !self.at_end() {
+            doctype.external_id = Some(self.external_id("DOCTYPE")?);
+            self.skip_whitespace();
+        }
+        if self.eat("[") {
+            self.internal_subset(&mut doctype, &mut Vec::new())?;
+            self.expect("]", "DOCTYPE")?;
+            self.skip_whitespace();
+        }
+        if self.at_end() { Ok(doctype) } else { self.unexpected("DOCTYPE") }
+    }
+
+    /// Reads markup declarations until `]` or the end of input.
+    ///
+    /// `expanding` contains names of parameter entities which are currently being expanded
+    /// and is used to detect recursive references.
+    fn internal_subset(&mut self, doctype: &mut Doctype, expanding: &mut Vec<String>) -> Result<()> {
+        loop {
+            self.skip_whitespace();
+            if self.at_end() || self.rest().starts_with(']') {
+                return Ok(());
+            }
+
+            let start = self.pos;
+            if self.eat("<!--") {
+                self.comment()?;
+            } else if self.eat("<?") {
+                self.processing_instruction()?;
+            } else if self.eat("<!ELEMENT") {
+                let decl = self.element_decl()?;
+                doctype.declarations.push(MarkupDeclaration::Element(decl));
+            } else if self.eat("<!ATTLIST") {
+                let decl = self.attlist_decl()?;
+                doctype.declarations.push(MarkupDeclaration::AttList(decl));
+            } else if self.eat("<!ENTITY") {
+                let decl = self.entity_decl()?;
+                doctype.declarations.push(MarkupDeclaration::Entity(decl));
+            } else if self.eat("<!NOTATION") {
+                let decl = self.notation_decl()?;
+                doctype.declarations.push(MarkupDeclaration::Notation(decl));
+            } else if self.eat("%") {
+                let name = self.name("parameter entity reference")?;
+                self.expect(";", "parameter entity reference")?;
+                self.expand_parameter_entity(start, &name, doctype, expanding)?;
+            } else {
+                return self.unexpected("internal subset");
+            }
+        }
+    }
+
+    fn expand_parameter_entity(&self, start: usize, name: &str,
+                               doctype: &mut Doctype, expanding: &mut Vec<String>) -> Result<()> {
+        let text = match doctype.parameter_entity(name).map(|e| e.value.clone()) {
+            Some(EntityValue::Internal(text)) => text,
+            // external entities are not read
+            Some(EntityValue::External { .. }) => return Ok(()),
+            // the entity may be declared in the external subset which is not read
+            None if doctype.external_id.is_some() => return Ok(()),
+            None => return self.error_at(start, format!("Undeclared parameter entity: {}", name)),
+        };
+        if expanding.iter().any(|n| n == name) {
+            return self.error_at(start, format!("Recursive parameter entity reference: {}", name));
+        }
+
+        expanding.push(name.into());
+        let mut nested = DoctypeParser::new(&text);
+        let result = nested.internal_subset(doctype, expanding).and_then(|_| {
+            if nested.at_end() { Ok(()) } else { nested.unexpected("internal subset") }
+        });
+        expanding.pop();
+
+        result.or_else(|e| self.error_at(start, format!("In parameter entity {}: {}", name, e.msg)))
+    }
+
+    fn comment(&mut self) -> Result<()> {
+        match self.rest().find("--") {
+            Some(i) if self.rest()[i..].starts_with("-->") => {
+                self.pos += i + 3;
+                Ok(())
+            }
+            Some(i) => self.error_at(self.pos + i, "Unexpected token inside a comment: --"),
+            None => self.error("Unexpected end of comment"),
+        }
+    }
+
+    fn processing_instruction(&mut self) -> Result<()> {
+        let start = self.pos;
+        let name = self.name("processing instruction")?;
+        if name.eq_ignore_ascii_case("xml") {
+            return self.error_at(start, format!("Invalid processing instruction: <?{}", name));
+        }
+        if !self.eat("?>") {
+            self.require_whitespace("processing instruction")?;
+            match self.rest().find("?>") {
+                Some(i) => self.pos += i + 2,
+                None => return self.error("Unexpected end of processing instruction"),
+            }
+        }
+        Ok(())
+    }
+
+    fn element_decl(&mut self) -> Result<ElementDecl> {
+        const CONTEXT: &str = "element type declaration";
+        self.require_whitespace(CONTEXT)?;
+        let name = self.name(CONTEXT)?;
+        self.require_whitespace(CONTEXT)?;
+
+        let content = if self.eat("EMPTY") {
+            ContentSpec::Empty
+        } else if self.eat("ANY") {
+            ContentSpec::Any
+        } else if self.eat("(") {
+            self.skip_whitespace();
+            if self.eat("#PCDATA") {
+                self.mixed_content()?
+            } else {
+                ContentSpec::Children(self.content_group()?)
+            }
+        } else {
+            return self.unexpected(CONTEXT);
+        };
+
+        self.skip_whitespace();
+        self.expect(">", CONTEXT)?;
+        Ok(ElementDecl { name, content })
+    }
+
+    /// Reads the rest of a mixed content declaration after `(#PCDATA`.
+    fn mixed_content(&mut self) -> Result<ContentSpec> {
+        const CONTEXT: &str = "mixed content declaration";
+        let mut names = Vec::new();
+        loop {
+            self.skip_whitespace();
+            if self.eat(")") {
+                if self.eat("*") || names.is_empty() {
+                    return Ok(ContentSpec::Mixed(names));
+                } else {
+                    return self.unexpected(CONTEXT);
+                }
+            }
+            self.expect("|", CONTEXT)?;
+            self.skip_whitespace();
+            names.push(self.name(CONTEXT)?);
+        }
+    }
+
+    /// Reads a choice or a sequence after its opening parenthesis.
+    fn content_group(&mut self) -> Result<ContentParticle> {
+        const CONTEXT: &str = "content model";
+        let mut particles = vec![self.content_particle()?];
+        let mut separator = None;
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some(')') => {
+                    self.pos += 1;
+                    break;
+                }
+                Some(c @ '|') | Some(c @ ',') => {
+                    if separator.is_some() && separator != Some(c) {
+                        return self.error(format!("Unexpected separator inside {}: {}", CONTEXT, c));
+                    }
+                    separator = Some(c);
+                    self.pos += 1;
+                    self.skip_whitespace();
+                    particles.push(self.content_particle()?);
+                }
+                _ => return self.unexpected(CONTEXT),
+            }
+        }
+        let kind = match separator {
+            Some('|') => ParticleKind::Choice(particles),
+            _ => ParticleKind::Sequence(particles),
+        };
+        Ok(ContentParticle { kind, repetition: self.repetition() })
+    }
+
+    fn content_particle(&mut self) -> Result<ContentParticle> {
+        self.skip_whitespace();
+        if self.eat("(") {
+            self.skip_whitespace();
+            self.content_group()
+        } else {
+            let name = self.name("content model")?;
+            Ok(ContentParticle { kind: ParticleKind::Name(name), repetition: self.repetition() })
+        }
+    }
+
+    fn repetition(&mut self) -> Repetition {
+        if self.eat("?") {
+            Repetition::Optional
+        } else if self.eat("*") {
+            Repetition::ZeroOrMore
+        } else if self.eat("+") {
+            Repetition::OneOrMore
+        } else {
+            Repetition::Once
+        }
+    }
+
+    fn attlist_decl(&mut self) -> Result<AttListDecl> {
+        const CONTEXT: &str = "attribute-list declaration";
+        self.require_whitespace(CONTEXT)?;
+        let element = self.name(CONTEXT)?;
+        let mut attributes = Vec::new();
+        loop {
+            let had_whitespace = self.skip_whitespace();
+            if self.eat(">") {
+                return Ok(AttListDecl { element, attributes });
+            }
+            if !had_whitespace {
+                return self.unexpected(CONTEXT);
+            }
+            let name = self.name(CONTEXT)?;
+            self.require_whitespace(CONTEXT)?;
+            let attribute_type = self.attribute_type()?;
+            self.require_whitespace(CONTEXT)?;
+            let default = self.default_decl()?;
+            attributes.push(AttributeDef { name, attribute_type, default });
+        }
+    }
+
+    fn attribute_type(&mut self) -> Result<AttributeType> {
+        const CONTEXT: &str = "attribute type";
+        // longer keywords must be checked before their prefixes
+        Ok(if self.eat("CDATA") {
+            AttributeType::CData
+        } else if self.eat("IDREFS") {
+            AttributeType::IdRefs
+        } else if self.eat("IDREF") {
+            AttributeType::IdRef
+        } else if self.eat("ID") {
+            AttributeType::Id
+        } else if self.eat("ENTITIES") {
+            AttributeType::Entities
+        } else if self.eat("ENTITY") {
+            AttributeType::Entity
+        } else if self.eat("NMTOKENS") {
+            AttributeType::NmTokens
+        } else if self.eat("NMTOKEN") {
+            AttributeType::NmToken
+        } else if self.eat("NOTATION") {
+            self.require_whitespace(CONTEXT)?;
+            self.expect("(", CONTEXT)?;
+            AttributeType::Notation(self.enumeration(|this| this.name(CONTEXT))?)
+        } else if self.eat("(") {
+            AttributeType::Enumeration(self.enumeration(|this| this.nmtoken(CONTEXT))?)
+        } else {
+            return self.unexpected(CONTEXT);
+        })
+    }
+
+    /// Reads a `|`-separated list of values after the opening parenthesis.
+    fn enumeration<F>(&mut self, mut value: F) -> Result<Vec<String>>
+        where F: FnMut(&mut Self) -> Result<String>
+    {
+        let mut values = Vec::new();
+        loop {
+            self.skip_whitespace();
+            values.push(value(self)?);
+            self.skip_whitespace();
+            if self.eat(")") {
+                return Ok(values);
+            }
+            self.expect("|", "enumerated attribute type")?;
+        }
+    }
+
+    fn default_decl(&mut self) -> Result<DefaultDecl> {
+        Ok(if self.eat("#REQUIRED") {
+            DefaultDecl::Required
+        } else if self.eat("#IMPLIED") {
+            DefaultDecl::Implied
+        } else if self.eat("#FIXED") {
+            self.require_whitespace("attribute default value")?;
+            DefaultDecl::Fixed(self.attribute_value()?)
+        } else {
+            DefaultDecl::Default(self.attribute_value()?)
+        })
+    }
+
+    fn attribute_value(&mut self) -> Result<String> {
+        let value = self.literal("attribute default value", |this, c| match c {
+            '<' => this.error("Unexpected token inside attribute value: <"),
+            _ => Ok(()),
+        })?;
+        // references are kept unexpanded, but they still have to be well-formed
+        let start = self.pos - value.len() - 1;
+        check_references(value).map_err(|e| DoctypeError { offset: start + e.offset, msg: e.msg })?;
+        Ok(value.into())
+    }
+
+    fn entity_decl(&mut self) -> Result<EntityDecl> {
+        const CONTEXT: &str = "entity declaration";
+        self.require_whitespace(CONTEXT)?;
+        let parameter = self.eat("%");
+        if parameter {
+            self.require_whitespace(CONTEXT)?;
+        }
+        let name = self.name(CONTEXT)?;
+        self.require_whitespace(CONTEXT)?;
+
+        let value = match self.peek() {
+            Some('"') | Some('\'') => EntityValue::Internal(self.entity_value()?),
+            _ => {
+                let external_id = self.external_id(CONTEXT)?;
+                let mut notation = None;
+                if self.skip_whitespace() && self.eat("NDATA") {
+                    if parameter {
+                        return self.error("Parameter entities cannot be unparsed");
+                    }
+                    self.require_whitespace(CONTEXT)?;
+                    notation = Some(self.name(CONTEXT)?);
+                }
+                EntityValue::External { external_id, notation }
+            }
+        };
+
+        self.skip_whitespace();
+        self.expect(">", CONTEXT)?;
+        Ok(EntityDecl { name, parameter, value })
+    }
+
+    /// Reads an entity value literal and returns its replacement text.
+    fn entity_value(&mut self) -> Result<String> {
+        let value = self.literal("entity value", |this, c| match c {
+            '%' => this.error("Parameter entity references are not allowed inside markup declarations"),
+            _ => Ok(()),
+        })?;
+        let start = self.pos - value.len() - 1;
+        expand_character_references(value).map_err(|e| DoctypeError { offset: start + e.offset, msg: e.msg })
+    }
+
+    fn notation_decl(&mut self) -> Result<NotationDecl> {
+        const CONTEXT: &str = "notation declaration";
+        self.require_whitespace(CONTEXT)?;
+        let name = self.name(CONTEXT)?;
+        self.require_whitespace(CONTEXT)?;
+
+        let (public_id, system_id) = if self.eat("SYSTEM") {
+            self.require_whitespace(CONTEXT)?;
+            (None, Some(self.system_literal()?))
+        } else if self.eat("PUBLIC") {
+            self.require_whitespace(CONTEXT)?;
+            let public_id = self.pubid_literal()?;
+            let system_id = match (self.skip_whitespace(), self.peek()) {
+                (true, Some('"')) | (true, Some('\'')) => Some(self.system_literal()?),
+                _ => None,
+            };
+            (Some(public_id), system_id)
+        } else {
+            return self.unexpected(CONTEXT);
+        };
+
+        self.skip_whitespace();
+        self.expect(">", CONTEXT)?;
+        Ok(NotationDecl { name, public_id, system_id })
+    }
+}
+
+/// Parses a reference which starts right after `&` in `s`.
+///
+/// Returns the reference, either a character or an entity name, and its length including
+/// the terminating `;`.
+fn reference(s: &str) -> Result<(Reference, usize)> {
+    let end = match s.find(';') {
+        Some(end) => end,
+        None => return Err(DoctypeError { offset: 0, msg: "Unterminated reference".into() }),
+    };
+    let name = &s[..end];
+    let invalid = || DoctypeError { offset: 0, msg: format!("Invalid reference: &{};", name).into() };
+
+    let r = if let Some(hex) = name.strip_prefix("#x") {
+        let c = u32::from_str_radix(hex, 16).ok().and_then(char::from_u32);
+        Reference::Char(c.filter(|&c| c != '\0').ok_or_else(invalid)?)
+    } else if let Some(dec) = name.strip_prefix('#') {
+        let c = dec.parse().ok().and_then(char::from_u32);
+        Reference::Char(c.filter(|&c| c != '\0').ok_or_else(invalid)?)
+    } else {
+        let mut chars = name.chars();
+        match chars.next() {
+            Some(c) if is_name_start_char(c) && chars.all(is_name_char) => Reference::Entity,
+            _ => return Err(invalid()),
+        }
+    };
+    Ok((r, end + 1))
+}
+
+enum Reference {
+    Char(char),
+    Entity,
+}
+
+/// Checks that all references in the given text are well-formed.
+fn check_references(s: &str) -> Result<()> {
+    expand_character_references(s).map(|_| ())
+}
+
+/// Replaces character references with the characters they refer to, keeping
+/// entity references intact.
+fn expand_character_references(s: &str) -> Result<String> {
+    let mut result = String::with_capacity(s.len());
+    let mut pos = 0;
+    while let Some(i) = s[pos..].find('&') {
+        let start = pos + i;
+        result.push_str(&s[pos..start]);
+        let (r, len) = reference(&s[start + 1..])
+            .map_err(|e| DoctypeError { offset: start, msg: e.msg })?;
+        match r {
+            Reference::Char(c) => result.push(c),
+            Reference::Entity => result.push_str(&s[start..start + 1 + len]),
+        }
+        pos = start + 1 + len;
+    }
+    result.push_str(&s[pos..]);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use dtd::{
+        ExternalId, MarkupDeclaration, ContentSpec, ContentParticle, ParticleKind, Repetition,
+        AttributeType, DefaultDecl, EntityValue
+    };
+
+    use super::parse_doctype;
+
+    #[test]
+    fn doctype_without_subset() {
+        let d = parse_doctype(" html").unwrap();
+        assert_eq!(d.name, "html");
+        assert_eq!(d.external_id, None);
+        assert!(d.declarations.is_empty());
+
+        let d = parse_doctype(r#" html PUBLIC "-//W3C//DTD XHTML 1.0 Strict//EN"
+            'http://www.w3.org/TR/xhtml1/DTD/xhtml1-strict.dtd' "#).unwrap();
+        assert_eq!(d.external_id, Some(ExternalId::Public(
+            "-//W3C//DTD XHTML 1.0 Strict//EN".into(),
+            "http://www.w3.org/TR/xhtml1/DTD/xhtml1-strict.dtd".into()
+        )));
+    }
+
+    #[test]
+    fn element_declarations() {
+        let d = parse_doctype(r#" a [
+            <!ELEMENT a (b, (c | d)*, e?)+>
+            <!ELEMENT b EMPTY>
+            <!ELEMENT c ANY>
+            <!ELEMENT d (#PCDATA | b | c)*>
+            <!ELEMENT e ( #PCDATA )>
+        ]"#).unwrap();
+
+        fn name(n: &str, repetition: Repetition) -> ContentParticle {
+            ContentParticle { kind: ParticleKind::Name(n.into()), repetition }
+        }
+
+        assert_eq!(d.element("a").unwrap().content, ContentSpec::Children(ContentParticle {
+            kind: ParticleKind::Sequence(vec![
+                name("b", Repetition::Once),
+                ContentParticle {
+                    kind: ParticleKind::Choice(vec![name("c", Repetition::Once), name("d", Repetition::Once)]),
+                    repetition: Repetition::ZeroOrMore
+                },
+                name("e", Repetition::Optional),
+            ]),
+            repetition: Repetition::OneOrMore
+        }));
+        assert_eq!(d.element("b").unwrap().content, ContentSpec::Empty);
+        assert_eq!(d.element("c").unwrap().content, ContentSpec::Any);
+        assert_eq!(d.element("d").unwrap().content, ContentSpec::Mixed(vec!["b".into(), "c".into()]));
+        assert_eq!(d.element("e").unwrap().content, ContentSpec::Mixed(vec![]));
+    }
+
+    #[test]
+    fn attlist_and_entity_declarations() {
+        let d = parse_doctype(r#" a [
+            <!-- a 'comment' with "quotes" -->
+            <?pi data?>
+            <!ATTLIST a
+                id    ID              #REQUIRED
+                kind  (x | y-z | 1)   "x"
+                ref   IDREFS          #IMPLIED
+                fmt   NOTATION (gif)  #FIXED 'a &amp; b'>
+            <!ENTITY e "&#60;b&#x3e; &amp; more">
+            <!ENTITY % p "<!ENTITY inner 'from p'>">
+            %p;
+            <!ENTITY pic SYSTEM "pic.gif" NDATA gif>
+            <!NOTATION gif PUBLIC "image/gif">
+        ]"#).unwrap();
+
+        let attrs: Vec<_> = d.attributes("a").collect();
+        assert_eq!(attrs.len(), 4);
+        assert_eq!(attrs[0].attribute_type, AttributeType::Id);
+        assert_eq!(attrs[0].default, DefaultDecl::Required);
+        assert_eq!(attrs[1].attribute_type,
+                   AttributeType::Enumeration(vec!["x".into(), "y-z".into(), "1".into()]));
+        assert_eq!(attrs[1].default, DefaultDecl::Default("x".into()));
+        assert_eq!(attrs[2].attribute_type, AttributeType::IdRefs);
+        assert_eq!(attrs[3].attribute_type, AttributeType::Notation(vec!["gif".into()]));
+        assert_eq!(attrs[3].default, DefaultDecl::Fixed("a &amp; b".into()));
+
+        assert_eq!(d.entity("e").unwrap().value, EntityValue::Internal("<b> &amp; more".into()));
+        assert_eq!(d.entity("inner").unwrap().value, EntityValue::Internal("from p".into()));
+        assert!(d.entity("p").is_none());
+        assert!(d.parameter_entity("p").is_some());
+        match d.entity("pic").unwrap().value {
+            EntityValue::External { ref external_id, ref notation } => {
+                assert_eq!(*external_id, ExternalId::System("pic.gif".into()));
+                assert_eq!(notation.as_ref().map(|s| &s[..]), Some("gif"));
+            }
+            ref v => panic!("Unexpected entity value: {:?}", v)
+        }
+        assert_eq!(d.notation("gif").unwrap().public_id.as_ref().map(|s| &s[..]), Some("image/gif"));
+
+        let kinds: Vec<_> = d.declarations.iter().map(|d| match *d {
+            MarkupDeclaration::Element(_) => "element",
+            MarkupDeclaration::AttList(_) => "attlist",
+            MarkupDeclaration::Entity(_) => "entity",
+            MarkupDeclaration::Notation(_) => "notation",
+        }).collect();
+        assert_eq!(kinds, vec!["attlist", "entity", "entity", "entity", "entity", "notation"]);
+    }
+
+    #[test]
+    fn errors() {
+        macro_rules! check_error(
+            ($text:expr; $offset:expr, $msg:expr) => ({
+                let e = parse_doctype($text).unwrap_err();
+                assert_eq!(($offset, &*e.msg), ($offset, $msg));
+            })
+        );
+
+        check_error!("html"; 0, "Unexpected character inside DOCTYPE: h");
+        check_error!(" a [<!ELEMENT a (b | c, d)>]"; 21, "Unexpected separator inside content model: ,");
+        check_error!(" a [<!ELEMENT a (#PCDATA | b)>]"; 28, "Unexpected character inside mixed content declaration: >");
+        check_error!(" a [<!ENTITY e '%x;'>]"; 16, "Parameter entity references are not allowed inside markup declarations");
+        check_error!(" a [<!ENTITY e '&#0;'>]"; 16, "Invalid reference: &#0;");
+        check_error!(" a [<!-- a -- b -->]"; 11, "Unexpected token inside a comment: --");
+        check_error!(" a [%p;]"; 4, "Undeclared parameter entity: p");
+        check_error!(" a [<!ENTITY % p '&#37;p;'> %p;]"; 28,
+                     "In parameter entity p: Recursive parameter entity reference: p");
+        check_error!(" a [<!ATTLIST a b CDATA '<'>]"; 25, "Unexpected token inside attribute value: <");
+        check_error!(" a [ foo ]"; 5, "Unexpected character inside internal subset: f");
+    }
+}
@@ -11,6 +11,8 @@ use common::{Position, TextPosition};
 #[derive(Debug)]
 pub enum ErrorKind {
     Syntax(Cow<'static, str>),
+    EntityExpansionLimit(Cow<'static, str>),
+    Validation(Cow<'static, str>),
     Io(io::Error),
     Utf8(str::Utf8Error),
     UnexpectedEof,
@@ -46,10 +48,22 @@ impl Error {
             Utf8(ref reason) => error_description(reason),
             Io(ref io_error) => error_description(io_error),
             Syntax(ref msg) => msg.as_ref(),
+            EntityExpansionLimit(ref msg) => msg.as_ref(),
+            Validation(ref msg) => msg.as_ref(),
         }
     }
 
     pub fn kind(&self) -> &ErrorKind { &self.kind }
+
+    /// Checks whether this is an I/O error of the `WouldBlock` kind, which means that
+    /// the source has no data available at the moment and reading may be retried later.
+    #[inline]
+    pub fn is_would_block(&self) -> bool {
+        match self.kind {
+            ErrorKind::Io(ref e) => e.kind() == io::ErrorKind::WouldBlock,
+            _ => false
+        }
+    }
 }
 
 impl error::Error for Error {
@@ -66,6 +80,15 @@ impl<'a, P, M> From<(&'a P, M)> for Error where P: Position, M: Into<Cow<'static
     }
 }
 
+impl<'a, P> From<(&'a P, ErrorKind)> for Error where P: Position {
+    fn from(orig: (&'a P, ErrorKind)) -> Self {
+        Error{
+            pos: orig.0.position(),
+            kind: orig.1
+        }
+    }
+}
+
 impl From<util::CharReadError> for Error {
     fn from(e: util::CharReadError) -> Self {
         use util::CharReadError::*;
@@ -74,6 +97,7 @@ impl From<util::CharReadError> for Error {
             kind: match e {
                 UnexpectedEof => ErrorKind::UnexpectedEof,
                 Utf8(reason) => ErrorKind::Utf8(reason),
+                Encoding(msg) => ErrorKind::Syntax(format!("Invalid character encoding: {}", msg).into()),
                 Io(io_error) => ErrorKind::Io(io_error),
             }
         }
@@ -97,6 +121,8 @@ impl Clone for ErrorKind {
             Utf8(ref reason) => Utf8(reason.clone()),
             Io(ref io_error) => Io(io::Error::new(io_error.kind(), error_description(io_error))),
             Syntax(ref msg) => Syntax(msg.clone()),
+            EntityExpansionLimit(ref msg) => EntityExpansionLimit(msg.clone()),
+            Validation(ref msg) => Validation(msg.clone()),
         }
     }
 }
@@ -111,6 +137,10 @@ impl PartialEq for ErrorKind {
                 error_description(left) == error_description(right),
             (&Syntax(ref left), &Syntax(ref right)) =>
                 left == right,
+            (&EntityExpansionLimit(ref left), &EntityExpansionLimit(ref right)) =>
+                left == right,
+            (&Validation(ref left), &Validation(ref right)) =>
+                left == right,
 
             (_, _) => false,
         }
@@ -7,6 +7,7 @@ use name::OwnedName;
 use attribute::OwnedAttribute;
 use common::XmlVersion;
 use namespace::Namespace;
+use dtd::Doctype;
 
 /// An element of an XML input stream.
 ///
@@ -39,6 +40,13 @@ pub enum XmlEvent {
         standalone: Option<bool>
     },
 
+    /// Denotes a document type declaration.
+    ///
+    /// This event is emitted after `StartDocument` if the document contains a `<!DOCTYPE>`
+    /// declaration. It contains the declarations of the internal subset; the external
+    /// subset is not read.
+    Doctype(Doctype),
+
     /// Denotes to the end of the document stream.
     ///
     /// This event is always emitted after any other event (except `Error`). After it
@@ -67,7 +75,9 @@ pub enum XmlEvent {
 
         /// A list of attributes associated with the element.
         ///
-        /// Currently attributes are not checked for duplicates (TODO)
+        /// Attributes are guaranteed to be unique: the reader reports an error if the same
+        /// qualified name appears twice, or if two attributes have the same local name and
+        /// prefixes bound to the same namespace URI.
         attributes: Vec<OwnedAttribute>,
 
         /// Contents of the namespace mapping at this point of the document.
@@ -119,6 +129,11 @@ impl fmt::Debug for XmlEvent {
         match *self {
             XmlEvent::StartDocument { ref version, ref encoding, ref standalone } =>
                 write!(f, "StartDocument({}, {}, {:?})", version, *encoding, *standalone),
+            XmlEvent::Doctype(ref doctype) =>
+                write!(f, "Doctype({}{})", doctype.name, match doctype.external_id {
+                    Some(ref id) => format!(", {}", id),
+                    None => String::new()
+                }),
             XmlEvent::EndDocument =>
                 write!(f, "EndDocument"),
             XmlEvent::ProcessingInstruction { ref name, ref data } =>
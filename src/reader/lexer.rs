@@ -8,9 +8,9 @@ use std::io::Read;
 use std::result;
 use std::borrow::Cow;
 
-use common::{Position, TextPosition, is_whitespace_char, is_name_char};
+use common::{Encoding, Position, TextPosition, is_whitespace_char, is_name_char};
 use reader::Error;
-use util;
+use util::CharReader;
 
 /// `Token` represents a single lexeme of an XML document. These lexemes
 /// are used to perform actual parsing.
@@ -151,8 +151,6 @@ enum State {
     CommentStarted,
     /// Triggered on '<!D' up to '<!DOCTYPE'
     DoctypeStarted(DoctypeStartedSubstate),
-    /// Triggered after DoctypeStarted to handle sub elements
-    DoctypeFinishing(u8),
     /// Triggered on '<![' up to '<![CDATA'
     CDataStarted(CDataStartedSubstate),
     /// Triggered on '?'
@@ -182,6 +180,13 @@ enum CDataStartedSubstate {
     E, C, CD, CDA, CDAT, CDATA
 }
 
+/// Checks whether the lexer handles the given character specially in its default state,
+/// that is, whether it can be a part of a token other than `Character` and `Whitespace`.
+#[inline]
+fn is_special_char(c: char) -> bool {
+    matches!(c, '<' | '>' | '/' | '=' | '"' | '\'' | '?' | '-' | ']' | '&' | ';')
+}
+
 /// `Result` represents lexing result. It is either a token or an error message.
 pub type Result = result::Result<Option<Token>, Error>;
 
@@ -216,9 +221,14 @@ macro_rules! dispatch_on_enum_state(
 /// By default this flag is not set. Use `enable_errors` and `disable_errors` methods
 /// to toggle the behavior.
 pub struct Lexer {
+    reader: CharReader,
     pos: TextPosition,
     head_pos: TextPosition,
     char_queue: VecDeque<char>,
+    reparse_queue: VecDeque<char>,
+    unread_reparsed: bool,
+    input_len: usize,
+    after_cr: bool,
     st: State,
     skip_errors: bool,
     inside_comment: bool,
@@ -235,10 +245,20 @@ impl Position for Lexer {
 impl Lexer {
     /// Returns a new lexer with default state.
     pub fn new() -> Lexer {
+        Lexer::new_with_reader(CharReader::new())
+    }
+
+    /// Returns a new lexer which reads characters using the given reader.
+    pub fn new_with_reader(reader: CharReader) -> Lexer {
         Lexer {
+            reader,
             pos: TextPosition::new(),
             head_pos: TextPosition::new(),
             char_queue: VecDeque::with_capacity(4),  // TODO: check size
+            reparse_queue: VecDeque::new(),
+            unread_reparsed: false,
+            input_len: 0,
+            after_cr: false,
             st: State::Normal,
             skip_errors: false,
             inside_comment: false,
@@ -270,6 +290,63 @@ impl Lexer {
     #[inline]
     pub fn reset_eof_handled(&mut self) { self.eof_handled = false; }
 
+    /// Returns the encoding which is used to decode the stream.
+    #[inline]
+    pub fn encoding(&self) -> Encoding { self.reader.encoding() }
+
+    /// Switches decoding of the rest of the stream to the given encoding, if possible.
+    ///
+    /// See `CharReader::set_encoding()` for details.
+    #[inline]
+    pub fn set_encoding(&mut self, encoding: Encoding) { self.reader.set_encoding(encoding) }
+
+    /// Returns the length of the document text read from the stream so far, in bytes of its
+    /// UTF-8 representation, not including text given to `reparse()`.
+    #[inline]
+    pub fn input_len(&self) -> usize { self.input_len }
+
+    /// Makes the lexer read the given text before continuing with the stream.
+    ///
+    /// This is used to parse replacement text of entities which may contain markup. Positions
+    /// of tokens read from this text are not advanced, so they point right after the reference
+    /// which was replaced.
+    pub fn reparse(&mut self, markup: &str) {
+        self.reparse_queue.extend(markup.chars());
+    }
+
+    /// Reads the rest of a run of characters which would be returned as `Character` and
+    /// `Whitespace` tokens, appending them to `target` while `accept` returns `true` for them.
+    ///
+    /// This allows consuming plain text and names in bulk after the first token of a run has
+    /// been handled. The run stops before markup, references and line endings, which are left
+    /// for `next_token()`, and the stream is not read past the character which ends the run.
+    pub fn read_run<B: Read, F: Fn(char) -> bool>(&mut self, b: &mut B, target: &mut String,
+                                                  accept: F) -> result::Result<(), Error> {
+        // Queued and reparsed characters, as well as line endings, are handled by `next_token()`
+        match self.st {
+            State::Normal if !self.inside_token && !self.after_cr && !self.eof_handled &&
+                             self.char_queue.is_empty() && self.reparse_queue.is_empty() => {}
+            _ => return Ok(())
+        }
+
+        let start = target.len();
+        let head_pos = &mut self.head_pos;
+        let result = self.reader.read_while_from(b, target, |c| {
+            if is_special_char(c) || c == '\r' || !accept(c) {
+                return false;
+            }
+            if c == '\n' {
+                head_pos.new_line();
+            } else {
+                head_pos.advance(1);
+            }
+            true
+        });
+        self.input_len += target.len() - start;
+        result?;
+        Ok(())
+    }
+
     /// Tries to read the next token from the buffer.
     ///
     /// It is possible to pass different instaces of `BufReader` each time
@@ -301,12 +378,47 @@ impl Lexer {
             }
         }
 
+        // Then read the text given to `reparse()`, without advancing the position
+        while let Some(c) = self.reparse_queue.pop_front() {
+            let res = self.dispatch_char(c);
+            if !self.char_queue.is_empty() {
+                self.unread_reparsed = true;
+            }
+            if let Some(t) = res? {
+                self.inside_token = false;
+                return Ok(Some(t));
+            }
+            // Unread chars go before the rest of the text
+            while let Some(c) = self.char_queue.pop_front() {
+                if let Some(t) = self.read_next_token(c)? {
+                    self.inside_token = false;
+                    return Ok(Some(t));
+                }
+            }
+        }
+
         loop {
-            // TODO: this should handle multiple encodings
-            let c = match try!(util::next_char_from(b)) {
+            let c = match self.reader.next_char_from(b)? {
                 Some(c) => c,   // got next char
                 None => break,  // nothing to read left
             };
+            self.input_len += c.len_utf8();
+
+            // Line endings are normalized to `\n`, see XML 1.0, section 2.11
+            let c = match c {
+                '\r' => {
+                    self.after_cr = true;
+                    '\n'
+                }
+                '\n' if self.after_cr => {
+                    self.after_cr = false;
+                    continue;
+                }
+                _ => {
+                    self.after_cr = false;
+                    c
+                }
+            };
 
             match try!(self.read_next_token(c)) {
                 Some(t) => {
@@ -325,8 +437,7 @@ impl Lexer {
         match self.st {
             State::TagStarted | State::CommentOrCDataOrDoctypeStarted |
             State::CommentStarted | State::CDataStarted(_)| State::DoctypeStarted(_) |
-            State::CommentClosing(ClosingSubstate::Second) |
-            State::DoctypeFinishing(_) =>
+            State::CommentClosing(ClosingSubstate::Second) =>
                 Err(self.error("Unexpected end of stream")),
             State::ProcessingInstructionClosing =>
                 Ok(Some(Token::Character('?'))),
@@ -352,7 +463,10 @@ impl Lexer {
     fn read_next_token(&mut self, c: char) -> Result {
         let res = self.dispatch_char(c);
         if self.char_queue.is_empty() {
-            if c == '\n' {
+            if self.unread_reparsed {
+                // The char comes from reparsed text, so the position must not be advanced
+                self.unread_reparsed = false;
+            } else if c == '\n' {
                 self.head_pos.new_line();
             } else {
                 self.head_pos.advance(1);
@@ -369,7 +483,6 @@ impl Lexer {
             State::CommentStarted                 => self.comment_started(c),
             State::CDataStarted(s)                => self.cdata_started(c, s),
             State::DoctypeStarted(s)              => self.doctype_started(c, s),
-            State::DoctypeFinishing(d)            => self.doctype_finishing(c, d),
             State::ProcessingInstructionClosing   => self.processing_instruction_closing(c),
             State::EmptyTagClosing                => self.empty_element_closing(c),
             State::CommentClosing(s)              => self.comment_closing(c, s),
@@ -475,20 +588,10 @@ impl Lexer {
             DOC    ; 'T' ; DOCT   ; "<!DOC",
             DOCT   ; 'Y' ; DOCTY  ; "<!DOCT",
             DOCTY  ; 'P' ; DOCTYP ; "<!DOCTY";
-            DOCTYP ; 'E' ; "<!DOCTYP" ; self.move_to_with(State::DoctypeFinishing(1), Token::DoctypeStart)
+            DOCTYP ; 'E' ; "<!DOCTYP" ; self.move_to_with(State::Normal, Token::DoctypeStart)
         )
     }
 
-    /// State used while awaiting the closing bracket for the <!DOCTYPE tag
-    fn doctype_finishing(&mut self, c: char, d: u8) -> Result {
-        match c {
-            '<' => self.move_to(State::DoctypeFinishing(d + 1)),
-            '>' if d == 1 => self.move_to_with(State::Normal, Token::TagEnd),
-            '>' => self.move_to(State::DoctypeFinishing(d - 1)),
-            _ => Ok(None),
-        }
-    }
-
     /// Encountered '?'
     fn processing_instruction_closing(&mut self, c: char) -> Result {
         match c {
@@ -542,7 +645,7 @@ impl Lexer {
 
 #[cfg(test)]
 mod tests {
-    use common::{Position};
+    use common::{Position, TextPosition};
     use std::io::{BufReader, Cursor};
 
     use super::{Lexer, Token};
@@ -699,10 +802,18 @@ mod tests {
             Token::Character('a')
             Token::TagEnd
             Token::DoctypeStart
+            Token::Whitespace(' ')
+            Token::Character('a')
+            Token::Character('b')
+            Token::Whitespace(' ')
+            Token::Character('x')
+            Token::Character('x')
+            Token::Whitespace(' ')
+            Token::Character('z')
             Token::TagEnd
             Token::Whitespace(' ')
         );
-        assert_none!(for lex and buf)
+        assert_none!(for lex and buf);
     }
 
     #[test]
@@ -710,15 +821,81 @@ mod tests {
         let (mut lex, mut buf) = make_lex_and_buf(
             r#"<a><!DOCTYPE ab[<!ELEMENT ba> ]> "#
         );
+        lex.disable_errors();
         assert_oks!(for lex and buf ;
             Token::OpeningTagStart
             Token::Character('a')
             Token::TagEnd
             Token::DoctypeStart
+            Token::Whitespace(' ')
+            Token::Character('a')
+            Token::Character('b')
+            Token::Character('[')
+            Token::Chunk("<!")
+            Token::Character('E')
+            Token::Character('L')
+            Token::Character('E')
+            Token::Character('M')
+            Token::Character('E')
+            Token::Character('N')
+            Token::Character('T')
+            Token::Whitespace(' ')
+            Token::Character('b')
+            Token::Character('a')
             Token::TagEnd
             Token::Whitespace(' ')
+            Token::Character(']')
+            Token::TagEnd
+            Token::Whitespace(' ')
+        );
+        assert_none!(for lex and buf);
+    }
+
+    #[test]
+    fn line_endings_test() {
+        let (mut lex, mut buf) = make_lex_and_buf(
+            "a\r\nb\rc\n\r\r\nd"
         );
-        assert_none!(for lex and buf)
+
+        assert_oks!(for lex and buf ;
+            Token::Character('a')
+            Token::Whitespace('\n')
+            Token::Character('b')
+            Token::Whitespace('\n')
+            Token::Character('c')
+            Token::Whitespace('\n')
+            Token::Whitespace('\n')
+            Token::Whitespace('\n')
+            Token::Character('d')
+        );
+        assert_eq!(lex.position(), TextPosition { row: 5, column: 0 });
+        assert_none!(for lex and buf);
+    }
+
+    #[test]
+    fn reparse_test() {
+        let (mut lex, mut buf) = make_lex_and_buf(
+            r#"a;b"#
+        );
+
+        assert_oks!(for lex and buf ;
+            Token::Character('a')
+            Token::ReferenceEnd
+        );
+        lex.reparse("<x-y/>");
+        assert_oks!(for lex and buf ;
+            Token::OpeningTagStart
+            Token::Character('x')
+            Token::Character('-')
+            Token::Character('y')
+            Token::EmptyTagEnd
+        );
+        assert_eq!(lex.position(), TextPosition { row: 0, column: 2 });
+        assert_oks!(for lex and buf ;
+            Token::Character('b')
+        );
+        assert_eq!(lex.position(), TextPosition { row: 0, column: 2 });
+        assert_none!(for lex and buf);
     }
 
     #[test]
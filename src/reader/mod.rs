@@ -10,13 +10,22 @@ use common::{Position, TextPosition};
 
 pub use self::config::ParserConfig;
 pub use self::events::XmlEvent;
+pub use self::borrowed::BorrowedReader;
+pub use self::push::PushReader;
+#[cfg(feature = "async")]
+pub use self::async_reader::AsyncEventReader;
 
 use self::parser::PullParser;
 
 mod lexer;
+mod doctype;
 mod parser;
 mod config;
 mod events;
+pub mod borrowed;
+pub mod push;
+#[cfg(feature = "async")]
+pub mod async_reader;
 
 mod error;
 pub use self::error::{Error, ErrorKind};
@@ -47,6 +56,10 @@ impl<R: Read> EventReader<R> {
     ///
     /// If returned event is `XmlEvent::Error` or `XmlEvent::EndDocument`, then
     /// further calls to this method will return this event again.
+    ///
+    /// The only exception are I/O errors of the `WouldBlock` kind, which non-blocking streams
+    /// return when they have no data available, as reported by `Error::is_would_block()`:
+    /// in this case parsing is resumed by the next call to this method.
     #[inline]
     pub fn next(&mut self) -> Result<XmlEvent> {
         self.parser.next(&mut self.source)
@@ -103,6 +116,14 @@ impl<R: Read> Events<R> {
 
 }
 
+impl<R: Read> Position for Events<R> {
+    /// Returns the position of the last event produced by the iterator.
+    #[inline]
+    fn position(&self) -> TextPosition {
+        self.reader.position()
+    }
+}
+
 impl<R: Read> Iterator for Events<R> {
     type Item = Result<XmlEvent>;
 
@@ -1,3 +1,5 @@
+use common::is_whitespace_char;
+
 use reader::events::XmlEvent;
 use reader::lexer::Token;
 
@@ -19,13 +21,13 @@ impl PullParser {
 
             Token::Whitespace(_) => {
                 t.push_to_string(&mut self.buf);
-                None
+                self.append_run_continue(is_whitespace_char)
             }
 
             _ => {
                 self.inside_whitespace = false;
                 t.push_to_string(&mut self.buf);
-                None
+                self.append_run_continue(|_| true)
             }
         }
     }
@@ -20,11 +20,13 @@ impl PullParser {
                 self.into_state_emit(State::OutsideTag, Ok(XmlEvent::Comment(data)))
             }
 
-            _ if self.config.ignore_comments => None,  // Do not modify buffer if ignoring the comment
+            _ if self.config.ignore_comments => {  // Do not modify buffer if ignoring the comment
+                self.skip_run_continue()
+            }
 
             _ => {
                 t.push_to_string(&mut self.buf);
-                None
+                self.append_run_continue(|_| true)
             }
         }
     }
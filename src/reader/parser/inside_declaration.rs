@@ -6,7 +6,7 @@ use reader::lexer::Token;
 
 use super::{
     Result, PullParser, State, DeclarationSubstate, QualifiedNameTarget,
-    DEFAULT_VERSION, DEFAULT_ENCODING
+    DEFAULT_VERSION
 };
 
 impl PullParser {
@@ -25,7 +25,7 @@ impl PullParser {
             let standalone = this.data.take_standalone();
             this.into_state_emit(State::OutsideTag, Ok(XmlEvent::StartDocument {
                 version: version.unwrap_or(DEFAULT_VERSION),
-                encoding: encoding.unwrap_or(DEFAULT_ENCODING.into()),
+                encoding: encoding.unwrap_or_else(|| this.lexer.encoding().to_string()),
                 standalone: standalone
             }))
         }
@@ -95,6 +95,14 @@ impl PullParser {
             },
 
             DeclarationSubstate::InsideEncodingValue => self.read_attribute_value(t, |this, value| {
+                if this.config.override_encoding.is_none() {
+                    match value.parse() {
+                        Ok(encoding) => this.lexer.set_encoding(encoding),
+                        Err(_) if this.config.reject_unsupported_encoding =>
+                            return Some(self_error!(this; "Unsupported encoding: {}", value)),
+                        Err(_) => {}  // keep decoding with the detected encoding
+                    }
+                }
                 this.data.encoding = Some(value);
                 this.into_state_continue(State::InsideDeclaration(DeclarationSubstate::BeforeStandaloneDecl))
             }),
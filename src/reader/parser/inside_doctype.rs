@@ -1,16 +1,96 @@
+use common::Position;
+
+use reader::events::XmlEvent;
 use reader::lexer::Token;
+use reader::doctype;
 
-use super::{Result, PullParser, State};
+use super::{Result, PullParser, State, DoctypeSubstate, QuoteToken};
 
 impl PullParser {
-    pub fn inside_doctype(&mut self, t: Token) -> Option<Result> {
-        match t {
-            Token::TagEnd => {
-                self.lexer.enable_errors();
-                self.into_state_continue(State::OutsideTag)
+    pub fn inside_doctype(&mut self, t: Token, s: DoctypeSubstate) -> Option<Result> {
+        match s {
+            // Everything inside literals is copied verbatim until the closing quote
+            DoctypeSubstate::Outside | DoctypeSubstate::InsideSubset if self.data.quote.is_some() => {
+                match t {
+                    Token::DoubleQuote | Token::SingleQuote
+                        if self.data.quote == Some(QuoteToken::from_token(&t)) => self.data.quote = None,
+                    _ => {}
+                }
+                t.push_to_string(&mut self.buf);
+                None
+            }
+
+            DoctypeSubstate::Outside => match t {
+                Token::TagEnd => self.emit_doctype(),
+                Token::Character('[') => {
+                    t.push_to_string(&mut self.buf);
+                    self.into_state_continue(State::InsideDoctype(DoctypeSubstate::InsideSubset))
+                }
+                _ => self.push_doctype_token(t)
+            },
+
+            DoctypeSubstate::InsideSubset => match t {
+                Token::Character(']') => {
+                    t.push_to_string(&mut self.buf);
+                    self.into_state_continue(State::InsideDoctype(DoctypeSubstate::Outside))
+                }
+                Token::CommentStart => {
+                    t.push_to_string(&mut self.buf);
+                    self.into_state_continue(State::InsideDoctype(DoctypeSubstate::InsideComment))
+                }
+                Token::ProcessingInstructionStart => {
+                    t.push_to_string(&mut self.buf);
+                    self.into_state_continue(State::InsideDoctype(DoctypeSubstate::InsideProcessingInstruction))
+                }
+                Token::CDataEnd => Some(self_error!(self; "Unexpected token inside DOCTYPE: {}", t)),
+                _ => self.push_doctype_token(t)
+            },
+
+            DoctypeSubstate::InsideComment => {
+                t.push_to_string(&mut self.buf);
+                if t == Token::CommentEnd {
+                    self.into_state_continue(State::InsideDoctype(DoctypeSubstate::InsideSubset))
+                } else {
+                    None
+                }
+            }
+
+            DoctypeSubstate::InsideProcessingInstruction => {
+                t.push_to_string(&mut self.buf);
+                if t == Token::ProcessingInstructionEnd {
+                    self.into_state_continue(State::InsideDoctype(DoctypeSubstate::InsideSubset))
+                } else {
+                    None
+                }
             }
+        }
+    }
 
-            _ => None
+    fn push_doctype_token(&mut self, t: Token) -> Option<Result> {
+        if let Token::DoubleQuote | Token::SingleQuote = t {
+            self.data.quote = Some(QuoteToken::from_token(&t));
+        }
+        t.push_to_string(&mut self.buf);
+        None
+    }
+
+    fn emit_doctype(&mut self) -> Option<Result> {
+        self.lexer.enable_errors();
+        let text = self.take_buf();
+        match doctype::parse_doctype(&text) {
+            Ok(doctype) => {
+                self.doctype = Some(doctype.clone());
+                self.into_state_emit(State::OutsideTag, Ok(XmlEvent::Doctype(doctype)))
+            }
+            Err(e) => {
+                // The buffer contains everything after the `<!DOCTYPE` token
+                let mut pos = self.data.doctype_pos.position();
+                pos.advance("<!DOCTYPE".len() as u8);
+                for c in text[..e.offset].chars() {
+                    if c == '\n' { pos.new_line(); } else { pos.advance(1); }
+                }
+                Some(Err((&pos, e.msg).into()))
+            }
         }
     }
 }
@@ -1,5 +1,6 @@
 use common::is_name_start_char;
 use attribute::OwnedAttribute;
+use name::OwnedName;
 use namespace;
 
 use reader::lexer::Token;
@@ -74,8 +75,9 @@ impl PullParser {
                                 Some(self_error!(this; "Prefix '{}' cannot be rebound to another value", namespace::NS_XML_PREFIX))
                             } else if value.is_empty() {
                                 Some(self_error!(this; "Cannot undefine prefix '{}'", ln))
+                            } else if !this.nst.put(name.local_name.clone(), value) {
+                                Some(self_error!(this; "Attribute '{}' is redefined", name))
                             } else {
-                                this.nst.put(name.local_name.clone(), value);
                                 this.into_state_continue(State::InsideOpeningTag(OpeningTagSubstate::InsideTag))
                             }
                         }
@@ -85,14 +87,14 @@ impl PullParser {
                             match &value[..] {
                                 namespace::NS_XMLNS_PREFIX | namespace::NS_XML_PREFIX =>
                                     Some(self_error!(this; "Namespace '{}' cannot be default", value)),
-                                _ => {
-                                    this.nst.put(namespace::NS_NO_PREFIX, value.clone());
-                                    this.into_state_continue(State::InsideOpeningTag(OpeningTagSubstate::InsideTag))
-                                }
+                                _ if !this.nst.put(namespace::NS_NO_PREFIX, value.clone()) =>
+                                    Some(self_error!(this; "Attribute '{}' is redefined", name)),
+                                _ => this.into_state_continue(State::InsideOpeningTag(OpeningTagSubstate::InsideTag))
                             },
 
                         // regular attribute
                         _ => {
+                            let value = this.tokenize_attribute_value(&name, value);
                             this.data.attributes.push(OwnedAttribute {
                                 name: name.clone(),
                                 value: value
@@ -105,4 +107,22 @@ impl PullParser {
         }
     }
 
+    /// Applies additional normalization to values of attributes which are declared in the DTD
+    /// with a type other than `CDATA`: leading and trailing spaces are removed and sequences
+    /// of spaces are replaced with a single space.
+    fn tokenize_attribute_value(&self, name: &OwnedName, value: String) -> String {
+        let is_tokenized = match (self.doctype.as_ref(), self.data.element_name.as_ref()) {
+            (Some(doctype), Some(element)) =>
+                match doctype.attribute(&element.borrow().to_repr(), &name.borrow().to_repr()) {
+                    Some(def) => !def.attribute_type.is_cdata(),
+                    None => false
+                },
+            _ => false
+        };
+        if is_tokenized {
+            value.split(' ').filter(|s| !s.is_empty()).collect::<Vec<_>>().join(" ")
+        } else {
+            value
+        }
+    }
 }
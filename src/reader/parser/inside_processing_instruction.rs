@@ -12,7 +12,10 @@ impl PullParser {
         match s {
             ProcessingInstructionSubstate::PIInsideName => match t {
                 Token::Character(c) if !self.buf_has_data() && is_name_start_char(c) ||
-                                 self.buf_has_data() && is_name_char(c) => self.append_char_continue(c),
+                                 self.buf_has_data() && is_name_char(c) => {
+                    self.buf.push(c);
+                    self.append_run_continue(is_name_char)
+                }
 
                 Token::ProcessingInstructionEnd => {
                     // self.buf contains PI name
@@ -87,7 +90,7 @@ impl PullParser {
                 // Any other token should be treated as plain characters
                 _ => {
                     t.push_to_string(&mut self.buf);
-                    None
+                    self.append_run_continue(|_| true)
                 }
             },
         }
@@ -1,8 +1,12 @@
 use std::char;
+use std::borrow::Cow;
+use std::result;
 
-use common::{is_name_start_char, is_name_char, is_whitespace_str};
+use common::{is_name_start_char, is_name_char, is_whitespace_char, is_whitespace_str};
+use dtd::EntityValue;
 
 use reader::lexer::Token;
+use reader::{Error, ErrorKind};
 
 use super::{Result, PullParser, State};
 
@@ -16,60 +20,25 @@ impl PullParser {
             }
 
             Token::ReferenceEnd => {
-                // TODO: check for unicode correctness
                 let name = self.data.take_ref_data();
-                let name_len = name.len();  // compute once
-                let c = match &name[..] {
-                    "lt"   => Ok('<'.to_string()),
-                    "gt"   => Ok('>'.to_string()),
-                    "amp"  => Ok('&'.to_string()),
-                    "apos" => Ok('\''.to_string()),
-                    "quot" => Ok('"'.to_string()),
-                    ""     => Err(self_error!(self; "Encountered empty entity")),
-                    _ if name_len > 2 && name.starts_with("#x") => {
-                        let num_str = &name[2..name_len];
-                        if num_str == "0" {
-                            Err(self_error!(self; "Null character entity is not allowed"))
-                        } else {
-                            if self.config.replace_unknown_entity_references {
-                                match u32::from_str_radix(num_str, 16).ok().map(|i| char::from_u32(i).unwrap_or('\u{fffd}')) {
-                                    Some(c) => Ok(c.to_string()),
-                                    None    => Err(self_error!(self; "Invalid hexadecimal character number in an entity: {}", name))
-                                }
-                            } else {
-                                match u32::from_str_radix(num_str, 16).ok().and_then(char::from_u32) {
-                                    Some(c) => Ok(c.to_string()),
-                                    None    => Err(self_error!(self; "Invalid hexadecimal character number in an entity: {}", name))
-                                }
-                            }
-                        }
-                    }
-                    _ if name_len > 1 && name.starts_with('#') => {
-                        let num_str = &name[1..name_len];
-                        if num_str == "0" {
-                            Err(self_error!(self; "Null character entity is not allowed"))
-                        } else {
-                            if self.config.replace_unknown_entity_references {
-                                match u32::from_str_radix(num_str, 10).ok().map(|i| char::from_u32(i).unwrap_or('\u{fffd}')) {
-                                    Some(c) => Ok(c.to_string()),
-                                    None    => Err(self_error!(self; "Invalid decimal character number in an entity: {}", name))
-                                }
-                            }
-                            else {
-                                match u32::from_str_radix(num_str, 10).ok().and_then(char::from_u32) {
-                                    Some(c) => Ok(c.to_string()),
-                                    None    => Err(self_error!(self; "Invalid decimal character number in an entity: {}", name))
-                                }
-                            }
-                        }
-                    },
-                    _ => {
-                        if let Some(v) = self.config.extra_entities.get(&name) {
-                            Ok(v.clone())
-                        } else {
-                            Err(self_error!(self; "Unexpected entity: {}", name))
+                let c = if self.dtd_entity(&name).is_some() {
+                    let in_attribute = prev_st != State::OutsideTag;
+                    let mut len = 0;
+                    let text = self.expand_entity(&name, in_attribute, &mut Vec::new(), &mut len);
+                    self.expanded_len += len;
+                    match text {
+                        // The replacement text may contain markup, so it is fed back to the lexer
+                        Ok(text) if !in_attribute => {
+                            self.lexer.reparse(&text);
+                            Ok(String::new())
                         }
+                        // Whitespace in the replacement text is normalized like literal
+                        // whitespace in the attribute value
+                        Ok(text) => Ok(text.replace(is_whitespace_char, " ")),
+                        text => text
                     }
+                } else {
+                    self.resolve_reference(&name).map_err(|e| (&self.lexer, e).into())
                 };
                 match c {
                     Ok(c) => {
@@ -79,11 +48,207 @@ impl PullParser {
                         }
                         self.into_state_continue(prev_st)
                     }
-                    Err(e) => Some(e)
+                    Err(e) => Some(Err(e))
                 }
             }
 
             _ => Some(self_error!(self; "Unexpected token inside an entity: {}", t))
         }
     }
+
+    /// Resolves a character reference or a reference to a predefined or an extra entity.
+    pub fn resolve_reference(&self, name: &str) -> result::Result<String, Cow<'static, str>> {
+        // TODO: check for unicode correctness
+        let name_len = name.len();  // compute once
+        match name {
+            "lt"   => Ok('<'.to_string()),
+            "gt"   => Ok('>'.to_string()),
+            "amp"  => Ok('&'.to_string()),
+            "apos" => Ok('\''.to_string()),
+            "quot" => Ok('"'.to_string()),
+            ""     => Err("Encountered empty entity".into()),
+            _ if name_len > 2 && name.starts_with("#x") => {
+                let num_str = &name[2..name_len];
+                if num_str == "0" {
+                    Err("Null character entity is not allowed".into())
+                } else {
+                    if self.config.replace_unknown_entity_references {
+                        match u32::from_str_radix(num_str, 16).ok().map(|i| char::from_u32(i).unwrap_or('\u{fffd}')) {
+                            Some(c) => Ok(c.to_string()),
+                            None    => Err(format!("Invalid hexadecimal character number in an entity: {}", name).into())
+                        }
+                    } else {
+                        match u32::from_str_radix(num_str, 16).ok().and_then(char::from_u32) {
+                            Some(c) => Ok(c.to_string()),
+                            None    => Err(format!("Invalid hexadecimal character number in an entity: {}", name).into())
+                        }
+                    }
+                }
+            }
+            _ if name_len > 1 && name.starts_with('#') => {
+                let num_str = &name[1..name_len];
+                if num_str == "0" {
+                    Err("Null character entity is not allowed".into())
+                } else {
+                    if self.config.replace_unknown_entity_references {
+                        match u32::from_str_radix(num_str, 10).ok().map(|i| char::from_u32(i).unwrap_or('\u{fffd}')) {
+                            Some(c) => Ok(c.to_string()),
+                            None    => Err(format!("Invalid decimal character number in an entity: {}", name).into())
+                        }
+                    }
+                    else {
+                        match u32::from_str_radix(num_str, 10).ok().and_then(char::from_u32) {
+                            Some(c) => Ok(c.to_string()),
+                            None    => Err(format!("Invalid decimal character number in an entity: {}", name).into())
+                        }
+                    }
+                }
+            },
+            _ => {
+                if let Some(v) = self.config.extra_entities.get(name) {
+                    Ok(v.clone())
+                } else {
+                    Err(format!("Unexpected entity: {}", name).into())
+                }
+            }
+        }
+    }
+
+    /// Looks up a general entity declared in the DTD.
+    ///
+    /// Predefined entities and entities from `ParserConfig::extra_entities` take precedence
+    /// over DTD declarations, so `None` is returned for them.
+    fn dtd_entity(&self, name: &str) -> Option<&EntityValue> {
+        match name {
+            "lt" | "gt" | "amp" | "apos" | "quot" => None,
+            _ if name.starts_with('#') || self.config.extra_entities.contains_key(name) => None,
+            _ => self.doctype.as_ref().and_then(|d| d.entity(name)).map(|e| &e.value)
+        }
+    }
+
+    /// Returns the replacement text of an entity declared in the DTD, with references to
+    /// other DTD entities expanded recursively.
+    ///
+    /// Inside attribute values all references are replaced. Elsewhere character references
+    /// and references to predefined and extra entities are kept as they are, because the
+    /// replacement text is parsed again as markup.
+    ///
+    /// `expanding` contains names of entities which are being expanded and is used to
+    /// detect recursive references. `len` accumulates the length of the produced text and
+    /// is checked against the expansion limits from the config.
+    fn expand_entity(&self, name: &str, in_attribute: bool, expanding: &mut Vec<String>,
+                     len: &mut usize) -> result::Result<String, Error> {
+        if expanding.iter().any(|n| n == name) {
+            return Err((&self.lexer, format!("Recursive entity reference: {}", name)).into());
+        }
+        if expanding.len() >= self.config.max_entity_expansion_depth {
+            return Err(self.expansion_limit_error(
+                format!("Entity expansion is nested too deeply in entity {}", name)));
+        }
+        let text = match self.dtd_entity(name) {
+            Some(EntityValue::Internal(text)) => text,
+            Some(EntityValue::External { .. }) => return Err((&self.lexer,
+                format!("References to external entities are not supported: {}", name)).into()),
+            None => return Err((&self.lexer, format!("Unexpected entity: {}", name)).into())
+        };
+        if in_attribute && text.contains('<') {
+            return Err((&self.lexer,
+                format!("Entity {} contains < which is not allowed inside attribute values", name)).into());
+        }
+
+        expanding.push(name.into());
+        let mut result = String::with_capacity(text.len());
+        let mut rest = &text[..];
+        while let Some(start) = rest.find('&') {
+            self.append_expanded(&mut result, &rest[..start], len)?;
+            let end = match rest[start..].find(';') {
+                Some(end) => start + end,
+                None => return Err((&self.lexer, format!("Unterminated reference in entity {}", name)).into())
+            };
+            let reference = &rest[start + 1..end];
+            if self.dtd_entity(reference).is_some() {
+                result.push_str(&self.expand_entity(reference, in_attribute, expanding, len)?);
+            } else if in_attribute {
+                let value = self.resolve_reference(reference).map_err(|e| Error::from((&self.lexer, e)))?;
+                self.append_expanded(&mut result, &value, len)?;
+            } else {
+                self.append_expanded(&mut result, &rest[start..end + 1], len)?;
+            }
+            rest = &rest[end + 1..];
+        }
+        self.append_expanded(&mut result, rest, len)?;
+        expanding.pop();
+        if !in_attribute {
+            check_balanced(&result).map_err(|msg| Error::from((&self.lexer, format!("Entity {} {}", name, msg))))?;
+        }
+        Ok(result)
+    }
+
+    /// Appends a piece of expanded text to `result`, checking that the total length of
+    /// expanded text stays within the limits.
+    fn append_expanded(&self, result: &mut String, s: &str, len: &mut usize) -> result::Result<(), Error> {
+        *len += s.len();
+        let total = self.expanded_len + *len;
+        if total > self.config.max_entity_expansion_length {
+            return Err(self.expansion_limit_error("Entity expansion exceeds the maximum length"));
+        }
+        if total > self.lexer.input_len().saturating_mul(self.config.max_entity_expansion_ratio) {
+            return Err(self.expansion_limit_error(
+                "Entity expansion exceeds the maximum ratio to the document length"));
+        }
+        result.push_str(s);
+        Ok(())
+    }
+
+    #[inline]
+    fn expansion_limit_error<M: Into<Cow<'static, str>>>(&self, msg: M) -> Error {
+        (&self.data.ref_pos, ErrorKind::EntityExpansionLimit(msg.into())).into()
+    }
+}
+
+/// Checks that every element started in the replacement text of an entity is also ended in it
+/// and vice versa, and that its markup is complete, as required by XML 1.0, [section 4.3.2][1].
+///
+/// [1]: http://www.w3.org/TR/2008/REC-xml-20081126/#wf-entities
+fn check_balanced(text: &str) -> result::Result<(), String> {
+    let mut open = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start..];
+        let end = if rest.starts_with("<!--") {
+            rest.find("-->").map(|i| i + 3)
+        } else if rest.starts_with("<![CDATA[") {
+            rest.find("]]>").map(|i| i + 3)
+        } else if rest.starts_with("<?") {
+            rest.find("?>").map(|i| i + 2)
+        } else {
+            // '>' may appear in attribute values
+            let mut quote = None;
+            rest.char_indices().find(|&(_, c)| match quote {
+                Some(q) if c == q => { quote = None; false }
+                Some(_) => false,
+                None if c == '"' || c == '\'' => { quote = Some(c); false }
+                None => c == '>'
+            }).map(|(i, _)| i + 1)
+        };
+        let markup = match end {
+            Some(end) => &rest[..end],
+            None => return Err("contains incomplete markup".into())
+        };
+        rest = &rest[markup.len()..];
+
+        let name = |s: &str| s.split(|c: char| is_whitespace_char(c) || c == '/' || c == '>').next().unwrap_or("").to_owned();
+        if let Some(end_tag) = markup.strip_prefix("</") {
+            let name = name(end_tag);
+            if open.pop().as_ref() != Some(&name) {
+                return Err(format!("closes element {} which is not started in it", name));
+            }
+        } else if !markup.starts_with("<!") && !markup.starts_with("<?") && !markup.ends_with("/>") {
+            open.push(name(&markup[1..]));
+        }
+    }
+    match open.pop() {
+        Some(name) => Err(format!("does not close element {}", name)),
+        None => Ok(())
+    }
 }
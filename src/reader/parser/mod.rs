@@ -6,16 +6,20 @@ use std::io::prelude::*;
 
 use common::{
     self,
-    XmlVersion, Position, TextPosition,
-    is_name_start_char, is_name_char,
+    Encoding, XmlVersion, Position, TextPosition,
+    is_name_start_char, is_name_char, is_whitespace_char,
 };
 use name::OwnedName;
 use attribute::OwnedAttribute;
 use namespace::NamespaceStack;
+use dtd::Doctype;
 
 use reader::events::XmlEvent;
 use reader::config::ParserConfig;
 use reader::lexer::{Lexer, Token};
+use util::CharReader;
+
+use self::validate::Validator;
 
 macro_rules! gen_takes(
     ($($field:ident -> $method:ident, $t:ty, $def:expr);+) => (
@@ -58,12 +62,14 @@ mod inside_closing_tag_name;
 mod inside_comment;
 mod inside_cdata;
 mod inside_reference;
+mod validate;
 
 static DEFAULT_VERSION: XmlVersion      = XmlVersion::Version10;
-static DEFAULT_ENCODING: &'static str   = "UTF-8";
 static DEFAULT_STANDALONE: Option<bool> = None;
 
 type ElementStack = Vec<OwnedName>;
+/// Accepted characters of a run which is consumed in bulk and whether they are kept in the buffer.
+type Run = (fn(char) -> bool, bool);
 pub type Result = super::Result<XmlEvent>;
 
 /// Pull-based XML parser.
@@ -72,6 +78,7 @@ pub struct PullParser {
     lexer: Lexer,
     st: State,
     buf: String,
+    run: Option<Run>,  // the run of characters started by the last token
     nst: NamespaceStack,
 
     data: MarkupData,
@@ -79,6 +86,9 @@ pub struct PullParser {
     next_event: Option<Result>,
     est: ElementStack,
     pos: Vec<TextPosition>,
+    doctype: Option<Doctype>,
+    expanded_len: usize,
+    validator: Validator,
 
     encountered_element: bool,
     parsed_declaration: bool,
@@ -91,10 +101,14 @@ impl PullParser {
     /// Returns a new parser using the given config.
     pub fn new(config: ParserConfig) -> PullParser {
         PullParser {
+            lexer: Lexer::new_with_reader(match config.override_encoding {
+                Some(encoding) => CharReader::with_encoding(encoding),
+                None => CharReader::new()
+            }),
             config: config,
-            lexer: Lexer::new(),
             st: State::OutsideTag,
             buf: String::new(),
+            run: None,
             nst: NamespaceStack::default(),
 
             data: MarkupData {
@@ -106,12 +120,17 @@ impl PullParser {
                 element_name: None,
                 quote: None,
                 attr_name: None,
-                attributes: Vec::new()
+                attributes: Vec::new(),
+                doctype_pos: TextPosition::new(),
+                ref_pos: TextPosition::new()
             },
             final_result: None,
             next_event: None,
             est: Vec::new(),
             pos: vec![TextPosition::new()],
+            doctype: None,
+            expanded_len: 0,
+            validator: Validator::new(),
 
             encountered_element: false,
             parsed_declaration: false,
@@ -123,6 +142,10 @@ impl PullParser {
 
     /// Checks if this parser ignores the end of stream errors.
     pub fn is_ignoring_end_of_stream(&self) -> bool { self.config.ignore_end_of_stream }
+
+    /// Returns the encoding which the rest of the input is decoded with.
+    #[inline]
+    pub fn encoding(&self) -> Encoding { self.lexer.encoding() }
 }
 
 impl Position for PullParser {
@@ -142,7 +165,7 @@ pub enum State {
     InsideComment,
     InsideCData,
     InsideDeclaration(DeclarationSubstate),
-    InsideDoctype,
+    InsideDoctype(DoctypeSubstate),
     InsideReference(Box<State>)
 }
 
@@ -192,6 +215,14 @@ pub enum DeclarationSubstate {
     AfterStandaloneDeclValue
 }
 
+#[derive(Clone, PartialEq)]
+pub enum DoctypeSubstate {
+    Outside,
+    InsideSubset,
+    InsideComment,
+    InsideProcessingInstruction
+}
+
 #[derive(PartialEq)]
 enum QualifiedNameTarget {
     AttributeNameTarget,
@@ -234,7 +265,10 @@ struct MarkupData {
 
     quote: Option<QuoteToken>,  // used to hold opening quote for attribute value
     attr_name: Option<OwnedName>,  // used to hold attribute name
-    attributes: Vec<OwnedAttribute>   // used to hold all accumulated attributes
+    attributes: Vec<OwnedAttribute>,  // used to hold all accumulated attributes
+
+    doctype_pos: TextPosition,  // used to hold the position of the DOCTYPE declaration
+    ref_pos: TextPosition  // used to hold the position of the current reference
 }
 
 impl PullParser {
@@ -247,6 +281,22 @@ impl PullParser {
             return ev.clone();
         }
 
+        let result = self.read_event(r);
+        if self.config.validate_dtd {
+            if let Ok(ref event) = result {
+                if let Err(e) = self.validate(event) {
+                    return self.set_final_result(Err(e));
+                }
+            }
+        }
+        result
+    }
+
+    fn read_event<R: Read>(&mut self, r: &mut R) -> Result {
+        if let Some(ref ev) = self.final_result {
+            return ev.clone();
+        }
+
         if let Some(ev) = self.next_event.take() {
             return ev;
         }
@@ -257,9 +307,15 @@ impl PullParser {
         }
 
         loop {
+            // A run of characters started by the last token is consumed in bulk
+            let token = match self.run.take() {
+                Some(run) => self.read_run(r, run).and_then(|_| self.lexer.next_token(r)),
+                None => self.lexer.next_token(r)
+            };
+
             // While lexer gives us Ok(maybe_token) -- we loop.
             // Upon having a complete XML-event -- we return from the whole function.
-            match self.lexer.next_token(r) {
+            match token {
                 Ok(maybe_token) =>
                     match maybe_token {
                         None => break,
@@ -283,6 +339,9 @@ impl PullParser {
                                     },
                             }
                     },
+                // The source has no data available yet; parsing is resumed on the next call
+                Err(ref lexer_error) if lexer_error.is_would_block() =>
+                    return Err(lexer_error.clone()),
                 Err(lexer_error) =>
                     return self.set_final_result(Err(lexer_error)),
             }
@@ -342,7 +401,7 @@ impl PullParser {
             State::OutsideTag                     => self.outside_tag(t),
             State::InsideProcessingInstruction(s) => self.inside_processing_instruction(t, s),
             State::InsideDeclaration(s)           => self.inside_declaration(t, s),
-            State::InsideDoctype                  => self.inside_doctype(t),
+            State::InsideDoctype(s)               => self.inside_doctype(t, s),
             State::InsideOpeningTag(s)            => self.inside_opening_tag(t, s),
             State::InsideClosingTag(s)            => self.inside_closing_tag_name(t, s),
             State::InsideComment                  => self.inside_comment(t),
@@ -366,12 +425,31 @@ impl PullParser {
         mem::replace(&mut self.buf, String::new())
     }
 
+    /// Makes the rest of the current run of characters accepted by the predicate be appended
+    /// to the buffer before the next token is read, consuming them in bulk instead of token
+    /// by token.
+    #[inline]
+    fn append_run_continue(&mut self, accept: fn(char) -> bool) -> Option<Result> {
+        self.run = Some((accept, true));
+        None
+    }
+
+    /// Makes the rest of the current run of characters be skipped before the next token is read.
     #[inline]
-    fn append_char_continue(&mut self, c: char) -> Option<Result> {
-        self.buf.push(c);
+    fn skip_run_continue(&mut self) -> Option<Result> {
+        self.run = Some((|_| true, false));
         None
     }
 
+    fn read_run<R: Read>(&mut self, r: &mut R, (accept, keep): Run) -> super::Result<()> {
+        let len = self.buf.len();
+        let result = self.lexer.read_run(r, &mut self.buf, accept);
+        if !keep {
+            self.buf.truncate(len);
+        }
+        result
+    }
+
     #[inline]
     fn into_state(&mut self, st: State, ev: Option<Result>) -> Option<Result> {
         self.st = st;
@@ -419,8 +497,10 @@ impl PullParser {
             }
 
             Token::Character(c) if c != ':' && (!self.buf_has_data() && is_name_start_char(c) ||
-                                          self.buf_has_data() && is_name_char(c)) =>
-                self.append_char_continue(c),
+                                          self.buf_has_data() && is_name_char(c)) => {
+                self.buf.push(c);
+                self.append_run_continue(|c| c != ':' && is_name_char(c))
+            }
 
             Token::EqualsSign if target == QualifiedNameTarget::AttributeNameTarget => invoke_callback(self, t),
 
@@ -462,6 +542,7 @@ impl PullParser {
             },
 
             Token::ReferenceStart => {
+                self.data.ref_pos = self.lexer.position();
                 let st = Box::new(self.st.clone());
                 self.into_state_continue(State::InsideReference(st))
             }
@@ -469,10 +550,17 @@ impl PullParser {
             Token::OpeningTagStart =>
                 Some(self_error!(self; "Unexpected token inside attribute value: <")),
 
+            // Literal whitespace characters are normalized to spaces; whitespace produced
+            // by character references is pushed by `inside_reference` and is kept as is
+            Token::Whitespace(_) => {
+                self.buf.push(' ');
+                None
+            }
+
             // Every character except " and ' and < is okay
             _  => {
                 t.push_to_string(&mut self.buf);
-                None
+                self.append_run_continue(|c| !is_whitespace_char(c))
             }
         }
     }
@@ -500,6 +588,20 @@ impl PullParser {
             }
         }
 
+        // attributes with different prefixes bound to the same namespace URI are duplicates too
+        for (i, attr) in attributes.iter().enumerate() {
+            if attr.name.namespace.is_none() {
+                continue;
+            }
+            let duplicate = attributes[..i].iter().find(|a|
+                a.name.namespace == attr.name.namespace && a.name.local_name == attr.name.local_name
+            );
+            if let Some(other) = duplicate {
+                return Some(self_error!(self; "Attributes '{}' and '{}' have the same local name and namespace",
+                                        other.name.borrow().repr_display(), attr.name.borrow().repr_display()));
+            }
+        }
+
         if emit_end_element {
             self.pop_namespace = true;
             self.next_event = Some(Ok(XmlEvent::EndElement {
@@ -1,18 +1,20 @@
-use common::is_whitespace_char;
+use common::{Position, is_whitespace_char};
 
 use reader::events::XmlEvent;
 use reader::lexer::Token;
 
 use super::{
-    Result, PullParser, State, ClosingTagSubstate, OpeningTagSubstate,
-    ProcessingInstructionSubstate, DEFAULT_VERSION, DEFAULT_ENCODING, DEFAULT_STANDALONE
+    Result, PullParser, State, ClosingTagSubstate, OpeningTagSubstate, DoctypeSubstate,
+    ProcessingInstructionSubstate, DEFAULT_VERSION, DEFAULT_STANDALONE
 };
 
 impl PullParser {
     pub fn outside_tag(&mut self, t: Token) -> Option<Result> {
         match t {
-            Token::ReferenceStart =>
-                self.into_state_continue(State::InsideReference(Box::new(State::OutsideTag))),
+            Token::ReferenceStart => {
+                self.data.ref_pos = self.lexer.position();
+                self.into_state_continue(State::InsideReference(Box::new(State::OutsideTag)))
+            }
 
             Token::Whitespace(_) if self.depth() == 0 && self.config.ignore_root_level_whitespace => None,  // skip whitespace outside of the root element
 
@@ -22,7 +24,8 @@ impl PullParser {
                 if !self.buf_has_data() {
                     self.push_pos();
                 }
-                self.append_char_continue(c)
+                self.buf.push(c);
+                self.append_run_continue(is_whitespace_char)
             }
 
             _ if t.contains_char_data() && self.depth() == 0 =>
@@ -34,7 +37,7 @@ impl PullParser {
                 }
                 self.inside_whitespace = false;
                 t.push_to_string(&mut self.buf);
-                None
+                self.append_run_continue(|_| true)
             }
 
             Token::ReferenceEnd => { // Semi-colon in a text outside an entity
@@ -79,12 +82,22 @@ impl PullParser {
                     Token::ProcessingInstructionStart =>
                         self.into_state(State::InsideProcessingInstruction(ProcessingInstructionSubstate::PIInsideName), next_event),
 
-                    Token::DoctypeStart if !self.encountered_element => {
-                        // We don't have a doctype event so skip this position
-                        // FIXME: update when we have a doctype event
-                        self.next_pos();
+                    Token::DoctypeStart if !self.encountered_element && self.doctype.is_none() => {
+                        // Emit the document declaration before the doctype if it was not parsed
+                        if !self.parsed_declaration {
+                            self.parsed_declaration = true;
+                            next_event = Some(Ok(XmlEvent::StartDocument {
+                                version: DEFAULT_VERSION,
+                                encoding: self.lexer.encoding().to_string(),
+                                standalone: DEFAULT_STANDALONE
+                            }));
+                            self.push_pos();
+                        }
+                        // Markup declarations are parsed after the whole doctype is read,
+                        // so we need to disable lexing errors inside of it
+                        self.data.doctype_pos = self.lexer.position();
                         self.lexer.disable_errors();
-                        self.into_state(State::InsideDoctype, next_event)
+                        self.into_state(State::InsideDoctype(DoctypeSubstate::Outside), next_event)
                     }
 
                     Token::OpeningTagStart => {
@@ -94,7 +107,7 @@ impl PullParser {
                             self.parsed_declaration = true;
                             let sd_event = XmlEvent::StartDocument {
                                 version: DEFAULT_VERSION,
-                                encoding: DEFAULT_ENCODING.into(),
+                                encoding: self.lexer.encoding().to_string(),
                                 standalone: DEFAULT_STANDALONE
                             };
                             // next_event is always none here because we're outside of
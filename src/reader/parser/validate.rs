@@ -0,0 +1,306 @@
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashSet};
+use std::result;
+
+use common::{Position, TextPosition, is_name_start_char, is_name_char, is_whitespace_char, is_whitespace_str};
+use attribute::OwnedAttribute;
+use dtd::{Doctype, ContentSpec, ContentParticle, ParticleKind, Repetition, AttributeType,
+          DefaultDecl, EntityValue};
+
+use reader::events::XmlEvent;
+use reader::{Error, ErrorKind};
+
+use super::PullParser;
+
+/// State of DTD validation of a document.
+pub struct Validator {
+    elements: Vec<OpenElement>,
+    ids: HashSet<String>,
+    idrefs: Vec<(String, TextPosition)>,
+}
+
+/// An element whose content is being validated.
+struct OpenElement {
+    name: String,
+    children: Vec<String>,
+}
+
+impl Validator {
+    pub fn new() -> Validator {
+        Validator {
+            elements: Vec::new(),
+            ids: HashSet::new(),
+            idrefs: Vec::new(),
+        }
+    }
+}
+
+impl PullParser {
+    /// Checks that the event is valid according to the DTD of the document.
+    pub fn validate(&mut self, event: &XmlEvent) -> result::Result<(), Error> {
+        let pos = self.position();
+        let doctype = match self.doctype {
+            Some(ref doctype) => doctype,
+            None => return match *event {
+                XmlEvent::StartElement { .. } =>
+                    Err(validation_error(&pos, "Document has no document type declaration")),
+                _ => Ok(())
+            }
+        };
+        let v = &mut self.validator;
+        match *event {
+            XmlEvent::StartElement { ref name, ref attributes, .. } => {
+                let name = name.borrow().to_repr();
+                match v.elements.last_mut() {
+                    Some(parent) => {
+                        match content_spec(doctype, &parent.name) {
+                            Some(&ContentSpec::Empty) => return Err(validation_error(&pos, format!(
+                                "Element {} is declared EMPTY but contains element {}", parent.name, name))),
+                            Some(ContentSpec::Mixed(names)) if !names.contains(&name) =>
+                                return Err(validation_error(&pos, format!(
+                                    "Element {} is not allowed in the content of element {}", name, parent.name))),
+                            _ => {}
+                        }
+                        parent.children.push(name.clone());
+                    }
+                    None if name != doctype.name => return Err(validation_error(&pos, format!(
+                        "Root element {} does not match the document type name {}", name, doctype.name))),
+                    None => {}
+                }
+                if doctype.element(&name).is_none() {
+                    return Err(validation_error(&pos, format!("Element {} is not declared", name)));
+                }
+                validate_attributes(v, doctype, &name, attributes, pos)?;
+                v.elements.push(OpenElement { name, children: Vec::new() });
+            }
+
+            XmlEvent::EndElement { .. } => {
+                let element = v.elements.pop().unwrap();  // the parser checks that tags are balanced
+                if let Some(ContentSpec::Children(model)) = content_spec(doctype, &element.name) {
+                    if !match_particle(model, &element.children, Some(0).into_iter().collect())
+                            .contains(&element.children.len()) {
+                        return Err(validation_error(&pos, format!(
+                            "Content of element {} does not match its declaration", element.name)));
+                    }
+                }
+            }
+
+            XmlEvent::Characters(ref data) | XmlEvent::CData(ref data) | XmlEvent::Whitespace(ref data) => {
+                if let Some(element) = v.elements.last() {
+                    let is_cdata = matches!(*event, XmlEvent::CData(_));
+                    match content_spec(doctype, &element.name) {
+                        Some(&ContentSpec::Empty) => return Err(validation_error(&pos, format!(
+                            "Element {} is declared EMPTY but has content", element.name))),
+                        Some(&ContentSpec::Children(_)) if is_cdata || !is_whitespace_str(data) =>
+                            return Err(validation_error(&pos, format!(
+                                "Element {} cannot contain character data", element.name))),
+                        _ => {}
+                    }
+                }
+            }
+
+            XmlEvent::EndDocument => {
+                if let Some((idref, pos)) = v.idrefs.iter().find(|&(r, _)| !v.ids.contains(r)) {
+                    return Err(validation_error(pos, format!("IDREF value {} does not match any ID", idref)));
+                }
+            }
+
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+fn validation_error<M: Into<Cow<'static, str>>>(pos: &TextPosition, msg: M) -> Error {
+    (pos, ErrorKind::Validation(msg.into())).into()
+}
+
+fn content_spec<'a>(doctype: &'a Doctype, element: &str) -> Option<&'a ContentSpec> {
+    doctype.element(element).map(|e| &e.content)
+}
+
+fn validate_attributes(v: &mut Validator, doctype: &Doctype, element: &str,
+                       attributes: &[OwnedAttribute], pos: TextPosition) -> result::Result<(), Error> {
+    for attr in attributes {
+        let attr_name = attr.name.borrow().to_repr();
+        let def = match doctype.attribute(element, &attr_name) {
+            Some(def) => def,
+            None => return Err(validation_error(&pos, format!(
+                "Attribute {} of element {} is not declared", attr_name, element)))
+        };
+        let value = &attr.value[..];
+        let invalid = || validation_error(&pos, format!(
+            "Value '{}' of attribute {} of element {} is not allowed by its type", value, attr_name, element));
+        match def.attribute_type {
+            AttributeType::CData => {}
+            AttributeType::Id => {
+                if !is_name(value) {
+                    return Err(invalid());
+                }
+                if !v.ids.insert(value.into()) {
+                    return Err(validation_error(&pos, format!("Duplicate ID value {}", value)));
+                }
+            }
+            AttributeType::IdRef | AttributeType::IdRefs => {
+                if (def.attribute_type == AttributeType::IdRef && value.contains(' ')) || value.is_empty() {
+                    return Err(invalid());
+                }
+                for idref in value.split(' ') {
+                    if !is_name(idref) {
+                        return Err(invalid());
+                    }
+                    v.idrefs.push((idref.into(), pos));
+                }
+            }
+            AttributeType::Entity | AttributeType::Entities => {
+                if (def.attribute_type == AttributeType::Entity && value.contains(' ')) || value.is_empty() {
+                    return Err(invalid());
+                }
+                for entity in value.split(' ') {
+                    match doctype.entity(entity).map(|e| &e.value) {
+                        Some(&EntityValue::External { notation: Some(_), .. }) => {}
+                        _ => return Err(validation_error(&pos, format!(
+                            "Value {} of attribute {} of element {} is not an unparsed entity", entity, attr_name, element)))
+                    }
+                }
+            }
+            AttributeType::NmToken => if !is_nmtoken(value) {
+                return Err(invalid());
+            },
+            AttributeType::NmTokens => if value.is_empty() || !value.split(' ').all(is_nmtoken) {
+                return Err(invalid());
+            },
+            AttributeType::Notation(ref values) | AttributeType::Enumeration(ref values) =>
+                if !values.iter().any(|v| v == value) {
+                    return Err(invalid());
+                },
+        }
+        if let DefaultDecl::Fixed(ref fixed) = def.default {
+            if *value != normalize_default(fixed, &def.attribute_type) {
+                return Err(validation_error(&pos, format!(
+                    "Attribute {} of element {} must have the fixed value '{}'", attr_name, element, fixed)));
+            }
+        }
+    }
+
+    for def in doctype.attributes(element) {
+        if def.default == DefaultDecl::Required && !attributes.iter().any(|a| a.name.borrow().to_repr() == def.name) {
+            return Err(validation_error(&pos, format!(
+                "Required attribute {} of element {} is missing", def.name, element)));
+        }
+    }
+    Ok(())
+}
+
+/// Normalizes a default value from an attribute declaration in the same way as the parser
+/// normalizes attribute values.
+fn normalize_default(value: &str, attribute_type: &AttributeType) -> String {
+    let value = value.replace(is_whitespace_char, " ");
+    if attribute_type.is_cdata() {
+        value
+    } else {
+        value.split(' ').filter(|s| !s.is_empty()).collect::<Vec<_>>().join(" ")
+    }
+}
+
+fn is_name(s: &str) -> bool {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) if is_name_start_char(c) => chars.all(is_name_char),
+        _ => false
+    }
+}
+
+fn is_nmtoken(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(is_name_char)
+}
+
+/// Returns the set of positions in `names` at which a match of the content particle may end,
+/// provided that the match starts at one of the `starts` positions.
+fn match_particle(particle: &ContentParticle, names: &[String], starts: BTreeSet<usize>) -> BTreeSet<usize> {
+    match particle.repetition {
+        Repetition::Once => match_once(particle, names, &starts),
+        Repetition::Optional => {
+            let mut ends = match_once(particle, names, &starts);
+            ends.extend(starts);
+            ends
+        }
+        Repetition::ZeroOrMore => match_repeated(particle, names, starts),
+        Repetition::OneOrMore => {
+            let ends = match_once(particle, names, &starts);
+            match_repeated(particle, names, ends)
+        }
+    }
+}
+
+fn match_once(particle: &ContentParticle, names: &[String], starts: &BTreeSet<usize>) -> BTreeSet<usize> {
+    match particle.kind {
+        ParticleKind::Name(ref name) =>
+            starts.iter().filter(|&&i| names.get(i) == Some(name)).map(|i| i + 1).collect(),
+        ParticleKind::Choice(ref particles) =>
+            particles.iter().flat_map(|p| match_particle(p, names, starts.clone())).collect(),
+        ParticleKind::Sequence(ref particles) =>
+            particles.iter().fold(starts.clone(), |starts, p| match_particle(p, names, starts)),
+    }
+}
+
+fn match_repeated(particle: &ContentParticle, names: &[String], starts: BTreeSet<usize>) -> BTreeSet<usize> {
+    let mut ends = starts.clone();
+    let mut current = starts;
+    while !current.is_empty() {
+        current = match_once(particle, names, &current).difference(&ends).cloned().collect();
+        ends.extend(current.iter().cloned());
+    }
+    ends
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use dtd::{ContentParticle, ParticleKind, Repetition};
+
+    use super::match_particle;
+
+    fn name(n: &str, repetition: Repetition) -> ContentParticle {
+        ContentParticle { kind: ParticleKind::Name(n.into()), repetition: repetition }
+    }
+
+    fn matches(particle: &ContentParticle, names: &[&str]) -> bool {
+        let names: Vec<String> = names.iter().map(|&n| n.into()).collect();
+        let starts: BTreeSet<usize> = Some(0).into_iter().collect();
+        match_particle(particle, &names, starts).contains(&names.len())
+    }
+
+    #[test]
+    fn content_model_matching() {
+        // (a, (b | c)*, d?)
+        let model = ContentParticle {
+            kind: ParticleKind::Sequence(vec![
+                name("a", Repetition::Once),
+                ContentParticle {
+                    kind: ParticleKind::Choice(vec![name("b", Repetition::Once), name("c", Repetition::Once)]),
+                    repetition: Repetition::ZeroOrMore
+                },
+                name("d", Repetition::Optional),
+            ]),
+            repetition: Repetition::Once
+        };
+        assert!(matches(&model, &["a"]));
+        assert!(matches(&model, &["a", "b", "c", "b", "d"]));
+        assert!(matches(&model, &["a", "d"]));
+        assert!(!matches(&model, &[]));
+        assert!(!matches(&model, &["b"]));
+        assert!(!matches(&model, &["a", "d", "b"]));
+        assert!(!matches(&model, &["a", "d", "d"]));
+
+        // (a?)+ can match nothing
+        let model = ContentParticle {
+            kind: ParticleKind::Sequence(vec![name("a", Repetition::Optional)]),
+            repetition: Repetition::OneOrMore
+        };
+        assert!(matches(&model, &[]));
+        assert!(matches(&model, &["a", "a"]));
+        assert!(!matches(&model, &["b"]));
+    }
+}
@@ -0,0 +1,171 @@
+//! Contains a push-based parser interface, which is fed with data as it becomes available.
+
+use std::io::{self, Read};
+
+use common::{Position, TextPosition};
+
+use super::parser::PullParser;
+use super::{ParserConfig, Result, XmlEvent};
+
+/// A push-based XML parser.
+///
+/// Unlike `EventReader`, it does not read data from a source; instead, the data is passed to
+/// it with `feed()` in chunks of arbitrary size, for example, as network packets arrive.
+/// After each chunk, the events which are complete can be drained with `next()` or `events()`.
+/// Chunk boundaries may fall anywhere, including in the middle of multibyte characters, markup
+/// or references; the incomplete part is kept until the rest of it is fed. When all data has
+/// been fed, `finish()` must be called to parse the rest of the document.
+///
+/// It uses the same parser as `EventReader` and produces exactly the same events.
+///
+/// # Example
+///
+/// ```rust
+/// use xml::reader::{PushReader, XmlEvent};
+///
+/// let mut reader = PushReader::new();
+/// let mut names = Vec::new();
+/// for packet in &["<hello>wor", "ld</he", "llo>"] {
+///     reader.feed(packet.as_bytes());
+///     for event in reader.events() {
+///         if let XmlEvent::EndElement { name } = event.unwrap() {
+///             names.push(name.local_name);
+///         }
+///     }
+/// }
+/// reader.finish();
+/// assert_eq!(reader.next().unwrap().unwrap(), XmlEvent::EndDocument);
+/// assert_eq!(names, vec!["hello".to_owned()]);
+/// ```
+pub struct PushReader {
+    parser: PullParser,
+    buf: Vec<u8>,    // data fed to the reader
+    pos: usize,      // offset of the first byte in `buf` not passed to the parser yet
+    eof: bool,       // whether `finish()` has been called
+    finished: bool   // whether the last event has been returned
+}
+
+impl PushReader {
+    /// Creates a new reader with the default configuration.
+    #[inline]
+    pub fn new() -> PushReader {
+        PushReader::new_with_config(ParserConfig::new())
+    }
+
+    /// Creates a new reader with the provded configuration.
+    #[inline]
+    pub fn new_with_config(config: ParserConfig) -> PushReader {
+        PushReader {
+            parser: PullParser::new(config),
+            buf: Vec::new(),
+            pos: 0,
+            eof: false,
+            finished: false
+        }
+    }
+
+    /// Passes the next chunk of the document to the reader.
+    ///
+    /// The data is not parsed until the events are requested.
+    pub fn feed(&mut self, data: &[u8]) {
+        self.buf.drain(..self.pos);
+        self.pos = 0;
+        self.buf.extend_from_slice(data);
+    }
+
+    /// Signals that the whole document has been fed to the reader.
+    ///
+    /// After this, `next()` returns all remaining events up to `XmlEvent::EndDocument`
+    /// or an error.
+    #[inline]
+    pub fn finish(&mut self) {
+        self.eof = true;
+    }
+
+    /// Returns the next XML event, or `None` if more data is needed to complete it.
+    ///
+    /// `None` is also returned after an error or `XmlEvent::EndDocument`, which are
+    /// returned only once.
+    pub fn next(&mut self) -> Option<Result<XmlEvent>> {
+        if self.finished {
+            return None;
+        }
+        let result = {
+            let mut input = Input { data: &self.buf[self.pos..], eof: self.eof };
+            let result = self.parser.next(&mut input);
+            self.pos = self.buf.len() - input.data.len();
+            result
+        };
+        match result {
+            Err(ref e) if e.is_would_block() => None,
+            result => {
+                if let Ok(XmlEvent::EndDocument) | Err(_) = result {
+                    self.finished = true;
+                }
+                Some(result)
+            }
+        }
+    }
+
+    /// Returns an iterator which drains the events which are complete.
+    #[inline]
+    pub fn events(&mut self) -> Events<'_> {
+        Events { reader: self }
+    }
+}
+
+impl Default for PushReader {
+    #[inline]
+    fn default() -> PushReader {
+        PushReader::new()
+    }
+}
+
+impl Position for PushReader {
+    /// Returns the position of the last event produced by the reader.
+    #[inline]
+    fn position(&self) -> TextPosition {
+        self.parser.position()
+    }
+}
+
+/// Passes the fed data to the parser, reporting errors of the `WouldBlock` kind when more data
+/// has to be fed.
+struct Input<'a> {
+    data: &'a [u8],
+    eof: bool
+}
+
+impl<'a> Read for Input<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.data.is_empty() && !self.eof {
+            return Err(io::Error::new(io::ErrorKind::WouldBlock, "no data has been fed yet"));
+        }
+        self.data.read(buf)
+    }
+}
+
+/// An iterator over the complete XML events of a `PushReader`.
+///
+/// It is returned by `PushReader::events()`, and ends when more data has to be fed
+/// to the reader.
+pub struct Events<'a> {
+    reader: &'a mut PushReader
+}
+
+impl<'a> Iterator for Events<'a> {
+    type Item = Result<XmlEvent>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Result<XmlEvent>> {
+        self.reader.next()
+    }
+}
+
+impl<'a> Position for Events<'a> {
+    /// Returns the position of the last event produced by the iterator.
+    #[inline]
+    fn position(&self) -> TextPosition {
+        self.reader.position()
+    }
+}
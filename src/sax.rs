@@ -0,0 +1,169 @@
+//! Contains a SAX-style interface, which passes the contents of a document to callbacks.
+//!
+//! A `ContentHandler` receives a callback for each event of an `EventReader`, together with
+//! the position of the event in the document. All callbacks have default implementations,
+//! so a handler only needs to implement those it is interested in. Each callback returns
+//! a `Control` value which tells whether parsing should go on:
+//!
+//! ```rust
+//! use xml::EventReader;
+//! use xml::attribute::OwnedAttribute;
+//! use xml::common::TextPosition;
+//! use xml::name::OwnedName;
+//! use xml::namespace::Namespace;
+//! use xml::sax::{self, ContentHandler, Control};
+//!
+//! /// Collects the names of elements until the first `stop` element.
+//! struct Names(Vec<String>);
+//!
+//! impl ContentHandler for Names {
+//!     fn start_element(&mut self, name: &OwnedName, _: &[OwnedAttribute], _: &Namespace,
+//!                      _: TextPosition) -> Control {
+//!         if name.local_name == "stop" {
+//!             return Control::Stop;
+//!         }
+//!         self.0.push(name.local_name.clone());
+//!         Control::Continue
+//!     }
+//! }
+//!
+//! let mut reader = EventReader::from_str("<a><b/><stop/><c/></a>");
+//! let mut names = Names(Vec::new());
+//! sax::parse(&mut reader, &mut names).unwrap();
+//! assert_eq!(names.0, vec!["a", "b"]);
+//! ```
+
+use std::io::Read;
+
+use attribute::OwnedAttribute;
+use common::{Position, TextPosition, XmlVersion};
+use dtd::Doctype;
+use name::OwnedName;
+use namespace::Namespace;
+use reader::{EventReader, Error, Result, XmlEvent};
+
+/// Tells the driver whether parsing should go on after a callback.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Control {
+    /// Parsing continues with the next event.
+    Continue,
+
+    /// Parsing stops; `parse()` returns without reading further events.
+    Stop
+}
+
+/// A receiver of the contents of a document.
+///
+/// Every callback is passed the position of the corresponding event in the document.
+/// Callbacks of the default implementation do nothing and continue parsing.
+pub trait ContentHandler {
+    /// Called at the beginning of the document, with the contents of its XML declaration.
+    fn start_document(&mut self, version: XmlVersion, encoding: &str, standalone: Option<bool>,
+                      position: TextPosition) -> Control {
+        Control::Continue
+    }
+
+    /// Called at the end of the document.
+    fn end_document(&mut self, position: TextPosition) -> Control {
+        Control::Continue
+    }
+
+    /// Called for the document type declaration.
+    fn doctype(&mut self, doctype: &Doctype, position: TextPosition) -> Control {
+        Control::Continue
+    }
+
+    /// Called for an opening tag or at the start of an empty element.
+    ///
+    /// `namespace` contains all namespace mappings in scope of the element.
+    fn start_element(&mut self, name: &OwnedName, attributes: &[OwnedAttribute], namespace: &Namespace,
+                     position: TextPosition) -> Control {
+        Control::Continue
+    }
+
+    /// Called for a closing tag or at the end of an empty element.
+    fn end_element(&mut self, name: &OwnedName, position: TextPosition) -> Control {
+        Control::Continue
+    }
+
+    /// Called for character data.
+    ///
+    /// Whitespace and CDATA sections are passed to this method as well, unless `whitespace()`
+    /// or `cdata()` are overridden.
+    fn characters(&mut self, text: &str, position: TextPosition) -> Control {
+        Control::Continue
+    }
+
+    /// Called for character data which consists of whitespace only.
+    fn whitespace(&mut self, text: &str, position: TextPosition) -> Control {
+        self.characters(text, position)
+    }
+
+    /// Called for the contents of a CDATA section.
+    fn cdata(&mut self, text: &str, position: TextPosition) -> Control {
+        self.characters(text, position)
+    }
+
+    /// Called for a processing instruction.
+    fn processing_instruction(&mut self, target: &str, data: Option<&str>, position: TextPosition) -> Control {
+        Control::Continue
+    }
+
+    /// Called for a comment.
+    ///
+    /// Note that comments are only reported when `ParserConfig::ignore_comments` is disabled.
+    fn comment(&mut self, text: &str, position: TextPosition) -> Control {
+        Control::Continue
+    }
+
+    /// Called when the document cannot be parsed further, with the position of the error.
+    ///
+    /// Parsing always stops after an error, and the error is returned from `parse()`.
+    fn error(&mut self, error: &Error, position: TextPosition) {}
+}
+
+/// Reads events from the reader and passes them to the handler.
+///
+/// Returns when the end of the document is reached or when a callback returns `Control::Stop`;
+/// in the latter case the reader is left right after the event of that callback, so parsing
+/// can be resumed by calling this function again.
+///
+/// If the document is malformed, the error is passed to `ContentHandler::error()` and then
+/// returned.
+pub fn parse<R: Read, H: ContentHandler + ?Sized>(reader: &mut EventReader<R>, handler: &mut H) -> Result<()> {
+    loop {
+        let event = reader.next();
+        let position = reader.position();
+        let control = match event {
+            Ok(XmlEvent::StartDocument { version, ref encoding, standalone }) =>
+                handler.start_document(version, encoding, standalone, position),
+            Ok(XmlEvent::EndDocument) => {
+                handler.end_document(position);
+                return Ok(());
+            }
+            Ok(XmlEvent::Doctype(ref doctype)) =>
+                handler.doctype(doctype, position),
+            Ok(XmlEvent::ProcessingInstruction { ref name, ref data }) =>
+                handler.processing_instruction(name, data.as_ref().map(|s| &s[..]), position),
+            Ok(XmlEvent::StartElement { ref name, ref attributes, ref namespace }) =>
+                handler.start_element(name, attributes, namespace, position),
+            Ok(XmlEvent::EndElement { ref name }) =>
+                handler.end_element(name, position),
+            Ok(XmlEvent::CData(ref text)) =>
+                handler.cdata(text, position),
+            Ok(XmlEvent::Comment(ref text)) =>
+                handler.comment(text, position),
+            Ok(XmlEvent::Characters(ref text)) =>
+                handler.characters(text, position),
+            Ok(XmlEvent::Whitespace(ref text)) =>
+                handler.whitespace(text, position),
+            Err(e) => {
+                handler.error(&e, e.position());
+                return Err(e);
+            }
+        };
+        if control == Control::Stop {
+            return Ok(());
+        }
+    }
+}
@@ -0,0 +1,616 @@
+//! Contains the loader which builds schema components from a schema document.
+
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+
+use common::{Position, TextPosition};
+use attribute::OwnedAttribute;
+use name::OwnedName;
+use namespace::Namespace;
+use reader::{EventReader, XmlEvent};
+
+use super::{SchemaError, NS_XSD_URI};
+use super::model::*;
+use super::regex::Regex;
+use super::types::is_ncname;
+
+type Result<T> = ::std::result::Result<T, SchemaError>;
+
+/// An element of a schema document.
+struct Node {
+    name: OwnedName,
+    attributes: Vec<OwnedAttribute>,
+    namespace: Namespace,
+    pos: TextPosition,
+    children: Vec<Node>,
+}
+
+/// A kind of a named component which can be referenced from other components.
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum ComponentKind {
+    Type,
+    Element,
+    Attribute,
+    Group,
+    AttributeGroup,
+}
+
+struct Loader {
+    model: Model,
+    element_qualified: bool,
+    attribute_qualified: bool,
+    references: Vec<(ComponentKind, OwnedName, TextPosition)>,
+}
+
+pub fn load<R: Read>(reader: EventReader<R>) -> Result<Model> {
+    let root = read_tree(reader)?;
+    if !root.is_xsd("schema") {
+        return error(&root, "The root element of a schema document must be xs:schema");
+    }
+    let mut loader = Loader {
+        model: Model {
+            target_namespace: root.attr("targetNamespace").map(|s| s.to_owned()),
+            elements: HashMap::new(),
+            attributes: HashMap::new(),
+            types: HashMap::new(),
+            groups: HashMap::new(),
+            attribute_groups: HashMap::new(),
+        },
+        element_qualified: root.attr("elementFormDefault") == Some("qualified"),
+        attribute_qualified: root.attr("attributeFormDefault") == Some("qualified"),
+        references: Vec::new(),
+    };
+    for child in &root.children {
+        loader.top_level(child)?;
+    }
+    loader.check_references()?;
+    loader.check_cycles(&root)?;
+    Ok(loader.model)
+}
+
+fn read_tree<R: Read>(mut reader: EventReader<R>) -> Result<Node> {
+    let mut stack: Vec<Node> = Vec::new();
+    loop {
+        match reader.next()? {
+            XmlEvent::StartElement { name, attributes, namespace } => stack.push(Node {
+                name,
+                attributes,
+                namespace,
+                pos: reader.position(),
+                children: Vec::new(),
+            }),
+            XmlEvent::EndElement { .. } => {
+                let node = stack.pop().unwrap();  // the reader checks that tags are balanced
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(node),
+                    None => return Ok(node)
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn error<T, M: Into<Cow<'static, str>>>(node: &Node, msg: M) -> Result<T> {
+    Err(SchemaError::Invalid { pos: node.pos, msg: msg.into() })
+}
+
+impl Node {
+    fn is_xsd(&self, local_name: &str) -> bool {
+        self.name.namespace_ref() == Some(NS_XSD_URI) && self.name.local_name == local_name
+    }
+
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attributes.iter()
+            .find(|a| a.name.namespace.is_none() && a.name.local_name == name)
+            .map(|a| a.value.trim())
+    }
+
+    fn required_attr(&self, name: &str) -> Result<&str> {
+        match self.attr(name) {
+            Some(value) => Ok(value),
+            None => error(self, format!("Attribute {} is required on {}", name, self.name.local_name))
+        }
+    }
+
+    /// Returns child elements except annotations.
+    fn content(&self) -> impl Iterator<Item=&Node> {
+        self.children.iter().filter(|c| !c.is_xsd("annotation"))
+    }
+
+    /// Resolves a QName-valued attribute using the namespace declarations in scope.
+    fn qname(&self, value: &str) -> Result<OwnedName> {
+        let (prefix, local_name) = match value.find(':') {
+            Some(i) => (&value[..i], &value[i + 1..]),
+            None => ("", value)
+        };
+        if !is_ncname(local_name) || !prefix.is_empty() && !is_ncname(prefix) {
+            return error(self, format!("Invalid qualified name: {}", value));
+        }
+        let namespace = match self.namespace.get(prefix) {
+            Some("") => None,
+            Some(ns) => Some(ns.to_owned()),
+            None if prefix.is_empty() => None,
+            None => return error(self, format!("Namespace prefix {} is not bound", prefix))
+        };
+        Ok(OwnedName { local_name: local_name.into(), namespace, prefix: None })
+    }
+
+    fn occurs(&self) -> Result<(usize, Option<usize>)> {
+        let parse = |name, value: &str| value.parse::<usize>()
+            .or_else(|_| error(self, format!("Invalid value of {}: {}", name, value)));
+        let min = match self.attr("minOccurs") {
+            Some(value) => parse("minOccurs", value)?,
+            None => 1
+        };
+        let max = match self.attr("maxOccurs") {
+            Some("unbounded") => None,
+            Some(value) => Some(parse("maxOccurs", value)?),
+            None => Some(1)
+        };
+        if max.map_or(false, |max| max < min) {
+            return error(self, "maxOccurs must not be less than minOccurs");
+        }
+        Ok((min, max))
+    }
+}
+
+impl Loader {
+    fn name(&self, local_name: &str, qualified: bool) -> OwnedName {
+        OwnedName {
+            local_name: local_name.into(),
+            namespace: if qualified { self.model.target_namespace.clone() } else { None },
+            prefix: None,
+        }
+    }
+
+    fn reference(&mut self, kind: ComponentKind, node: &Node, value: &str) -> Result<OwnedName> {
+        let name = node.qname(value)?;
+        self.references.push((kind, name.clone(), node.pos));
+        Ok(name)
+    }
+
+    fn top_level(&mut self, node: &Node) -> Result<()> {
+        if node.name.namespace_ref() != Some(NS_XSD_URI) {
+            return error(node, format!("Unexpected element in schema: {}", node.name));
+        }
+        let duplicate = match &node.name.local_name[..] {
+            "annotation" | "notation" => false,
+            "element" => {
+                let decl = self.element(node, true)?;
+                self.model.elements.insert(decl.name.clone(), decl).is_some()
+            }
+            "attribute" => {
+                let (decl, _) = self.attribute(node, true)?;
+                self.model.attributes.insert(decl.name.clone(), decl).is_some()
+            }
+            "simpleType" | "complexType" => {
+                let name = self.name(node.required_attr("name")?, true);
+                let def = self.type_def(node)?;
+                self.model.types.insert(name, def).is_some()
+            }
+            "group" => {
+                let name = self.name(node.required_attr("name")?, true);
+                let particle = match node.content().next() {
+                    Some(child) if child.is_xsd("sequence") || child.is_xsd("choice") || child.is_xsd("all") =>
+                        Particle { min: 1, max: Some(1), term: self.model_group(child)? },
+                    _ => return error(node, "A model group definition must contain a sequence, choice or all")
+                };
+                self.model.groups.insert(name, particle).is_some()
+            }
+            "attributeGroup" => {
+                let name = self.name(node.required_attr("name")?, true);
+                let set = self.attribute_set(node.content())?;
+                self.model.attribute_groups.insert(name, set).is_some()
+            }
+            "import" | "include" | "redefine" =>
+                return error(node, format!("xs:{} is not supported", node.name.local_name)),
+            _ => return error(node, format!("Unexpected element in schema: {}", node.name))
+        };
+        if duplicate {
+            return error(node, format!("Duplicate declaration of {} {}",
+                                       node.name.local_name, node.attr("name").unwrap_or("")));
+        }
+        Ok(())
+    }
+
+    fn element(&mut self, node: &Node, global: bool) -> Result<ElementDecl> {
+        if node.attr("substitutionGroup").is_some() {
+            return error(node, "Substitution groups are not supported");
+        }
+        let qualified = global || match node.attr("form") {
+            Some(form) => form == "qualified",
+            None => self.element_qualified
+        };
+        let name = self.name(node.required_attr("name")?, qualified);
+        let mut inline = None;
+        for child in node.content() {
+            match &child.name.local_name[..] {
+                "simpleType" | "complexType" if child.name.namespace_ref() == Some(NS_XSD_URI) =>
+                    inline = Some(TypeRef::Inline(Box::new(self.type_def(child)?))),
+                "unique" | "key" | "keyref" if child.name.namespace_ref() == Some(NS_XSD_URI) => {}
+                _ => return error(child, format!("Unexpected element in element declaration: {}", child.name))
+            }
+        }
+        let type_ref = match (node.attr("type"), inline) {
+            (Some(_), Some(_)) => return error(node, "An element declaration cannot have both a type attribute and an inline type"),
+            (Some(t), None) => TypeRef::Named(self.reference(ComponentKind::Type, node, t)?),
+            (None, Some(inline)) => inline,
+            (None, None) => TypeRef::Named(OwnedName::qualified("anyType", NS_XSD_URI, None::<&str>)),
+        };
+        Ok(ElementDecl {
+            name,
+            type_ref,
+            fixed: node.attr("fixed").map(|s| s.to_owned()),
+            default: node.attr("default").map(|s| s.to_owned()),
+        })
+    }
+
+    fn attribute(&mut self, node: &Node, global: bool) -> Result<(AttributeDecl, AttributeUse)> {
+        let qualified = global || match node.attr("form") {
+            Some(form) => form == "qualified",
+            None => self.attribute_qualified
+        };
+        let name = self.name(node.required_attr("name")?, qualified);
+        let mut inline = None;
+        for child in node.content() {
+            if child.is_xsd("simpleType") {
+                inline = Some(TypeRef::Inline(Box::new(self.type_def(child)?)));
+            } else {
+                return error(child, format!("Unexpected element in attribute declaration: {}", child.name));
+            }
+        }
+        let type_ref = match (node.attr("type"), inline) {
+            (Some(_), Some(_)) => return error(node, "An attribute declaration cannot have both a type attribute and an inline type"),
+            (Some(t), None) => TypeRef::Named(self.reference(ComponentKind::Type, node, t)?),
+            (None, Some(inline)) => inline,
+            (None, None) => TypeRef::Named(OwnedName::qualified("anySimpleType", NS_XSD_URI, None::<&str>)),
+        };
+        let decl = AttributeDecl { name, type_ref, fixed: node.attr("fixed").map(|s| s.to_owned()) };
+        Ok((decl, attribute_use(node)?))
+    }
+
+    fn type_def(&mut self, node: &Node) -> Result<TypeDef> {
+        if node.is_xsd("simpleType") {
+            self.simple_type(node).map(TypeDef::Simple)
+        } else {
+            self.complex_type(node).map(TypeDef::Complex)
+        }
+    }
+
+    fn simple_type(&mut self, node: &Node) -> Result<SimpleType> {
+        let child = match node.content().next() {
+            Some(child) => child,
+            None => return error(node, "A simple type must contain a restriction, list or union")
+        };
+        if child.is_xsd("restriction") {
+            let mut base = match child.attr("base") {
+                Some(base) => Some(TypeRef::Named(self.reference(ComponentKind::Type, child, base)?)),
+                None => None
+            };
+            let mut facets = Vec::new();
+            for facet in child.content() {
+                if facet.is_xsd("simpleType") {
+                    base = Some(TypeRef::Inline(Box::new(TypeDef::Simple(self.simple_type(facet)?))));
+                } else {
+                    add_facet(&mut facets, facet)?;
+                }
+            }
+            match base {
+                Some(base) => Ok(SimpleType::Restriction { base, facets }),
+                None => error(child, "A restriction must have a base type")
+            }
+        } else if child.is_xsd("list") {
+            let item = match (child.attr("itemType"), child.content().find(|c| c.is_xsd("simpleType"))) {
+                (Some(t), None) => TypeRef::Named(self.reference(ComponentKind::Type, child, t)?),
+                (None, Some(inline)) => TypeRef::Inline(Box::new(TypeDef::Simple(self.simple_type(inline)?))),
+                _ => return error(child, "A list must have exactly one item type")
+            };
+            Ok(SimpleType::List(item))
+        } else if child.is_xsd("union") {
+            let mut members = Vec::new();
+            for t in child.attr("memberTypes").unwrap_or("").split_whitespace() {
+                members.push(TypeRef::Named(self.reference(ComponentKind::Type, child, t)?));
+            }
+            for inline in child.content() {
+                members.push(TypeRef::Inline(Box::new(TypeDef::Simple(self.simple_type(inline)?))));
+            }
+            if members.is_empty() {
+                return error(child, "A union must have member types");
+            }
+            Ok(SimpleType::Union(members))
+        } else {
+            error(child, format!("Unexpected element in simple type: {}", child.name))
+        }
+    }
+
+    fn complex_type(&mut self, node: &Node) -> Result<ComplexType> {
+        let mut mixed = node.attr("mixed") == Some("true");
+        let first = node.content().next();
+        match first {
+            Some(child) if child.is_xsd("simpleContent") => {
+                let derivation = match child.content().next() {
+                    Some(d) if d.is_xsd("extension") || d.is_xsd("restriction") => d,
+                    _ => return error(child, "Simple content must contain an extension or restriction")
+                };
+                let base = self.reference(ComponentKind::Type, derivation, derivation.required_attr("base")?)?;
+                if derivation.is_xsd("extension") {
+                    Ok(ComplexType {
+                        derivation: Some(Derivation::Extension(base.clone())),
+                        mixed: false,
+                        content: Content::Simple(TypeRef::Named(base)),
+                        attributes: self.attribute_set(derivation.content())?,
+                    })
+                } else {
+                    let mut facets = Vec::new();
+                    let mut attributes = Vec::new();
+                    for c in derivation.content() {
+                        if c.is_xsd("attribute") || c.is_xsd("attributeGroup") || c.is_xsd("anyAttribute") {
+                            attributes.push(c);
+                        } else if c.is_xsd("simpleType") {
+                            return error(c, "Inline simple types in simple content restrictions are not supported");
+                        } else {
+                            add_facet(&mut facets, c)?;
+                        }
+                    }
+                    let content = SimpleType::Restriction { base: TypeRef::Named(base.clone()), facets };
+                    Ok(ComplexType {
+                        derivation: Some(Derivation::Restriction(base)),
+                        mixed: false,
+                        content: Content::Simple(TypeRef::Inline(Box::new(TypeDef::Simple(content)))),
+                        attributes: self.attribute_set(attributes.into_iter())?,
+                    })
+                }
+            }
+            Some(child) if child.is_xsd("complexContent") => {
+                if let Some(m) = child.attr("mixed") {
+                    mixed = m == "true";
+                }
+                let derivation = match child.content().next() {
+                    Some(d) if d.is_xsd("extension") || d.is_xsd("restriction") => d,
+                    _ => return error(child, "Complex content must contain an extension or restriction")
+                };
+                let base = self.reference(ComponentKind::Type, derivation, derivation.required_attr("base")?)?;
+                let (content, attributes) = self.content_and_attributes(derivation)?;
+                Ok(ComplexType {
+                    derivation: Some(if derivation.is_xsd("extension") {
+                        Derivation::Extension(base)
+                    } else {
+                        Derivation::Restriction(base)
+                    }),
+                    mixed,
+                    content,
+                    attributes,
+                })
+            }
+            _ => {
+                let (content, attributes) = self.content_and_attributes(node)?;
+                Ok(ComplexType { derivation: None, mixed, content, attributes })
+            }
+        }
+    }
+
+    /// Parses an optional model group followed by attribute declarations.
+    fn content_and_attributes(&mut self, node: &Node) -> Result<(Content, AttributeSet)> {
+        let mut children = node.content().peekable();
+        let content = match children.peek() {
+            Some(c) if c.is_xsd("sequence") || c.is_xsd("choice") || c.is_xsd("all") || c.is_xsd("group") => {
+                let particle = self.particle(c)?;
+                children.next();
+                Content::Elements(particle)
+            }
+            _ => Content::Empty
+        };
+        Ok((content, self.attribute_set(children)?))
+    }
+
+    fn attribute_set<'a, I: Iterator<Item=&'a Node>>(&mut self, nodes: I) -> Result<AttributeSet> {
+        let mut set = AttributeSet { items: Vec::new(), wildcard: None };
+        for node in nodes {
+            if node.is_xsd("attribute") {
+                match node.attr("ref") {
+                    Some(r) => {
+                        let name = self.reference(ComponentKind::Attribute, node, r)?;
+                        let fixed = node.attr("fixed").map(|s| s.to_owned());
+                        set.items.push(AttributeItem::AttributeRef(name, attribute_use(node)?, fixed));
+                    }
+                    None => {
+                        let (decl, use_) = self.attribute(node, false)?;
+                        set.items.push(AttributeItem::Attribute(decl, use_));
+                    }
+                }
+            } else if node.is_xsd("attributeGroup") {
+                let name = self.reference(ComponentKind::AttributeGroup, node, node.required_attr("ref")?)?;
+                set.items.push(AttributeItem::GroupRef(name));
+            } else if node.is_xsd("anyAttribute") {
+                set.wildcard = Some(self.wildcard(node)?);
+            } else {
+                return error(node, format!("Unexpected element in attribute declarations: {}", node.name));
+            }
+        }
+        Ok(set)
+    }
+
+    fn particle(&mut self, node: &Node) -> Result<Particle> {
+        let (min, max) = node.occurs()?;
+        let term = match &node.name.local_name[..] {
+            _ if node.name.namespace_ref() != Some(NS_XSD_URI) =>
+                return error(node, format!("Unexpected element in model group: {}", node.name)),
+            "element" => match node.attr("ref") {
+                Some(r) => Term::ElementRef(self.reference(ComponentKind::Element, node, r)?),
+                None => Term::Element(self.element(node, false)?)
+            },
+            "group" => Term::GroupRef(self.reference(ComponentKind::Group, node, node.required_attr("ref")?)?),
+            "sequence" | "choice" | "all" => self.model_group(node)?,
+            "any" => Term::Any(self.wildcard(node)?),
+            _ => return error(node, format!("Unexpected element in model group: {}", node.name))
+        };
+        Ok(Particle { min, max, term })
+    }
+
+    fn model_group(&mut self, node: &Node) -> Result<Term> {
+        let mut particles = Vec::new();
+        for child in node.content() {
+            particles.push(self.particle(child)?);
+        }
+        Ok(match &node.name.local_name[..] {
+            "sequence" => Term::Sequence(particles),
+            "choice" => Term::Choice(particles),
+            _ => {
+                let valid = particles.iter().all(|p| match p.term {
+                    Term::Element(_) | Term::ElementRef(_) => p.max.map_or(false, |max| max <= 1),
+                    _ => false
+                });
+                if !valid {
+                    return error(node, "xs:all may only contain elements which occur at most once");
+                }
+                Term::All(particles)
+            }
+        })
+    }
+
+    fn wildcard(&mut self, node: &Node) -> Result<Wildcard> {
+        let tns = self.model.target_namespace.clone();
+        let namespaces = match node.attr("namespace").unwrap_or("##any") {
+            "##any" => NamespaceConstraint::Any,
+            "##other" => NamespaceConstraint::Not(tns),
+            list => NamespaceConstraint::List(list.split_whitespace().map(|ns| match ns {
+                "##targetNamespace" => tns.clone(),
+                "##local" => None,
+                ns => Some(ns.to_owned())
+            }).collect())
+        };
+        let process_contents = match node.attr("processContents").unwrap_or("strict") {
+            "strict" => ProcessContents::Strict,
+            "lax" => ProcessContents::Lax,
+            "skip" => ProcessContents::Skip,
+            other => return error(node, format!("Invalid value of processContents: {}", other))
+        };
+        Ok(Wildcard { namespaces, process_contents })
+    }
+
+    fn check_references(&self) -> Result<()> {
+        for &(kind, ref name, pos) in &self.references {
+            let (exists, what) = match kind {
+                ComponentKind::Type => (self.model.resolve_name(name).is_some(), "type"),
+                ComponentKind::Element => (self.model.elements.contains_key(name), "element"),
+                ComponentKind::Attribute => (self.model.attributes.contains_key(name), "attribute"),
+                ComponentKind::Group => (self.model.groups.contains_key(name), "group"),
+                ComponentKind::AttributeGroup => (self.model.attribute_groups.contains_key(name), "attribute group"),
+            };
+            if !exists {
+                return Err(SchemaError::Invalid { pos, msg: format!("Unknown {}: {}", what, name).into() });
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that no named type is derived from itself and no named group contains itself,
+    /// because otherwise validation would never terminate.
+    fn check_cycles(&self, root: &Node) -> Result<()> {
+        for (name, def) in &self.model.types {
+            let mut seen = HashSet::new();
+            let mut current = Some(def);
+            while let Some(def) = current {
+                let base = match *def {
+                    TypeDef::Simple(SimpleType::Restriction { base: TypeRef::Named(ref base), .. }) => base,
+                    TypeDef::Complex(ComplexType { derivation: Some(Derivation::Extension(ref base)), .. }) |
+                    TypeDef::Complex(ComplexType { derivation: Some(Derivation::Restriction(ref base)), .. }) => base,
+                    _ => break
+                };
+                if base == name || !seen.insert(base) {
+                    return error(root, format!("Type {} is derived from itself", name));
+                }
+                current = self.model.types.get(base);
+            }
+        }
+        for (name, particle) in &self.model.groups {
+            if self.group_contains(particle, name, &mut HashSet::new()) {
+                return error(root, format!("Group {} contains itself", name));
+            }
+        }
+        for (name, set) in &self.model.attribute_groups {
+            if self.attribute_group_contains(set, name, &mut HashSet::new()) {
+                return error(root, format!("Attribute group {} contains itself", name));
+            }
+        }
+        Ok(())
+    }
+
+    fn group_contains<'a>(&'a self, particle: &'a Particle, name: &OwnedName, seen: &mut HashSet<&'a OwnedName>) -> bool {
+        match particle.term {
+            Term::GroupRef(ref r) => r == name || seen.insert(r) &&
+                self.model.groups.get(r).map_or(false, |p| self.group_contains(p, name, seen)),
+            Term::Sequence(ref ps) | Term::Choice(ref ps) | Term::All(ref ps) =>
+                ps.iter().any(|p| self.group_contains(p, name, seen)),
+            _ => false
+        }
+    }
+
+    fn attribute_group_contains<'a>(&'a self, set: &'a AttributeSet, name: &OwnedName, seen: &mut HashSet<&'a OwnedName>) -> bool {
+        set.items.iter().any(|item| match *item {
+            AttributeItem::GroupRef(ref r) => r == name || seen.insert(r) &&
+                self.model.attribute_groups.get(r).map_or(false, |s| self.attribute_group_contains(s, name, seen)),
+            _ => false
+        })
+    }
+}
+
+fn attribute_use(node: &Node) -> Result<AttributeUse> {
+    match node.attr("use").unwrap_or("optional") {
+        "optional" => Ok(AttributeUse::Optional),
+        "required" => Ok(AttributeUse::Required),
+        "prohibited" => Ok(AttributeUse::Prohibited),
+        other => error(node, format!("Invalid value of use: {}", other))
+    }
+}
+
+fn add_facet(facets: &mut Vec<Facet>, node: &Node) -> Result<()> {
+    if node.name.namespace_ref() != Some(NS_XSD_URI) {
+        return error(node, format!("Unexpected element in restriction: {}", node.name));
+    }
+    let value = node.required_attr("value")?;
+    let number = || value.parse::<usize>()
+        .or_else(|_| error(node, format!("Invalid value of {} facet: {}", node.name.local_name, value)));
+    let facet = match &node.name.local_name[..] {
+        "enumeration" => {
+            // all enumeration facets of a restriction form a single set of values
+            for facet in facets.iter_mut() {
+                if let Facet::Enumeration(ref mut values) = *facet {
+                    values.push(value.into());
+                    return Ok(());
+                }
+            }
+            Facet::Enumeration(vec![value.into()])
+        }
+        "pattern" => {
+            // the original value is used, because whitespace is significant in patterns
+            let pattern = node.attributes.iter().find(|a| a.name.local_name == "value").unwrap();
+            let regex = match Regex::new(&pattern.value) {
+                Ok(regex) => regex,
+                Err(e) => return error(node, e)
+            };
+            for facet in facets.iter_mut() {
+                if let Facet::Pattern(ref mut regexes) = *facet {
+                    regexes.push(regex);
+                    return Ok(());
+                }
+            }
+            Facet::Pattern(vec![regex])
+        }
+        "length" => Facet::Length(number()?),
+        "minLength" => Facet::MinLength(number()?),
+        "maxLength" => Facet::MaxLength(number()?),
+        "totalDigits" => Facet::TotalDigits(number()?),
+        "fractionDigits" => Facet::FractionDigits(number()?),
+        "minInclusive" => Facet::MinInclusive(value.into()),
+        "maxInclusive" => Facet::MaxInclusive(value.into()),
+        "minExclusive" => Facet::MinExclusive(value.into()),
+        "maxExclusive" => Facet::MaxExclusive(value.into()),
+        "whiteSpace" => return Ok(()),
+        _ => return error(node, format!("Unexpected element in restriction: {}", node.name))
+    };
+    facets.push(facet);
+    Ok(())
+}
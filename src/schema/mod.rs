@@ -0,0 +1,238 @@
+//! Contains a validator of XML documents against XML Schema 1.0 (XSD) schemas.
+//!
+//! A `Schema` is loaded from an `EventReader` reading a schema document. It can then be used
+//! to validate instance documents: `Schema::validate()` wraps an iterator over reader events,
+//! like `reader::Events`, into a `Validator` which passes all events through and additionally
+//! yields a `ValidationError` for every violation of the schema it finds.
+//!
+//! ```rust
+//! use xml::EventReader;
+//! use xml::schema::{Schema, Error};
+//!
+//! let schema = Schema::from_reader(EventReader::from_str(r#"
+//!     <xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">
+//!       <xs:element name="count" type="xs:nonNegativeInteger"/>
+//!     </xs:schema>
+//! "#)).unwrap();
+//!
+//! let errors: Vec<_> = schema.validate(EventReader::from_str("<count>-1</count>").into_iter())
+//!     .filter_map(|e| match e {
+//!         Err(Error::Validation(e)) => Some(e.to_string()),
+//!         _ => None
+//!     })
+//!     .collect();
+//! assert_eq!(errors, ["1:10 Value '-1' is not a valid nonNegativeInteger in element count"]);
+//! ```
+//!
+//! Most of XSD 1.0 structures are supported: global and local element and attribute
+//! declarations, named and anonymous simple and complex types, derivation of complex types by
+//! extension and restriction, simple content, model groups with occurrence constraints,
+//! wildcards, named model and attribute groups and all built-in simple types with their facets.
+//! Identity constraints, substitution groups, `xsi:type` and `xsi:nil` are not supported,
+//! and `xs:import`, `xs:include` and `xs:redefine` result in an error when the schema is loaded.
+
+use std::borrow::Cow;
+use std::error;
+use std::fmt;
+use std::io::Read;
+
+use common::{Position, TextPosition};
+use reader::{self, EventReader};
+
+pub use self::validator::Validator;
+
+mod model;
+mod loader;
+mod types;
+mod regex;
+mod validator;
+
+/// The namespace of XML Schema definition documents.
+pub const NS_XSD_URI: &'static str = "http://www.w3.org/2001/XMLSchema";
+
+/// The namespace of XML Schema attributes used in instance documents, like `xsi:type`.
+pub const NS_XSI_URI: &'static str = "http://www.w3.org/2001/XMLSchema-instance";
+
+/// A compiled XML schema.
+pub struct Schema {
+    model: model::Model,
+}
+
+impl Schema {
+    /// Loads a schema from a schema document.
+    pub fn from_reader<R: Read>(reader: EventReader<R>) -> Result<Schema, SchemaError> {
+        loader::load(reader).map(|model| Schema { model })
+    }
+
+    /// Returns the target namespace of the schema.
+    #[inline]
+    pub fn target_namespace(&self) -> Option<&str> {
+        self.model.target_namespace.as_ref().map(|s| &s[..])
+    }
+
+    /// Wraps an iterator over reader events into a validator which checks the events
+    /// against this schema.
+    ///
+    /// The iterator should produce events of a whole document; `reader::Events` is the most
+    /// common example.
+    #[inline]
+    pub fn validate<I>(&self, events: I) -> Validator<'_, I>
+        where I: Iterator<Item=reader::Result<reader::XmlEvent>> + Position
+    {
+        Validator::new(&self.model, events)
+    }
+}
+
+/// An error which may be returned when loading a schema.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum SchemaError {
+    /// The schema document is not a well-formed XML document.
+    Reader(reader::Error),
+
+    /// The schema document is not a valid or supported schema.
+    Invalid {
+        /// Position of the offending schema component.
+        pos: TextPosition,
+
+        /// Description of the error.
+        msg: Cow<'static, str>,
+    },
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SchemaError::Reader(ref e) => e.fmt(f),
+            SchemaError::Invalid { ref pos, ref msg } => write!(f, "{} {}", pos, msg),
+        }
+    }
+}
+
+impl Position for SchemaError {
+    #[inline]
+    fn position(&self) -> TextPosition {
+        match *self {
+            SchemaError::Reader(ref e) => e.position(),
+            SchemaError::Invalid { pos, .. } => pos,
+        }
+    }
+}
+
+impl error::Error for SchemaError {
+    fn description(&self) -> &str {
+        match *self {
+            SchemaError::Reader(ref e) => e.msg(),
+            SchemaError::Invalid { ref msg, .. } => msg,
+        }
+    }
+}
+
+impl From<reader::Error> for SchemaError {
+    #[inline]
+    fn from(e: reader::Error) -> SchemaError {
+        SchemaError::Reader(e)
+    }
+}
+
+/// The kind of a validation error.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ValidationErrorKind {
+    /// The root element has no global declaration in the schema.
+    UnknownRootElement,
+
+    /// An element is not allowed at its place by the content model of its parent.
+    UnexpectedElement,
+
+    /// The child elements of an element do not match its content model.
+    InvalidContent,
+
+    /// Character data is not allowed at its place.
+    UnexpectedCharacters,
+
+    /// An attribute is not declared for the element or is prohibited.
+    UnexpectedAttribute,
+
+    /// A required attribute is missing.
+    MissingAttribute,
+
+    /// A value of an attribute or of an element with simple content does not match its type.
+    InvalidValue,
+
+    /// An ID value is used more than once in the document.
+    DuplicateId,
+
+    /// An IDREF value does not match any ID in the document.
+    UnknownIdRef,
+}
+
+/// An error found during validation of a document.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ValidationError {
+    pos: TextPosition,
+    kind: ValidationErrorKind,
+    msg: String,
+}
+
+impl ValidationError {
+    /// Returns the kind of this error.
+    #[inline]
+    pub fn kind(&self) -> ValidationErrorKind { self.kind }
+
+    /// Returns a message describing this error.
+    #[inline]
+    pub fn msg(&self) -> &str { &self.msg }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.pos, self.msg)
+    }
+}
+
+impl Position for ValidationError {
+    #[inline]
+    fn position(&self) -> TextPosition { self.pos }
+}
+
+impl error::Error for ValidationError {
+    #[inline]
+    fn description(&self) -> &str { &self.msg }
+}
+
+/// An item produced by `Validator` which is not an event.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Error {
+    /// The underlying reader failed; no more events follow.
+    Reader(reader::Error),
+
+    /// The document is not valid. Validation continues after such errors.
+    Validation(ValidationError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Reader(ref e) => e.fmt(f),
+            Error::Validation(ref e) => e.fmt(f),
+        }
+    }
+}
+
+impl Position for Error {
+    #[inline]
+    fn position(&self) -> TextPosition {
+        match *self {
+            Error::Reader(ref e) => e.position(),
+            Error::Validation(ref e) => e.position(),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::Reader(ref e) => e.msg(),
+            Error::Validation(ref e) => e.msg(),
+        }
+    }
+}
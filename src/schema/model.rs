@@ -0,0 +1,253 @@
+//! Contains schema components as they are used by the validator.
+//!
+//! All names are expanded names, i.e. `OwnedName`s with a namespace and without a prefix.
+//! References between components are kept as names and are resolved during validation;
+//! the loader checks that all of them can be resolved.
+
+use std::collections::HashMap;
+
+use name::OwnedName;
+
+use super::NS_XSD_URI;
+use super::regex::Regex;
+use super::types::Builtin;
+
+pub struct Model {
+    pub target_namespace: Option<String>,
+    pub elements: HashMap<OwnedName, ElementDecl>,
+    pub attributes: HashMap<OwnedName, AttributeDecl>,
+    pub types: HashMap<OwnedName, TypeDef>,
+    pub groups: HashMap<OwnedName, Particle>,
+    pub attribute_groups: HashMap<OwnedName, AttributeSet>,
+}
+
+pub enum TypeDef {
+    Simple(SimpleType),
+    Complex(ComplexType),
+}
+
+pub enum TypeRef {
+    Named(OwnedName),
+    Inline(Box<TypeDef>),
+}
+
+pub enum SimpleType {
+    Restriction { base: TypeRef, facets: Vec<Facet> },
+    List(TypeRef),
+    Union(Vec<TypeRef>),
+}
+
+pub enum Facet {
+    Length(usize),
+    MinLength(usize),
+    MaxLength(usize),
+    Enumeration(Vec<String>),
+    /// Patterns specified in the same derivation step; a value must match any of them.
+    Pattern(Vec<Regex>),
+    MinInclusive(String),
+    MaxInclusive(String),
+    MinExclusive(String),
+    MaxExclusive(String),
+    TotalDigits(usize),
+    FractionDigits(usize),
+}
+
+pub struct ComplexType {
+    pub derivation: Option<Derivation>,
+    pub mixed: bool,
+    pub content: Content,
+    pub attributes: AttributeSet,
+}
+
+pub enum Derivation {
+    Extension(OwnedName),
+    Restriction(OwnedName),
+}
+
+pub enum Content {
+    Empty,
+    Simple(TypeRef),
+    Elements(Particle),
+}
+
+pub struct Particle {
+    pub min: usize,
+    pub max: Option<usize>,
+    pub term: Term,
+}
+
+pub enum Term {
+    Element(ElementDecl),
+    ElementRef(OwnedName),
+    GroupRef(OwnedName),
+    Sequence(Vec<Particle>),
+    Choice(Vec<Particle>),
+    All(Vec<Particle>),
+    Any(Wildcard),
+}
+
+pub struct ElementDecl {
+    pub name: OwnedName,
+    pub type_ref: TypeRef,
+    pub fixed: Option<String>,
+    pub default: Option<String>,
+}
+
+pub struct AttributeDecl {
+    pub name: OwnedName,
+    pub type_ref: TypeRef,
+    pub fixed: Option<String>,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum AttributeUse {
+    Optional,
+    Required,
+    Prohibited,
+}
+
+pub enum AttributeItem {
+    Attribute(AttributeDecl, AttributeUse),
+    /// A reference to a global attribute declaration, with an optional fixed value
+    /// overriding the one from the declaration.
+    AttributeRef(OwnedName, AttributeUse, Option<String>),
+    GroupRef(OwnedName),
+}
+
+pub struct AttributeSet {
+    pub items: Vec<AttributeItem>,
+    pub wildcard: Option<Wildcard>,
+}
+
+pub struct Wildcard {
+    pub namespaces: NamespaceConstraint,
+    pub process_contents: ProcessContents,
+}
+
+pub enum NamespaceConstraint {
+    Any,
+    /// Any namespace except the given one and no namespace.
+    Not(Option<String>),
+    List(Vec<Option<String>>),
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum ProcessContents {
+    Strict,
+    Lax,
+    Skip,
+}
+
+/// A type definition with all names resolved.
+#[derive(Copy, Clone)]
+pub enum Resolved<'a> {
+    AnyType,
+    Builtin(Builtin),
+    Simple(&'a SimpleType),
+    Complex(&'a ComplexType),
+}
+
+impl Wildcard {
+    pub fn allows(&self, namespace: Option<&str>) -> bool {
+        match self.namespaces {
+            NamespaceConstraint::Any => true,
+            NamespaceConstraint::Not(ref ns) => namespace.is_some() && namespace != ns.as_ref().map(|s| &s[..]),
+            NamespaceConstraint::List(ref list) => list.iter().any(|ns| ns.as_ref().map(|s| &s[..]) == namespace),
+        }
+    }
+}
+
+impl Model {
+    /// Resolves a type reference; `None` is returned for names of unknown types.
+    pub fn resolve<'a>(&'a self, type_ref: &'a TypeRef) -> Option<Resolved<'a>> {
+        match *type_ref {
+            TypeRef::Inline(ref def) => Some(resolved(def)),
+            TypeRef::Named(ref name) => self.resolve_name(name),
+        }
+    }
+
+    pub fn resolve_name(&self, name: &OwnedName) -> Option<Resolved<'_>> {
+        if name.namespace_ref() == Some(NS_XSD_URI) {
+            if name.local_name == "anyType" {
+                return Some(Resolved::AnyType);
+            }
+            if let Some(builtin) = Builtin::from_name(&name.local_name) {
+                return Some(Resolved::Builtin(builtin));
+            }
+        }
+        self.types.get(name).map(resolved)
+    }
+
+    /// Returns the content of a complex type, taking its derivation into account.
+    ///
+    /// The content of a type derived by extension is a sequence of the content of its base
+    /// type and its own content, so the content is returned as a list of parts; empty parts
+    /// are omitted. Simple content is always returned as the only part.
+    pub fn content<'a>(&'a self, ty: &'a ComplexType) -> Vec<&'a Content> {
+        if let Content::Simple(_) = ty.content {
+            return vec![&ty.content];
+        }
+        let mut parts = match ty.derivation {
+            Some(Derivation::Extension(ref base)) => match self.resolve_name(base) {
+                Some(Resolved::Complex(base)) => self.content(base),
+                _ => Vec::new()
+            },
+            _ => Vec::new()
+        };
+        parts.retain(|c| match **c { Content::Elements(_) => true, _ => false });
+        if let Content::Elements(_) = ty.content {
+            parts.push(&ty.content);
+        }
+        parts
+    }
+
+    /// Calls `f` for all attribute declarations of a complex type, including those from its
+    /// base types and attribute groups, and returns the attribute wildcard of the type.
+    ///
+    /// Declarations of derived types are visited before the declarations of their base
+    /// types, so the first declaration found for a name is the effective one.
+    pub fn attributes<'a, F>(&'a self, ty: &'a ComplexType, f: &mut F) -> Option<&'a Wildcard>
+        where F: FnMut(&'a AttributeDecl, AttributeUse, Option<&'a str>)
+    {
+        let wildcard = self.attribute_set(&ty.attributes, f);
+        let base = match ty.derivation {
+            Some(Derivation::Extension(ref base)) | Some(Derivation::Restriction(ref base)) => base,
+            None => return wildcard
+        };
+        match self.resolve_name(base) {
+            Some(Resolved::Complex(base)) => {
+                let base_wildcard = self.attributes(base, f);
+                match ty.derivation {
+                    Some(Derivation::Extension(_)) => wildcard.or(base_wildcard),
+                    _ => wildcard
+                }
+            }
+            _ => wildcard
+        }
+    }
+
+    fn attribute_set<'a, F>(&'a self, set: &'a AttributeSet, f: &mut F) -> Option<&'a Wildcard>
+        where F: FnMut(&'a AttributeDecl, AttributeUse, Option<&'a str>)
+    {
+        let mut wildcard = set.wildcard.as_ref();
+        for item in &set.items {
+            match *item {
+                AttributeItem::Attribute(ref decl, use_) => f(decl, use_, decl.fixed.as_ref().map(|s| &s[..])),
+                AttributeItem::AttributeRef(ref name, use_, ref fixed) => if let Some(decl) = self.attributes.get(name) {
+                    f(decl, use_, fixed.as_ref().or(decl.fixed.as_ref()).map(|s| &s[..]))
+                },
+                AttributeItem::GroupRef(ref name) => if let Some(group) = self.attribute_groups.get(name) {
+                    wildcard = wildcard.or(self.attribute_set(group, f));
+                },
+            }
+        }
+        wildcard
+    }
+}
+
+fn resolved(def: &TypeDef) -> Resolved<'_> {
+    match *def {
+        TypeDef::Simple(ref ty) => Resolved::Simple(ty),
+        TypeDef::Complex(ref ty) => Resolved::Complex(ty),
+    }
+}
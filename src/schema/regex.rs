@@ -0,0 +1,453 @@
+//! Contains a small implementation of XML Schema regular expressions.
+//!
+//! Expressions are compiled to a nondeterministic automaton which is simulated on all states
+//! at once, so matching takes time linear in the length of the value, whatever the pattern.
+//! XML Schema regular expressions always match the whole value, so they have no anchors.
+//! Branches, groups, quantifiers, character classes with ranges, negation and subtraction,
+//! single-character escapes and multi-character escapes (`\s`, `\d`, `\w`, `\i`, `\c` and
+//! their complements) are supported. Of the Unicode category escapes only the `L`, `Lu`,
+//! `Ll`, `N` and `Nd` categories are supported, and `\w` is approximated using ASCII
+//! punctuation instead of the Unicode punctuation category.
+
+use common::{is_name_start_char, is_name_char, is_whitespace_char};
+
+/// A compiled regular expression.
+pub struct Regex {
+    program: Vec<Inst>,
+    classes: Vec<Class>,
+}
+
+/// An instruction of the automaton; the state which follows an instruction is the next one.
+enum Inst {
+    /// Consumes a character of the class with the given index.
+    Class(usize),
+    /// Continues in both states.
+    Split(usize, usize),
+    Jump(usize),
+    Match,
+}
+
+struct Piece {
+    atom: Atom,
+    min: usize,
+    max: Option<usize>,
+}
+
+enum Atom {
+    Class(usize),
+    Group(Vec<Vec<Piece>>),
+}
+
+struct Class {
+    negated: bool,
+    items: Vec<ClassItem>,
+    subtracted: Option<Box<Class>>,
+}
+
+enum ClassItem {
+    Range(char, char),
+    Escape(Escape, bool),
+}
+
+#[derive(Copy, Clone)]
+enum Escape {
+    Any,
+    Space,
+    Digit,
+    Word,
+    NameStart,
+    NameChar,
+    Letter,
+    Uppercase,
+    Lowercase,
+}
+
+impl Regex {
+    /// Compiles a regular expression, returning a description of the syntax error on failure.
+    pub fn new(pattern: &str) -> Result<Regex, String> {
+        let mut parser = Parser { chars: pattern.chars().collect(), pos: 0, classes: Vec::new() };
+        let branches = parser.branches()?;
+        if parser.pos < parser.chars.len() {
+            return Err(format!("Unexpected '{}' in regular expression", parser.chars[parser.pos]));
+        }
+        let mut program = Vec::new();
+        compile_branches(&branches, &mut program);
+        program.push(Inst::Match);
+        Ok(Regex { program, classes: parser.classes })
+    }
+
+    /// Returns `true` if the whole string matches this expression.
+    pub fn is_match(&self, s: &str) -> bool {
+        // `seen` holds the step at which a state was last added, so that states reachable
+        // through loops which match the empty string are added only once
+        let mut seen = vec![None; self.program.len()];
+        let mut current = Vec::new();
+        let mut next = Vec::new();
+        self.add_state(0, 0, &mut seen, &mut current);
+        for (step, c) in s.chars().enumerate() {
+            for &pc in &current {
+                if let Inst::Class(class) = self.program[pc] {
+                    if self.classes[class].matches(c) {
+                        self.add_state(pc + 1, step + 1, &mut seen, &mut next);
+                    }
+                }
+            }
+            if next.is_empty() {
+                return false;
+            }
+            current.clear();
+            ::std::mem::swap(&mut current, &mut next);
+        }
+        // the only `Match` instruction is the last one
+        current.contains(&(self.program.len() - 1))
+    }
+
+    /// Adds the state and all states reachable from it without consuming a character.
+    fn add_state(&self, pc: usize, step: usize, seen: &mut [Option<usize>], states: &mut Vec<usize>) {
+        let mut stack = vec![pc];
+        while let Some(pc) = stack.pop() {
+            if seen[pc] == Some(step) {
+                continue;
+            }
+            seen[pc] = Some(step);
+            match self.program[pc] {
+                Inst::Jump(to) => stack.push(to),
+                Inst::Split(first, second) => {
+                    stack.push(second);
+                    stack.push(first);
+                }
+                Inst::Class(_) | Inst::Match => states.push(pc),
+            }
+        }
+    }
+}
+
+fn compile_branches(branches: &[Vec<Piece>], program: &mut Vec<Inst>) {
+    let mut jumps = Vec::new();
+    for (i, branch) in branches.iter().enumerate() {
+        if i + 1 == branches.len() {
+            compile_sequence(branch, program);
+        } else {
+            let split = program.len();
+            program.push(Inst::Split(split + 1, 0));
+            compile_sequence(branch, program);
+            jumps.push(program.len());
+            program.push(Inst::Jump(0));
+            program[split] = Inst::Split(split + 1, program.len());
+        }
+    }
+    let end = program.len();
+    for jump in jumps {
+        program[jump] = Inst::Jump(end);
+    }
+}
+
+fn compile_sequence(pieces: &[Piece], program: &mut Vec<Inst>) {
+    for piece in pieces {
+        compile_piece(piece, program);
+    }
+}
+
+/// Compiles a piece by repeating its atom `min` times, followed by either a loop or the
+/// optional repetitions up to `max`.
+fn compile_piece(piece: &Piece, program: &mut Vec<Inst>) {
+    for _ in 0..piece.min {
+        compile_atom(&piece.atom, program);
+    }
+    match piece.max {
+        None => {
+            let split = program.len();
+            program.push(Inst::Split(split + 1, 0));
+            compile_atom(&piece.atom, program);
+            program.push(Inst::Jump(split));
+            program[split] = Inst::Split(split + 1, program.len());
+        }
+        Some(max) => {
+            let mut splits = Vec::new();
+            for _ in piece.min..max {
+                splits.push(program.len());
+                program.push(Inst::Split(0, 0));
+                compile_atom(&piece.atom, program);
+            }
+            let end = program.len();
+            for split in splits {
+                program[split] = Inst::Split(split + 1, end);
+            }
+        }
+    }
+}
+
+fn compile_atom(atom: &Atom, program: &mut Vec<Inst>) {
+    match *atom {
+        Atom::Class(class) => program.push(Inst::Class(class)),
+        Atom::Group(ref branches) => compile_branches(branches, program),
+    }
+}
+
+impl Class {
+    fn matches(&self, c: char) -> bool {
+        let in_items = self.items.iter().any(|item| match *item {
+            ClassItem::Range(from, to) => from <= c && c <= to,
+            ClassItem::Escape(e, negated) => e.matches(c) != negated,
+        });
+        in_items != self.negated && !self.subtracted.as_ref().map_or(false, |s| s.matches(c))
+    }
+
+    fn single(item: ClassItem) -> Class {
+        Class { negated: false, items: vec![item], subtracted: None }
+    }
+}
+
+impl Escape {
+    fn matches(self, c: char) -> bool {
+        match self {
+            Escape::Any => c != '\n' && c != '\r',
+            Escape::Space => is_whitespace_char(c),
+            Escape::Digit => c.is_numeric(),
+            Escape::Word => !(c.is_ascii_punctuation() || c.is_whitespace() || c.is_control()),
+            Escape::NameStart => is_name_start_char(c) || c == ':',
+            Escape::NameChar => is_name_char(c) || c == ':',
+            Escape::Letter => c.is_alphabetic(),
+            Escape::Uppercase => c.is_uppercase(),
+            Escape::Lowercase => c.is_lowercase(),
+        }
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    classes: Vec<Class>,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn next(&mut self) -> Result<char, String> {
+        let c = self.peek().ok_or_else(|| "Unexpected end of regular expression".to_string())?;
+        self.pos += 1;
+        Ok(c)
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn branches(&mut self) -> Result<Vec<Vec<Piece>>, String> {
+        let mut branches = vec![self.sequence()?];
+        while self.eat('|') {
+            branches.push(self.sequence()?);
+        }
+        Ok(branches)
+    }
+
+    fn sequence(&mut self) -> Result<Vec<Piece>, String> {
+        let mut pieces = Vec::new();
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let atom = self.atom()?;
+            let (min, max) = self.quantifier()?;
+            pieces.push(Piece { atom, min, max });
+        }
+        Ok(pieces)
+    }
+
+    fn quantifier(&mut self) -> Result<(usize, Option<usize>), String> {
+        Ok(match self.peek() {
+            Some('?') => { self.pos += 1; (0, Some(1)) }
+            Some('*') => { self.pos += 1; (0, None) }
+            Some('+') => { self.pos += 1; (1, None) }
+            Some('{') => {
+                self.pos += 1;
+                let min = self.number()?;
+                let max = if self.eat(',') {
+                    if self.peek() == Some('}') { None } else { Some(self.number()?) }
+                } else {
+                    Some(min)
+                };
+                if !self.eat('}') {
+                    return Err("Unterminated quantifier in regular expression".into());
+                }
+                if max.map_or(false, |max| max < min) {
+                    return Err("Invalid quantifier in regular expression".into());
+                }
+                (min, max)
+            }
+            _ => (1, Some(1))
+        })
+    }
+
+    fn number(&mut self) -> Result<usize, String> {
+        let start = self.pos;
+        while self.peek().map_or(false, |c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect::<String>().parse()
+            .map_err(|_| "Invalid number in a quantifier of regular expression".to_string())
+    }
+
+    fn atom(&mut self) -> Result<Atom, String> {
+        match self.next()? {
+            '(' => {
+                let branches = self.branches()?;
+                if !self.eat(')') {
+                    return Err("Unterminated group in regular expression".into());
+                }
+                Ok(Atom::Group(branches))
+            }
+            '[' => {
+                let class = self.class()?;
+                Ok(self.class_atom(class))
+            }
+            '.' => Ok(self.class_atom(Class::single(ClassItem::Escape(Escape::Any, false)))),
+            '\\' => {
+                let item = self.escape()?;
+                Ok(self.class_atom(Class::single(item)))
+            }
+            c @ '?' | c @ '*' | c @ '+' | c @ '{' | c @ '}' | c @ ']' =>
+                Err(format!("Unexpected '{}' in regular expression", c)),
+            c => Ok(self.class_atom(Class::single(ClassItem::Range(c, c)))),
+        }
+    }
+
+    fn class_atom(&mut self, class: Class) -> Atom {
+        self.classes.push(class);
+        Atom::Class(self.classes.len() - 1)
+    }
+
+    /// Parses a character class expression after the opening bracket.
+    fn class(&mut self) -> Result<Class, String> {
+        let negated = self.eat('^');
+        let mut items = Vec::new();
+        let mut subtracted = None;
+        loop {
+            match self.next()? {
+                ']' if !items.is_empty() => break,
+                '-' if self.peek() == Some('[') && !items.is_empty() => {
+                    self.pos += 1;
+                    subtracted = Some(Box::new(self.class()?));
+                    if !self.eat(']') {
+                        return Err("Unterminated character class in regular expression".into());
+                    }
+                    break;
+                }
+                '\\' => items.push(self.escape()?),
+                '[' => return Err("Unexpected '[' in a character class of regular expression".into()),
+                c => {
+                    let from = c;
+                    if self.peek() == Some('-') && self.chars.get(self.pos + 1).map_or(false, |&c| c != ']' && c != '[') {
+                        self.pos += 1;
+                        let to = match self.next()? {
+                            '\\' => match self.escape()? {
+                                ClassItem::Range(c, _) => c,
+                                ClassItem::Escape(..) => return Err("Invalid range in regular expression".into())
+                            },
+                            c => c
+                        };
+                        if to < from {
+                            return Err("Invalid range in regular expression".into());
+                        }
+                        items.push(ClassItem::Range(from, to));
+                    } else {
+                        items.push(ClassItem::Range(c, c));
+                    }
+                }
+            }
+        }
+        Ok(Class { negated, items, subtracted })
+    }
+
+    /// Parses an escape after the backslash.
+    fn escape(&mut self) -> Result<ClassItem, String> {
+        let c = self.next()?;
+        let single = |c| Ok(ClassItem::Range(c, c));
+        match c {
+            'n' => single('\n'),
+            'r' => single('\r'),
+            't' => single('\t'),
+            '\\' | '|' | '.' | '-' | '^' | '?' | '*' | '+' | '{' | '}' | '(' | ')' | '[' | ']' => single(c),
+            's' | 'S' => Ok(ClassItem::Escape(Escape::Space, c == 'S')),
+            'd' | 'D' => Ok(ClassItem::Escape(Escape::Digit, c == 'D')),
+            'w' | 'W' => Ok(ClassItem::Escape(Escape::Word, c == 'W')),
+            'i' | 'I' => Ok(ClassItem::Escape(Escape::NameStart, c == 'I')),
+            'c' | 'C' => Ok(ClassItem::Escape(Escape::NameChar, c == 'C')),
+            'p' | 'P' => {
+                if !self.eat('{') {
+                    return Err("Expected '{' after \\p in regular expression".into());
+                }
+                let start = self.pos;
+                while self.peek().map_or(false, |c| c != '}') {
+                    self.pos += 1;
+                }
+                let name: String = self.chars[start..self.pos].iter().collect();
+                self.pos += 1;
+                let escape = match &name[..] {
+                    "L" => Escape::Letter,
+                    "Lu" => Escape::Uppercase,
+                    "Ll" => Escape::Lowercase,
+                    "N" | "Nd" => Escape::Digit,
+                    _ => return Err(format!("Unsupported character property in regular expression: {}", name))
+                };
+                Ok(ClassItem::Escape(escape, c == 'P'))
+            }
+            c => Err(format!("Invalid escape in regular expression: \\{}", c))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Regex;
+
+    #[test]
+    fn regex_matching() {
+        let cases: &[(&str, &[&str], &[&str])] = &[
+            ("abc", &["abc"], &["ab", "abcd", "xabc"]),
+            ("a|bc|", &["a", "bc", ""], &["b", "abc"]),
+            ("(ab)*c?", &["", "abab", "ababc", "c"], &["aba", "cc"]),
+            (r"\d{3}-\d{2,}", &["123-45", "123-456"], &["12-45", "123-4"]),
+            ("[a-c-]+x{0,1}", &["a-b", "cx"], &["d", "axx"]),
+            ("[^aeiou]+", &["xyz"], &["xaz"]),
+            (r"[a-z-[aeiou]]+", &["xyz"], &["xaz"]),
+            (r"\i\c*", &["_a1", "x:y"], &["1a"]),
+            (r"\p{Lu}\p{Ll}*", &["Hello", "É"], &["hello"]),
+            (r"(a?)+b", &["b", "aab"], &["a"]),
+            (r"\s\S\.", &[" a."], &["  .", " ab"]),
+        ];
+        for &(pattern, valid, invalid) in cases {
+            let regex = Regex::new(pattern).unwrap();
+            for s in valid {
+                assert!(regex.is_match(s), "{} should match {}", pattern, s);
+            }
+            for s in invalid {
+                assert!(!regex.is_match(s), "{} should not match {}", pattern, s);
+            }
+        }
+    }
+
+    #[test]
+    fn nested_repetitions_match_in_linear_time() {
+        let long = "a".repeat(10000);
+        for pattern in &["(a*)*b", "(a|aa)+b", "(a?){20}(a*)*b", "((a+)*)+b"] {
+            let regex = Regex::new(pattern).unwrap();
+            assert!(!regex.is_match(&long), "{} should not match", pattern);
+            assert!(regex.is_match(&(long.clone() + "b")), "{} should match", pattern);
+        }
+    }
+
+    #[test]
+    fn regex_errors() {
+        for pattern in &["(ab", "a{2,1}", "[]", "*a", r"\q", r"\p{Sc}", "a)"] {
+            assert!(Regex::new(pattern).is_err(), "{} should be invalid", pattern);
+        }
+    }
+}
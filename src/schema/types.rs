@@ -0,0 +1,476 @@
+//! Contains built-in simple types and validation of values against simple types.
+
+use std::cmp::Ordering;
+
+use common::{is_name_start_char, is_name_char, is_whitespace_char};
+
+use super::model::{Model, SimpleType, Facet, Resolved, Content};
+use super::regex::Regex;
+
+/// A built-in simple type of XML Schema, except `anyType`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Builtin {
+    AnySimpleType,
+    String,
+    NormalizedString,
+    Token,
+    Language,
+    Name,
+    NCName,
+    NmToken,
+    NmTokens,
+    Id,
+    IdRef,
+    IdRefs,
+    Entity,
+    Entities,
+    QName,
+    Notation,
+    AnyUri,
+    Boolean,
+    Decimal,
+    Integer,
+    NonPositiveInteger,
+    NegativeInteger,
+    NonNegativeInteger,
+    PositiveInteger,
+    Long,
+    Int,
+    Short,
+    Byte,
+    UnsignedLong,
+    UnsignedInt,
+    UnsignedShort,
+    UnsignedByte,
+    Float,
+    Double,
+    Duration,
+    DateTime,
+    Time,
+    Date,
+    GYearMonth,
+    GYear,
+    GMonthDay,
+    GDay,
+    GMonth,
+    HexBinary,
+    Base64Binary,
+}
+
+static BUILTIN_NAMES: &[(&str, Builtin)] = &[
+    ("anySimpleType", Builtin::AnySimpleType),
+    ("string", Builtin::String),
+    ("normalizedString", Builtin::NormalizedString),
+    ("token", Builtin::Token),
+    ("language", Builtin::Language),
+    ("Name", Builtin::Name),
+    ("NCName", Builtin::NCName),
+    ("NMTOKEN", Builtin::NmToken),
+    ("NMTOKENS", Builtin::NmTokens),
+    ("ID", Builtin::Id),
+    ("IDREF", Builtin::IdRef),
+    ("IDREFS", Builtin::IdRefs),
+    ("ENTITY", Builtin::Entity),
+    ("ENTITIES", Builtin::Entities),
+    ("QName", Builtin::QName),
+    ("NOTATION", Builtin::Notation),
+    ("anyURI", Builtin::AnyUri),
+    ("boolean", Builtin::Boolean),
+    ("decimal", Builtin::Decimal),
+    ("integer", Builtin::Integer),
+    ("nonPositiveInteger", Builtin::NonPositiveInteger),
+    ("negativeInteger", Builtin::NegativeInteger),
+    ("nonNegativeInteger", Builtin::NonNegativeInteger),
+    ("positiveInteger", Builtin::PositiveInteger),
+    ("long", Builtin::Long),
+    ("int", Builtin::Int),
+    ("short", Builtin::Short),
+    ("byte", Builtin::Byte),
+    ("unsignedLong", Builtin::UnsignedLong),
+    ("unsignedInt", Builtin::UnsignedInt),
+    ("unsignedShort", Builtin::UnsignedShort),
+    ("unsignedByte", Builtin::UnsignedByte),
+    ("float", Builtin::Float),
+    ("double", Builtin::Double),
+    ("duration", Builtin::Duration),
+    ("dateTime", Builtin::DateTime),
+    ("time", Builtin::Time),
+    ("date", Builtin::Date),
+    ("gYearMonth", Builtin::GYearMonth),
+    ("gYear", Builtin::GYear),
+    ("gMonthDay", Builtin::GMonthDay),
+    ("gDay", Builtin::GDay),
+    ("gMonth", Builtin::GMonth),
+    ("hexBinary", Builtin::HexBinary),
+    ("base64Binary", Builtin::Base64Binary),
+];
+
+const TIMEZONE: &str = r"(Z|[+\-]((0\d|1[0-3]):[0-5]\d|14:00))?";
+const YEAR: &str = r"-?([1-9]\d{4,}|\d{4})";
+const MONTH: &str = r"(0[1-9]|1[0-2])";
+const DAY: &str = r"(0[1-9]|[12]\d|3[01])";
+const TIME: &str = r"(([01]\d|2[0-3]):[0-5]\d:[0-5]\d(\.\d+)?|24:00:00(\.0+)?)";
+
+impl Builtin {
+    pub fn from_name(name: &str) -> Option<Builtin> {
+        BUILTIN_NAMES.iter().find(|&&(n, _)| n == name).map(|&(_, b)| b)
+    }
+
+    pub fn name(self) -> &'static str {
+        BUILTIN_NAMES.iter().find(|&&(_, b)| b == self).map(|&(n, _)| n).unwrap()
+    }
+
+    /// Returns `true` if values of this type are lists of whitespace-separated items.
+    fn is_list(self) -> bool {
+        match self {
+            Builtin::NmTokens | Builtin::IdRefs | Builtin::Entities => true,
+            _ => false
+        }
+    }
+
+    /// Returns `true` if values of this type are ordered numerically.
+    fn is_numeric(self) -> bool {
+        match self {
+            Builtin::Decimal | Builtin::Integer | Builtin::NonPositiveInteger |
+            Builtin::NegativeInteger | Builtin::NonNegativeInteger | Builtin::PositiveInteger |
+            Builtin::Long | Builtin::Int | Builtin::Short | Builtin::Byte | Builtin::UnsignedLong |
+            Builtin::UnsignedInt | Builtin::UnsignedShort | Builtin::UnsignedByte |
+            Builtin::Float | Builtin::Double => true,
+            _ => false
+        }
+    }
+
+    /// Checks that a whitespace-normalized value is in the lexical space of this type.
+    fn is_valid(self, value: &str) -> bool {
+        match self {
+            Builtin::AnySimpleType | Builtin::String | Builtin::NormalizedString |
+            Builtin::Token | Builtin::AnyUri => true,
+            Builtin::Language => matches(r"[a-zA-Z]{1,8}(-[a-zA-Z0-9]{1,8})*", value),
+            Builtin::Name => is_name(value),
+            Builtin::NCName | Builtin::Id | Builtin::IdRef | Builtin::Entity => is_ncname(value),
+            Builtin::NmToken => is_nmtoken(value),
+            Builtin::NmTokens => !value.is_empty() && list_items(value).all(is_nmtoken),
+            Builtin::IdRefs | Builtin::Entities => !value.is_empty() && list_items(value).all(is_ncname),
+            Builtin::QName | Builtin::Notation => match value.find(':') {
+                Some(i) => is_ncname(&value[..i]) && is_ncname(&value[i + 1..]),
+                None => is_ncname(value)
+            },
+            Builtin::Boolean => match value {
+                "true" | "false" | "1" | "0" => true,
+                _ => false
+            },
+            Builtin::Decimal => matches(r"[+\-]?(\d+(\.\d*)?|\.\d+)", value),
+            Builtin::Integer => is_integer(value),
+            Builtin::NonPositiveInteger => is_integer(value) && integer_in(value, None, Some(0)),
+            Builtin::NegativeInteger => is_integer(value) && integer_in(value, None, Some(-1)),
+            Builtin::NonNegativeInteger => is_integer(value) && integer_in(value, Some(0), None),
+            Builtin::PositiveInteger => is_integer(value) && integer_in(value, Some(1), None),
+            Builtin::Long => is_integer(value) && integer_in(value, Some(i64::min_value() as i128), Some(i64::max_value() as i128)),
+            Builtin::Int => is_integer(value) && integer_in(value, Some(i32::min_value() as i128), Some(i32::max_value() as i128)),
+            Builtin::Short => is_integer(value) && integer_in(value, Some(i16::min_value() as i128), Some(i16::max_value() as i128)),
+            Builtin::Byte => is_integer(value) && integer_in(value, Some(i8::min_value() as i128), Some(i8::max_value() as i128)),
+            Builtin::UnsignedLong => is_integer(value) && integer_in(value, Some(0), Some(u64::max_value() as i128)),
+            Builtin::UnsignedInt => is_integer(value) && integer_in(value, Some(0), Some(u32::max_value() as i128)),
+            Builtin::UnsignedShort => is_integer(value) && integer_in(value, Some(0), Some(u16::max_value() as i128)),
+            Builtin::UnsignedByte => is_integer(value) && integer_in(value, Some(0), Some(u8::max_value() as i128)),
+            Builtin::Float | Builtin::Double => match value {
+                "INF" | "-INF" | "NaN" => true,
+                _ => matches(r"[+\-]?(\d+(\.\d*)?|\.\d+)([eE][+\-]?\d+)?", value)
+            },
+            Builtin::Duration =>
+                matches(r"-?P(\d+Y)?(\d+M)?(\d+D)?(T(\d+H)?(\d+M)?(\d+(\.\d+)?S)?)?", value) &&
+                    !value.ends_with('P') && !value.ends_with('T'),
+            Builtin::DateTime => matches(&format!("{}-{}-{}T{}{}", YEAR, MONTH, DAY, TIME, TIMEZONE), value),
+            Builtin::Time => matches(&format!("{}{}", TIME, TIMEZONE), value),
+            Builtin::Date => matches(&format!("{}-{}-{}{}", YEAR, MONTH, DAY, TIMEZONE), value),
+            Builtin::GYearMonth => matches(&format!("{}-{}{}", YEAR, MONTH, TIMEZONE), value),
+            Builtin::GYear => matches(&format!("{}{}", YEAR, TIMEZONE), value),
+            Builtin::GMonthDay => matches(&format!("--{}-{}{}", MONTH, DAY, TIMEZONE), value),
+            Builtin::GDay => matches(&format!("---{}{}", DAY, TIMEZONE), value),
+            Builtin::GMonth => matches(&format!("--{}{}", MONTH, TIMEZONE), value),
+            Builtin::HexBinary => value.len() % 2 == 0 && value.chars().all(|c| c.is_digit(16)),
+            Builtin::Base64Binary => {
+                let chars: Vec<char> = value.chars().filter(|&c| c != ' ').collect();
+                let padding = chars.iter().rev().take_while(|&&c| c == '=').count();
+                chars.len() % 4 == 0 && padding <= 2 && chars[..chars.len() - padding].iter()
+                    .all(|&c| c.is_ascii_alphanumeric() || c == '+' || c == '/')
+            }
+        }
+    }
+}
+
+/// Whitespace processing applied to values of a type before validation.
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum WhiteSpace {
+    Preserve,
+    Replace,
+    Collapse,
+}
+
+/// Properties of a simple type which are determined by its primitive built-in type.
+struct Primitive {
+    builtin: Builtin,
+    whitespace: WhiteSpace,
+    is_list: bool,
+}
+
+/// Checks a value against a simple type, or against the simple content of a complex type.
+///
+/// Returns a description of the problem when the value is not valid. Names of all types
+/// must be resolvable; unknown types accept any value.
+pub fn check_value(model: &Model, ty: Resolved, value: &str) -> Result<(), String> {
+    let primitive = match primitive(model, ty) {
+        Some(p) => p,
+        None => return Ok(())
+    };
+    let value = match primitive.whitespace {
+        WhiteSpace::Preserve => value.into(),
+        WhiteSpace::Replace => value.replace(is_whitespace_char, " "),
+        WhiteSpace::Collapse => collapse(value),
+    };
+    check_normalized(model, ty, &value)
+}
+
+fn check_normalized(model: &Model, ty: Resolved, value: &str) -> Result<(), String> {
+    match ty {
+        Resolved::AnyType => Ok(()),
+        Resolved::Builtin(builtin) => if builtin.is_valid(value) {
+            Ok(())
+        } else {
+            Err(format!("Value '{}' is not a valid {}", value, builtin.name()))
+        },
+        Resolved::Complex(complex) => match complex.content {
+            Content::Simple(ref base) => match model.resolve(base) {
+                Some(base) => check_normalized(model, base, value),
+                None => Ok(())
+            },
+            _ => Ok(())
+        },
+        Resolved::Simple(&SimpleType::List(ref item)) => match model.resolve(item) {
+            Some(item) => {
+                for v in list_items(value) {
+                    check_normalized(model, item, v)?;
+                }
+                Ok(())
+            }
+            None => Ok(())
+        },
+        Resolved::Simple(&SimpleType::Union(ref members)) => {
+            let valid = members.iter().filter_map(|m| model.resolve(m))
+                .any(|m| check_value(model, m, value).is_ok());
+            if valid { Ok(()) } else { Err(format!("Value '{}' does not match any member type of a union", value)) }
+        }
+        Resolved::Simple(&SimpleType::Restriction { ref base, ref facets }) => {
+            let base = match model.resolve(base) {
+                Some(base) => base,
+                None => return Ok(())
+            };
+            check_normalized(model, base, value)?;
+            let primitive = match primitive(model, base) {
+                Some(p) => p,
+                None => return Ok(())
+            };
+            for facet in facets {
+                check_facet(facet, &primitive, value)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+fn check_facet(facet: &Facet, primitive: &Primitive, value: &str) -> Result<(), String> {
+    let length = || if primitive.is_list {
+        list_items(value).count()
+    } else if primitive.builtin == Builtin::HexBinary {
+        value.len() / 2
+    } else {
+        value.chars().count()
+    };
+    let compare = |bound: &str| compare(primitive.builtin, value, bound);
+    let ok = match *facet {
+        Facet::Length(n) => length() == n,
+        Facet::MinLength(n) => length() >= n,
+        Facet::MaxLength(n) => length() <= n,
+        Facet::Enumeration(ref values) => values.iter().any(|v| compare(v) == Some(Ordering::Equal)),
+        Facet::Pattern(ref patterns) => patterns.iter().any(|p| p.is_match(value)),
+        Facet::MinInclusive(ref bound) => compare(bound).map_or(false, |o| o != Ordering::Less),
+        Facet::MaxInclusive(ref bound) => compare(bound).map_or(false, |o| o != Ordering::Greater),
+        Facet::MinExclusive(ref bound) => compare(bound) == Some(Ordering::Greater),
+        Facet::MaxExclusive(ref bound) => compare(bound) == Some(Ordering::Less),
+        Facet::TotalDigits(n) => digits(value).map_or(false, |(total, _)| total <= n),
+        Facet::FractionDigits(n) => digits(value).map_or(false, |(_, fraction)| fraction <= n),
+    };
+    if ok {
+        Ok(())
+    } else {
+        Err(format!("Value '{}' does not satisfy the {} facet", value, facet_name(facet)))
+    }
+}
+
+pub fn facet_name(facet: &Facet) -> &'static str {
+    match *facet {
+        Facet::Length(_) => "length",
+        Facet::MinLength(_) => "minLength",
+        Facet::MaxLength(_) => "maxLength",
+        Facet::Enumeration(_) => "enumeration",
+        Facet::Pattern(_) => "pattern",
+        Facet::MinInclusive(_) => "minInclusive",
+        Facet::MaxInclusive(_) => "maxInclusive",
+        Facet::MinExclusive(_) => "minExclusive",
+        Facet::MaxExclusive(_) => "maxExclusive",
+        Facet::TotalDigits(_) => "totalDigits",
+        Facet::FractionDigits(_) => "fractionDigits",
+    }
+}
+
+/// Returns the built-in type a simple type is ultimately derived from.
+///
+/// Unions have no single primitive type, so they are treated as `anySimpleType`.
+fn primitive(model: &Model, ty: Resolved) -> Option<Primitive> {
+    let builtin = |builtin: Builtin| Primitive {
+        builtin,
+        whitespace: match builtin {
+            Builtin::String | Builtin::AnySimpleType => WhiteSpace::Preserve,
+            Builtin::NormalizedString => WhiteSpace::Replace,
+            _ => WhiteSpace::Collapse
+        },
+        is_list: builtin.is_list(),
+    };
+    match ty {
+        Resolved::AnyType => None,
+        Resolved::Builtin(b) => Some(builtin(b)),
+        Resolved::Complex(complex) => match complex.content {
+            Content::Simple(ref base) => model.resolve(base).and_then(|base| primitive(model, base)),
+            _ => None
+        },
+        Resolved::Simple(&SimpleType::List(_)) =>
+            Some(Primitive { builtin: Builtin::AnySimpleType, whitespace: WhiteSpace::Collapse, is_list: true }),
+        Resolved::Simple(&SimpleType::Union(_)) => Some(builtin(Builtin::AnySimpleType)),
+        Resolved::Simple(&SimpleType::Restriction { ref base, .. }) =>
+            model.resolve(base).and_then(|base| primitive(model, base)),
+    }
+}
+
+/// Compares a value with a facet value according to the order of the primitive type.
+fn compare(builtin: Builtin, value: &str, other: &str) -> Option<Ordering> {
+    if builtin.is_numeric() {
+        let parse = |s: &str| match s.trim() {
+            "INF" => Some(::std::f64::INFINITY),
+            "-INF" => Some(::std::f64::NEG_INFINITY),
+            s => s.parse::<f64>().ok()
+        };
+        match (parse(value), parse(other)) {
+            (Some(a), Some(b)) => a.partial_cmp(&b),
+            _ => None
+        }
+    } else if builtin == Builtin::Boolean {
+        let parse = |s: &str| match s.trim() {
+            "true" | "1" => Some(true),
+            "false" | "0" => Some(false),
+            _ => None
+        };
+        match (parse(value), parse(other)) {
+            (Some(a), Some(b)) => Some(a.cmp(&b)),
+            _ => None
+        }
+    } else {
+        // values of date and time types in the same format and time zone are ordered lexically
+        Some(value.cmp(other.trim()))
+    }
+}
+
+/// Returns the number of significant digits and of fraction digits in a decimal value.
+fn digits(value: &str) -> Option<(usize, usize)> {
+    if !matches(r"[+\-]?(\d+(\.\d*)?|\.\d+)", value) {
+        return None;
+    }
+    let value = value.trim_start_matches(|c| c == '+' || c == '-');
+    let (integer, fraction) = match value.find('.') {
+        Some(i) => (&value[..i], value[i + 1..].trim_end_matches('0')),
+        None => (value, "")
+    };
+    let integer = integer.trim_start_matches('0');
+    Some((integer.len() + fraction.len(), fraction.len()))
+}
+
+fn matches(pattern: &str, value: &str) -> bool {
+    Regex::new(pattern).map(|r| r.is_match(value)).unwrap_or(false)
+}
+
+fn collapse(value: &str) -> String {
+    value.split(is_whitespace_char).filter(|s| !s.is_empty()).collect::<Vec<_>>().join(" ")
+}
+
+fn list_items(value: &str) -> ::std::str::SplitWhitespace<'_> {
+    value.split_whitespace()
+}
+
+fn is_integer(value: &str) -> bool {
+    matches(r"[+\-]?\d+", value)
+}
+
+fn integer_in(value: &str, min: Option<i128>, max: Option<i128>) -> bool {
+    match value.trim_start_matches('+').parse::<i128>() {
+        Ok(n) => min.map_or(true, |min| n >= min) && max.map_or(true, |max| n <= max),
+        // the value has too many digits to fit into i128, so only its sign matters
+        Err(_) => if value.starts_with('-') { min.is_none() } else { max.is_none() }
+    }
+}
+
+pub fn is_name(s: &str) -> bool {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) if is_name_start_char(c) => chars.all(is_name_char),
+        _ => false
+    }
+}
+
+pub fn is_ncname(s: &str) -> bool {
+    is_name(s) && !s.contains(':')
+}
+
+fn is_nmtoken(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(is_name_char)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Builtin, digits};
+
+    #[test]
+    fn builtin_lexical_spaces() {
+        let cases: &[(Builtin, &[&str], &[&str])] = &[
+            (Builtin::Boolean, &["true", "0"], &["yes", "TRUE"]),
+            (Builtin::Decimal, &["1", "-1.5", ".5", "+2."], &["", "1e5", "."]),
+            (Builtin::Byte, &["-128", "127"], &["128", "1.0"]),
+            (Builtin::UnsignedLong, &["18446744073709551615"], &["18446744073709551616", "-1"]),
+            (Builtin::Integer, &["123456789012345678901234567890123456789012"], &["1.5"]),
+            (Builtin::Double, &["1e10", "-INF", "NaN", "1.5E-3"], &["inf", "1e"]),
+            (Builtin::Date, &["2020-02-29", "-0001-12-31Z", "2020-01-01+05:30"], &["2020-13-01", "20-01-01"]),
+            (Builtin::DateTime, &["2020-01-01T12:30:00.5Z", "2020-01-01T24:00:00"], &["2020-01-01", "2020-01-01T25:00:00"]),
+            (Builtin::Duration, &["P1Y2M", "PT1.5S", "-P1DT2H"], &["P", "PT", "P1H"]),
+            (Builtin::GMonthDay, &["--12-31"], &["12-31"]),
+            (Builtin::Language, &["en", "en-US"], &["en_US", "abcdefghi"]),
+            (Builtin::NCName, &["a-b.c"], &["a:b", "1a"]),
+            (Builtin::QName, &["a:b"], &["a:b:c", ":b"]),
+            (Builtin::HexBinary, &["0fA9"], &["0fA"]),
+            (Builtin::Base64Binary, &["aGVsbG8=", ""], &["aGVsbG8", "a==="]),
+        ];
+        for &(builtin, valid, invalid) in cases {
+            for v in valid {
+                assert!(builtin.is_valid(v), "{} should be a valid {:?}", v, builtin);
+            }
+            for v in invalid {
+                assert!(!builtin.is_valid(v), "{} should not be a valid {:?}", v, builtin);
+            }
+        }
+    }
+
+    #[test]
+    fn decimal_digits() {
+        assert_eq!(digits("123.4500"), Some((5, 2)));
+        assert_eq!(digits("-0.01"), Some((2, 2)));
+        assert_eq!(digits("007"), Some((1, 0)));
+        assert_eq!(digits("x"), None);
+    }
+}
@@ -0,0 +1,464 @@
+//! Contains the validator which checks a stream of events against a schema.
+
+use std::collections::{BTreeSet, HashSet, VecDeque};
+
+use common::{Position, TextPosition, is_whitespace_str};
+use attribute::OwnedAttribute;
+use name::OwnedName;
+use namespace::NS_XML_URI;
+use reader::{self, XmlEvent};
+
+use super::{Error, ValidationError, ValidationErrorKind, NS_XSI_URI};
+use super::model::*;
+use super::types::{Builtin, check_value};
+
+/// An iterator adapter which validates events against a schema.
+///
+/// All events of the underlying iterator are passed through unchanged. Every violation of
+/// the schema is reported as an `Error::Validation` item which precedes the event where it
+/// was found, and validation continues after it. Errors of the underlying reader are
+/// reported as `Error::Reader` items.
+///
+/// Attributes in the `xsi` and `xml` namespaces are not validated.
+pub struct Validator<'s, I> {
+    model: &'s Model,
+    events: I,
+    stack: Vec<Frame<'s>>,
+    pending: VecDeque<Result<XmlEvent, Error>>,
+    ids: HashSet<String>,
+    idrefs: Vec<(String, TextPosition)>,
+}
+
+/// An element whose content is being validated.
+struct Frame<'s> {
+    name: OwnedName,
+    kind: FrameKind<'s>,
+    decl: Option<&'s ElementDecl>,
+    children: Vec<OwnedName>,
+    text: String,
+    /// Set when an unexpected child element has already been reported, so that
+    /// the whole content model is not reported as violated once more.
+    invalid: bool,
+}
+
+#[derive(Copy, Clone)]
+enum FrameKind<'s> {
+    /// Content is not validated at all.
+    Skip,
+    /// Only elements and attributes which have global declarations are validated.
+    Lax,
+    Typed(Resolved<'s>),
+}
+
+enum ContentKind<'s> {
+    Any,
+    Empty,
+    Simple,
+    Elements(Vec<&'s Particle>),
+}
+
+/// A particle term matched by a child element.
+enum Child<'s> {
+    Decl(&'s ElementDecl),
+    Wildcard(&'s Wildcard),
+}
+
+impl<'s, I> Validator<'s, I> where I: Iterator<Item=reader::Result<XmlEvent>> + Position {
+    pub fn new(model: &'s Model, events: I) -> Validator<'s, I> {
+        Validator {
+            model,
+            events,
+            stack: Vec::new(),
+            pending: VecDeque::new(),
+            ids: HashSet::new(),
+            idrefs: Vec::new(),
+        }
+    }
+
+    /// Unwraps this validator, returning the underlying iterator.
+    pub fn into_inner(self) -> I {
+        self.events
+    }
+
+    fn check(&mut self, event: &XmlEvent, pos: TextPosition, errors: &mut Vec<ValidationError>) {
+        match *event {
+            XmlEvent::StartElement { ref name, ref attributes, .. } => self.start_element(name, attributes, pos, errors),
+            XmlEvent::EndElement { .. } => self.end_element(pos, errors),
+            XmlEvent::Characters(ref data) | XmlEvent::CData(ref data) | XmlEvent::Whitespace(ref data) =>
+                self.characters(data, pos, errors),
+            XmlEvent::EndDocument => for &(ref idref, pos) in &self.idrefs {
+                if !self.ids.contains(idref) {
+                    errors.push(error(pos, ValidationErrorKind::UnknownIdRef,
+                                      format!("IDREF value {} does not match any ID", idref)));
+                }
+            },
+            _ => {}
+        }
+    }
+
+    fn start_element(&mut self, name: &OwnedName, attributes: &[OwnedAttribute], pos: TextPosition,
+                     errors: &mut Vec<ValidationError>) {
+        let name = expanded(name);
+        let model = self.model;
+        let (kind, decl) = match self.stack.last_mut() {
+            None => match model.elements.get(&name) {
+                Some(decl) => (self.typed(decl), Some(decl)),
+                None => {
+                    errors.push(error(pos, ValidationErrorKind::UnknownRootElement,
+                                      format!("Element {} is not declared in the schema", name)));
+                    (FrameKind::Skip, None)
+                }
+            },
+            Some(parent) => match parent.kind {
+                FrameKind::Skip => (FrameKind::Skip, None),
+                FrameKind::Lax => lax(model, &name),
+                FrameKind::Typed(ty) => match content_kind(model, ty) {
+                    ContentKind::Any => lax(model, &name),
+                    ContentKind::Empty | ContentKind::Simple => {
+                        errors.push(error(pos, ValidationErrorKind::UnexpectedElement,
+                                          format!("Element {} is not allowed in element {} which cannot have child elements",
+                                                  name, parent.name)));
+                        (FrameKind::Skip, None)
+                    }
+                    ContentKind::Elements(particles) => match find_child(model, &particles, &name) {
+                        Some(Child::Decl(decl)) => {
+                            parent.children.push(name.clone());
+                            (typed(model, decl), Some(decl))
+                        }
+                        Some(Child::Wildcard(wildcard)) => {
+                            parent.children.push(name.clone());
+                            match (wildcard.process_contents, model.elements.get(&name)) {
+                                (ProcessContents::Skip, _) => (FrameKind::Skip, None),
+                                (_, Some(decl)) => (typed(model, decl), Some(decl)),
+                                (ProcessContents::Lax, None) => (FrameKind::Lax, None),
+                                (ProcessContents::Strict, None) => {
+                                    errors.push(error(pos, ValidationErrorKind::UnexpectedElement,
+                                                      format!("Element {} is not declared in the schema", name)));
+                                    (FrameKind::Skip, None)
+                                }
+                            }
+                        }
+                        None => {
+                            if !parent.invalid {
+                                errors.push(error(pos, ValidationErrorKind::UnexpectedElement,
+                                                  format!("Element {} is not allowed in element {}", name, parent.name)));
+                            }
+                            parent.invalid = true;
+                            (FrameKind::Skip, None)
+                        }
+                    }
+                }
+            }
+        };
+        self.attributes(&name, kind, attributes, pos, errors);
+        self.stack.push(Frame { name, kind, decl, children: Vec::new(), text: String::new(), invalid: false });
+    }
+
+    fn typed(&self, decl: &'s ElementDecl) -> FrameKind<'s> {
+        typed(self.model, decl)
+    }
+
+    fn attributes(&mut self, element: &OwnedName, kind: FrameKind<'s>, attributes: &[OwnedAttribute],
+                  pos: TextPosition, errors: &mut Vec<ValidationError>) {
+        let model = self.model;
+        let mut decls: Vec<(&AttributeDecl, AttributeUse, Option<&str>)> = Vec::new();
+        let wildcard = match kind {
+            FrameKind::Skip => return,
+            FrameKind::Lax | FrameKind::Typed(Resolved::AnyType) => None,
+            FrameKind::Typed(Resolved::Complex(ty)) => model.attributes(ty, &mut |decl, use_, fixed| {
+                if !decls.iter().any(|&(d, _, _)| d.name == decl.name) {
+                    decls.push((decl, use_, fixed));
+                }
+            }),
+            FrameKind::Typed(_) => None,
+        };
+        let lax = match kind {
+            FrameKind::Lax | FrameKind::Typed(Resolved::AnyType) => true,
+            _ => false
+        };
+
+        for attr in attributes {
+            let name = expanded(&attr.name);
+            match name.namespace_ref() {
+                Some(NS_XSI_URI) | Some(NS_XML_URI) => continue,
+                _ => {}
+            }
+            let (decl, fixed) = match decls.iter().find(|&&(d, _, _)| d.name == name) {
+                Some(&(_, AttributeUse::Prohibited, _)) => {
+                    errors.push(error(pos, ValidationErrorKind::UnexpectedAttribute,
+                                      format!("Attribute {} is prohibited in element {}", name, element)));
+                    continue;
+                }
+                Some(&(decl, _, fixed)) => (decl, fixed),
+                None => match (wildcard, model.attributes.get(&name)) {
+                    (Some(w), _) if !w.allows(name.namespace_ref()) => {
+                        errors.push(error(pos, ValidationErrorKind::UnexpectedAttribute,
+                                          format!("Attribute {} is not allowed in element {}", name, element)));
+                        continue;
+                    }
+                    (Some(w), _) if w.process_contents == ProcessContents::Skip => continue,
+                    (Some(_), Some(decl)) => (decl, decl.fixed.as_ref().map(|s| &s[..])),
+                    (Some(w), None) if w.process_contents == ProcessContents::Lax => continue,
+                    (None, Some(decl)) if lax => (decl, decl.fixed.as_ref().map(|s| &s[..])),
+                    (None, None) if lax => continue,
+                    _ => {
+                        errors.push(error(pos, ValidationErrorKind::UnexpectedAttribute,
+                                          format!("Attribute {} is not allowed in element {}", name, element)));
+                        continue;
+                    }
+                }
+            };
+            let ty = match model.resolve(&decl.type_ref) {
+                Some(ty) => ty,
+                None => continue
+            };
+            if let Err(msg) = check_value(model, ty, &attr.value) {
+                errors.push(error(pos, ValidationErrorKind::InvalidValue,
+                                  format!("{} in attribute {} of element {}", msg, name, element)));
+                continue;
+            }
+            if let Some(fixed) = fixed {
+                if attr.value.trim() != fixed {
+                    errors.push(error(pos, ValidationErrorKind::InvalidValue,
+                                      format!("Attribute {} of element {} must have the fixed value '{}'", name, element, fixed)));
+                }
+            }
+            self.identity(ty, &attr.value, pos, errors);
+        }
+
+        for &(decl, use_, _) in &decls {
+            if use_ == AttributeUse::Required && !attributes.iter().any(|a| expanded(&a.name) == decl.name) {
+                errors.push(error(pos, ValidationErrorKind::MissingAttribute,
+                                  format!("Required attribute {} is missing in element {}", decl.name, element)));
+            }
+        }
+    }
+
+    /// Records values of ID and IDREF attributes.
+    fn identity(&mut self, ty: Resolved, value: &str, pos: TextPosition, errors: &mut Vec<ValidationError>) {
+        let builtin = match ty {
+            Resolved::Builtin(b) => b,
+            _ => return
+        };
+        match builtin {
+            Builtin::Id => if !self.ids.insert(value.trim().into()) {
+                errors.push(error(pos, ValidationErrorKind::DuplicateId, format!("Duplicate ID value {}", value.trim())));
+            },
+            Builtin::IdRef | Builtin::IdRefs =>
+                self.idrefs.extend(value.split_whitespace().map(|r| (r.to_owned(), pos))),
+            _ => {}
+        }
+    }
+
+    fn characters(&mut self, data: &str, pos: TextPosition, errors: &mut Vec<ValidationError>) {
+        let model = self.model;
+        let frame = match self.stack.last_mut() {
+            Some(frame) => frame,
+            None => return
+        };
+        let ty = match frame.kind {
+            FrameKind::Typed(ty) => ty,
+            _ => return
+        };
+        let allowed = match content_kind(model, ty) {
+            ContentKind::Any => true,
+            ContentKind::Simple => {
+                frame.text.push_str(data);
+                true
+            }
+            ContentKind::Empty => is_whitespace_str(data),
+            ContentKind::Elements(_) => match ty {
+                Resolved::Complex(ty) => ty.mixed || is_whitespace_str(data),
+                _ => true
+            },
+        };
+        if !allowed {
+            errors.push(error(pos, ValidationErrorKind::UnexpectedCharacters,
+                              format!("Character data is not allowed in element {}", frame.name)));
+        }
+    }
+
+    fn end_element(&mut self, pos: TextPosition, errors: &mut Vec<ValidationError>) {
+        let model = self.model;
+        let frame = self.stack.pop().unwrap();  // the reader checks that tags are balanced
+        let ty = match frame.kind {
+            FrameKind::Typed(ty) => ty,
+            _ => return
+        };
+        match content_kind(model, ty) {
+            ContentKind::Simple => {
+                let decl = frame.decl;
+                let value = match decl.and_then(|d| d.fixed.as_ref().or(d.default.as_ref())) {
+                    Some(default) if frame.text.is_empty() => &default[..],
+                    _ => &frame.text[..]
+                };
+                if let Err(msg) = check_value(model, ty, value) {
+                    errors.push(error(pos, ValidationErrorKind::InvalidValue,
+                                      format!("{} in element {}", msg, frame.name)));
+                } else if let Some(fixed) = decl.and_then(|d| d.fixed.as_ref()) {
+                    if value.trim() != fixed {
+                        errors.push(error(pos, ValidationErrorKind::InvalidValue,
+                                          format!("Element {} must have the fixed value '{}'", frame.name, fixed)));
+                    }
+                }
+            }
+            ContentKind::Elements(ref particles) if !frame.invalid => {
+                let starts: BTreeSet<usize> = Some(0).into_iter().collect();
+                let ends = particles.iter().fold(starts, |starts, p| match_particle(model, p, &frame.children, starts));
+                if !ends.contains(&frame.children.len()) {
+                    errors.push(error(pos, ValidationErrorKind::InvalidContent,
+                                      format!("Content of element {} does not match its type", frame.name)));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl<'s, I> Iterator for Validator<'s, I> where I: Iterator<Item=reader::Result<XmlEvent>> + Position {
+    type Item = Result<XmlEvent, Error>;
+
+    fn next(&mut self) -> Option<Result<XmlEvent, Error>> {
+        if let Some(item) = self.pending.pop_front() {
+            return Some(item);
+        }
+        let event = match self.events.next()? {
+            Ok(event) => event,
+            Err(e) => return Some(Err(Error::Reader(e)))
+        };
+        let pos = self.events.position();
+        let mut errors = Vec::new();
+        self.check(&event, pos, &mut errors);
+        self.pending.extend(errors.into_iter().map(|e| Err(Error::Validation(e))));
+        self.pending.push_back(Ok(event));
+        self.pending.pop_front()
+    }
+}
+
+fn error(pos: TextPosition, kind: ValidationErrorKind, msg: String) -> ValidationError {
+    ValidationError { pos, kind, msg }
+}
+
+/// Removes the prefix from a name, so that it can be compared with names of declarations.
+fn expanded(name: &OwnedName) -> OwnedName {
+    OwnedName { local_name: name.local_name.clone(), namespace: name.namespace.clone(), prefix: None }
+}
+
+fn typed<'s>(model: &'s Model, decl: &'s ElementDecl) -> FrameKind<'s> {
+    match model.resolve(&decl.type_ref) {
+        Some(Resolved::AnyType) | None => FrameKind::Lax,
+        Some(ty) => FrameKind::Typed(ty),
+    }
+}
+
+/// Returns the frame for an element without a declaration in its parent.
+fn lax<'s>(model: &'s Model, name: &OwnedName) -> (FrameKind<'s>, Option<&'s ElementDecl>) {
+    match model.elements.get(name) {
+        Some(decl) => (typed(model, decl), Some(decl)),
+        None => (FrameKind::Lax, None)
+    }
+}
+
+fn content_kind<'s>(model: &'s Model, ty: Resolved<'s>) -> ContentKind<'s> {
+    match ty {
+        Resolved::AnyType => ContentKind::Any,
+        Resolved::Builtin(_) | Resolved::Simple(_) => ContentKind::Simple,
+        Resolved::Complex(complex) => {
+            let parts = model.content(complex);
+            match parts.first() {
+                None => ContentKind::Empty,
+                Some(&&Content::Simple(_)) => ContentKind::Simple,
+                _ => ContentKind::Elements(parts.into_iter().filter_map(|c| match *c {
+                    Content::Elements(ref p) => Some(p),
+                    _ => None
+                }).collect())
+            }
+        }
+    }
+}
+
+/// Finds the declaration which matches a child element in a content model.
+///
+/// Element declarations take precedence over wildcards.
+fn find_child<'s>(model: &'s Model, particles: &[&'s Particle], name: &OwnedName) -> Option<Child<'s>> {
+    fn find_decl<'s>(model: &'s Model, p: &'s Particle, name: &OwnedName) -> Option<&'s ElementDecl> {
+        match p.term {
+            Term::Element(ref decl) if decl.name == *name => Some(decl),
+            Term::ElementRef(ref r) if r == name => model.elements.get(r),
+            Term::GroupRef(ref r) => model.groups.get(r).and_then(|g| find_decl(model, g, name)),
+            Term::Sequence(ref ps) | Term::Choice(ref ps) | Term::All(ref ps) =>
+                ps.iter().filter_map(|p| find_decl(model, p, name)).next(),
+            _ => None
+        }
+    }
+    fn find_wildcard<'s>(model: &'s Model, p: &'s Particle, name: &OwnedName) -> Option<&'s Wildcard> {
+        match p.term {
+            Term::Any(ref w) if w.allows(name.namespace_ref()) => Some(w),
+            Term::GroupRef(ref r) => model.groups.get(r).and_then(|g| find_wildcard(model, g, name)),
+            Term::Sequence(ref ps) | Term::Choice(ref ps) | Term::All(ref ps) =>
+                ps.iter().filter_map(|p| find_wildcard(model, p, name)).next(),
+            _ => None
+        }
+    }
+    particles.iter().filter_map(|p| find_decl(model, p, name)).next().map(Child::Decl)
+        .or_else(|| particles.iter().filter_map(|p| find_wildcard(model, p, name)).next().map(Child::Wildcard))
+}
+
+/// Returns the set of positions in `names` at which a match of the particle may end,
+/// provided that the match starts at one of the `starts` positions.
+fn match_particle(model: &Model, particle: &Particle, names: &[OwnedName], starts: BTreeSet<usize>) -> BTreeSet<usize> {
+    let mut ends = BTreeSet::new();
+    let mut current = starts;
+    let mut count = 0;
+    loop {
+        if count >= particle.min {
+            ends.extend(current.iter().cloned());
+        }
+        if current.is_empty() || particle.max.map_or(false, |max| count >= max) {
+            break;
+        }
+        let next = match_term(model, &particle.term, names, &current);
+        // once the minimum is reached, stop when repetitions do not reach new positions
+        if count >= particle.min && next.is_subset(&ends) {
+            break;
+        }
+        current = next;
+        count += 1;
+    }
+    ends
+}
+
+fn match_term(model: &Model, term: &Term, names: &[OwnedName], starts: &BTreeSet<usize>) -> BTreeSet<usize> {
+    let single = |matches: &dyn Fn(&OwnedName) -> bool|
+        starts.iter().filter(|&&i| names.get(i).map_or(false, |n| matches(n))).map(|i| i + 1).collect();
+    match *term {
+        Term::Element(ref decl) => single(&|n| *n == decl.name),
+        Term::ElementRef(ref r) => single(&|n| n == r),
+        Term::Any(ref w) => single(&|n| w.allows(n.namespace_ref())),
+        Term::GroupRef(ref r) => match model.groups.get(r) {
+            Some(group) => match_particle(model, group, names, starts.clone()),
+            None => BTreeSet::new()
+        },
+        Term::Sequence(ref ps) =>
+            ps.iter().fold(starts.clone(), |starts, p| match_particle(model, p, names, starts)),
+        Term::Choice(ref ps) =>
+            ps.iter().flat_map(|p| match_particle(model, p, names, starts.clone())).collect(),
+        Term::All(ref ps) => starts.iter().filter_map(|&start| {
+            // elements of an `all` group have distinct names and occur at most once,
+            // so they can be matched greedily
+            let mut used = vec![false; ps.len()];
+            let mut i = start;
+            'names: while i < names.len() {
+                for (j, p) in ps.iter().enumerate() {
+                    if !used[j] && match_term(model, &p.term, names, &Some(i).into_iter().collect()).contains(&(i + 1)) {
+                        used[j] = true;
+                        i += 1;
+                        continue 'names;
+                    }
+                }
+                break;
+            }
+            if ps.iter().zip(&used).all(|(p, &u)| u || p.min == 0) { Some(i) } else { None }
+        }).collect(),
+    }
+}
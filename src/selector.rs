@@ -0,0 +1,599 @@
+//! Contains an engine of CSS selectors over `dom::Document` trees.
+//!
+//! The following parts of [Selectors Level 3][1] are supported:
+//!
+//! * type selectors (`book`, `*`) with optional namespace prefixes (`c|book`, `*|book`, `|book`);
+//! * ID selectors (`#b1`), which match values of `id` attributes without a namespace, `xml:id`
+//!   attributes and attributes declared with the `ID` type in the DTD;
+//! * attribute selectors (`[lang]`, `[lang=en]`, `[class~=new]`, `[lang|=en]`, `[href^="http:"]`,
+//!   `[href$=".xml"]`, `[title*=Dune]`), also with namespace prefixes (`[x|lang]`);
+//! * `:nth-child()`, `:nth-last-child()`, `:first-child` and `:last-child` pseudo-classes;
+//! * descendant (` `), child (`>`), adjacent sibling (`+`) and general sibling (`~`)
+//!   combinators, and groups of selectors separated by commas.
+//!
+//! Namespace prefixes are resolved through the `Namespace` given when a selector is compiled.
+//! As in CSS, the default namespace mapping of this `Namespace`, if any, applies to type selectors
+//! without a prefix; otherwise they match elements in any namespace. Attribute names without a
+//! prefix only match attributes without a namespace.
+//!
+//! ```rust
+//! use xml::EventReader;
+//! use xml::dom::Document;
+//! use xml::namespace::Namespace;
+//! use xml::selector::Selector;
+//!
+//! let document = Document::from_reader(EventReader::from_str(r#"
+//!     <catalog xmlns="urn:catalog" xmlns:x="urn:extra">
+//!       <book id="b1" x:lang="en"><title>Dune</title></book>
+//!       <book id="b2" x:lang="de"><title>Foundation</title></book>
+//!     </catalog>
+//! "#)).unwrap();
+//!
+//! let mut namespaces = Namespace::empty();
+//! namespaces.put("c", "urn:catalog");
+//! namespaces.put("ex", "urn:extra");
+//!
+//! let selector = Selector::with_namespaces("c|book[ex|lang=de] > c|title", &namespaces).unwrap();
+//! let titles: Vec<_> = selector.select(&document, document.root()).collect();
+//! assert_eq!(titles.len(), 1);
+//! assert_eq!(document.parent(titles[0]), Selector::new("#b2").unwrap().select_first(&document, document.root()));
+//! ```
+//!
+//! [1]: https://www.w3.org/TR/selectors-3/
+
+use std::borrow::Cow;
+use std::error;
+use std::fmt;
+
+use common::{is_name_char, is_name_start_char, is_whitespace_char};
+use dom::{Descendants, Document, Element, NodeId};
+use dtd::AttributeType;
+use name::OwnedName;
+use namespace::{Namespace, NS_NO_PREFIX, NS_EMPTY_URI, NS_XML_URI};
+
+/// An error of compilation of a selector.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Error {
+    /// Offset of the offending character in the selector, in characters.
+    pub pos: usize,
+
+    /// Description of the error.
+    pub msg: Cow<'static, str>,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.pos, self.msg)
+    }
+}
+
+impl error::Error for Error {
+    fn description(&self) -> &str {
+        &self.msg
+    }
+}
+
+/// A result type of selector compilation.
+pub type Result<T> = ::std::result::Result<T, Error>;
+
+/// A compiled group of selectors.
+#[derive(Clone, Debug)]
+pub struct Selector {
+    alternatives: Vec<Complex>,
+}
+
+/// A sequence of compound selectors separated by combinators.
+#[derive(Clone, Debug)]
+struct Complex {
+    compounds: Vec<Vec<Simple>>,
+    /// Combinators between compounds; the `i`-th one precedes the `i + 1`-th compound.
+    combinators: Vec<Combinator>,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum Combinator { Descendant, Child, Adjacent, Sibling }
+
+#[derive(Clone, Debug)]
+enum Simple {
+    /// A type selector; `None` as the name stands for `*`.
+    Type(NamespaceTest, Option<String>),
+    Id(String),
+    Attribute(NamespaceTest, String, Option<(AttributeOp, String)>),
+    /// An `:nth-child(an+b)` pseudo-class, or `:nth-last-child(an+b)` when `from_end` is set.
+    NthChild { a: i64, b: i64, from_end: bool },
+}
+
+#[derive(Clone, Debug)]
+enum NamespaceTest {
+    Any,
+    Empty,
+    Uri(String),
+}
+
+impl NamespaceTest {
+    fn matches(&self, name: &OwnedName) -> bool {
+        match *self {
+            NamespaceTest::Any => true,
+            NamespaceTest::Empty => name.namespace.is_none(),
+            NamespaceTest::Uri(ref uri) => name.namespace.as_ref() == Some(uri),
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum AttributeOp { Equals, Includes, DashMatch, Prefix, Suffix, Substring }
+
+impl AttributeOp {
+    fn matches(self, value: &str, pattern: &str) -> bool {
+        match self {
+            AttributeOp::Equals => value == pattern,
+            AttributeOp::Includes =>
+                !pattern.is_empty() && value.split(is_whitespace_char).any(|word| word == pattern),
+            AttributeOp::DashMatch =>
+                value.starts_with(pattern) && (value.len() == pattern.len() || value[pattern.len()..].starts_with('-')),
+            AttributeOp::Prefix => !pattern.is_empty() && value.starts_with(pattern),
+            AttributeOp::Suffix => !pattern.is_empty() && value.ends_with(pattern),
+            AttributeOp::Substring => !pattern.is_empty() && value.contains(pattern),
+        }
+    }
+}
+
+impl Selector {
+    /// Compiles a selector which does not use namespace prefixes.
+    #[inline]
+    pub fn new(selector: &str) -> Result<Selector> {
+        Selector::with_namespaces(selector, &Namespace::empty())
+    }
+
+    /// Compiles a selector, resolving namespace prefixes used in it with the given mappings.
+    pub fn with_namespaces(selector: &str, namespaces: &Namespace) -> Result<Selector> {
+        let mut parser = Parser { chars: selector.chars().collect(), pos: 0, namespaces };
+        let mut alternatives = Vec::new();
+        loop {
+            parser.skip_whitespace();
+            alternatives.push(parser.complex()?);
+            match parser.next() {
+                Some(',') => {}
+                None => break,
+                Some(_) => return parser.error(parser.pos - 1, "Unexpected character"),
+            }
+        }
+        Ok(Selector { alternatives })
+    }
+
+    /// Checks whether the given node is an element matching this selector.
+    ///
+    /// Combinators may match any ancestors and siblings of the node.
+    pub fn matches(&self, document: &Document, id: NodeId) -> bool {
+        document.element(id).is_some() &&
+            self.alternatives.iter().any(|c| c.matches(document, id, c.compounds.len() - 1))
+    }
+
+    /// Returns an iterator over descendants of the given node which match this selector,
+    /// in document order.
+    #[inline]
+    pub fn select<'a>(&'a self, document: &'a Document, scope: NodeId) -> Select<'a> {
+        Select { selector: self, document, descendants: document.descendants(scope) }
+    }
+
+    /// Returns the first descendant of the given node in document order which matches
+    /// this selector.
+    #[inline]
+    pub fn select_first(&self, document: &Document, scope: NodeId) -> Option<NodeId> {
+        self.select(document, scope).next()
+    }
+}
+
+/// An iterator over elements matching a selector.
+///
+/// It is returned by `Selector::select()`.
+pub struct Select<'a> {
+    selector: &'a Selector,
+    document: &'a Document,
+    descendants: Descendants<'a>,
+}
+
+impl<'a> Iterator for Select<'a> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        let selector = self.selector;
+        let document = self.document;
+        self.descendants.find(|&id| selector.matches(document, id))
+    }
+}
+
+impl Complex {
+    /// Checks whether the element matches compounds of this selector up to the `i`-th one.
+    fn matches(&self, document: &Document, id: NodeId, i: usize) -> bool {
+        let element = document.element(id).unwrap();
+        if !self.compounds[i].iter().all(|s| s.matches(document, id, element)) {
+            return false;
+        }
+        if i == 0 {
+            return true;
+        }
+        let mut candidates = match self.combinators[i - 1] {
+            Combinator::Descendant => Box::new(document.ancestors(id)) as Box<dyn Iterator<Item=NodeId>>,
+            Combinator::Child => Box::new(document.parent(id).into_iter()),
+            Combinator::Adjacent => Box::new(previous_elements(document, id).take(1)),
+            Combinator::Sibling => Box::new(previous_elements(document, id)),
+        };
+        candidates.any(|id| document.element(id).is_some() && self.matches(document, id, i - 1))
+    }
+}
+
+fn previous_elements(document: &Document, id: NodeId) -> impl Iterator<Item=NodeId> + '_ {
+    let siblings = document.parent(id).map_or(&[][..], |parent| document.children(parent));
+    let index = siblings.iter().position(|&s| s == id).unwrap_or(0);
+    siblings[..index].iter().rev().cloned().filter(move |&s| document.element(s).is_some())
+}
+
+impl Simple {
+    fn matches(&self, document: &Document, id: NodeId, element: &Element) -> bool {
+        match *self {
+            Simple::Type(ref namespace, ref local_name) =>
+                namespace.matches(&element.name) && local_name.as_ref().map_or(true, |n| *n == element.name.local_name),
+            Simple::Id(ref value) => element.attributes.iter().any(|a| a.value == *value && is_id(document, element, &a.name)),
+            Simple::Attribute(ref namespace, ref local_name, ref condition) => element.attributes.iter().any(|a| {
+                a.name.local_name == *local_name && namespace.matches(&a.name) &&
+                    condition.as_ref().map_or(true, |&(op, ref pattern)| op.matches(&a.value, pattern))
+            }),
+            Simple::NthChild { a, b, from_end } => {
+                let siblings = document.parent(id).map_or(&[][..], |parent| document.children(parent));
+                let mut elements = siblings.iter().filter(|&&s| document.element(s).is_some());
+                let index = if from_end {
+                    elements.rev().position(|&s| s == id)
+                } else {
+                    elements.position(|&s| s == id)
+                };
+                let n = index.map_or(1, |i| i as i64 + 1) - b;
+                if a == 0 { n == 0 } else { n % a == 0 && n / a >= 0 }
+            }
+        }
+    }
+}
+
+fn is_id(document: &Document, element: &Element, name: &OwnedName) -> bool {
+    match name.namespace {
+        None if name.local_name == "id" => true,
+        Some(ref uri) if uri == NS_XML_URI && name.local_name == "id" => true,
+        _ => document.doctype.as_ref()
+            .and_then(|d| d.attribute(&element.name.borrow().to_repr(), &name.borrow().to_repr()))
+            .map_or(false, |def| def.attribute_type == AttributeType::Id)
+    }
+}
+
+struct Parser<'n> {
+    chars: Vec<char>,
+    pos: usize,
+    namespaces: &'n Namespace,
+}
+
+fn is_ident_start(c: char) -> bool {
+    c == '-' || c == '\\' || c != ':' && is_name_start_char(c)
+}
+
+fn is_ident_char(c: char) -> bool {
+    c == '\\' || c != ':' && c != '.' && is_name_char(c)
+}
+
+impl<'n> Parser<'n> {
+    fn error<T, M: Into<Cow<'static, str>>>(&self, pos: usize, msg: M) -> Result<T> {
+        Err(Error { pos, msg: msg.into() })
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        if c.is_some() {
+            self.pos += 1;
+        }
+        c
+    }
+
+    fn expect(&mut self, expected: char) -> Result<()> {
+        match self.next() {
+            Some(c) if c == expected => Ok(()),
+            Some(_) => self.error(self.pos - 1, format!("Expected '{}'", expected)),
+            None => self.error(self.pos, format!("Expected '{}'", expected)),
+        }
+    }
+
+    /// Skips whitespace and returns whether there was any.
+    fn skip_whitespace(&mut self) -> bool {
+        let start = self.pos;
+        while self.peek().map_or(false, is_whitespace_char) {
+            self.pos += 1;
+        }
+        self.pos != start
+    }
+
+    fn complex(&mut self) -> Result<Complex> {
+        let mut complex = Complex { compounds: vec![self.compound()?], combinators: Vec::new() };
+        loop {
+            let whitespace = self.skip_whitespace();
+            let combinator = match self.peek() {
+                Some('>') => Combinator::Child,
+                Some('+') => Combinator::Adjacent,
+                Some('~') => Combinator::Sibling,
+                None | Some(',') => return Ok(complex),
+                Some(_) if whitespace => Combinator::Descendant,
+                Some(_) => return self.error(self.pos, "Unexpected character"),
+            };
+            if combinator != Combinator::Descendant {
+                self.pos += 1;
+                self.skip_whitespace();
+            }
+            complex.combinators.push(combinator);
+            complex.compounds.push(self.compound()?);
+        }
+    }
+
+    fn compound(&mut self) -> Result<Vec<Simple>> {
+        let mut compound = Vec::new();
+        match self.peek() {
+            Some('*') | Some('|') => compound.push(self.type_selector()?),
+            Some(c) if is_ident_start(c) => compound.push(self.type_selector()?),
+            _ => {}
+        }
+        loop {
+            let start = self.pos;
+            match self.peek() {
+                Some('#') => {
+                    self.pos += 1;
+                    compound.push(Simple::Id(self.ident()?));
+                }
+                Some('[') => {
+                    self.pos += 1;
+                    compound.push(self.attribute_selector()?);
+                }
+                Some(':') => {
+                    self.pos += 1;
+                    compound.push(self.pseudo_class(start)?);
+                }
+                _ => break
+            }
+        }
+        if compound.is_empty() {
+            return self.error(self.pos, "Expected a selector");
+        }
+        Ok(compound)
+    }
+
+    /// Parses an optional namespace prefix followed by a local name or `*`, if allowed.
+    fn qualified_name(&mut self, allow_any: bool) -> Result<(Option<NamespaceTest>, Option<String>)> {
+        let start = self.pos;
+        let first = match self.peek() {
+            Some('|') => None,
+            _ => Some(self.name_or_any(allow_any || self.chars.get(self.pos + 1) == Some(&'|'))?),
+        };
+        // `[a|=b]` has no prefix
+        if self.peek() != Some('|') || self.chars.get(self.pos + 1) == Some(&'=') {
+            return match first {
+                Some(name) => Ok((None, name)),
+                None => self.error(start, "Expected a name"),
+            };
+        }
+        self.pos += 1;
+        let namespace = match first {
+            None => NamespaceTest::Empty,
+            Some(None) => NamespaceTest::Any,
+            Some(Some(prefix)) => match self.namespaces.get(&prefix) {
+                Some(uri) if prefix != NS_NO_PREFIX => NamespaceTest::Uri(uri.into()),
+                _ => return self.error(start, format!("Unbound namespace prefix: {}", prefix)),
+            }
+        };
+        Ok((Some(namespace), self.name_or_any(allow_any)?))
+    }
+
+    fn name_or_any(&mut self, allow_any: bool) -> Result<Option<String>> {
+        if allow_any && self.peek() == Some('*') {
+            self.pos += 1;
+            Ok(None)
+        } else {
+            self.ident().map(Some)
+        }
+    }
+
+    fn type_selector(&mut self) -> Result<Simple> {
+        let (namespace, name) = self.qualified_name(true)?;
+        let namespace = namespace.unwrap_or_else(|| match self.namespaces.get(NS_NO_PREFIX) {
+            Some(uri) if uri != NS_EMPTY_URI => NamespaceTest::Uri(uri.into()),
+            _ => NamespaceTest::Any,
+        });
+        Ok(Simple::Type(namespace, name))
+    }
+
+    fn attribute_selector(&mut self) -> Result<Simple> {
+        self.skip_whitespace();
+        let (namespace, name) = self.qualified_name(false)?;
+        let namespace = namespace.unwrap_or(NamespaceTest::Empty);
+        let name = name.unwrap();
+        self.skip_whitespace();
+        let start = self.pos;
+        let op = match self.next() {
+            Some(']') => return Ok(Simple::Attribute(namespace, name, None)),
+            Some('=') => AttributeOp::Equals,
+            Some(c) => {
+                let op = match c {
+                    '~' => AttributeOp::Includes,
+                    '|' => AttributeOp::DashMatch,
+                    '^' => AttributeOp::Prefix,
+                    '$' => AttributeOp::Suffix,
+                    '*' => AttributeOp::Substring,
+                    _ => return self.error(start, "Expected an attribute operator or ']'"),
+                };
+                self.expect('=')?;
+                op
+            }
+            None => return self.error(start, "Expected an attribute operator or ']'"),
+        };
+        self.skip_whitespace();
+        let value = match self.peek() {
+            Some(q @ '"') | Some(q @ '\'') => self.string(q)?,
+            _ => self.ident()?,
+        };
+        self.skip_whitespace();
+        self.expect(']')?;
+        Ok(Simple::Attribute(namespace, name, Some((op, value))))
+    }
+
+    fn pseudo_class(&mut self, start: usize) -> Result<Simple> {
+        let name = self.ident()?.to_lowercase();
+        let from_end = match &name[..] {
+            "first-child" => return Ok(Simple::NthChild { a: 0, b: 1, from_end: false }),
+            "last-child" => return Ok(Simple::NthChild { a: 0, b: 1, from_end: true }),
+            "nth-child" => false,
+            "nth-last-child" => true,
+            _ => return self.error(start, format!("Unsupported pseudo-class: {}", name)),
+        };
+        self.expect('(')?;
+        let start = self.pos;
+        while self.peek().map_or(false, |c| c != ')') {
+            self.pos += 1;
+        }
+        let argument: String = self.chars[start..self.pos].iter().filter(|&&c| !is_whitespace_char(c)).collect();
+        let (a, b) = match parse_nth(&argument.to_lowercase()) {
+            Some(ab) => ab,
+            None => return self.error(start, format!("Invalid argument of :{}", name)),
+        };
+        self.expect(')')?;
+        Ok(Simple::NthChild { a, b, from_end })
+    }
+
+    fn ident(&mut self) -> Result<String> {
+        if !self.peek().map_or(false, is_ident_start) {
+            return self.error(self.pos, "Expected a name");
+        }
+        let mut result = String::new();
+        while let Some(c) = self.peek() {
+            if !is_ident_char(c) {
+                break;
+            }
+            self.pos += 1;
+            if c == '\\' {
+                result.push(self.escape()?);
+            } else {
+                result.push(c);
+            }
+        }
+        Ok(result)
+    }
+
+    fn string(&mut self, quote: char) -> Result<String> {
+        let start = self.pos;
+        self.pos += 1;
+        let mut result = String::new();
+        loop {
+            match self.next() {
+                Some(c) if c == quote => return Ok(result),
+                Some('\\') => result.push(self.escape()?),
+                Some(c) => result.push(c),
+                None => return self.error(start, "Unterminated string"),
+            }
+        }
+    }
+
+    /// Parses an escape sequence after a backslash: up to six hexadecimal digits optionally
+    /// followed by a whitespace character, or any other character.
+    fn escape(&mut self) -> Result<char> {
+        let start = self.pos;
+        let mut code = 0;
+        while self.pos - start < 6 {
+            match self.peek().and_then(|c| c.to_digit(16)) {
+                Some(d) => {
+                    code = code * 16 + d;
+                    self.pos += 1;
+                }
+                None => break
+            }
+        }
+        if self.pos == start {
+            return match self.next() {
+                Some(c) => Ok(c),
+                None => self.error(start, "Unterminated escape sequence"),
+            };
+        }
+        if self.peek().map_or(false, is_whitespace_char) {
+            self.pos += 1;
+        }
+        match ::std::char::from_u32(code) {
+            Some(c) if code != 0 => Ok(c),
+            _ => self.error(start, "Invalid escape sequence"),
+        }
+    }
+}
+
+/// Parses an `an+b` expression without whitespace, or `odd` or `even`.
+fn parse_nth(s: &str) -> Option<(i64, i64)> {
+    fn integer(s: &str) -> Option<i64> {
+        let digits = s.trim_start_matches(&['+', '-'][..]);
+        if digits.is_empty() || s.len() - digits.len() > 1 || !digits.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        s.parse().ok()
+    }
+
+    match s {
+        "odd" => return Some((2, 1)),
+        "even" => return Some((2, 0)),
+        _ => {}
+    }
+    match s.find('n') {
+        Some(i) => {
+            let a = match &s[..i] {
+                "" | "+" => 1,
+                "-" => -1,
+                a => integer(a)?,
+            };
+            let b = match &s[i + 1..] {
+                "" => 0,
+                b if b.starts_with('+') || b.starts_with('-') => integer(b)?,
+                _ => return None,
+            };
+            Some((a, b))
+        }
+        None => integer(s).map(|b| (0, b)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_nth, Selector};
+
+    #[test]
+    fn nth_expressions() {
+        assert_eq!(parse_nth("odd"), Some((2, 1)));
+        assert_eq!(parse_nth("even"), Some((2, 0)));
+        assert_eq!(parse_nth("3"), Some((0, 3)));
+        assert_eq!(parse_nth("-n+3"), Some((-1, 3)));
+        assert_eq!(parse_nth("+n"), Some((1, 0)));
+        assert_eq!(parse_nth("2n-1"), Some((2, -1)));
+        assert_eq!(parse_nth("10n+0"), Some((10, 0)));
+        assert_eq!(parse_nth("n2"), None);
+        assert_eq!(parse_nth("--n"), None);
+        assert_eq!(parse_nth("2n+-1"), None);
+        assert_eq!(parse_nth(""), None);
+    }
+
+    #[test]
+    fn syntax_errors() {
+        let error = |s: &str| Selector::new(s).unwrap_err().to_string();
+        assert_eq!(error(""), "0: Expected a selector");
+        assert_eq!(error("a >"), "3: Expected a selector");
+        assert_eq!(error("a,,b"), "2: Expected a selector");
+        assert_eq!(error("a.b"), "1: Unexpected character");
+        assert_eq!(error("p|a"), "0: Unbound namespace prefix: p");
+        assert_eq!(error("a[b"), "3: Expected an attribute operator or ']'");
+        assert_eq!(error("a[b=c d]"), "6: Expected ']'");
+        assert_eq!(error("a[b='c]"), "4: Unterminated string");
+        assert_eq!(error("[*|*]"), "3: Expected a name");
+        assert_eq!(error("a:hover"), "1: Unsupported pseudo-class: hover");
+        assert_eq!(error("a:nth-child(x)"), "12: Invalid argument of :nth-child");
+        assert_eq!(error("a:nth-child(2"), "13: Expected ')'");
+    }
+}
@@ -0,0 +1,929 @@
+//! Contains a serde `Serializer` which writes values through an `EventWriter`.
+//!
+//! This module is only available with the `serde` feature.
+//!
+//! Values are mapped onto XML with the same conventions which are used by the `de` module:
+//!
+//! * a struct or a map is written as an element. A field named `@name` is written as an
+//!   attribute, and fields named `@xmlns` and `@xmlns:prefix` are written as namespace
+//!   declarations. A field named `$value` is written as content of the element, and all other
+//!   fields are written as child elements named after the field;
+//! * a sequence is written as repeated elements named after the field, or, for a `$value` field,
+//!   as consecutive content. Sequences written as attribute values are joined with spaces;
+//! * an enum is written as an element named after the variant. A struct field which is an enum is
+//!   written as the element named after the field, which wraps either the element of the variant
+//!   or, for unit variants, the name of the variant as text;
+//! * `None` fields are not written at all;
+//! * strings, numbers, booleans and unit variants are written as text content of elements and as
+//!   values of attributes.
+//!
+//! Attribute fields may be declared in any order relative to other fields: before the start tag
+//! of an element is written, the attribute fields of its struct are serialized on their own, so
+//! the rest of the element is written as it is serialized and only the start tag is kept in
+//! memory. The root element is named after the serialized type unless another name is set with
+//! `Serializer::root_name()`.
+//! Names of fields may have prefixes; they must be bound either with namespace declaration
+//! fields or with `Serializer::namespace()`.
+//!
+//! Since all output goes through an `EventWriter`, its `EmitterConfig` controls indentation,
+//! escaping and other details of the written document.
+//!
+//! ```rust
+//! # extern crate xml;
+//! # #[macro_use] extern crate serde_derive;
+//! use xml::{EmitterConfig, EventWriter};
+//!
+//! #[derive(Serialize)]
+//! #[serde(rename = "catalog")]
+//! struct Catalog {
+//!     #[serde(rename = "@name")]
+//!     name: String,
+//!     book: Vec<Book>,
+//! }
+//!
+//! #[derive(Serialize)]
+//! struct Book {
+//!     #[serde(rename = "@year")]
+//!     year: u32,
+//!     title: String,
+//!     note: Option<String>,
+//! }
+//!
+//! # fn main() {
+//! let catalog = Catalog {
+//!     name: "classics".into(),
+//!     book: vec![
+//!         Book { year: 1965, title: "Dune".into(), note: None },
+//!         Book { year: 1951, title: "Foundation".into(), note: Some("First of a series".into()) },
+//!     ],
+//! };
+//!
+//! let config = EmitterConfig::new().perform_indent(true).write_document_declaration(false);
+//! let mut writer = EventWriter::new_with_config(Vec::new(), config);
+//! xml::ser::to_writer(&mut writer, &catalog).unwrap();
+//!
+//! assert_eq!(String::from_utf8(writer.into_inner()).unwrap(), r#"<catalog name="classics">
+//!   <book year="1965">
+//!     <title>Dune</title>
+//!   </book>
+//!   <book year="1951">
+//!     <title>Foundation</title>
+//!     <note>First of a series</note>
+//!   </book>
+//! </catalog>"#);
+//! # }
+//! ```
+
+use std::borrow::Cow;
+use std::error;
+use std::fmt;
+use std::io::Write;
+
+use serde::ser::{self, Impossible, Serialize};
+
+use attribute::Attribute;
+use name::Name;
+use namespace::{Namespace, NS_NO_PREFIX};
+use writer::{self, EmitterConfig, EventWriter, XmlEvent};
+
+/// An error of serialization.
+#[derive(Debug)]
+pub enum Error {
+    /// The underlying writer has failed.
+    Writer(writer::Error),
+
+    /// The value cannot be written as XML.
+    Data(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Writer(ref e) => e.fmt(f),
+            Error::Data(ref msg) => f.write_str(msg),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::Writer(ref e) => e.description(),
+            Error::Data(ref msg) => msg,
+        }
+    }
+}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Error {
+        Error::Data(msg.to_string())
+    }
+}
+
+impl From<writer::Error> for Error {
+    #[inline]
+    fn from(e: writer::Error) -> Error { Error::Writer(e) }
+}
+
+/// A result type of serialization.
+pub type Result<T> = ::std::result::Result<T, Error>;
+
+/// Serializes a value as an XML element written to the given writer.
+///
+/// Events are passed to the writer as soon as they are serialized; only the attribute fields
+/// of the element being started are kept in memory.
+pub fn to_writer<W: Write, T: ?Sized + Serialize>(writer: &mut EventWriter<W>, value: &T) -> Result<()> {
+    Serializer::new(writer).write(value)
+}
+
+/// Serializes a value as an XML document using the default emitter configuration.
+pub fn to_string<T: ?Sized + Serialize>(value: &T) -> Result<String> {
+    let mut writer = EmitterConfig::new().create_writer(Vec::new());
+    to_writer(&mut writer, value)?;
+    Ok(String::from_utf8(writer.into_inner()).expect("the writer emits UTF-8 by default"))
+}
+
+/// Determines how the next serialized value is written.
+#[derive(Clone)]
+enum Target {
+    /// The root element, named after the value type or the configured root name.
+    Root,
+    /// Content of an element: text, or elements named after types and variants.
+    Content,
+    /// A struct field; sequences are written as repeated elements.
+    Field(String),
+    /// A single element with the given name.
+    Element(String),
+}
+
+/// A start tag which is not written yet, so attributes may still be added to it.
+struct Start {
+    name: String,
+    attributes: Attributes,
+    namespace: Namespace,
+}
+
+impl Start {
+    /// Adds an attribute or, for `xmlns` attributes, a namespace declaration.
+    fn add(&mut self, name: &str, value: String) {
+        if name == "xmlns" {
+            self.namespace.force_put(NS_NO_PREFIX, value);
+        } else if let Some(prefix) = name.strip_prefix("xmlns:") {
+            self.namespace.force_put(prefix, value);
+        } else {
+            self.attributes.push((name.into(), value));
+        }
+    }
+}
+
+/// A serde `Serializer` which writes values through an `EventWriter`.
+///
+/// Values should be written with `Serializer::write()`, which finds attribute fields of each
+/// element before its start tag is written. When a value is passed to this serializer directly,
+/// attribute fields of the root element must precede its other fields.
+pub struct Serializer<'w, W: Write + 'w> {
+    writer: &'w mut EventWriter<W>,
+    root_name: Option<String>,
+    root_namespace: Option<Namespace>,
+    target: Target,
+    attributes: Option<Attributes>,  // attribute fields of the next struct or map
+    start: Option<Start>,  // the start tag of a struct whose attribute fields are not known
+}
+
+impl<'w, W: Write> Serializer<'w, W> {
+    /// Creates a new serializer which writes to the given writer.
+    pub fn new(writer: &'w mut EventWriter<W>) -> Serializer<'w, W> {
+        Serializer {
+            writer: writer,
+            root_name: None,
+            root_namespace: Some(Namespace::empty()),
+            target: Target::Root,
+            attributes: None,
+            start: None,
+        }
+    }
+
+    /// Sets the name of the root element.
+    ///
+    /// By default the root element is named after the serialized struct; values of other types
+    /// can only be serialized when the root name is set.
+    pub fn root_name<S: Into<String>>(mut self, name: S) -> Serializer<'w, W> {
+        self.root_name = Some(name.into());
+        self
+    }
+
+    /// Declares a namespace on the root element.
+    ///
+    /// An empty prefix sets the default namespace.
+    pub fn namespace<P, U>(mut self, prefix: P, uri: U) -> Serializer<'w, W>
+        where P: Into<String>, U: Into<String>
+    {
+        if let Some(ref mut namespace) = self.root_namespace {
+            namespace.force_put(prefix, uri);
+        }
+        self
+    }
+
+    /// Serializes a value as the root element.
+    pub fn write<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.collect_attributes(value)?;
+        value.serialize(self)
+    }
+
+    fn no_root_name(&self, what: &str) -> Error {
+        Error::Data(format!("Cannot serialize {} as the root element without a root name", what))
+    }
+
+    /// Returns the name of the element which wraps a value, or `None` for bare content.
+    fn element_name(&self, target: Target, type_name: Option<&str>, what: &str) -> Result<Option<String>> {
+        match target {
+            Target::Field(name) | Target::Element(name) => Ok(Some(name)),
+            Target::Content => Ok(type_name.map(Into::into)),
+            Target::Root => match self.root_name.clone().or_else(|| type_name.map(Into::into)) {
+                Some(name) => Ok(Some(name)),
+                None => Err(self.no_root_name(what)),
+            },
+        }
+    }
+
+    /// Finds values of attribute fields of the struct or map which the value starts with.
+    ///
+    /// Only the attribute fields are serialized, so that the start tag of its element can be
+    /// written before its content, whatever the order of the fields is.
+    fn collect_attributes<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.attributes = Some(value.serialize(AttributeCollector)?);
+        Ok(())
+    }
+
+    /// Writes the start tag which waits for attribute fields, if any.
+    fn flush(&mut self) -> Result<()> {
+        match self.start.take() {
+            Some(start) => self.write_start(start),
+            None => Ok(()),
+        }
+    }
+
+    fn write_start(&mut self, start: Start) -> Result<()> {
+        let attributes: Vec<_> = start.attributes.iter()
+            .map(|&(ref name, ref value)| Attribute::new(Name::from(name.as_str()), value))
+            .collect();
+        self.writer.write(XmlEvent::StartElement {
+            name: Name::from(start.name.as_str()),
+            attributes: Cow::Owned(attributes),
+            namespace: Cow::Owned(start.namespace),
+        })?;
+        Ok(())
+    }
+
+    fn new_start(&mut self, name: String) -> Start {
+        let namespace = self.root_namespace.take().unwrap_or_else(Namespace::empty);
+        Start { name: name, attributes: Vec::new(), namespace: namespace }
+    }
+
+    fn start_element(&mut self, name: String) -> Result<()> {
+        self.flush()?;
+        let start = self.new_start(name);
+        self.write_start(start)
+    }
+
+    /// Starts the element of a struct or a map with its collected attribute fields and returns
+    /// `true`. If they have not been collected, the start tag waits for attribute fields until
+    /// other content is written.
+    fn start_attributed_element(&mut self, name: String) -> Result<bool> {
+        self.flush()?;
+        let mut start = self.new_start(name);
+        match self.attributes.take() {
+            Some(attributes) => {
+                for (name, value) in attributes {
+                    start.add(&name, value);
+                }
+                self.write_start(start)?;
+                Ok(true)
+            }
+            None => {
+                self.start = Some(start);
+                Ok(false)
+            }
+        }
+    }
+
+    fn end_element(&mut self) -> Result<()> {
+        self.flush()?;
+        self.writer.write(XmlEvent::end_element())?;
+        Ok(())
+    }
+
+    fn text(&mut self, text: &str) -> Result<()> {
+        self.flush()?;
+        if !text.is_empty() {
+            self.writer.write(XmlEvent::characters(text))?;
+        }
+        Ok(())
+    }
+
+    fn scalar(&mut self, text: &str, what: &str) -> Result<()> {
+        let target = self.take_target();
+        match self.element_name(target, None, what)? {
+            Some(name) => {
+                self.start_element(name)?;
+                self.text(text)?;
+                self.end_element()
+            }
+            None => self.text(text),
+        }
+    }
+
+    fn take_target(&mut self) -> Target {
+        ::std::mem::replace(&mut self.target, Target::Root)
+    }
+
+    /// Starts elements of an enum variant and returns the number of started elements.
+    fn start_variant(&mut self, target: Target, variant: &str) -> Result<usize> {
+        match target {
+            Target::Field(name) | Target::Element(name) => {
+                self.start_element(name)?;
+                self.start_element(variant.into())?;
+                Ok(2)
+            }
+            Target::Content | Target::Root => {
+                self.start_element(variant.into())?;
+                Ok(1)
+            }
+        }
+    }
+
+    /// Starts elements of a struct variant and returns the number of started elements and
+    /// whether its attribute fields have been collected.
+    fn start_struct_variant(&mut self, target: Target, variant: &str) -> Result<(usize, bool)> {
+        let closes = match target {
+            Target::Field(name) | Target::Element(name) => {
+                self.start_element(name)?;
+                2
+            }
+            Target::Content | Target::Root => 1,
+        };
+        let collected = self.start_attributed_element(variant.into())?;
+        Ok((closes, collected))
+    }
+
+    fn field<T: ?Sized + Serialize>(&mut self, key: &str, value: &T) -> Result<()> {
+        if let Some(name) = key.strip_prefix('@') {
+            let value = match value.serialize(TextSerializer)? {
+                Some(value) => value,
+                None => return Ok(()),
+            };
+            match self.start {
+                Some(ref mut start) => start.add(name, value),
+                None => return Err(Error::Data(format!(
+                    "Attribute {} follows content of the element; use Serializer::write() to write it", name))),
+            }
+            Ok(())
+        } else {
+            self.flush()?;
+            self.target = if key == "$value" { Target::Content } else { Target::Field(key.into()) };
+            self.collect_attributes(value)?;
+            value.serialize(self)
+        }
+    }
+}
+
+impl<'a, 'w, W: Write> ser::Serializer for &'a mut Serializer<'w, W> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Compound<'a, 'w, W>;
+    type SerializeTuple = Compound<'a, 'w, W>;
+    type SerializeTupleStruct = Compound<'a, 'w, W>;
+    type SerializeTupleVariant = Compound<'a, 'w, W>;
+    type SerializeMap = Compound<'a, 'w, W>;
+    type SerializeStruct = Compound<'a, 'w, W>;
+    type SerializeStructVariant = Compound<'a, 'w, W>;
+
+    fn serialize_bool(self, v: bool) -> Result<()> { self.scalar(if v { "true" } else { "false" }, "a bool") }
+    fn serialize_i8(self, v: i8) -> Result<()> { self.scalar(&v.to_string(), "a number") }
+    fn serialize_i16(self, v: i16) -> Result<()> { self.scalar(&v.to_string(), "a number") }
+    fn serialize_i32(self, v: i32) -> Result<()> { self.scalar(&v.to_string(), "a number") }
+    fn serialize_i64(self, v: i64) -> Result<()> { self.scalar(&v.to_string(), "a number") }
+    fn serialize_u8(self, v: u8) -> Result<()> { self.scalar(&v.to_string(), "a number") }
+    fn serialize_u16(self, v: u16) -> Result<()> { self.scalar(&v.to_string(), "a number") }
+    fn serialize_u32(self, v: u32) -> Result<()> { self.scalar(&v.to_string(), "a number") }
+    fn serialize_u64(self, v: u64) -> Result<()> { self.scalar(&v.to_string(), "a number") }
+    fn serialize_f32(self, v: f32) -> Result<()> { self.scalar(&v.to_string(), "a number") }
+    fn serialize_f64(self, v: f64) -> Result<()> { self.scalar(&v.to_string(), "a number") }
+    fn serialize_char(self, v: char) -> Result<()> { self.scalar(&v.to_string(), "a char") }
+    fn serialize_str(self, v: &str) -> Result<()> { self.scalar(v, "a string") }
+
+    fn serialize_bytes(self, _: &[u8]) -> Result<()> {
+        Err(Error::Data("Byte arrays cannot be serialized as XML".into()))
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        self.take_target();
+        Ok(())
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        let target = self.take_target();
+        match self.element_name(target, None, "a unit")? {
+            Some(name) => {
+                self.start_element(name)?;
+                self.end_element()
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn serialize_unit_struct(self, name: &'static str) -> Result<()> {
+        let target = self.take_target();
+        if let Some(name) = self.element_name(target, Some(name), "a unit struct")? {
+            self.start_element(name)?;
+            self.end_element()?;
+        }
+        Ok(())
+    }
+
+    fn serialize_unit_variant(self, _: &'static str, _: u32, variant: &'static str) -> Result<()> {
+        match self.take_target() {
+            Target::Field(name) | Target::Element(name) => {
+                self.start_element(name)?;
+                self.text(variant)?;
+                self.end_element()
+            }
+            Target::Content | Target::Root => {
+                self.start_element(variant.into())?;
+                self.end_element()
+            }
+        }
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(self, _: &'static str, value: &T) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(self, _: &'static str, _: u32, variant: &'static str,
+                                                        value: &T) -> Result<()> {
+        match self.take_target() {
+            Target::Field(name) | Target::Element(name) => {
+                self.start_element(name)?;
+                self.target = Target::Element(variant.into());
+                value.serialize(&mut *self)?;
+                self.end_element()
+            }
+            Target::Content | Target::Root => {
+                self.target = Target::Element(variant.into());
+                value.serialize(self)
+            }
+        }
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Compound<'a, 'w, W>> {
+        let (item, closes) = match self.take_target() {
+            Target::Field(name) => (Target::Element(name), 0),
+            Target::Element(name) => {
+                self.start_element(name)?;
+                (Target::Content, 1)
+            }
+            Target::Content => (Target::Content, 0),
+            Target::Root => match self.root_name.clone() {
+                Some(name) => {
+                    self.start_element(name)?;
+                    (Target::Content, 1)
+                }
+                None => return Err(self.no_root_name("a sequence")),
+            },
+        };
+        Ok(Compound { ser: self, item, key: None, closes, collected: false })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Compound<'a, 'w, W>> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _: &'static str, len: usize) -> Result<Compound<'a, 'w, W>> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(self, _: &'static str, _: u32, variant: &'static str,
+                               _: usize) -> Result<Compound<'a, 'w, W>> {
+        let target = self.take_target();
+        let closes = self.start_variant(target, variant)?;
+        Ok(Compound { ser: self, item: Target::Content, key: None, closes, collected: false })
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<Compound<'a, 'w, W>> {
+        let target = self.take_target();
+        let collected = match self.element_name(target, None, "a map")? {
+            Some(name) => self.start_attributed_element(name)?,
+            None => return Err(Error::Data("Cannot serialize a map as content without an element name".into())),
+        };
+        Ok(Compound { ser: self, item: Target::Content, key: None, closes: 1, collected })
+    }
+
+    fn serialize_struct(self, name: &'static str, _: usize) -> Result<Compound<'a, 'w, W>> {
+        let target = self.take_target();
+        let (closes, collected) = match self.element_name(target, Some(name), "a struct")? {
+            Some(name) => (1, self.start_attributed_element(name)?),
+            None => (0, false),
+        };
+        Ok(Compound { ser: self, item: Target::Content, key: None, closes, collected })
+    }
+
+    fn serialize_struct_variant(self, _: &'static str, _: u32, variant: &'static str,
+                                _: usize) -> Result<Compound<'a, 'w, W>> {
+        let target = self.take_target();
+        let (closes, collected) = self.start_struct_variant(target, variant)?;
+        Ok(Compound { ser: self, item: Target::Content, key: None, closes, collected })
+    }
+}
+
+/// Serializes sequences, maps and structs; created by `Serializer`.
+pub struct Compound<'a, 'w: 'a, W: Write + 'w> {
+    ser: &'a mut Serializer<'w, W>,
+    item: Target,
+    key: Option<String>,
+    closes: usize,
+    collected: bool,  // whether attribute fields of the struct or map are already written
+}
+
+impl<'a, 'w, W: Write> Compound<'a, 'w, W> {
+    fn element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.ser.target = self.item.clone();
+        self.ser.collect_attributes(value)?;
+        value.serialize(&mut *self.ser)
+    }
+
+    fn field<T: ?Sized + Serialize>(&mut self, key: &str, value: &T) -> Result<()> {
+        if self.collected && key.starts_with('@') {
+            return Ok(());
+        }
+        self.ser.field(key, value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.ser.flush()?;
+        for _ in 0..self.closes {
+            self.ser.end_element()?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a, 'w, W: Write> ser::SerializeSeq for Compound<'a, 'w, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> { self.element(value) }
+    fn end(self) -> Result<()> { Compound::end(self) }
+}
+
+impl<'a, 'w, W: Write> ser::SerializeTuple for Compound<'a, 'w, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> { self.element(value) }
+    fn end(self) -> Result<()> { Compound::end(self) }
+}
+
+impl<'a, 'w, W: Write> ser::SerializeTupleStruct for Compound<'a, 'w, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> { self.element(value) }
+    fn end(self) -> Result<()> { Compound::end(self) }
+}
+
+impl<'a, 'w, W: Write> ser::SerializeTupleVariant for Compound<'a, 'w, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> { self.element(value) }
+    fn end(self) -> Result<()> { Compound::end(self) }
+}
+
+impl<'a, 'w, W: Write> ser::SerializeMap for Compound<'a, 'w, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<()> {
+        match key.serialize(TextSerializer)? {
+            Some(key) => {
+                self.key = Some(key);
+                Ok(())
+            }
+            None => Err(Error::Data("Map keys must not be empty".into())),
+        }
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        let key = self.key.take().expect("serialize_value() is called after serialize_key()");
+        self.field(&key, value)
+    }
+
+    fn end(self) -> Result<()> { Compound::end(self) }
+}
+
+impl<'a, 'w, W: Write> ser::SerializeStruct for Compound<'a, 'w, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, key: &'static str, value: &T) -> Result<()> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<()> { Compound::end(self) }
+}
+
+impl<'a, 'w, W: Write> ser::SerializeStructVariant for Compound<'a, 'w, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, key: &'static str, value: &T) -> Result<()> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<()> { Compound::end(self) }
+}
+
+/// Collects values of attribute fields of the struct or map a value starts with, skipping
+/// all other fields; values of other types have no attributes.
+struct AttributeCollector;
+
+type Attributes = Vec<(String, String)>;
+
+impl ser::Serializer for AttributeCollector {
+    type Ok = Attributes;
+    type Error = Error;
+    type SerializeSeq = CollectedAttributes;
+    type SerializeTuple = CollectedAttributes;
+    type SerializeTupleStruct = CollectedAttributes;
+    type SerializeTupleVariant = CollectedAttributes;
+    type SerializeMap = CollectedAttributes;
+    type SerializeStruct = CollectedAttributes;
+    type SerializeStructVariant = CollectedAttributes;
+
+    fn serialize_bool(self, _: bool) -> Result<Attributes> { Ok(Vec::new()) }
+    fn serialize_i8(self, _: i8) -> Result<Attributes> { Ok(Vec::new()) }
+    fn serialize_i16(self, _: i16) -> Result<Attributes> { Ok(Vec::new()) }
+    fn serialize_i32(self, _: i32) -> Result<Attributes> { Ok(Vec::new()) }
+    fn serialize_i64(self, _: i64) -> Result<Attributes> { Ok(Vec::new()) }
+    fn serialize_u8(self, _: u8) -> Result<Attributes> { Ok(Vec::new()) }
+    fn serialize_u16(self, _: u16) -> Result<Attributes> { Ok(Vec::new()) }
+    fn serialize_u32(self, _: u32) -> Result<Attributes> { Ok(Vec::new()) }
+    fn serialize_u64(self, _: u64) -> Result<Attributes> { Ok(Vec::new()) }
+    fn serialize_f32(self, _: f32) -> Result<Attributes> { Ok(Vec::new()) }
+    fn serialize_f64(self, _: f64) -> Result<Attributes> { Ok(Vec::new()) }
+    fn serialize_char(self, _: char) -> Result<Attributes> { Ok(Vec::new()) }
+    fn serialize_str(self, _: &str) -> Result<Attributes> { Ok(Vec::new()) }
+    fn serialize_bytes(self, _: &[u8]) -> Result<Attributes> { Ok(Vec::new()) }
+    fn serialize_none(self) -> Result<Attributes> { Ok(Vec::new()) }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Attributes> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Attributes> { Ok(Vec::new()) }
+    fn serialize_unit_struct(self, _: &'static str) -> Result<Attributes> { Ok(Vec::new()) }
+
+    fn serialize_unit_variant(self, _: &'static str, _: u32, _: &'static str) -> Result<Attributes> {
+        Ok(Vec::new())
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(self, _: &'static str, value: &T) -> Result<Attributes> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(self, _: &'static str, _: u32, _: &'static str,
+                                                        value: &T) -> Result<Attributes> {
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<CollectedAttributes> { Ok(CollectedAttributes::new()) }
+    fn serialize_tuple(self, _: usize) -> Result<CollectedAttributes> { Ok(CollectedAttributes::new()) }
+
+    fn serialize_tuple_struct(self, _: &'static str, _: usize) -> Result<CollectedAttributes> {
+        Ok(CollectedAttributes::new())
+    }
+
+    fn serialize_tuple_variant(self, _: &'static str, _: u32, _: &'static str,
+                               _: usize) -> Result<CollectedAttributes> {
+        Ok(CollectedAttributes::new())
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<CollectedAttributes> { Ok(CollectedAttributes::new()) }
+
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<CollectedAttributes> {
+        Ok(CollectedAttributes::new())
+    }
+
+    fn serialize_struct_variant(self, _: &'static str, _: u32, _: &'static str,
+                                _: usize) -> Result<CollectedAttributes> {
+        Ok(CollectedAttributes::new())
+    }
+}
+
+/// Keeps values of attribute fields for `AttributeCollector`; items of sequences are skipped.
+struct CollectedAttributes {
+    attributes: Attributes,
+    key: Option<String>,
+}
+
+impl CollectedAttributes {
+    fn new() -> CollectedAttributes {
+        CollectedAttributes { attributes: Vec::new(), key: None }
+    }
+
+    fn field<T: ?Sized + Serialize>(&mut self, key: &str, value: &T) -> Result<()> {
+        if let Some(name) = key.strip_prefix('@') {
+            if let Some(value) = value.serialize(TextSerializer)? {
+                self.attributes.push((name.into(), value));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ser::SerializeSeq for CollectedAttributes {
+    type Ok = Attributes;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, _: &T) -> Result<()> { Ok(()) }
+    fn end(self) -> Result<Attributes> { Ok(self.attributes) }
+}
+
+impl ser::SerializeTuple for CollectedAttributes {
+    type Ok = Attributes;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, _: &T) -> Result<()> { Ok(()) }
+    fn end(self) -> Result<Attributes> { Ok(self.attributes) }
+}
+
+impl ser::SerializeTupleStruct for CollectedAttributes {
+    type Ok = Attributes;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, _: &T) -> Result<()> { Ok(()) }
+    fn end(self) -> Result<Attributes> { Ok(self.attributes) }
+}
+
+impl ser::SerializeTupleVariant for CollectedAttributes {
+    type Ok = Attributes;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, _: &T) -> Result<()> { Ok(()) }
+    fn end(self) -> Result<Attributes> { Ok(self.attributes) }
+}
+
+impl ser::SerializeMap for CollectedAttributes {
+    type Ok = Attributes;
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<()> {
+        self.key = key.serialize(TextSerializer)?;
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        match self.key.take() {
+            Some(key) => self.field(&key, value),
+            None => Ok(()),
+        }
+    }
+
+    fn end(self) -> Result<Attributes> { Ok(self.attributes) }
+}
+
+impl ser::SerializeStruct for CollectedAttributes {
+    type Ok = Attributes;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, key: &'static str, value: &T) -> Result<()> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<Attributes> { Ok(self.attributes) }
+}
+
+impl ser::SerializeStructVariant for CollectedAttributes {
+    type Ok = Attributes;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, key: &'static str, value: &T) -> Result<()> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<Attributes> { Ok(self.attributes) }
+}
+
+/// Converts values of attributes and keys of maps to strings; `None` values are omitted.
+struct TextSerializer;
+
+impl TextSerializer {
+    fn unsupported(what: &str) -> Error {
+        Error::Data(format!("Cannot serialize {} as an attribute value or a map key", what))
+    }
+}
+
+impl ser::Serializer for TextSerializer {
+    type Ok = Option<String>;
+    type Error = Error;
+    type SerializeSeq = TextList;
+    type SerializeTuple = TextList;
+    type SerializeTupleStruct = Impossible<Option<String>, Error>;
+    type SerializeTupleVariant = Impossible<Option<String>, Error>;
+    type SerializeMap = Impossible<Option<String>, Error>;
+    type SerializeStruct = Impossible<Option<String>, Error>;
+    type SerializeStructVariant = Impossible<Option<String>, Error>;
+
+    fn serialize_bool(self, v: bool) -> Result<Option<String>> { Ok(Some(v.to_string())) }
+    fn serialize_i8(self, v: i8) -> Result<Option<String>> { Ok(Some(v.to_string())) }
+    fn serialize_i16(self, v: i16) -> Result<Option<String>> { Ok(Some(v.to_string())) }
+    fn serialize_i32(self, v: i32) -> Result<Option<String>> { Ok(Some(v.to_string())) }
+    fn serialize_i64(self, v: i64) -> Result<Option<String>> { Ok(Some(v.to_string())) }
+    fn serialize_u8(self, v: u8) -> Result<Option<String>> { Ok(Some(v.to_string())) }
+    fn serialize_u16(self, v: u16) -> Result<Option<String>> { Ok(Some(v.to_string())) }
+    fn serialize_u32(self, v: u32) -> Result<Option<String>> { Ok(Some(v.to_string())) }
+    fn serialize_u64(self, v: u64) -> Result<Option<String>> { Ok(Some(v.to_string())) }
+    fn serialize_f32(self, v: f32) -> Result<Option<String>> { Ok(Some(v.to_string())) }
+    fn serialize_f64(self, v: f64) -> Result<Option<String>> { Ok(Some(v.to_string())) }
+    fn serialize_char(self, v: char) -> Result<Option<String>> { Ok(Some(v.to_string())) }
+    fn serialize_str(self, v: &str) -> Result<Option<String>> { Ok(Some(v.into())) }
+    fn serialize_bytes(self, _: &[u8]) -> Result<Option<String>> { Err(TextSerializer::unsupported("a byte array")) }
+    fn serialize_none(self) -> Result<Option<String>> { Ok(None) }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Option<String>> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Option<String>> { Ok(Some(String::new())) }
+    fn serialize_unit_struct(self, _: &'static str) -> Result<Option<String>> { Ok(Some(String::new())) }
+
+    fn serialize_unit_variant(self, _: &'static str, _: u32, variant: &'static str) -> Result<Option<String>> {
+        Ok(Some(variant.into()))
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(self, _: &'static str, value: &T) -> Result<Option<String>> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(self, _: &'static str, _: u32, _: &'static str,
+                                                        _: &T) -> Result<Option<String>> {
+        Err(TextSerializer::unsupported("a newtype variant"))
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<TextList> { Ok(TextList(Vec::new())) }
+    fn serialize_tuple(self, _: usize) -> Result<TextList> { Ok(TextList(Vec::new())) }
+
+    fn serialize_tuple_struct(self, _: &'static str, _: usize) -> Result<Impossible<Option<String>, Error>> {
+        Err(TextSerializer::unsupported("a tuple struct"))
+    }
+
+    fn serialize_tuple_variant(self, _: &'static str, _: u32, _: &'static str,
+                               _: usize) -> Result<Impossible<Option<String>, Error>> {
+        Err(TextSerializer::unsupported("a tuple variant"))
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<Impossible<Option<String>, Error>> {
+        Err(TextSerializer::unsupported("a map"))
+    }
+
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<Impossible<Option<String>, Error>> {
+        Err(TextSerializer::unsupported("a struct"))
+    }
+
+    fn serialize_struct_variant(self, _: &'static str, _: u32, _: &'static str,
+                                _: usize) -> Result<Impossible<Option<String>, Error>> {
+        Err(TextSerializer::unsupported("a struct variant"))
+    }
+}
+
+/// Joins items of a sequence written as text with spaces.
+struct TextList(Vec<String>);
+
+impl TextList {
+    fn push<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        if let Some(item) = value.serialize(TextSerializer)? {
+            self.0.push(item);
+        }
+        Ok(())
+    }
+}
+
+impl ser::SerializeSeq for TextList {
+    type Ok = Option<String>;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> { self.push(value) }
+    fn end(self) -> Result<Option<String>> { Ok(Some(self.0.join(" "))) }
+}
+
+impl ser::SerializeTuple for TextList {
+    type Ok = Option<String>;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> { self.push(value) }
+    fn end(self) -> Result<Option<String>> { Ok(Some(self.0.join(" "))) }
+}
@@ -1,11 +1,15 @@
-use std::io::{self, Read};
+use std::io::{self, Read, Write};
 use std::str;
+use std::char;
 use std::fmt;
 
+use common::Encoding;
+
 #[derive(Debug)]
 pub enum CharReadError {
     UnexpectedEof,
     Utf8(str::Utf8Error),
+    Encoding(&'static str),
     Io(io::Error)
 }
 
@@ -27,34 +31,373 @@ impl fmt::Display for CharReadError {
         match *self {
             UnexpectedEof => write!(f, "unexpected end of stream"),
             Utf8(ref e) => write!(f, "UTF-8 decoding error: {}", e),
+            Encoding(msg) => write!(f, "decoding error: {}", msg),
             Io(ref e) => write!(f, "I/O error: {}", e)
         }
     }
 }
 
-pub fn next_char_from<R: Read>(source: &mut R) -> Result<Option<char>, CharReadError> {
-    const MAX_CODEPOINT_LEN: usize = 4;
+/// Decodes characters from a byte stream.
+///
+/// The encoding is detected from the byte order mark or from the first bytes of the stream
+/// when they are read; afterwards it may be changed with `set_encoding()`, for example,
+/// according to the encoding declaration of a document.
+///
+/// The stream is never read further than the bytes of the next character, so the rest of it may
+/// still be written to or read by others, for example, when a document is read incrementally.
+pub struct CharReader {
+    encoding: Encoding,
+    fixed: bool,    // whether the encoding can no longer be changed with `set_encoding()`
+    sniffed: bool,  // whether the beginning of the stream has been examined
+    buf: [u8; 4],   // bytes read from the stream which have not been decoded yet
+    pos: usize,     // offset of the first byte in `buf` which has not been decoded yet
+    end: usize      // offset right after the last byte in `buf` which was read from the stream
+}
+
+impl CharReader {
+    /// Returns a new reader which detects the encoding of the stream, defaulting to UTF-8.
+    pub fn new() -> CharReader {
+        CharReader {
+            encoding: Encoding::Utf8,
+            fixed: false,
+            sniffed: false,
+            buf: [0; 4],
+            pos: 0,
+            end: 0
+        }
+    }
+
+    /// Returns a new reader which always uses the given encoding.
+    ///
+    /// A byte order mark of this encoding at the beginning of the stream is still skipped.
+    pub fn with_encoding(encoding: Encoding) -> CharReader {
+        CharReader { encoding, fixed: true, ..CharReader::new() }
+    }
 
-    let mut bytes = source.bytes();
-    let mut buf = [0u8; MAX_CODEPOINT_LEN];
-    let mut pos = 0;
+    /// Returns the current encoding.
+    #[inline]
+    pub fn encoding(&self) -> Encoding { self.encoding }
 
-    loop {
-        let next = match bytes.next() {
-            Some(Ok(b)) => b,
-            Some(Err(e)) => return Err(e.into()),
-            None if pos == 0 => return Ok(None),
-            None => return Err(CharReadError::UnexpectedEof)
+    /// Switches decoding of the rest of the stream to the given encoding.
+    ///
+    /// This does nothing if the encoding was given explicitly, was detected from a byte order
+    /// mark or is a UTF-16 one, because in these cases it is already known for sure.
+    /// A switch to a UTF-16 encoding is ignored as well, because the text read so far
+    /// could not have been read if the stream was encoded in UTF-16.
+    pub fn set_encoding(&mut self, encoding: Encoding) {
+        if !self.fixed && encoding.is_ascii_compatible() {
+            self.encoding = encoding;
+        }
+    }
+
+    pub fn next_char_from<R: Read>(&mut self, source: &mut R) -> Result<Option<char>, CharReadError> {
+        if !self.sniffed {
+            self.sniff_encoding(source)?;
+            self.sniffed = true;
+        }
+
+        match self.encoding {
+            Encoding::Utf8 => self.next_utf8_char_from(source),
+            Encoding::Utf16Le | Encoding::Utf16Be => self.next_utf16_char_from(source),
+            Encoding::Latin1 => Ok(self.next_byte_from(source)?.map(|b| b as char)),
+            Encoding::Windows1252 => Ok(self.next_byte_from(source)?.map(windows_1252_char)),
+            Encoding::Ascii => match self.next_byte_from(source)? {
+                Some(b) if b >= 0x80 => Err(CharReadError::Encoding("invalid ASCII byte")),
+                b => Ok(b.map(|b| b as char))
+            }
+        }
+    }
+
+    /// Decodes characters and appends them to `target` while `accept` returns `true` for them.
+    ///
+    /// The first character which is not accepted is left for the next call. So are incomplete
+    /// and invalid byte sequences, so that `next_char_from()` reports them.
+    pub fn read_while_from<R: Read, F: FnMut(char) -> bool>(&mut self, source: &mut R, target: &mut String,
+                                                            mut accept: F) -> io::Result<()> {
+        loop {
+            match self.peek_char_from(source)? {
+                Some((c, len)) if accept(c) => {
+                    target.push(c);
+                    self.pos += len;
+                }
+                _ => return Ok(())
+            }
+        }
+    }
+
+    /// Reads the bytes of the next character and decodes it without consuming it, returning
+    /// the character and the number of bytes it takes.
+    fn peek_char_from<R: Read>(&mut self, source: &mut R) -> io::Result<Option<(char, usize)>> {
+        if !self.fill_to(source, 1)? {
+            return Ok(None);
+        }
+        let len = match self.encoding {
+            _ if self.buf[self.pos] < 0x80 && self.encoding.is_ascii_compatible() =>
+                return Ok(Some((self.buf[self.pos] as char, 1))),
+            Encoding::Utf8 => match self.buf[self.pos] {
+                0xC2..=0xDF => 2,
+                0xE0..=0xEF => 3,
+                0xF0..=0xF4 => 4,
+                _ => 1
+            },
+            Encoding::Utf16Le | Encoding::Utf16Be => {
+                if !self.fill_to(source, 2)? {
+                    return Ok(None);
+                }
+                match self.utf16_unit_at(self.pos) {
+                    0xD800..=0xDBFF => 4,
+                    _ => 2
+                }
+            }
+            _ => 1
         };
-        buf[pos] = next;
-        pos += 1;
+        Ok(if self.fill_to(source, len)? { self.peek_buffered() } else { None })
+    }
 
-        match str::from_utf8(&buf[..pos]) {
-            Ok(s) => return Ok(s.chars().next()),  // always Some(..)
-            Err(_) if pos < MAX_CODEPOINT_LEN => {},
-            Err(e) => return Err(e.into())
+    /// Decodes the next buffered character without consuming it, returning the character
+    /// and the number of bytes it takes.
+    fn peek_buffered(&self) -> Option<(char, usize)> {
+        let bytes = &self.buf[self.pos..self.end];
+        let first = *bytes.first()?;
+        match self.encoding {
+            Encoding::Utf8 if first < 0x80 => Some((first as char, 1)),
+            Encoding::Utf8 => {
+                let len = match first {
+                    0xC2..=0xDF => 2,
+                    0xE0..=0xEF => 3,
+                    0xF0..=0xF4 => 4,
+                    _ => return None
+                };
+                let s = str::from_utf8(bytes.get(..len)?).ok()?;
+                s.chars().next().map(|c| (c, len))
+            }
+            Encoding::Utf16Le | Encoding::Utf16Be => {
+                let unit = |i: usize| if i + 2 <= bytes.len() { Some(self.utf16_unit_at(self.pos + i)) } else { None };
+                let first = unit(0)?;
+                let units = match first {
+                    0xD800..=0xDBFF => vec![first, unit(2)?],
+                    _ => vec![first]
+                };
+                let len = 2 * units.len();
+                char::decode_utf16(units).next()?.ok().map(|c| (c, len))
+            }
+            Encoding::Latin1 => Some((first as char, 1)),
+            Encoding::Windows1252 => Some((windows_1252_char(first), 1)),
+            Encoding::Ascii if first < 0x80 => Some((first as char, 1)),
+            Encoding::Ascii => None
         }
     }
+
+    /// Looks for a byte order mark or a UTF-16-encoded `<` at the beginning of the stream.
+    fn sniff_encoding<R: Read>(&mut self, source: &mut R) -> io::Result<()> {
+        const PREFIXES: [(&[u8], Encoding, bool); 5] = [
+            (b"\xef\xbb\xbf", Encoding::Utf8, true),
+            (b"\xff\xfe", Encoding::Utf16Le, true),
+            (b"\xfe\xff", Encoding::Utf16Be, true),
+            (b"<\0", Encoding::Utf16Le, false),
+            (b"\0<", Encoding::Utf16Be, false),
+        ];
+
+        loop {
+            let available = &self.buf[self.pos..self.end];
+            match PREFIXES.iter().find(|&&(p, _, _)| available.starts_with(p)) {
+                Some(&(p, encoding, bom)) => {
+                    if !self.fixed || self.encoding == encoding {
+                        self.encoding = encoding;
+                        self.fixed = true;
+                        if bom {
+                            self.pos += p.len();
+                        }
+                    }
+                    return Ok(());
+                }
+                // some prefix may still match when more bytes are read
+                None if PREFIXES.iter().any(|&(p, _, _)| p.len() > available.len() && p.starts_with(available)) => {
+                    let len = available.len() + 1;
+                    if !self.fill_to(source, len)? {
+                        return Ok(());
+                    }
+                }
+                None => return Ok(())
+            }
+        }
+    }
+
+    /// Reads the stream until `len` bytes are buffered, but not further. Returns `false` if
+    /// the end of the stream is reached before that.
+    ///
+    /// Buffered bytes are consumed only after a whole character is decoded, so if reading fails,
+    /// for example, with an error of the `WouldBlock` kind, it may be retried later.
+    fn fill_to<R: Read>(&mut self, source: &mut R, len: usize) -> io::Result<bool> {
+        if self.end - self.pos >= len {
+            return Ok(true);
+        }
+        if self.pos > 0 {
+            self.buf.copy_within(self.pos..self.end, 0);
+            self.end -= self.pos;
+            self.pos = 0;
+        }
+        while self.end < len {
+            match source.read(&mut self.buf[self.end..len]) {
+                Ok(0) => return Ok(false),
+                Ok(n) => self.end += n,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {},
+                Err(e) => return Err(e)
+            }
+        }
+        Ok(true)
+    }
+
+    fn next_byte_from<R: Read>(&mut self, source: &mut R) -> io::Result<Option<u8>> {
+        if !self.fill_to(source, 1)? {
+            return Ok(None);
+        }
+        self.pos += 1;
+        Ok(Some(self.buf[self.pos - 1]))
+    }
+
+    fn next_utf8_char_from<R: Read>(&mut self, source: &mut R) -> Result<Option<char>, CharReadError> {
+        const MAX_CODEPOINT_LEN: usize = 4;
+
+        let mut len = 1;
+        loop {
+            if !self.fill_to(source, len)? {
+                return if self.pos == self.end { Ok(None) } else { Err(CharReadError::UnexpectedEof) };
+            }
+
+            match str::from_utf8(&self.buf[self.pos..self.pos + len]) {
+                Ok(s) => {
+                    self.pos += len;
+                    return Ok(s.chars().next());  // always Some(..)
+                }
+                Err(_) if len < MAX_CODEPOINT_LEN => len += 1,
+                Err(e) => {
+                    self.pos += len;
+                    return Err(e.into());
+                }
+            }
+        }
+    }
+
+    fn next_utf16_char_from<R: Read>(&mut self, source: &mut R) -> Result<Option<char>, CharReadError> {
+        if !self.fill_to(source, 2)? {
+            return if self.pos == self.end { Ok(None) } else { Err(CharReadError::UnexpectedEof) };
+        }
+        let first = self.utf16_unit_at(self.pos);
+        let units = match first {
+            0xD800..=0xDBFF => {
+                if !self.fill_to(source, 4)? {
+                    return Err(CharReadError::UnexpectedEof);
+                }
+                vec![first, self.utf16_unit_at(self.pos + 2)]
+            }
+            _ => vec![first]
+        };
+        self.pos += 2 * units.len();
+        match char::decode_utf16(units).next() {
+            Some(Ok(c)) => Ok(Some(c)),
+            _ => Err(CharReadError::Encoding("invalid UTF-16 surrogate pair"))
+        }
+    }
+
+    fn utf16_unit_at(&self, offset: usize) -> u16 {
+        let bytes = [self.buf[offset], self.buf[offset + 1]];
+        match self.encoding {
+            Encoding::Utf16Le => u16::from_le_bytes(bytes),
+            _ => u16::from_be_bytes(bytes)
+        }
+    }
+}
+
+/// Characters of the Windows-1252 encoding for bytes 0x80-0x9F.
+///
+/// Bytes which are not assigned in this encoding are mapped to the corresponding
+/// C1 control characters, like web browsers do.
+const WINDOWS_1252_HIGH: [char; 32] = [
+    '\u{20AC}', '\u{81}', '\u{201A}', '\u{192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{2C6}', '\u{2030}', '\u{160}', '\u{2039}', '\u{152}', '\u{8D}', '\u{17D}', '\u{8F}',
+    '\u{90}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{2DC}', '\u{2122}', '\u{161}', '\u{203A}', '\u{153}', '\u{9D}', '\u{17E}', '\u{178}',
+];
+
+/// Decodes a byte in the Windows-1252 encoding.
+fn windows_1252_char(b: u8) -> char {
+    match b {
+        0x80..=0x9F => WINDOWS_1252_HIGH[(b - 0x80) as usize],
+        _ => b as char
+    }
+}
+
+/// Encodes a character in a single-byte encoding, returning `None` if it cannot be represented.
+fn single_byte(encoding: Encoding, c: char) -> Option<u8> {
+    match (encoding, c as u32) {
+        (Encoding::Ascii, 0..=0x7F) => Some(c as u8),
+        (Encoding::Ascii, _) | (Encoding::Windows1252, 0x80..=0x9F) => None,
+        (_, 0..=0xFF) => Some(c as u8),
+        (Encoding::Windows1252, _) =>
+            WINDOWS_1252_HIGH.iter().position(|&h| h == c).map(|i| 0x80 + i as u8),
+        _ => None
+    }
+}
+
+/// Checks whether the given character can be represented in the given encoding.
+pub fn can_encode(encoding: Encoding, c: char) -> bool {
+    match encoding {
+        Encoding::Utf8 | Encoding::Utf16Le | Encoding::Utf16Be => true,
+        Encoding::Latin1 | Encoding::Windows1252 | Encoding::Ascii => single_byte(encoding, c).is_some()
+    }
+}
+
+/// A writer which accepts UTF-8 text and writes it to the underlying writer in another encoding.
+///
+/// Data passed to `write()` must consist of complete UTF-8 sequences, and all characters must be
+/// representable in the target encoding; otherwise an error of the `InvalidData` kind is returned.
+pub struct EncodingWriter<'a, W: 'a> {
+    inner: &'a mut W,
+    encoding: Encoding,
+    buf: Vec<u8>
+}
+
+impl<'a, W: Write> EncodingWriter<'a, W> {
+    pub fn new(inner: &'a mut W, encoding: Encoding) -> EncodingWriter<'a, W> {
+        EncodingWriter { inner, encoding, buf: Vec::new() }
+    }
+}
+
+impl<'a, W: Write> Write for EncodingWriter<'a, W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        if self.encoding == Encoding::Utf8 {
+            self.inner.write_all(data)?;
+            return Ok(data.len());
+        }
+
+        let s = str::from_utf8(data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        self.buf.clear();
+        for c in s.chars() {
+            match self.encoding {
+                Encoding::Utf16Le | Encoding::Utf16Be => {
+                    let mut units = [0u16; 2];
+                    for &u in c.encode_utf16(&mut units).iter() {
+                        self.buf.extend_from_slice(&match self.encoding {
+                            Encoding::Utf16Le => u.to_le_bytes(),
+                            _ => u.to_be_bytes()
+                        });
+                    }
+                }
+                _ => match single_byte(self.encoding, c) {
+                    Some(b) => self.buf.push(b),
+                    None => return Err(io::Error::new(io::ErrorKind::InvalidData,
+                        format!("character {:?} cannot be represented in {}", c, self.encoding)))
+                }
+            }
+        }
+        self.inner.write_all(&self.buf)?;
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
 }
 
 #[cfg(test)]
@@ -62,28 +405,27 @@ mod tests {
     #[test]
     fn test_next_char_from() {
         use std::io;
-        use std::error::Error;
 
         let mut bytes: &[u8] = "correct".as_bytes();    // correct ASCII
-        assert_eq!(super::next_char_from(&mut bytes).unwrap(), Some('c'));
+        assert_eq!(super::CharReader::new().next_char_from(&mut bytes).unwrap(), Some('c'));
 
         let mut bytes: &[u8] = "правильно".as_bytes();  // correct BMP
-        assert_eq!(super::next_char_from(&mut bytes).unwrap(), Some('п'));
+        assert_eq!(super::CharReader::new().next_char_from(&mut bytes).unwrap(), Some('п'));
 
         let mut bytes: &[u8] = "😊".as_bytes();          // correct non-BMP
-        assert_eq!(super::next_char_from(&mut bytes).unwrap(), Some('😊'));
+        assert_eq!(super::CharReader::new().next_char_from(&mut bytes).unwrap(), Some('😊'));
 
         let mut bytes: &[u8] = b"";                     // empty
-        assert_eq!(super::next_char_from(&mut bytes).unwrap(), None);
+        assert_eq!(super::CharReader::new().next_char_from(&mut bytes).unwrap(), None);
 
         let mut bytes: &[u8] = b"\xf0\x9f\x98";         // incomplete code point
-        match super::next_char_from(&mut bytes).unwrap_err() {
+        match super::CharReader::new().next_char_from(&mut bytes).unwrap_err() {
             super::CharReadError::UnexpectedEof => {},
             e => panic!("Unexpected result: {:?}", e)
         };
 
         let mut bytes: &[u8] = b"\xff\x9f\x98\x32";     // invalid code point
-        match super::next_char_from(&mut bytes).unwrap_err() {
+        match super::CharReader::new().next_char_from(&mut bytes).unwrap_err() {
             super::CharReadError::Utf8(_) => {},
             e => panic!("Unexpected result: {:?}", e)
         };
@@ -98,10 +440,72 @@ mod tests {
         }
 
         let mut r = ErrorReader;
-        match super::next_char_from(&mut r).unwrap_err() {
+        match super::CharReader::new().next_char_from(&mut r).unwrap_err() {
             super::CharReadError::Io(ref e) if e.kind() == io::ErrorKind::Other &&
-                                               e.description() == "test error" => {},
+                                               e.to_string() == "test error" => {},
             e => panic!("Unexpected result: {:?}", e)
         }
     }
+
+    #[test]
+    fn test_char_reader_encodings() {
+        use common::Encoding;
+        use super::{CharReader, CharReadError};
+
+        fn read_all(reader: &mut CharReader, mut bytes: &[u8]) -> Result<String, CharReadError> {
+            let mut result = String::new();
+            while let Some(c) = reader.next_char_from(&mut bytes)? {
+                result.push(c);
+            }
+            Ok(result)
+        }
+
+        let mut reader = CharReader::new();
+        assert_eq!(read_all(&mut reader, b"\xef\xbb\xbf<a>\xc3\xa9</a>").unwrap(), "<a>é</a>");
+        assert_eq!(reader.encoding(), Encoding::Utf8);
+
+        let mut reader = CharReader::new();
+        assert_eq!(read_all(&mut reader, b"\xff\xfe<\0a\0=\xd8\x00\xde").unwrap(), "<a😀");
+        assert_eq!(reader.encoding(), Encoding::Utf16Le);
+
+        let mut reader = CharReader::new();
+        assert_eq!(read_all(&mut reader, b"\xfe\xff\0<\0a").unwrap(), "<a");
+        assert_eq!(reader.encoding(), Encoding::Utf16Be);
+
+        let mut reader = CharReader::new();
+        assert_eq!(read_all(&mut reader, b"<\0a\0").unwrap(), "<a");
+        assert_eq!(reader.encoding(), Encoding::Utf16Le);
+
+        let mut reader = CharReader::new();
+        match read_all(&mut reader, b"\xff\xfe\x00\xdc") {
+            Err(CharReadError::Encoding(_)) => {}
+            r => panic!("Unexpected result: {:?}", r)
+        }
+
+        let mut reader = CharReader::new();
+        reader.set_encoding(Encoding::Latin1);
+        assert_eq!(read_all(&mut reader, b"a\xe9\x80").unwrap(), "aé\u{80}");
+
+        let mut reader = CharReader::new();
+        reader.set_encoding(Encoding::Windows1252);
+        assert_eq!(read_all(&mut reader, b"a\xe9\x80\x81").unwrap(), "aé€\u{81}");
+
+        let mut reader = CharReader::new();
+        reader.set_encoding(Encoding::Ascii);
+        assert_eq!(read_all(&mut reader, b"<a>").unwrap(), "<a>");
+        match read_all(&mut reader, b"\xe9") {
+            Err(CharReadError::Encoding(_)) => {}
+            r => panic!("Unexpected result: {:?}", r)
+        }
+
+        // encodings detected from a byte order mark or given explicitly are not changed
+        let mut reader = CharReader::new();
+        assert_eq!(read_all(&mut reader, b"\xef\xbb\xbf").unwrap(), "");
+        reader.set_encoding(Encoding::Latin1);
+        assert_eq!(reader.encoding(), Encoding::Utf8);
+
+        let mut reader = CharReader::with_encoding(Encoding::Latin1);
+        reader.set_encoding(Encoding::Utf8);
+        assert_eq!(read_all(&mut reader, b"\xef\xbb\xbf").unwrap(), "\u{ef}\u{bb}\u{bf}");
+    }
 }
@@ -0,0 +1,158 @@
+//! Contains an asynchronous counterpart of `EventWriter` for `tokio::io::AsyncWrite` sinks.
+//!
+//! This module is only available when the `async` feature is enabled.
+
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use tokio::io::AsyncWrite;
+
+use super::{EmitterConfig, EventWriter, Result, XmlEvent};
+
+/// Size of the internal buffer after which written events are passed to the sink.
+const BUFFER_SIZE: usize = 8 * 1024;
+
+/// A wrapper around a `tokio::io::AsyncWrite` instance which emits XML document according to
+/// provided events without blocking.
+///
+/// Events are formatted by the same emitter as with `EventWriter` into an internal buffer.
+/// The buffer is written to the sink when it grows larger than 8 KiB and when the writer
+/// is flushed, so `flush()` must be called after the last event.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let mut writer = AsyncEventWriter::new(tcp_stream);
+/// writer.write(XmlEvent::start_element("greeting")).await?;
+/// writer.write("hello").await?;
+/// writer.write(XmlEvent::end_element()).await?;
+/// writer.flush().await?;
+/// ```
+pub struct AsyncEventWriter<W> {
+    sink: W,
+    writer: EventWriter<Vec<u8>>,  // formats events into the buffer
+    pos: usize                     // offset of the first byte in the buffer not written yet
+}
+
+impl<W: AsyncWrite + Unpin> AsyncEventWriter<W> {
+    /// Creates a new `AsyncEventWriter` out of a `tokio::io::AsyncWrite` instance using
+    /// the default configuration.
+    #[inline]
+    pub fn new(sink: W) -> AsyncEventWriter<W> {
+        AsyncEventWriter::new_with_config(sink, EmitterConfig::new())
+    }
+
+    /// Creates a new `AsyncEventWriter` out of a `tokio::io::AsyncWrite` instance using
+    /// the provided configuration.
+    #[inline]
+    pub fn new_with_config(sink: W, config: EmitterConfig) -> AsyncEventWriter<W> {
+        AsyncEventWriter {
+            sink,
+            writer: EventWriter::new_with_config(Vec::new(), config),
+            pos: 0
+        }
+    }
+
+    /// Returns a future which writes the next piece of XML document according to the provided
+    /// event.
+    ///
+    /// The event is formatted when the future is first polled, exactly as with
+    /// `EventWriter::write()`. The future waits for the sink only if the internal buffer
+    /// has to be written out.
+    #[inline]
+    pub fn write<'a, E>(&mut self, event: E) -> WriteEvent<'_, 'a, W> where E: Into<XmlEvent<'a>> {
+        WriteEvent { writer: self, event: Some(event.into()) }
+    }
+
+    /// Returns a future which writes all buffered data to the sink and then flushes it.
+    #[inline]
+    pub fn flush(&mut self) -> Flush<'_, W> {
+        Flush { writer: self }
+    }
+
+    /// Attempts to write all buffered data to the sink and then to flush it.
+    ///
+    /// Returns `Poll::Pending` if the sink is not ready; in this case the current task is
+    /// scheduled to be woken up when it is.
+    pub fn poll_flush(&mut self, cx: &mut Context) -> Poll<Result<()>> {
+        match self.poll_write_buffered(cx) {
+            Poll::Ready(Ok(())) => {}
+            other => return other
+        }
+        Pin::new(&mut self.sink).poll_flush(cx).map(|r| r.map_err(From::from))
+    }
+
+    /// Writes the contents of the internal buffer to the sink, clearing the buffer.
+    fn poll_write_buffered(&mut self, cx: &mut Context) -> Poll<Result<()>> {
+        let buf = &mut self.writer.sink;
+        while self.pos < buf.len() {
+            match Pin::new(&mut self.sink).poll_write(cx, &buf[self.pos..]) {
+                Poll::Ready(Ok(0)) => return Poll::Ready(Err(io::Error::new(
+                    io::ErrorKind::WriteZero, "failed to write the buffered data"
+                ).into())),
+                Poll::Ready(Ok(n)) => self.pos += n,
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e.into())),
+                Poll::Pending => return Poll::Pending
+            }
+        }
+        buf.clear();
+        self.pos = 0;
+        Poll::Ready(Ok(()))
+    }
+
+    /// Returns a mutable reference to the underlying sink.
+    ///
+    /// Note that the events written since the last flush may still be in the internal buffer.
+    /// Use this method with care, as with `EventWriter::inner_mut()`.
+    pub fn inner_mut(&mut self) -> &mut W {
+        &mut self.sink
+    }
+
+    /// Unwraps this `AsyncEventWriter`, returning the underlying writer.
+    ///
+    /// Note that the data which has not been flushed yet is lost.
+    pub fn into_inner(self) -> W {
+        self.sink
+    }
+}
+
+/// A future which writes an event to an `AsyncEventWriter`.
+///
+/// It is returned by `AsyncEventWriter::write()`.
+pub struct WriteEvent<'w, 'a, W: 'w> {
+    writer: &'w mut AsyncEventWriter<W>,
+    event: Option<XmlEvent<'a>>
+}
+
+impl<'w, 'a, W: AsyncWrite + Unpin> Future for WriteEvent<'w, 'a, W> {
+    type Output = Result<()>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<()>> {
+        let this = self.get_mut();
+        if let Some(event) = this.event.take() {
+            this.writer.writer.write(event)?;
+        }
+        if this.writer.writer.sink.len() < BUFFER_SIZE {
+            return Poll::Ready(Ok(()));
+        }
+        this.writer.poll_write_buffered(cx)
+    }
+}
+
+/// A future which flushes an `AsyncEventWriter`.
+///
+/// It is returned by `AsyncEventWriter::flush()`.
+pub struct Flush<'w, W: 'w> {
+    writer: &'w mut AsyncEventWriter<W>
+}
+
+impl<'w, W: AsyncWrite + Unpin> Future for Flush<'w, W> {
+    type Output = Result<()>;
+
+    #[inline]
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<()>> {
+        self.get_mut().writer.poll_flush(cx)
+    }
+}
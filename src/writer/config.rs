@@ -82,6 +82,23 @@ pub struct EmitterConfig {
     /// this option is also true, the same element would appear `<a />`. If this option is false,
     /// then the same element would appear `<a/>`.
     pub pad_self_closing: bool,
+
+    /// Whether or not to check the content of comments, CDATA sections and processing
+    /// instructions. Default is false.
+    ///
+    /// When this option is enabled, the emitter returns an error instead of writing content
+    /// which would make the document malformed: `--` in comments, `]]>` in CDATA sections
+    /// (unless `split_cdata` is enabled), `?>` in processing instructions, processing
+    /// instruction targets which are not names or are reserved (like `xml`), and characters
+    /// which are not allowed in XML documents at all.
+    pub validate_content: bool,
+
+    /// Whether or not to split CDATA sections which contain `]]>`. Default is false.
+    ///
+    /// When this option is enabled, the content of a CDATA section is written as several
+    /// adjacent sections, so that `]]>` does not terminate it prematurely: for example,
+    /// `a]]>b` is written as `<![CDATA[a]]]]><![CDATA[>b]]>`.
+    pub split_cdata: bool,
 }
 
 impl EmitterConfig {
@@ -109,7 +126,9 @@ impl EmitterConfig {
             cdata_to_characters: false,
             keep_element_names_stack: true,
             autopad_comments: true,
-            pad_self_closing: true
+            pad_self_closing: true,
+            validate_content: false,
+            split_cdata: false
         }
     }
 
@@ -153,5 +172,7 @@ gen_setters!(EmitterConfig,
     cdata_to_characters: val bool,
     keep_element_names_stack: val bool,
     autopad_comments: val bool,
-    pad_self_closing: val bool
+    pad_self_closing: val bool,
+    validate_content: val bool,
+    split_cdata: val bool
 );
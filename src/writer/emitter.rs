@@ -9,8 +9,9 @@ use common;
 use name::{Name, OwnedName};
 use attribute::Attribute;
 use escape::{escape_str_attribute, escape_str_pcdata};
-use common::XmlVersion;
-use namespace::{NamespaceStack, NS_NO_PREFIX, NS_EMPTY_URI, NS_XMLNS_PREFIX, NS_XML_PREFIX};
+use common::{XmlVersion, Encoding};
+use util::{self, EncodingWriter};
+use namespace::{NamespaceStack, NS_NO_PREFIX, NS_EMPTY_URI, NS_XMLNS_PREFIX, NS_XML_PREFIX, NS_XML_URI};
 
 use writer::config::EmitterConfig;
 
@@ -32,7 +33,46 @@ pub enum EmitterError {
 
     /// End element name is not specified when it is needed, for example, when automatic
     /// closing is not enabled in configuration.
-    EndElementNameIsNotSpecified
+    EndElementNameIsNotSpecified,
+
+    /// The encoding specified in the document declaration is not supported.
+    UnsupportedEncoding(String),
+
+    /// A character cannot be represented in the encoding of the document.
+    ///
+    /// Such characters are written as character references in text and attribute values;
+    /// this error is returned when they occur in names, comments, processing instructions
+    /// or CDATA sections.
+    UnrepresentableCharacter(char),
+
+    /// The content of a comment contains `--` or ends with `-`.
+    DoubleHyphenInComment,
+
+    /// The content of a CDATA section contains `]]>`.
+    CDataEndInCData,
+
+    /// The data of a processing instruction contains `?>`.
+    ProcessingInstructionEndInData,
+
+    /// The target of a processing instruction is not a name, or it is reserved
+    /// (`xml` in any case).
+    InvalidProcessingInstructionTarget(String),
+
+    /// A character is not allowed in XML documents.
+    InvalidCharacter(char),
+
+    /// The prefix of an element or attribute name is not bound to a namespace URI
+    /// in the current context.
+    UnboundNamespacePrefix(String),
+
+    /// The prefix of an element or attribute name is bound to a namespace URI different
+    /// from the one specified in the name. The prefix is empty for the default namespace.
+    NamespacePrefixBoundToAnotherUri {
+        /// The prefix of the name.
+        prefix: String,
+        /// The namespace URI specified in the name.
+        uri: String
+    }
 }
 
 impl From<io::Error> for EmitterError {
@@ -48,6 +88,20 @@ impl fmt::Display for EmitterError {
         match *self {
             EmitterError::Io(ref e) =>
                 write!(f, "I/O error: {}", e),
+            EmitterError::UnsupportedEncoding(ref encoding) =>
+                write!(f, "unsupported encoding: {}", encoding),
+            EmitterError::UnrepresentableCharacter(c) =>
+                write!(f, "character {:?} cannot be represented in the document encoding", c),
+            EmitterError::InvalidProcessingInstructionTarget(ref target) =>
+                write!(f, "invalid processing instruction target: {}", target),
+            EmitterError::InvalidCharacter(c) =>
+                write!(f, "character {:?} is not allowed in XML documents", c),
+            EmitterError::UnboundNamespacePrefix(ref prefix) =>
+                write!(f, "namespace prefix '{}' is not bound", prefix),
+            EmitterError::NamespacePrefixBoundToAnotherUri { ref prefix, ref uri } if prefix.is_empty() =>
+                write!(f, "default namespace is not bound to '{}'", uri),
+            EmitterError::NamespacePrefixBoundToAnotherUri { ref prefix, ref uri } =>
+                write!(f, "namespace prefix '{}' is not bound to '{}'", prefix, uri),
             ref other =>
                 write!(f, "{}", other.description()),
         }
@@ -67,6 +121,24 @@ impl Error for EmitterError {
                 "end element name is not equal to last start element name",
             EmitterError::EndElementNameIsNotSpecified =>
                 "end element name is not specified and can't be inferred",
+            EmitterError::UnsupportedEncoding(_) =>
+                "unsupported encoding",
+            EmitterError::UnrepresentableCharacter(_) =>
+                "character cannot be represented in the document encoding",
+            EmitterError::DoubleHyphenInComment =>
+                "comment contains '--' or ends with '-'",
+            EmitterError::CDataEndInCData =>
+                "CDATA section contains ']]>'",
+            EmitterError::ProcessingInstructionEndInData =>
+                "processing instruction data contains '?>'",
+            EmitterError::InvalidProcessingInstructionTarget(_) =>
+                "invalid processing instruction target",
+            EmitterError::InvalidCharacter(_) =>
+                "character is not allowed in XML documents",
+            EmitterError::UnboundNamespacePrefix(_) =>
+                "namespace prefix is not bound",
+            EmitterError::NamespacePrefixBoundToAnotherUri { .. } =>
+                "namespace prefix is bound to another namespace URI",
         }
     }
 }
@@ -86,6 +158,8 @@ pub struct Emitter {
 
     element_names: Vec<OwnedName>,
 
+    encoding: Encoding,
+
     start_document_emitted: bool,
     just_wrote_start_element: bool
 }
@@ -102,6 +176,8 @@ impl Emitter {
 
             element_names: Vec::new(),
 
+            encoding: Encoding::Utf8,
+
             start_document_emitted: false,
             just_wrote_start_element: false
         }
@@ -122,6 +198,44 @@ impl Emitter {
         &mut self.nst
     }
 
+    /// Returns the encoding of the document, which is specified by its declaration.
+    #[inline]
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    /// Checks that all characters of the given string can be written in the document encoding.
+    fn check_representable(&self, s: &str) -> Result<()> {
+        match s.chars().find(|&c| !util::can_encode(self.encoding, c)) {
+            Some(c) => Err(EmitterError::UnrepresentableCharacter(c)),
+            None => Ok(())
+        }
+    }
+
+    fn check_name_representable(&self, name: Name) -> Result<()> {
+        if let Some(prefix) = name.prefix {
+            self.check_representable(prefix)?;
+        }
+        self.check_representable(name.local_name)
+    }
+
+    /// Replaces characters which cannot be represented in the document encoding with
+    /// character references.
+    fn escape_unrepresentable<'a>(&self, s: Cow<'a, str>) -> Cow<'a, str> {
+        if s.chars().all(|c| util::can_encode(self.encoding, c)) {
+            return s;
+        }
+        let mut result = String::with_capacity(s.len());
+        for c in s.chars() {
+            if util::can_encode(self.encoding, c) {
+                result.push(c);
+            } else {
+                result.push_str(&format!("&#x{:X};", c as u32));
+            }
+        }
+        Cow::Owned(result)
+    }
+
     #[inline]
     fn wrote_text(&self) -> bool {
         *self.indent_stack.last().unwrap() == IndentFlags::WroteText
@@ -211,8 +325,18 @@ impl Emitter {
         if self.start_document_emitted {
             return Err(EmitterError::DocumentStartAlreadyEmitted);
         }
+        self.encoding = encoding.parse()
+            .map_err(|_| EmitterError::UnsupportedEncoding(encoding.into()))?;
         self.start_document_emitted = true;
 
+        // UTF-16 documents must start with a byte order mark
+        match self.encoding {
+            Encoding::Utf16Le => target.write_all(b"\xff\xfe")?,
+            Encoding::Utf16Be => target.write_all(b"\xfe\xff")?,
+            _ => {}
+        }
+        let target = &mut EncodingWriter::new(target, self.encoding);
+
         self.before_markup(target)?;
         let result = {
             let mut write = move || {
@@ -257,6 +381,14 @@ impl Emitter {
         self.check_document_started(target)?;
         self.fix_non_empty_element(target)?;
 
+        if self.config.validate_content {
+            check_processing_instruction(name, data)?;
+        }
+        self.check_representable(name)?;
+        if let Some(data) = data {
+            self.check_representable(data)?;
+        }
+
         self.before_markup(target)?;
 
         let result = {
@@ -286,6 +418,7 @@ impl Emitter {
     {
         self.check_document_started(target)?;
         self.fix_non_empty_element(target)?;
+        self.check_name_representable(name)?;
         self.before_start_element(target)?;
         write!(target, "<{}", name.repr_display())?;
         self.emit_current_namespace_attributes(target)?;
@@ -299,6 +432,11 @@ impl Emitter {
                                  attributes: &[Attribute]) -> Result<()>
         where W: Write
     {
+        self.check_name_namespace(name, false, attributes)?;
+        for attr in attributes {
+            self.check_name_namespace(attr.name, true, attributes)?;
+        }
+
         if self.config.keep_element_names_stack {
             self.element_names.push(name.to_owned());
         }
@@ -317,13 +455,15 @@ impl Emitter {
         where W: Write
     {
         for (prefix, uri) in self.nst.peek() {
+            self.check_representable(prefix)?;
+            let uri = self.escape_unrepresentable(Cow::Borrowed(uri));
             match prefix {
                 // internal namespaces are not emitted
                 NS_XMLNS_PREFIX | NS_XML_PREFIX => Ok(()),
                 //// there is already a namespace binding with this prefix in scope
                 //prefix if self.nst.get(prefix) == Some(uri) => Ok(()),
-                // emit xmlns only if it is overridden
-                NS_NO_PREFIX => if uri != NS_EMPTY_URI {
+                // emit xmlns only if it is overridden, including undeclaring the default namespace
+                NS_NO_PREFIX => if uri != NS_EMPTY_URI || self.outer_default_namespace_declared() {
                     write!(target, " xmlns=\"{}\"", uri)
                 } else { Ok(()) },
                 // everything else
@@ -333,13 +473,54 @@ impl Emitter {
         Ok(())
     }
 
+    /// Checks that the prefix of the name is bound in the current context, and that it is bound
+    /// to the namespace URI of the name if the latter is specified.
+    ///
+    /// Namespace declarations given as `xmlns` and `xmlns:*` attributes of the element take
+    /// precedence over the namespace stack. Unprefixed attributes do not belong to any namespace,
+    /// and attributes with the `xmlns` prefix are namespace declarations, so they are not checked.
+    fn check_name_namespace(&self, name: Name, is_attribute: bool, attributes: &[Attribute]) -> Result<()> {
+        let prefix = match name.prefix {
+            None | Some(NS_XMLNS_PREFIX) if is_attribute => return Ok(()),
+            Some(prefix) => prefix,
+            None => NS_NO_PREFIX
+        };
+        let declared_uri = attributes.iter().find(|attr| match (attr.name.prefix, prefix) {
+            (None, NS_NO_PREFIX) => attr.name.local_name == NS_XMLNS_PREFIX,
+            (Some(NS_XMLNS_PREFIX), prefix) => attr.name.local_name == prefix,
+            _ => false
+        }).map(|attr| attr.value);
+        let bound_uri = match prefix {
+            NS_XML_PREFIX => Some(NS_XML_URI),
+            prefix => declared_uri.or_else(|| self.nst.get(prefix))
+        };
+        match (bound_uri, name.namespace) {
+            (None, _) if prefix != NS_NO_PREFIX =>
+                Err(EmitterError::UnboundNamespacePrefix(prefix.into())),
+            (bound_uri, Some(uri)) if bound_uri.unwrap_or(NS_EMPTY_URI) != uri =>
+                Err(EmitterError::NamespacePrefixBoundToAnotherUri { prefix: prefix.into(), uri: uri.into() }),
+            _ => Ok(())
+        }
+    }
+
+    /// Returns `true` if the default namespace is bound outside of the current element.
+    fn outer_default_namespace_declared(&self) -> bool {
+        let outer = &self.nst.0[..self.nst.0.len().saturating_sub(1)];
+        match outer.iter().rev().find_map(|ns| ns.get(NS_NO_PREFIX)) {
+            Some(uri) => uri != NS_EMPTY_URI,
+            None => false
+        }
+    }
+
     pub fn emit_attributes<W: Write>(&mut self, target: &mut W,
                                       attributes: &[Attribute]) -> Result<()> {
         for attr in attributes.iter() {
+            self.check_name_representable(attr.name)?;
+            let value = if self.config.perform_escaping { escape_str_attribute(attr.value) } else { Cow::Borrowed(attr.value) };
             write!(
                 target, " {}=\"{}\"",
                 attr.name.repr_display(),
-                if self.config.perform_escaping { escape_str_attribute(attr.value) } else { Cow::Borrowed(attr.value) }
+                self.escape_unrepresentable(value)
             )?
         }
         Ok(())
@@ -388,9 +569,25 @@ impl Emitter {
         if self.config.cdata_to_characters {
             self.emit_characters(target, content)
         } else {
-            // TODO: escape ']]>' characters in CDATA as two adjacent CDATA blocks
+            if self.config.validate_content {
+                check_chars(content)?;
+                if !self.config.split_cdata && content.contains("]]>") {
+                    return Err(EmitterError::CDataEndInCData);
+                }
+            }
+            self.check_representable(content)?;
             target.write(b"<![CDATA[")?;
-            target.write(content.as_bytes())?;
+            if self.config.split_cdata {
+                // ']]>' is written as ']]' at the end of one section and '>' at the start of the next one
+                let mut parts = content.split("]]>");
+                target.write_all(parts.next().unwrap_or("").as_bytes())?;
+                for part in parts {
+                    target.write_all(b"]]]]><![CDATA[>")?;
+                    target.write_all(part.as_bytes())?;
+                }
+            } else {
+                target.write(content.as_bytes())?;
+            }
             target.write(b"]]>")?;
 
             self.after_text();
@@ -402,13 +599,12 @@ impl Emitter {
     pub fn emit_characters<W: Write>(&mut self, target: &mut W,
                                       content: &str) -> Result<()> {
         self.fix_non_empty_element(target)?;
-        target.write(
-            (if self.config.perform_escaping {
-                escape_str_pcdata(content)
-            } else {
-                Cow::Borrowed(content)
-            }).as_bytes()
-        )?;
+        let content = if self.config.perform_escaping {
+            escape_str_pcdata(content)
+        } else {
+            Cow::Borrowed(content)
+        };
+        target.write_all(self.escape_unrepresentable(content).as_bytes())?;
         self.after_text();
         Ok(())
     }
@@ -418,6 +614,16 @@ impl Emitter {
 
         // TODO: add escaping dashes at the end of the comment
 
+        if self.config.validate_content {
+            check_chars(content)?;
+            // a trailing dash would be followed by the closing '-->' unless a space is added
+            let trailing_dash = content.ends_with('-') && !self.config.autopad_comments;
+            if content.contains("--") || trailing_dash {
+                return Err(EmitterError::DoubleHyphenInComment);
+            }
+        }
+        self.check_representable(content)?;
+
         let autopad_comments = self.config.autopad_comments;
         let write = |target: &mut W| -> Result<()> {
             target.write(b"<!--")?;
@@ -444,3 +650,30 @@ impl Emitter {
         result
     }
 }
+
+/// Checks that all characters of the string are allowed in XML documents.
+fn check_chars(s: &str) -> Result<()> {
+    match s.chars().find(|&c| !common::is_xml_char(c)) {
+        Some(c) => Err(EmitterError::InvalidCharacter(c)),
+        None => Ok(())
+    }
+}
+
+/// Checks that the processing instruction can be written as is.
+fn check_processing_instruction(name: &str, data: Option<&str>) -> Result<()> {
+    let mut chars = name.chars();
+    let is_name = match chars.next() {
+        Some(c) => common::is_name_start_char(c) && chars.all(common::is_name_char),
+        None => false
+    };
+    if !is_name || name.eq_ignore_ascii_case("xml") {
+        return Err(EmitterError::InvalidProcessingInstructionTarget(name.into()));
+    }
+    if let Some(data) = data {
+        check_chars(data)?;
+        if data.contains("?>") {
+            return Err(EmitterError::ProcessingInstructionEndInData);
+        }
+    }
+    Ok(())
+}
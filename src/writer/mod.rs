@@ -7,14 +7,20 @@ pub use self::emitter::Result;
 pub use self::emitter::EmitterError as Error;
 pub use self::config::EmitterConfig;
 pub use self::events::XmlEvent;
+#[cfg(feature = "async")]
+pub use self::async_writer::AsyncEventWriter;
 
 use self::emitter::Emitter;
 
 use std::io::prelude::*;
 
+use util::EncodingWriter;
+
 mod emitter;
 mod config;
 pub mod events;
+#[cfg(feature = "async")]
+pub mod async_writer;
 
 /// A wrapper around an `std::io::Write` instance which emits XML document according to provided
 /// events.
@@ -49,26 +55,29 @@ impl<W: Write> EventWriter<W> {
     /// Another example is that `XmlEvent::CData` may be represented as characters in
     /// the output stream.
     pub fn write<'a, E>(&mut self, event: E) -> Result<()> where E: Into<XmlEvent<'a>> {
+        // Output is transcoded to the encoding specified in the document declaration
+        let encoding = self.emitter.encoding();
+        let sink = &mut EncodingWriter::new(&mut self.sink, encoding);
         match event.into() {
             XmlEvent::StartDocument { version, encoding, standalone } =>
-                self.emitter.emit_start_document(&mut self.sink, version, encoding.unwrap_or("UTF-8"), standalone),
+                self.emitter.emit_start_document(sink, version, encoding.unwrap_or("UTF-8"), standalone),
             XmlEvent::ProcessingInstruction { name, data } =>
-                self.emitter.emit_processing_instruction(&mut self.sink, name, data),
+                self.emitter.emit_processing_instruction(sink, name, data),
             XmlEvent::StartElement { name, attributes, namespace } => {
                 self.emitter.namespace_stack_mut().push_empty().checked_target().extend(namespace.as_ref());
-                self.emitter.emit_start_element(&mut self.sink, name, &attributes)
+                self.emitter.emit_start_element(sink, name, &attributes)
             }
             XmlEvent::EndElement { name } => {
-                let r = self.emitter.emit_end_element(&mut self.sink, name);
+                let r = self.emitter.emit_end_element(sink, name);
                 self.emitter.namespace_stack_mut().try_pop();
                 r
             }
             XmlEvent::Comment(content) =>
-                self.emitter.emit_comment(&mut self.sink, content),
+                self.emitter.emit_comment(sink, content),
             XmlEvent::CData(content) =>
-                self.emitter.emit_cdata(&mut self.sink, content),
+                self.emitter.emit_cdata(sink, content),
             XmlEvent::Characters(content) =>
-                self.emitter.emit_characters(&mut self.sink, content)
+                self.emitter.emit_characters(sink, content)
         }
     }
 
@@ -0,0 +1,600 @@
+//! Contains evaluation of parsed XPath expressions.
+
+use std::collections::HashMap;
+
+use common::is_whitespace_char;
+use dom::{self, Document, NodeId};
+use dtd::AttributeType;
+use namespace::NS_XML_URI;
+
+use super::parser::{Expr, PathStart, Step, Axis, NodeTest, CompareOp, ArithmeticOp, Function};
+use super::{Node, Value, Error, Result, namespaces};
+
+/// The dynamic context of evaluation of an expression.
+#[derive(Copy, Clone)]
+struct Context {
+    node: Node,
+    position: usize,
+    size: usize,
+}
+
+/// Evaluates expressions against a single tree.
+pub struct Evaluator<'a> {
+    document: &'a Document,
+    variables: &'a HashMap<String, Value>,
+    /// The root of the tree which contains the context node.
+    root: NodeId,
+    /// Nodes of the tree in document order, excluding text nodes which are merged into
+    /// preceding ones.
+    order: Vec<NodeId>,
+    /// Positions of nodes in `order` and positions after the last of their descendants.
+    index: HashMap<NodeId, (usize, usize)>,
+}
+
+fn is_text(node: &dom::Node) -> bool {
+    match *node {
+        dom::Node::Text(_) | dom::Node::CData(_) => true,
+        _ => false
+    }
+}
+
+impl<'a> Evaluator<'a> {
+    pub fn new(document: &'a Document, node: NodeId, variables: &'a HashMap<String, Value>) -> Evaluator<'a> {
+        let root = document.ancestors(node).last().unwrap_or(node);
+        let mut evaluator = Evaluator { document, variables, root, order: Vec::new(), index: HashMap::new() };
+
+        // `None` marks the end of the subtree of the node on top of the stack
+        let mut stack = vec![Some(root)];
+        let mut open = Vec::new();
+        while let Some(item) = stack.pop() {
+            match item {
+                Some(id) => {
+                    open.push((id, evaluator.order.len()));
+                    evaluator.order.push(id);
+                    stack.push(None);
+                    stack.extend(evaluator.children(id).into_iter().rev().map(Some));
+                }
+                None => {
+                    let (id, start) = open.pop().unwrap();
+                    evaluator.index.insert(id, (start, evaluator.order.len()));
+                }
+            }
+        }
+        evaluator
+    }
+
+    pub fn evaluate(&self, expr: &Expr, node: Node) -> Result<Value> {
+        self.eval(expr, Context { node, position: 1, size: 1 })
+    }
+
+    /// Returns children of a node which are XPath nodes.
+    fn children(&self, id: NodeId) -> Vec<NodeId> {
+        let mut previous_is_text = false;
+        self.document.children(id).iter().cloned().filter(|&c| {
+            let text = is_text(self.document.node(c));
+            let visible = !(text && previous_is_text);
+            previous_is_text = text;
+            visible
+        }).collect()
+    }
+
+    fn range(&self, id: NodeId) -> (usize, usize) {
+        self.index[&id]
+    }
+
+    fn order_key(&self, node: Node) -> (usize, usize, usize) {
+        match node {
+            Node::Tree(id) => (self.range(id).0, 0, 0),
+            Node::Namespace(id, i) => (self.range(id).0, 1, i),
+            Node::Attribute(id, i) => (self.range(id).0, 2, i),
+        }
+    }
+
+    fn sort(&self, mut nodes: Vec<Node>) -> Vec<Node> {
+        nodes.sort_by_key(|&n| self.order_key(n));
+        nodes.dedup();
+        nodes
+    }
+
+    fn eval(&self, expr: &Expr, ctx: Context) -> Result<Value> {
+        Ok(match *expr {
+            Expr::Or(ref l, ref r) =>
+                Value::Boolean(self.eval(l, ctx)?.to_boolean() || self.eval(r, ctx)?.to_boolean()),
+            Expr::And(ref l, ref r) =>
+                Value::Boolean(self.eval(l, ctx)?.to_boolean() && self.eval(r, ctx)?.to_boolean()),
+            Expr::Compare(op, ref l, ref r) => {
+                let (l, r) = (self.eval(l, ctx)?, self.eval(r, ctx)?);
+                Value::Boolean(self.compare(op, &l, &r))
+            }
+            Expr::Arithmetic(op, ref l, ref r) => {
+                let (l, r) = (self.number(l, ctx)?, self.number(r, ctx)?);
+                Value::Number(match op {
+                    ArithmeticOp::Add => l + r,
+                    ArithmeticOp::Sub => l - r,
+                    ArithmeticOp::Mul => l * r,
+                    ArithmeticOp::Div => l / r,
+                    ArithmeticOp::Mod => l % r,
+                })
+            }
+            Expr::Negate(ref e) => Value::Number(-self.number(e, ctx)?),
+            Expr::Union(ref l, ref r) => {
+                let mut nodes = self.node_set(l, ctx)?;
+                nodes.extend(self.node_set(r, ctx)?);
+                Value::NodeSet(self.sort(nodes))
+            }
+            Expr::Literal(ref s) => Value::String(s.clone()),
+            Expr::Number(n) => Value::Number(n),
+            Expr::Variable(ref name) => match self.variables.get(name) {
+                Some(value) => value.clone(),
+                None => return Err(Error::Evaluation(format!("Variable ${} is not defined", name).into()))
+            },
+            Expr::Function(f, ref args) => self.function(f, args, ctx)?,
+            Expr::Filter(ref e, ref predicates) => {
+                let nodes = self.node_set(e, ctx)?;
+                Value::NodeSet(self.filter(nodes, predicates)?)
+            }
+            Expr::Path(ref start, ref steps) => {
+                let mut nodes = match *start {
+                    PathStart::Root => vec![Node::Tree(self.root)],
+                    PathStart::Context => vec![ctx.node],
+                    PathStart::Expr(ref e) => self.node_set(e, ctx)?,
+                };
+                for step in steps {
+                    nodes = self.step(&nodes, step)?;
+                }
+                Value::NodeSet(nodes)
+            }
+        })
+    }
+
+    fn node_set(&self, expr: &Expr, ctx: Context) -> Result<Vec<Node>> {
+        match self.eval(expr, ctx)? {
+            Value::NodeSet(nodes) => Ok(nodes),
+            _ => Err(Error::Evaluation("Expression does not evaluate to a node-set".into()))
+        }
+    }
+
+    fn number(&self, expr: &Expr, ctx: Context) -> Result<f64> {
+        self.eval(expr, ctx).map(|v| v.to_number(self.document))
+    }
+
+    fn string(&self, expr: &Expr, ctx: Context) -> Result<String> {
+        self.eval(expr, ctx).map(|v| v.to_string_value(self.document))
+    }
+
+    fn step(&self, input: &[Node], step: &Step) -> Result<Vec<Node>> {
+        let mut result = Vec::new();
+        for &node in input {
+            let nodes = self.axis(step.axis, node).into_iter()
+                .filter(|&n| self.test(&step.test, step.axis, n))
+                .collect();
+            result.extend(self.filter(nodes, &step.predicates)?);
+        }
+        Ok(self.sort(result))
+    }
+
+    /// Applies predicates to nodes, which are given in the order of the axis.
+    fn filter(&self, mut nodes: Vec<Node>, predicates: &[Expr]) -> Result<Vec<Node>> {
+        for predicate in predicates {
+            let size = nodes.len();
+            let mut kept = Vec::new();
+            for (i, node) in nodes.into_iter().enumerate() {
+                let ctx = Context { node, position: i + 1, size };
+                let keep = match self.eval(predicate, ctx)? {
+                    Value::Number(n) => n == ctx.position as f64,
+                    value => value.to_boolean()
+                };
+                if keep {
+                    kept.push(node);
+                }
+            }
+            nodes = kept;
+        }
+        Ok(nodes)
+    }
+
+    /// Returns nodes along an axis, in reverse document order for reverse axes.
+    fn axis(&self, axis: Axis, node: Node) -> Vec<Node> {
+        let tree = |ids: &[NodeId]| ids.iter().map(|&id| Node::Tree(id)).collect::<Vec<_>>();
+        match (axis, node) {
+            (Axis::SelfAxis, _) => vec![node],
+            (Axis::Child, Node::Tree(id)) => tree(&self.children(id)),
+            (Axis::Descendant, Node::Tree(id)) => {
+                let (start, end) = self.range(id);
+                tree(&self.order[start + 1..end])
+            }
+            (Axis::DescendantOrSelf, Node::Tree(id)) => {
+                let (start, end) = self.range(id);
+                tree(&self.order[start..end])
+            }
+            (Axis::DescendantOrSelf, _) => vec![node],
+            (Axis::Parent, _) => self.parent(node).into_iter().collect(),
+            (Axis::Ancestor, _) | (Axis::AncestorOrSelf, _) => {
+                let mut result = if axis == Axis::AncestorOrSelf { vec![node] } else { Vec::new() };
+                let mut current = self.parent(node);
+                while let Some(parent) = current {
+                    result.push(parent);
+                    current = self.parent(parent);
+                }
+                result
+            }
+            (Axis::FollowingSibling, Node::Tree(id)) | (Axis::PrecedingSibling, Node::Tree(id)) => {
+                let siblings = match self.document.parent(id) {
+                    Some(parent) if id != self.root => self.children(parent),
+                    _ => return Vec::new()
+                };
+                let i = siblings.iter().position(|&s| s == id).unwrap();
+                if axis == Axis::FollowingSibling {
+                    tree(&siblings[i + 1..])
+                } else {
+                    siblings[..i].iter().rev().map(|&id| Node::Tree(id)).collect()
+                }
+            }
+            (Axis::Following, Node::Tree(id)) => tree(&self.order[self.range(id).1..]),
+            (Axis::Following, Node::Attribute(id, _)) | (Axis::Following, Node::Namespace(id, _)) =>
+                tree(&self.order[self.range(id).0 + 1..]),
+            (Axis::Preceding, _) => {
+                let id = match node {
+                    Node::Tree(id) | Node::Attribute(id, _) | Node::Namespace(id, _) => id
+                };
+                let (start, _) = self.range(id);
+                // the ancestors of a node are exactly the preceding nodes whose subtrees contain it
+                self.order[..start].iter().rev()
+                    .filter(|&&p| self.range(p).1 <= start)
+                    .map(|&id| Node::Tree(id))
+                    .collect()
+            }
+            (Axis::Attribute, Node::Tree(id)) => match self.document.element(id) {
+                Some(element) => (0..element.attributes.len()).map(|i| Node::Attribute(id, i)).collect(),
+                None => Vec::new()
+            },
+            (Axis::Namespace, Node::Tree(id)) =>
+                (0..namespaces(self.document, id).count()).map(|i| Node::Namespace(id, i)).collect(),
+            _ => Vec::new()
+        }
+    }
+
+    fn parent(&self, node: Node) -> Option<Node> {
+        match node {
+            Node::Tree(id) if id == self.root => None,
+            Node::Tree(id) => self.document.parent(id).map(Node::Tree),
+            Node::Attribute(id, _) | Node::Namespace(id, _) => Some(Node::Tree(id)),
+        }
+    }
+
+    fn test(&self, test: &NodeTest, axis: Axis, node: Node) -> bool {
+        let dom_node = match node {
+            Node::Tree(id) => Some(self.document.node(id)),
+            _ => None
+        };
+        let is_principal = match (axis, node, dom_node) {
+            (Axis::Attribute, Node::Attribute(..), _) | (Axis::Namespace, Node::Namespace(..), _) => true,
+            (Axis::Attribute, ..) | (Axis::Namespace, ..) => false,
+            (_, _, Some(&dom::Node::Element(_))) => true,
+            _ => false
+        };
+        match *test {
+            NodeTest::Node => true,
+            NodeTest::Text => dom_node.map_or(false, is_text),
+            NodeTest::Comment => match dom_node {
+                Some(&dom::Node::Comment(_)) => true,
+                _ => false
+            },
+            NodeTest::ProcessingInstruction(ref target) => match dom_node {
+                Some(&dom::Node::ProcessingInstruction { ref name, .. }) =>
+                    target.as_ref().map_or(true, |t| t == name),
+                _ => false
+            },
+            NodeTest::AnyName => is_principal,
+            NodeTest::Namespace(ref uri) => is_principal && match self.expanded_name(node) {
+                Some((Some(ns), _)) => ns == uri,
+                _ => false
+            },
+            NodeTest::Name(ref uri, ref local) => is_principal && match self.expanded_name(node) {
+                Some((ns, name)) => name == local && ns == uri.as_ref().map(|s| &s[..]),
+                None => false
+            },
+        }
+    }
+
+    /// Returns the namespace URI and the local name of a node.
+    fn expanded_name(&self, node: Node) -> Option<(Option<&'a str>, &'a str)> {
+        let document = self.document;
+        match node {
+            Node::Tree(id) => match *document.node(id) {
+                dom::Node::Element(ref e) => Some((e.name.namespace_ref(), &e.name.local_name)),
+                dom::Node::ProcessingInstruction { ref name, .. } => Some((None, name)),
+                _ => None
+            },
+            Node::Attribute(id, i) => {
+                let name = &document.element(id).unwrap().attributes[i].name;
+                Some((name.namespace_ref(), &name.local_name))
+            }
+            Node::Namespace(id, i) => namespaces(document, id).nth(i).map(|(prefix, _)| (None, prefix)),
+        }
+    }
+
+    /// Returns the node an optional node-set argument refers to: the first node of the node-set
+    /// or the context node.
+    fn node_argument(&self, args: &[Expr], ctx: Context) -> Result<Option<Node>> {
+        match args.first() {
+            Some(arg) => self.node_set(arg, ctx).map(|nodes| nodes.first().cloned()),
+            None => Ok(Some(ctx.node))
+        }
+    }
+
+    fn string_argument(&self, args: &[Expr], ctx: Context) -> Result<String> {
+        match args.first() {
+            Some(arg) => self.string(arg, ctx),
+            None => Ok(ctx.node.string_value(self.document))
+        }
+    }
+
+    fn function(&self, f: Function, args: &[Expr], ctx: Context) -> Result<Value> {
+        let string = |i: usize| self.string(&args[i], ctx);
+        let number = |i: usize| self.number(&args[i], ctx);
+        Ok(match f {
+            Function::Last => Value::Number(ctx.size as f64),
+            Function::Position => Value::Number(ctx.position as f64),
+            Function::Count => Value::Number(self.node_set(&args[0], ctx)?.len() as f64),
+            Function::Id => {
+                let ids = match self.eval(&args[0], ctx)? {
+                    Value::NodeSet(nodes) => nodes.iter()
+                        .map(|n| n.string_value(self.document))
+                        .collect::<Vec<_>>().join(" "),
+                    value => value.to_string_value(self.document)
+                };
+                Value::NodeSet(self.elements_by_id(&ids))
+            }
+            Function::LocalName => Value::String(match self.node_argument(args, ctx)? {
+                Some(node) => self.expanded_name(node).map_or("", |(_, local)| local).into(),
+                None => String::new()
+            }),
+            Function::NamespaceUri => Value::String(match self.node_argument(args, ctx)? {
+                Some(node) => self.expanded_name(node).and_then(|(ns, _)| ns).unwrap_or("").into(),
+                None => String::new()
+            }),
+            Function::Name => Value::String(match self.node_argument(args, ctx)? {
+                Some(Node::Tree(id)) => match *self.document.node(id) {
+                    dom::Node::Element(ref e) => e.name.borrow().to_repr(),
+                    dom::Node::ProcessingInstruction { ref name, .. } => name.clone(),
+                    _ => String::new()
+                },
+                Some(Node::Attribute(id, i)) => self.document.element(id).unwrap().attributes[i].name.borrow().to_repr(),
+                Some(node) => self.expanded_name(node).map_or("", |(_, local)| local).into(),
+                None => String::new()
+            }),
+            Function::String => Value::String(self.string_argument(args, ctx)?),
+            Function::Concat => {
+                let mut result = String::new();
+                for i in 0..args.len() {
+                    result.push_str(&string(i)?);
+                }
+                Value::String(result)
+            }
+            Function::StartsWith => Value::Boolean(string(0)?.starts_with(&string(1)?[..])),
+            Function::Contains => Value::Boolean(string(0)?.contains(&string(1)?[..])),
+            Function::SubstringBefore => {
+                let (s, pattern) = (string(0)?, string(1)?);
+                Value::String(s.find(&pattern[..]).map_or("", |i| &s[..i]).into())
+            }
+            Function::SubstringAfter => {
+                let (s, pattern) = (string(0)?, string(1)?);
+                Value::String(s.find(&pattern[..]).map_or("", |i| &s[i + pattern.len()..]).into())
+            }
+            Function::Substring => {
+                let s = string(0)?;
+                let start = round(number(1)?);
+                let end = if args.len() > 2 { start + round(number(2)?) } else { ::std::f64::INFINITY };
+                // positions are 1-based, and comparisons with NaN exclude all characters
+                Value::String(s.chars().enumerate()
+                    .filter(|&(i, _)| { let p = (i + 1) as f64; p >= start && p < end })
+                    .map(|(_, c)| c)
+                    .collect())
+            }
+            Function::StringLength => Value::Number(self.string_argument(args, ctx)?.chars().count() as f64),
+            Function::NormalizeSpace => Value::String(
+                self.string_argument(args, ctx)?.split(is_whitespace_char)
+                    .filter(|s| !s.is_empty())
+                    .collect::<Vec<_>>().join(" ")
+            ),
+            Function::Translate => {
+                let (s, from, to) = (string(0)?, string(1)?, string(2)?);
+                let from: Vec<char> = from.chars().collect();
+                let to: Vec<char> = to.chars().collect();
+                Value::String(s.chars().filter_map(|c| match from.iter().position(|&f| f == c) {
+                    Some(i) => to.get(i).cloned(),
+                    None => Some(c)
+                }).collect())
+            }
+            Function::Boolean => Value::Boolean(self.eval(&args[0], ctx)?.to_boolean()),
+            Function::Not => Value::Boolean(!self.eval(&args[0], ctx)?.to_boolean()),
+            Function::True => Value::Boolean(true),
+            Function::False => Value::Boolean(false),
+            Function::Lang => {
+                let lang = string(0)?.to_lowercase();
+                Value::Boolean(match self.language(ctx.node) {
+                    Some(actual) => {
+                        let actual = actual.to_lowercase();
+                        actual == lang || actual.starts_with(&lang) && actual[lang.len()..].starts_with('-')
+                    }
+                    None => false
+                })
+            }
+            Function::Number => Value::Number(match args.first() {
+                Some(arg) => self.number(arg, ctx)?,
+                None => string_to_number(&ctx.node.string_value(self.document))
+            }),
+            Function::Sum => Value::Number(self.node_set(&args[0], ctx)?.iter()
+                .map(|n| string_to_number(&n.string_value(self.document)))
+                // `Iterator::sum()` of no floats is -0.0, while the sum of an empty node-set is 0
+                .fold(0.0, |a, b| a + b)),
+            Function::Floor => Value::Number(number(0)?.floor()),
+            Function::Ceiling => Value::Number(number(0)?.ceil()),
+            Function::Round => Value::Number(round(number(0)?)),
+        })
+    }
+
+    /// Returns the value of `xml:lang` in scope of a node.
+    fn language(&self, node: Node) -> Option<&'a str> {
+        let mut current = Some(node);
+        while let Some(node) = current {
+            if let Node::Tree(id) = node {
+                let lang = self.document.element(id).and_then(|e| e.attributes.iter().find(|a| {
+                    a.name.local_name == "lang" && a.name.namespace_ref() == Some(NS_XML_URI)
+                }));
+                if let Some(lang) = lang {
+                    return Some(&lang.value);
+                }
+            }
+            current = self.parent(node);
+        }
+        None
+    }
+
+    /// Returns elements which have any of the given whitespace-separated IDs.
+    ///
+    /// Attributes declared with the `ID` type in the DTD and `xml:id` attributes are IDs.
+    fn elements_by_id(&self, ids: &str) -> Vec<Node> {
+        let ids: Vec<_> = ids.split(is_whitespace_char).filter(|s| !s.is_empty()).collect();
+        let doctype = self.document.doctype.as_ref();
+        let mut result = Vec::new();
+        for &id in &self.order {
+            let element = match self.document.element(id) {
+                Some(e) => e,
+                None => continue
+            };
+            let has_id = element.attributes.iter().any(|a| {
+                let is_id = a.name.local_name == "id" && a.name.namespace_ref() == Some(NS_XML_URI) ||
+                    doctype.and_then(|d| d.attribute(&element.name.borrow().to_repr(), &a.name.borrow().to_repr()))
+                        .map_or(false, |def| def.attribute_type == AttributeType::Id);
+                is_id && ids.contains(&&a.value[..])
+            });
+            if has_id {
+                result.push(Node::Tree(id));
+            }
+        }
+        result
+    }
+
+    fn compare(&self, op: CompareOp, left: &Value, right: &Value) -> bool {
+        let string_value = |n: &Node| n.string_value(self.document);
+        match (left, right) {
+            (&Value::NodeSet(ref l), &Value::NodeSet(ref r)) => {
+                let r: Vec<_> = r.iter().map(string_value).collect();
+                l.iter().any(|n| {
+                    let l = Value::String(string_value(n));
+                    r.iter().any(|s| compare_atomic(op, &l, &Value::String(s.clone())))
+                })
+            }
+            (&Value::NodeSet(ref l), other) => self.compare_node_set(op, l, other),
+            (other, &Value::NodeSet(ref r)) => self.compare_node_set(op.flip(), r, other),
+            _ => compare_atomic(op, left, right)
+        }
+    }
+
+    fn compare_node_set(&self, op: CompareOp, nodes: &[Node], other: &Value) -> bool {
+        match *other {
+            Value::Boolean(_) => compare_atomic(op, &Value::Boolean(!nodes.is_empty()), other),
+            Value::Number(n) => nodes.iter()
+                .any(|node| compare_numbers(op, string_to_number(&node.string_value(self.document)), n)),
+            _ => nodes.iter()
+                .any(|node| compare_atomic(op, &Value::String(node.string_value(self.document)), other)),
+        }
+    }
+}
+
+/// Compares two values none of which is a node-set.
+fn compare_atomic(op: CompareOp, left: &Value, right: &Value) -> bool {
+    let number = |v: &Value| match *v {
+        Value::Boolean(b) => if b { 1.0 } else { 0.0 },
+        Value::Number(n) => n,
+        Value::String(ref s) => string_to_number(s),
+        Value::NodeSet(_) => unreachable!(),
+    };
+    match (op, left, right) {
+        (CompareOp::Eq, &Value::Boolean(_), _) | (CompareOp::Eq, _, &Value::Boolean(_)) =>
+            left.to_boolean() == right.to_boolean(),
+        (CompareOp::Ne, &Value::Boolean(_), _) | (CompareOp::Ne, _, &Value::Boolean(_)) =>
+            left.to_boolean() != right.to_boolean(),
+        (CompareOp::Eq, &Value::String(ref l), &Value::String(ref r)) => l == r,
+        (CompareOp::Ne, &Value::String(ref l), &Value::String(ref r)) => l != r,
+        _ => compare_numbers(op, number(left), number(right))
+    }
+}
+
+fn compare_numbers(op: CompareOp, left: f64, right: f64) -> bool {
+    match op {
+        CompareOp::Eq => left == right,
+        CompareOp::Ne => left != right,
+        CompareOp::Lt => left < right,
+        CompareOp::Le => left <= right,
+        CompareOp::Gt => left > right,
+        CompareOp::Ge => left >= right,
+    }
+}
+
+/// Rounds a number as the XPath `round()` function does: halves are rounded towards positive
+/// infinity, and the sign of zero is kept.
+fn round(n: f64) -> f64 {
+    if n.is_nan() || n.is_infinite() || n == 0.0 {
+        n
+    } else if n < 0.0 && n >= -0.5 {
+        -0.0
+    } else {
+        (n + 0.5).floor()
+    }
+}
+
+/// Converts a number to a string as the XPath `string()` function does.
+pub fn number_to_string(n: f64) -> String {
+    if n.is_nan() {
+        "NaN".into()
+    } else if n.is_infinite() {
+        if n > 0.0 { "Infinity" } else { "-Infinity" }.into()
+    } else if n == 0.0 {
+        "0".into()
+    } else {
+        // `Display` of floats never uses the exponential notation
+        n.to_string()
+    }
+}
+
+/// Converts a string to a number as the XPath `number()` function does.
+pub fn string_to_number(s: &str) -> f64 {
+    let s = s.trim_matches(is_whitespace_char);
+    let digits = if s.starts_with('-') { &s[1..] } else { s };
+    let valid = !digits.is_empty() && digits != "." &&
+        digits.chars().all(|c| c.is_ascii_digit() || c == '.') &&
+        digits.chars().filter(|&c| c == '.').count() <= 1;
+    if valid { s.parse().unwrap_or(::std::f64::NAN) } else { ::std::f64::NAN }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{number_to_string, string_to_number, round};
+
+    #[test]
+    fn number_conversions() {
+        assert_eq!(number_to_string(1.0), "1");
+        assert_eq!(number_to_string(-0.0), "0");
+        assert_eq!(number_to_string(0.5), "0.5");
+        assert_eq!(number_to_string(-12.25), "-12.25");
+        assert_eq!(number_to_string(1e21), "1000000000000000000000");
+        assert_eq!(number_to_string(::std::f64::NAN), "NaN");
+        assert_eq!(number_to_string(-::std::f64::INFINITY), "-Infinity");
+
+        assert_eq!(string_to_number(" 12 "), 12.0);
+        assert_eq!(string_to_number("-.5"), -0.5);
+        assert_eq!(string_to_number("3."), 3.0);
+        assert!(string_to_number("+1").is_nan());
+        assert!(string_to_number("1e3").is_nan());
+        assert!(string_to_number(".").is_nan());
+        assert!(string_to_number("").is_nan());
+
+        assert_eq!(round(2.5), 3.0);
+        assert_eq!(round(-2.5), -2.0);
+        assert!(round(-0.3).is_sign_negative());
+        assert!(round(::std::f64::NAN).is_nan());
+    }
+}
@@ -0,0 +1,247 @@
+//! Contains an implementation of XPath 1.0 over `dom::Document` trees.
+//!
+//! An expression is compiled once with `XPath::new()` or `XPath::with_namespaces()` and can then
+//! be evaluated against any node of any document. Prefixes used in the expression are resolved
+//! through the `Namespace` given at compile time; as in XPath 1.0, unprefixed names in node tests
+//! only match nodes without a namespace.
+//!
+//! ```rust
+//! use xml::EventReader;
+//! use xml::dom::Document;
+//! use xml::namespace::Namespace;
+//! use xml::xpath::{XPath, Value};
+//!
+//! let document = Document::from_reader(EventReader::from_str(r#"
+//!     <catalog xmlns="urn:catalog">
+//!       <book year="1965"><title>Dune</title></book>
+//!       <book year="1951"><title>Foundation</title></book>
+//!     </catalog>
+//! "#)).unwrap();
+//!
+//! let mut namespaces = Namespace::empty();
+//! namespaces.put("c", "urn:catalog");
+//!
+//! let titles = XPath::with_namespaces("//c:book[@year < 1960]/c:title", &namespaces).unwrap();
+//! let nodes = titles.evaluate(&document, document.root()).unwrap().into_nodes().unwrap();
+//! assert_eq!(nodes.len(), 1);
+//! assert_eq!(nodes[0].string_value(&document), "Foundation");
+//!
+//! let count = XPath::with_namespaces("count(/c:catalog/c:book)", &namespaces).unwrap();
+//! assert_eq!(count.evaluate(&document, document.root()).unwrap(), Value::Number(2.0));
+//! ```
+//!
+//! Documents which are too large to be loaded into memory can be searched with `StreamingPath`,
+//! which supports a forward-only subset of location paths and works directly on `EventReader`.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+
+use dom::{self, Document, NodeId};
+use namespace::{Namespace, NS_XMLNS_PREFIX, NS_NO_PREFIX, NS_EMPTY_URI};
+
+mod parser;
+mod eval;
+mod streaming;
+
+pub use self::streaming::{StreamingPath, Match, Matches};
+
+/// A node of the XPath data model.
+///
+/// Besides nodes of the document tree, XPath has attribute and namespace nodes, which are
+/// identified by their element and their index in the list of attributes or namespace mappings.
+/// Adjacent text and CDATA nodes of a document form a single XPath text node, which is
+/// identified by the first of them.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Node {
+    /// A node of the document tree.
+    Tree(NodeId),
+
+    /// An attribute of an element, as an index in `Element::attributes`.
+    Attribute(NodeId, usize),
+
+    /// A namespace mapping in scope of an element, as an index among mappings returned by
+    /// `namespaces()`.
+    Namespace(NodeId, usize),
+}
+
+impl Node {
+    /// Returns the string-value of this node as defined by XPath.
+    pub fn string_value(self, document: &Document) -> String {
+        match self {
+            Node::Tree(id) => match *document.node(id) {
+                dom::Node::Document | dom::Node::Element(_) => {
+                    let mut result = String::new();
+                    for id in document.descendants(id) {
+                        if let Some(text) = text_content(document.node(id)) {
+                            result.push_str(text);
+                        }
+                    }
+                    result
+                }
+                dom::Node::Text(_) | dom::Node::CData(_) => {
+                    let mut result = String::new();
+                    let mut next = Some(id);
+                    while let Some(text) = next.and_then(|id| text_content(document.node(id))) {
+                        result.push_str(text);
+                        next = next.and_then(|id| document.next_sibling(id));
+                    }
+                    result
+                }
+                dom::Node::Comment(ref data) => data.clone(),
+                dom::Node::ProcessingInstruction { ref data, .. } => data.clone().unwrap_or_default(),
+            },
+            Node::Attribute(id, i) => document.element(id).unwrap().attributes[i].value.clone(),
+            Node::Namespace(id, i) => namespaces(document, id).nth(i).unwrap().1.into(),
+        }
+    }
+}
+
+fn text_content(node: &dom::Node) -> Option<&str> {
+    match *node {
+        dom::Node::Text(ref data) | dom::Node::CData(ref data) => Some(data),
+        _ => None
+    }
+}
+
+/// Returns namespace mappings which are represented by namespace nodes of an element.
+fn namespaces(document: &Document, id: NodeId) -> impl Iterator<Item=(&str, &str)> {
+    document.element(id).into_iter()
+        .flat_map(|e| e.namespace.0.iter())
+        .filter(|&(prefix, uri)| prefix != NS_XMLNS_PREFIX && !(prefix == NS_NO_PREFIX && uri == NS_EMPTY_URI))
+        .map(|(prefix, uri)| (&prefix[..], &uri[..]))
+}
+
+/// A result of evaluation of an XPath expression.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Value {
+    /// A set of nodes in document order.
+    NodeSet(Vec<Node>),
+
+    /// A boolean value.
+    Boolean(bool),
+
+    /// A floating-point number.
+    Number(f64),
+
+    /// A string.
+    String(String),
+}
+
+impl Value {
+    /// Returns the nodes if this value is a node-set.
+    #[inline]
+    pub fn into_nodes(self) -> Option<Vec<Node>> {
+        match self {
+            Value::NodeSet(nodes) => Some(nodes),
+            _ => None
+        }
+    }
+
+    /// Converts this value to a string as the XPath `string()` function does.
+    pub fn to_string_value(&self, document: &Document) -> String {
+        match *self {
+            Value::NodeSet(ref nodes) => nodes.first().map(|n| n.string_value(document)).unwrap_or_default(),
+            Value::Boolean(b) => b.to_string(),
+            Value::Number(n) => eval::number_to_string(n),
+            Value::String(ref s) => s.clone(),
+        }
+    }
+
+    /// Converts this value to a number as the XPath `number()` function does.
+    pub fn to_number(&self, document: &Document) -> f64 {
+        match *self {
+            Value::Boolean(b) => if b { 1.0 } else { 0.0 },
+            Value::Number(n) => n,
+            _ => eval::string_to_number(&self.to_string_value(document)),
+        }
+    }
+
+    /// Converts this value to a boolean as the XPath `boolean()` function does.
+    pub fn to_boolean(&self) -> bool {
+        match *self {
+            Value::NodeSet(ref nodes) => !nodes.is_empty(),
+            Value::Boolean(b) => b,
+            Value::Number(n) => n != 0.0 && !n.is_nan(),
+            Value::String(ref s) => !s.is_empty(),
+        }
+    }
+}
+
+/// An error of compilation or evaluation of an XPath expression.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Error {
+    /// The expression is not a valid XPath expression.
+    Syntax {
+        /// Offset of the offending token in the expression, in characters.
+        pos: usize,
+
+        /// Description of the error.
+        msg: Cow<'static, str>,
+    },
+
+    /// The expression cannot be evaluated, for example, because a variable is not defined
+    /// or a value which is not a node-set is used as one.
+    Evaluation(Cow<'static, str>),
+
+    /// The expression is valid but cannot be matched against a stream of events by
+    /// `StreamingPath`.
+    Unsupported(Cow<'static, str>),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Syntax { pos, ref msg } => write!(f, "{}: {}", pos, msg),
+            Error::Evaluation(ref msg) | Error::Unsupported(ref msg) => f.write_str(msg),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::Syntax { ref msg, .. } | Error::Evaluation(ref msg) | Error::Unsupported(ref msg) => msg,
+        }
+    }
+}
+
+/// A result type of XPath operations.
+pub type Result<T> = ::std::result::Result<T, Error>;
+
+/// A compiled XPath expression.
+#[derive(Clone, Debug)]
+pub struct XPath {
+    expr: parser::Expr,
+}
+
+impl XPath {
+    /// Compiles an expression which does not use namespace prefixes.
+    #[inline]
+    pub fn new(expr: &str) -> Result<XPath> {
+        XPath::with_namespaces(expr, &Namespace::empty())
+    }
+
+    /// Compiles an expression, resolving namespace prefixes used in it with the given mappings.
+    ///
+    /// The `xml` prefix is always bound. The default namespace mapping is not used.
+    pub fn with_namespaces(expr: &str, namespaces: &Namespace) -> Result<XPath> {
+        parser::parse(expr, namespaces).map(|expr| XPath { expr })
+    }
+
+    /// Evaluates the expression with the given node as the context node.
+    #[inline]
+    pub fn evaluate(&self, document: &Document, node: NodeId) -> Result<Value> {
+        self.evaluate_with_variables(document, node, &HashMap::new())
+    }
+
+    /// Evaluates the expression with the given node as the context node and the given values
+    /// of variables.
+    ///
+    /// Variables are looked up by their names as written in the expression, including prefixes.
+    pub fn evaluate_with_variables(&self, document: &Document, node: NodeId,
+                                   variables: &HashMap<String, Value>) -> Result<Value> {
+        eval::Evaluator::new(document, node, variables).evaluate(&self.expr, Node::Tree(node))
+    }
+}
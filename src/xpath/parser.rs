@@ -0,0 +1,727 @@
+//! Contains a lexer and a parser of XPath 1.0 expressions.
+
+use std::borrow::Cow;
+
+use common::{is_name_start_char, is_name_char, is_whitespace_char};
+use namespace::{Namespace, NS_XML_PREFIX, NS_XML_URI};
+
+use super::{Error, Result};
+
+/// A parsed expression with resolved namespace prefixes.
+#[derive(Clone, Debug)]
+pub enum Expr {
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Compare(CompareOp, Box<Expr>, Box<Expr>),
+    Arithmetic(ArithmeticOp, Box<Expr>, Box<Expr>),
+    Negate(Box<Expr>),
+    Union(Box<Expr>, Box<Expr>),
+    Literal(String),
+    Number(f64),
+    Variable(String),
+    Function(Function, Vec<Expr>),
+    /// A primary expression with predicates.
+    Filter(Box<Expr>, Vec<Expr>),
+    Path(PathStart, Vec<Step>),
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum CompareOp { Eq, Ne, Lt, Le, Gt, Ge }
+
+impl CompareOp {
+    /// Returns the operator with swapped operands, so that `a op b` is `b op.flip() a`.
+    pub fn flip(self) -> CompareOp {
+        match self {
+            CompareOp::Lt => CompareOp::Gt,
+            CompareOp::Le => CompareOp::Ge,
+            CompareOp::Gt => CompareOp::Lt,
+            CompareOp::Ge => CompareOp::Le,
+            op => op
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ArithmeticOp { Add, Sub, Mul, Div, Mod }
+
+#[derive(Clone, Debug)]
+pub enum PathStart {
+    /// The root node of the tree containing the context node.
+    Root,
+    /// The context node.
+    Context,
+    /// A node-set produced by a filter expression.
+    Expr(Box<Expr>),
+}
+
+#[derive(Clone, Debug)]
+pub struct Step {
+    pub axis: Axis,
+    pub test: NodeTest,
+    pub predicates: Vec<Expr>,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Axis {
+    Ancestor,
+    AncestorOrSelf,
+    Attribute,
+    Child,
+    Descendant,
+    DescendantOrSelf,
+    Following,
+    FollowingSibling,
+    Namespace,
+    Parent,
+    Preceding,
+    PrecedingSibling,
+    SelfAxis,
+}
+
+static AXES: &[(&str, Axis)] = &[
+    ("ancestor", Axis::Ancestor),
+    ("ancestor-or-self", Axis::AncestorOrSelf),
+    ("attribute", Axis::Attribute),
+    ("child", Axis::Child),
+    ("descendant", Axis::Descendant),
+    ("descendant-or-self", Axis::DescendantOrSelf),
+    ("following", Axis::Following),
+    ("following-sibling", Axis::FollowingSibling),
+    ("namespace", Axis::Namespace),
+    ("parent", Axis::Parent),
+    ("preceding", Axis::Preceding),
+    ("preceding-sibling", Axis::PrecedingSibling),
+    ("self", Axis::SelfAxis),
+];
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum NodeTest {
+    /// `*`
+    AnyName,
+    /// `prefix:*`, with the resolved namespace URI
+    Namespace(String),
+    /// A qualified name, with the resolved namespace URI
+    Name(Option<String>, String),
+    /// `node()`
+    Node,
+    /// `text()`
+    Text,
+    /// `comment()`
+    Comment,
+    /// `processing-instruction()`, optionally with a target
+    ProcessingInstruction(Option<String>),
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Function {
+    Last, Position, Count, Id, LocalName, NamespaceUri, Name,
+    String, Concat, StartsWith, Contains, SubstringBefore, SubstringAfter, Substring,
+    StringLength, NormalizeSpace, Translate,
+    Boolean, Not, True, False, Lang,
+    Number, Sum, Floor, Ceiling, Round,
+}
+
+/// Names of the core functions with their minimum and maximum numbers of arguments.
+static FUNCTIONS: &[(&str, Function, usize, usize)] = &[
+    ("last", Function::Last, 0, 0),
+    ("position", Function::Position, 0, 0),
+    ("count", Function::Count, 1, 1),
+    ("id", Function::Id, 1, 1),
+    ("local-name", Function::LocalName, 0, 1),
+    ("namespace-uri", Function::NamespaceUri, 0, 1),
+    ("name", Function::Name, 0, 1),
+    ("string", Function::String, 0, 1),
+    ("concat", Function::Concat, 2, usize::MAX),
+    ("starts-with", Function::StartsWith, 2, 2),
+    ("contains", Function::Contains, 2, 2),
+    ("substring-before", Function::SubstringBefore, 2, 2),
+    ("substring-after", Function::SubstringAfter, 2, 2),
+    ("substring", Function::Substring, 2, 3),
+    ("string-length", Function::StringLength, 0, 1),
+    ("normalize-space", Function::NormalizeSpace, 0, 1),
+    ("translate", Function::Translate, 3, 3),
+    ("boolean", Function::Boolean, 1, 1),
+    ("not", Function::Not, 1, 1),
+    ("true", Function::True, 0, 0),
+    ("false", Function::False, 0, 0),
+    ("lang", Function::Lang, 1, 1),
+    ("number", Function::Number, 0, 1),
+    ("sum", Function::Sum, 1, 1),
+    ("floor", Function::Floor, 1, 1),
+    ("ceiling", Function::Ceiling, 1, 1),
+    ("round", Function::Round, 1, 1),
+];
+
+#[derive(Clone, PartialEq, Debug)]
+enum Token {
+    LeftParen,
+    RightParen,
+    LeftBracket,
+    RightBracket,
+    Dot,
+    DotDot,
+    At,
+    Comma,
+    ColonColon,
+    Slash,
+    DoubleSlash,
+    Pipe,
+    Plus,
+    Minus,
+    Equals,
+    NotEquals,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Multiply,
+    And,
+    Or,
+    Mod,
+    Div,
+    Literal(String),
+    Number(f64),
+    Variable(String),
+    FunctionName(String),
+    NodeType(String),
+    AxisName(String),
+    /// A name test; `None` local name stands for `*`
+    NameTest(Option<String>, Option<String>),
+}
+
+impl Token {
+    /// Returns `true` if a `*` or a name after this token is a name test rather than
+    /// an operator.
+    fn precedes_operand(&self) -> bool {
+        match *self {
+            Token::At | Token::ColonColon | Token::LeftParen | Token::LeftBracket | Token::Comma |
+            Token::Slash | Token::DoubleSlash | Token::Pipe | Token::Plus | Token::Minus |
+            Token::Equals | Token::NotEquals | Token::Less | Token::LessOrEqual | Token::Greater |
+            Token::GreaterOrEqual | Token::Multiply | Token::And | Token::Or | Token::Mod |
+            Token::Div => true,
+            _ => false
+        }
+    }
+}
+
+fn syntax_error<T, S: Into<Cow<'static, str>>>(pos: usize, msg: S) -> Result<T> {
+    Err(Error::Syntax { pos, msg: msg.into() })
+}
+
+fn is_ncname_start_char(c: char) -> bool { c != ':' && is_name_start_char(c) }
+fn is_ncname_char(c: char) -> bool { c != ':' && is_name_char(c) }
+
+struct Lexer {
+    chars: Vec<char>,
+    pos: usize,
+    tokens: Vec<(Token, usize)>,
+}
+
+impl Lexer {
+    fn peek(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).cloned()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek(0).map_or(false, is_whitespace_char) {
+            self.pos += 1;
+        }
+    }
+
+    fn ncname(&mut self) -> String {
+        let start = self.pos;
+        while self.peek(0).map_or(false, is_ncname_char) {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().cloned().collect()
+    }
+
+    /// Returns the next non-whitespace character and the one after it.
+    fn lookahead(&self) -> (Option<char>, Option<char>) {
+        let mut i = self.pos;
+        while self.chars.get(i).map_or(false, |&c| is_whitespace_char(c)) {
+            i += 1;
+        }
+        (self.chars.get(i).cloned(), self.chars.get(i + 1).cloned())
+    }
+
+    fn tokenize(mut self) -> Result<Vec<(Token, usize)>> {
+        loop {
+            self.skip_whitespace();
+            let start = self.pos;
+            let c = match self.peek(0) {
+                Some(c) => c,
+                None => return Ok(self.tokens)
+            };
+            let operand_expected = self.tokens.last().map_or(true, |&(ref t, _)| t.precedes_operand());
+            let (token, len) = match (c, self.peek(1)) {
+                ('(', _) => (Token::LeftParen, 1),
+                (')', _) => (Token::RightParen, 1),
+                ('[', _) => (Token::LeftBracket, 1),
+                (']', _) => (Token::RightBracket, 1),
+                ('.', Some('.')) => (Token::DotDot, 2),
+                ('.', Some(d)) if d.is_ascii_digit() => (self.number(), 0),
+                ('.', _) => (Token::Dot, 1),
+                ('@', _) => (Token::At, 1),
+                (',', _) => (Token::Comma, 1),
+                (':', Some(':')) => (Token::ColonColon, 2),
+                ('/', Some('/')) => (Token::DoubleSlash, 2),
+                ('/', _) => (Token::Slash, 1),
+                ('|', _) => (Token::Pipe, 1),
+                ('+', _) => (Token::Plus, 1),
+                ('-', _) => (Token::Minus, 1),
+                ('=', _) => (Token::Equals, 1),
+                ('!', Some('=')) => (Token::NotEquals, 2),
+                ('<', Some('=')) => (Token::LessOrEqual, 2),
+                ('<', _) => (Token::Less, 1),
+                ('>', Some('=')) => (Token::GreaterOrEqual, 2),
+                ('>', _) => (Token::Greater, 1),
+                ('*', _) if !operand_expected => (Token::Multiply, 1),
+                ('*', _) => (Token::NameTest(None, None), 1),
+                ('"', _) | ('\'', _) => match self.chars[start + 1..].iter().position(|&q| q == c) {
+                    Some(end) => {
+                        let literal = self.chars[start + 1..start + 1 + end].iter().cloned().collect();
+                        (Token::Literal(literal), end + 2)
+                    }
+                    None => return syntax_error(start, "Unterminated string literal")
+                },
+                (d, _) if d.is_ascii_digit() => (self.number(), 0),
+                ('$', _) => {
+                    self.pos += 1;
+                    match self.qname(start)? {
+                        (prefix, Some(local)) => (Token::Variable(match prefix {
+                            Some(prefix) => format!("{}:{}", prefix, local),
+                            None => local
+                        }), 0),
+                        _ => return syntax_error(start, "Invalid variable name")
+                    }
+                }
+                (c, _) if is_ncname_start_char(c) => (self.name(start, operand_expected)?, 0),
+                (c, _) => return syntax_error(start, format!("Unexpected character '{}'", c))
+            };
+            self.pos += len;
+            self.tokens.push((token, start));
+        }
+    }
+
+    fn number(&mut self) -> Token {
+        let start = self.pos;
+        while self.peek(0).map_or(false, |c| c.is_ascii_digit() || c == '.') {
+            if self.peek(0) == Some('.') && self.chars[start..self.pos].contains(&'.') {
+                break;
+            }
+            self.pos += 1;
+        }
+        let s: String = self.chars[start..self.pos].iter().cloned().collect();
+        Token::Number(s.parse().unwrap())
+    }
+
+    /// Reads a qualified name or a `prefix:*` wildcard.
+    fn qname(&mut self, start: usize) -> Result<(Option<String>, Option<String>)> {
+        if !self.peek(0).map_or(false, is_ncname_start_char) {
+            return syntax_error(start, "Expected a name");
+        }
+        let first = self.ncname();
+        if self.peek(0) == Some(':') && self.peek(1) != Some(':') {
+            match self.peek(1) {
+                Some('*') => {
+                    self.pos += 2;
+                    Ok((Some(first), None))
+                }
+                Some(c) if is_ncname_start_char(c) => {
+                    self.pos += 1;
+                    let local = self.ncname();
+                    Ok((Some(first), Some(local)))
+                }
+                _ => syntax_error(start, "Invalid qualified name")
+            }
+        } else {
+            Ok((None, Some(first)))
+        }
+    }
+
+    /// Reads a name and disambiguates it according to the lexical rules of XPath.
+    fn name(&mut self, start: usize, operand_expected: bool) -> Result<Token> {
+        if !operand_expected {
+            let name = self.ncname();
+            return match &name[..] {
+                "and" => Ok(Token::And),
+                "or" => Ok(Token::Or),
+                "mod" => Ok(Token::Mod),
+                "div" => Ok(Token::Div),
+                _ => syntax_error(start, format!("Expected an operator, found '{}'", name))
+            };
+        }
+        let (prefix, local) = self.qname(start)?;
+        let local = match local {
+            Some(local) => local,
+            None => return Ok(Token::NameTest(prefix, None))
+        };
+        match (prefix, self.lookahead()) {
+            (None, (Some(':'), Some(':'))) => Ok(Token::AxisName(local)),
+            (None, (Some('('), _)) => match &local[..] {
+                "comment" | "text" | "processing-instruction" | "node" => Ok(Token::NodeType(local)),
+                _ => Ok(Token::FunctionName(local))
+            },
+            (Some(prefix), (Some('('), _)) => Ok(Token::FunctionName(format!("{}:{}", prefix, local))),
+            (prefix, _) => Ok(Token::NameTest(prefix, Some(local)))
+        }
+    }
+}
+
+struct Parser<'a> {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    end: usize,
+    namespaces: &'a Namespace,
+}
+
+/// Parses an expression.
+pub fn parse(expr: &str, namespaces: &Namespace) -> Result<Expr> {
+    let lexer = Lexer { chars: expr.chars().collect(), pos: 0, tokens: Vec::new() };
+    let end = lexer.chars.len();
+    let tokens = lexer.tokenize()?;
+    let mut parser = Parser { tokens, pos: 0, end, namespaces };
+    let expr = parser.expr()?;
+    match parser.peek() {
+        None => Ok(expr),
+        Some(_) => syntax_error(parser.position(), "Unexpected token")
+    }
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|&(ref t, _)| t)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens.get(self.pos).map_or(self.end, |&(_, pos)| pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).map(|&(ref t, _)| t.clone());
+        self.pos += 1;
+        token
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &Token, what: &'static str) -> Result<()> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            syntax_error(self.position(), format!("Expected {}", what))
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr> {
+        let mut left = self.and_expr()?;
+        while self.eat(&Token::Or) {
+            left = Expr::Or(Box::new(left), Box::new(self.and_expr()?));
+        }
+        Ok(left)
+    }
+
+    fn and_expr(&mut self) -> Result<Expr> {
+        let mut left = self.equality_expr()?;
+        while self.eat(&Token::And) {
+            left = Expr::And(Box::new(left), Box::new(self.equality_expr()?));
+        }
+        Ok(left)
+    }
+
+    fn equality_expr(&mut self) -> Result<Expr> {
+        let mut left = self.relational_expr()?;
+        loop {
+            let op = match self.peek() {
+                Some(&Token::Equals) => CompareOp::Eq,
+                Some(&Token::NotEquals) => CompareOp::Ne,
+                _ => return Ok(left)
+            };
+            self.pos += 1;
+            left = Expr::Compare(op, Box::new(left), Box::new(self.relational_expr()?));
+        }
+    }
+
+    fn relational_expr(&mut self) -> Result<Expr> {
+        let mut left = self.additive_expr()?;
+        loop {
+            let op = match self.peek() {
+                Some(&Token::Less) => CompareOp::Lt,
+                Some(&Token::LessOrEqual) => CompareOp::Le,
+                Some(&Token::Greater) => CompareOp::Gt,
+                Some(&Token::GreaterOrEqual) => CompareOp::Ge,
+                _ => return Ok(left)
+            };
+            self.pos += 1;
+            left = Expr::Compare(op, Box::new(left), Box::new(self.additive_expr()?));
+        }
+    }
+
+    fn additive_expr(&mut self) -> Result<Expr> {
+        let mut left = self.multiplicative_expr()?;
+        loop {
+            let op = match self.peek() {
+                Some(&Token::Plus) => ArithmeticOp::Add,
+                Some(&Token::Minus) => ArithmeticOp::Sub,
+                _ => return Ok(left)
+            };
+            self.pos += 1;
+            left = Expr::Arithmetic(op, Box::new(left), Box::new(self.multiplicative_expr()?));
+        }
+    }
+
+    fn multiplicative_expr(&mut self) -> Result<Expr> {
+        let mut left = self.unary_expr()?;
+        loop {
+            let op = match self.peek() {
+                Some(&Token::Multiply) => ArithmeticOp::Mul,
+                Some(&Token::Div) => ArithmeticOp::Div,
+                Some(&Token::Mod) => ArithmeticOp::Mod,
+                _ => return Ok(left)
+            };
+            self.pos += 1;
+            left = Expr::Arithmetic(op, Box::new(left), Box::new(self.unary_expr()?));
+        }
+    }
+
+    fn unary_expr(&mut self) -> Result<Expr> {
+        if self.eat(&Token::Minus) {
+            Ok(Expr::Negate(Box::new(self.unary_expr()?)))
+        } else {
+            self.union_expr()
+        }
+    }
+
+    fn union_expr(&mut self) -> Result<Expr> {
+        let mut left = self.path_expr()?;
+        while self.eat(&Token::Pipe) {
+            left = Expr::Union(Box::new(left), Box::new(self.path_expr()?));
+        }
+        Ok(left)
+    }
+
+    fn path_expr(&mut self) -> Result<Expr> {
+        match self.peek() {
+            Some(&Token::Slash) => {
+                self.pos += 1;
+                let steps = if self.at_step() { self.relative_path()? } else { Vec::new() };
+                Ok(Expr::Path(PathStart::Root, steps))
+            }
+            Some(&Token::DoubleSlash) => {
+                self.pos += 1;
+                let mut steps = vec![descendant_or_self()];
+                steps.extend(self.relative_path()?);
+                Ok(Expr::Path(PathStart::Root, steps))
+            }
+            _ if self.at_step() => Ok(Expr::Path(PathStart::Context, self.relative_path()?)),
+            _ => {
+                let primary = self.primary_expr()?;
+                let mut predicates = Vec::new();
+                while self.peek() == Some(&Token::LeftBracket) {
+                    predicates.push(self.predicate()?);
+                }
+                let filter = if predicates.is_empty() { primary } else { Expr::Filter(Box::new(primary), predicates) };
+                let mut steps = Vec::new();
+                match self.peek() {
+                    Some(&Token::Slash) => {}
+                    Some(&Token::DoubleSlash) => steps.push(descendant_or_self()),
+                    _ => return Ok(filter)
+                }
+                self.pos += 1;
+                steps.extend(self.relative_path()?);
+                Ok(Expr::Path(PathStart::Expr(Box::new(filter)), steps))
+            }
+        }
+    }
+
+    fn at_step(&self) -> bool {
+        match self.peek() {
+            Some(&Token::Dot) | Some(&Token::DotDot) | Some(&Token::At) | Some(&Token::AxisName(_)) |
+            Some(&Token::NodeType(_)) | Some(&Token::NameTest(..)) => true,
+            _ => false
+        }
+    }
+
+    fn relative_path(&mut self) -> Result<Vec<Step>> {
+        let mut steps = vec![self.step()?];
+        loop {
+            match self.peek() {
+                Some(&Token::Slash) => {}
+                Some(&Token::DoubleSlash) => steps.push(descendant_or_self()),
+                _ => return Ok(steps)
+            }
+            self.pos += 1;
+            steps.push(self.step()?);
+        }
+    }
+
+    fn step(&mut self) -> Result<Step> {
+        let start = self.position();
+        let axis = match self.next() {
+            Some(Token::Dot) => return Ok(Step { axis: Axis::SelfAxis, test: NodeTest::Node, predicates: Vec::new() }),
+            Some(Token::DotDot) => return Ok(Step { axis: Axis::Parent, test: NodeTest::Node, predicates: Vec::new() }),
+            Some(Token::At) => Axis::Attribute,
+            Some(Token::AxisName(name)) => {
+                self.pos += 1;  // `::`
+                match AXES.iter().find(|&&(n, _)| n == name) {
+                    Some(&(_, axis)) => axis,
+                    None => return syntax_error(start, format!("Unknown axis: {}", name))
+                }
+            }
+            _ => {
+                self.pos -= 1;
+                Axis::Child
+            }
+        };
+        let test = self.node_test()?;
+        let mut predicates = Vec::new();
+        while self.peek() == Some(&Token::LeftBracket) {
+            predicates.push(self.predicate()?);
+        }
+        Ok(Step { axis, test, predicates })
+    }
+
+    fn node_test(&mut self) -> Result<NodeTest> {
+        let start = self.position();
+        match self.next() {
+            Some(Token::NameTest(None, None)) => Ok(NodeTest::AnyName),
+            Some(Token::NameTest(Some(prefix), None)) => Ok(NodeTest::Namespace(self.resolve(&prefix, start)?)),
+            Some(Token::NameTest(prefix, Some(local))) => {
+                let namespace = match prefix {
+                    Some(prefix) => Some(self.resolve(&prefix, start)?),
+                    None => None
+                };
+                Ok(NodeTest::Name(namespace, local))
+            }
+            Some(Token::NodeType(name)) => {
+                self.expect(&Token::LeftParen, "'('")?;
+                let test = match &name[..] {
+                    "comment" => NodeTest::Comment,
+                    "text" => NodeTest::Text,
+                    "node" => NodeTest::Node,
+                    _ => match self.peek() {
+                        Some(&Token::Literal(ref target)) => {
+                            let target = target.clone();
+                            self.pos += 1;
+                            NodeTest::ProcessingInstruction(Some(target))
+                        }
+                        _ => NodeTest::ProcessingInstruction(None)
+                    }
+                };
+                self.expect(&Token::RightParen, "')'")?;
+                Ok(test)
+            }
+            _ => syntax_error(start, "Expected a node test")
+        }
+    }
+
+    fn resolve(&self, prefix: &str, pos: usize) -> Result<String> {
+        match self.namespaces.get(prefix) {
+            Some(uri) => Ok(uri.into()),
+            None if prefix == NS_XML_PREFIX => Ok(NS_XML_URI.into()),
+            None => syntax_error(pos, format!("Namespace prefix {} is not bound", prefix))
+        }
+    }
+
+    fn predicate(&mut self) -> Result<Expr> {
+        self.expect(&Token::LeftBracket, "'['")?;
+        let expr = self.expr()?;
+        self.expect(&Token::RightBracket, "']'")?;
+        Ok(expr)
+    }
+
+    fn primary_expr(&mut self) -> Result<Expr> {
+        let start = self.position();
+        match self.next() {
+            Some(Token::Variable(name)) => Ok(Expr::Variable(name)),
+            Some(Token::LeftParen) => {
+                let expr = self.expr()?;
+                self.expect(&Token::RightParen, "')'")?;
+                Ok(expr)
+            }
+            Some(Token::Literal(s)) => Ok(Expr::Literal(s)),
+            Some(Token::Number(n)) => Ok(Expr::Number(n)),
+            Some(Token::FunctionName(name)) => {
+                let &(_, function, min, max) = match FUNCTIONS.iter().find(|&&(n, ..)| n == name) {
+                    Some(f) => f,
+                    None => return syntax_error(start, format!("Unknown function: {}", name))
+                };
+                self.expect(&Token::LeftParen, "'('")?;
+                let mut args = Vec::new();
+                if !self.eat(&Token::RightParen) {
+                    loop {
+                        args.push(self.expr()?);
+                        if self.eat(&Token::RightParen) {
+                            break;
+                        }
+                        self.expect(&Token::Comma, "',' or ')'")?;
+                    }
+                }
+                if args.len() < min || args.len() > max {
+                    return syntax_error(start, format!("Wrong number of arguments of function {}", name));
+                }
+                Ok(Expr::Function(function, args))
+            }
+            _ => syntax_error(start, "Expected an expression")
+        }
+    }
+}
+
+/// Returns the step which `//` stands for.
+fn descendant_or_self() -> Step {
+    Step { axis: Axis::DescendantOrSelf, test: NodeTest::Node, predicates: Vec::new() }
+}
+
+#[cfg(test)]
+mod tests {
+    use namespace::Namespace;
+
+    use super::parse;
+
+    fn error(expr: &str) -> String {
+        match parse(expr, &Namespace::empty()) {
+            Ok(e) => panic!("Expression should be invalid: {} => {:?}", expr, e),
+            Err(e) => e.to_string()
+        }
+    }
+
+    #[test]
+    fn disambiguation() {
+        let ok = |expr: &str| parse(expr, &Namespace::empty()).unwrap_or_else(|e| panic!("{}: {}", expr, e));
+        // names of operators, functions and node types are only special in their positions
+        ok("and and and");
+        ok("div div div");
+        ok("* * *");
+        ok("mod/mod[mod mod 2]");
+        ok("child::text | text()");
+        ok("node ( ) [last()]");
+        ok("@*[. != 'x'] or -$var");
+        ok("count(//a) div 2.5 + .5 - 1.");
+        ok("xml:lang | @xml:*");
+        ok("processing-instruction('pi')");
+    }
+
+    #[test]
+    fn syntax_errors() {
+        assert_eq!(error(""), "0: Expected an expression");
+        assert_eq!(error("a b"), "2: Expected an operator, found 'b'");
+        assert_eq!(error("a["), "2: Expected an expression");
+        assert_eq!(error("(1"), "2: Expected ')'");
+        assert_eq!(error("'x"), "0: Unterminated string literal");
+        assert_eq!(error("p:a"), "0: Namespace prefix p is not bound");
+        assert_eq!(error("foo()"), "0: Unknown function: foo");
+        assert_eq!(error("concat('a')"), "0: Wrong number of arguments of function concat");
+        assert_eq!(error("sideways::a"), "0: Unknown axis: sideways");
+        assert_eq!(error("a/"), "2: Expected a node test");
+        assert_eq!(error("1 # 2"), "2: Unexpected character '#'");
+        assert_eq!(error("1 2"), "2: Unexpected token");
+    }
+}
@@ -0,0 +1,350 @@
+//! Contains a matcher of a forward-only subset of XPath which works directly on reader events.
+
+use std::collections::{HashMap, VecDeque};
+use std::io::Read;
+
+use attribute::OwnedAttribute;
+use name::OwnedName;
+use namespace::Namespace;
+use reader::{self, EventReader, XmlEvent};
+
+use super::parser::{self, Expr, PathStart, Step, Axis, NodeTest, CompareOp};
+use super::{Error, Result};
+
+/// A path expression compiled for matching against a stream of events.
+///
+/// Only absolute location paths (relative paths are evaluated from the document node) made of
+/// the following parts are supported:
+///
+/// * child (`a`, `child::a`) and descendant (`//a`) steps with name tests (`a`, `p:a`, `p:*`,
+///   `*`);
+/// * predicates on these steps which test presence (`[@id]`) or values (`[@id = 'x']`,
+///   `[@id != 'x']`) of attributes, and positional predicates (`[2]`) which count siblings
+///   matching the step;
+/// * a final attribute step (`@id`, `@*`), optionally after `//`.
+///
+/// ```rust
+/// use xml::EventReader;
+/// use xml::xpath::{StreamingPath, Match};
+///
+/// let path = StreamingPath::new("/catalog/book[@lang = 'en'][2]/@id").unwrap();
+/// let reader = EventReader::from_str(r#"<catalog>
+///   <book id="1" lang="en"/><book id="2" lang="de"/><book id="3" lang="en"/>
+/// </catalog>"#);
+/// let ids: Vec<_> = path.matches(reader)
+///     .map(|m| match m.unwrap() {
+///         Match::Attribute(attribute) => attribute.value,
+///         Match::Element(_) => unreachable!()
+///     })
+///     .collect();
+/// assert_eq!(ids, ["3"]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct StreamingPath {
+    steps: Vec<ElementStep>,
+    attribute: Option<AttributeStep>,
+}
+
+#[derive(Clone, Debug)]
+struct ElementStep {
+    descendant: bool,
+    test: NodeTest,
+    predicates: Vec<Predicate>,
+}
+
+#[derive(Clone, Debug)]
+struct AttributeStep {
+    descendant: bool,
+    test: NodeTest,
+}
+
+#[derive(Clone, Debug)]
+enum Predicate {
+    Position(usize),
+    HasAttribute(NodeTest),
+    AttributeValue(NodeTest, CompareOp, String),
+}
+
+fn unsupported<T>(what: &'static str) -> Result<T> {
+    Err(Error::Unsupported(what.into()))
+}
+
+impl StreamingPath {
+    /// Compiles a path which does not use namespace prefixes.
+    #[inline]
+    pub fn new(expr: &str) -> Result<StreamingPath> {
+        StreamingPath::with_namespaces(expr, &Namespace::empty())
+    }
+
+    /// Compiles a path, resolving namespace prefixes used in it with the given mappings.
+    ///
+    /// Paths outside of the supported subset result in an `Error::Unsupported` error.
+    pub fn with_namespaces(expr: &str, namespaces: &Namespace) -> Result<StreamingPath> {
+        let steps = match parser::parse(expr, namespaces)? {
+            Expr::Path(PathStart::Root, ref steps) | Expr::Path(PathStart::Context, ref steps)
+                if !steps.is_empty() => steps.clone(),
+            _ => return unsupported("Only location paths can be matched in a stream")
+        };
+
+        let mut path = StreamingPath { steps: Vec::new(), attribute: None };
+        let mut descendant = false;
+        let count = steps.len();
+        for (i, step) in steps.into_iter().enumerate() {
+            let Step { axis, test, predicates } = step;
+            match (axis, &test) {
+                (Axis::DescendantOrSelf, &NodeTest::Node) if predicates.is_empty() => {
+                    descendant = true;
+                    continue;
+                }
+                (Axis::Child, &NodeTest::AnyName) | (Axis::Child, &NodeTest::Name(..)) |
+                (Axis::Child, &NodeTest::Namespace(_)) => {
+                    let predicates = predicates.into_iter().map(predicate).collect::<Result<_>>()?;
+                    path.steps.push(ElementStep { descendant, test, predicates });
+                }
+                (Axis::Attribute, _) if i + 1 == count && predicates.is_empty() && is_name_test(&test) =>
+                    path.attribute = Some(AttributeStep { descendant, test }),
+                (Axis::Attribute, _) => return unsupported("Only the last step can select attributes"),
+                _ => return unsupported("Only child and descendant steps with name tests are supported")
+            }
+            descendant = false;
+        }
+        match path.attribute {
+            Some(AttributeStep { descendant: false, .. }) if path.steps.is_empty() =>
+                unsupported("The document node has no attributes"),
+            _ => Ok(path)
+        }
+    }
+
+    /// Wraps a reader into an iterator over matches of this path.
+    ///
+    /// Elements are yielded when their end is read, so an element which is nested into another
+    /// matching element is yielded before it. Attributes are yielded as soon as their element
+    /// starts.
+    #[inline]
+    pub fn matches<R: Read>(&self, reader: EventReader<R>) -> Matches<'_, R> {
+        let root = Frame {
+            states: if self.steps.is_empty() { Vec::new() } else { vec![0] },
+            attribute_scope: self.steps.is_empty(),
+            counters: HashMap::new(),
+        };
+        Matches {
+            path: self,
+            reader,
+            frames: vec![root],
+            captures: Vec::new(),
+            pending: VecDeque::new(),
+            finished: false,
+        }
+    }
+}
+
+fn is_name_test(test: &NodeTest) -> bool {
+    match *test {
+        NodeTest::AnyName | NodeTest::Name(..) | NodeTest::Namespace(_) => true,
+        _ => false
+    }
+}
+
+fn predicate(expr: Expr) -> Result<Predicate> {
+    fn attribute_test(expr: &Expr) -> Option<NodeTest> {
+        match *expr {
+            Expr::Path(PathStart::Context, ref steps) if steps.len() == 1 => {
+                let step = &steps[0];
+                if step.axis == Axis::Attribute && step.predicates.is_empty() && is_name_test(&step.test) {
+                    Some(step.test.clone())
+                } else {
+                    None
+                }
+            }
+            _ => None
+        }
+    }
+
+    match expr {
+        Expr::Number(n) if n >= 1.0 && n.fract() == 0.0 => return Ok(Predicate::Position(n as usize)),
+        Expr::Compare(op, ref l, ref r) if op == CompareOp::Eq || op == CompareOp::Ne => {
+            match (attribute_test(l), &**l, attribute_test(r), &**r) {
+                (Some(test), _, None, &Expr::Literal(ref value)) |
+                (None, &Expr::Literal(ref value), Some(test), _) =>
+                    return Ok(Predicate::AttributeValue(test, op, value.clone())),
+                _ => {}
+            }
+        }
+        ref other => if let Some(test) = attribute_test(other) {
+            return Ok(Predicate::HasAttribute(test));
+        }
+    }
+    unsupported("Only positional predicates and predicates on attributes are supported")
+}
+
+fn name_matches(test: &NodeTest, name: &OwnedName) -> bool {
+    match *test {
+        NodeTest::AnyName => true,
+        NodeTest::Namespace(ref uri) => name.namespace.as_ref() == Some(uri),
+        NodeTest::Name(ref uri, ref local) => name.local_name == *local && name.namespace == *uri,
+        _ => false
+    }
+}
+
+/// Matching state of an open element.
+struct Frame {
+    /// Indices of element steps which children of the element may match.
+    states: Vec<usize>,
+    /// Whether attributes of the element and its descendants match a final `//@attr` step.
+    attribute_scope: bool,
+    /// Numbers of children which matched a step and its predicates before a positional one,
+    /// by indices of the step and of the predicate.
+    counters: HashMap<(usize, usize), usize>,
+}
+
+/// An item matched by a `StreamingPath`.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Match {
+    /// An element, as its events from `StartElement` to the corresponding `EndElement`.
+    Element(Vec<XmlEvent>),
+
+    /// An attribute.
+    Attribute(OwnedAttribute),
+}
+
+/// An iterator over matches of a `StreamingPath` in a document.
+///
+/// Memory used by the iterator is bounded by the depth of the document and the size of matching
+/// elements which are being read.
+pub struct Matches<'p, R: Read> {
+    path: &'p StreamingPath,
+    reader: EventReader<R>,
+    frames: Vec<Frame>,
+    /// Events of matching elements which are not read completely, by their depths.
+    captures: Vec<(usize, Vec<XmlEvent>)>,
+    pending: VecDeque<Match>,
+    finished: bool,
+}
+
+impl<'p, R: Read> Matches<'p, R> {
+    /// Returns the underlying reader.
+    #[inline]
+    pub fn into_inner(self) -> EventReader<R> {
+        self.reader
+    }
+
+    fn start_element(&mut self, name: &OwnedName, attributes: &[OwnedAttribute]) {
+        let steps = &self.path.steps;
+        let parent = self.frames.last_mut().unwrap();
+        let mut states = Vec::new();
+        let mut matched = false;
+        for &k in &parent.states {
+            let step = &steps[k];
+            if step.descendant && !states.contains(&k) {
+                states.push(k);
+            }
+            if !step_matches(step, k, name, attributes, &mut parent.counters) {
+                continue;
+            }
+            if k + 1 == steps.len() {
+                matched = true;
+            } else if !states.contains(&(k + 1)) {
+                states.push(k + 1);
+            }
+        }
+
+        let attribute_scope = match self.path.attribute {
+            Some(ref step) => {
+                let selected = matched || step.descendant && parent.attribute_scope;
+                if selected {
+                    self.pending.extend(attributes.iter()
+                        .filter(|a| name_matches(&step.test, &a.name))
+                        .map(|a| Match::Attribute(a.clone())));
+                }
+                step.descendant && selected
+            }
+            None => {
+                if matched {
+                    self.captures.push((self.frames.len(), Vec::new()));
+                }
+                false
+            }
+        };
+        self.frames.push(Frame { states, attribute_scope, counters: HashMap::new() });
+    }
+
+    fn end_element(&mut self) {
+        let depth = self.frames.len() - 1;
+        self.frames.pop();
+        if let Some(&(d, _)) = self.captures.last() {
+            if d == depth {
+                let (_, events) = self.captures.pop().unwrap();
+                self.pending.push_back(Match::Element(events));
+            }
+        }
+    }
+}
+
+fn step_matches(step: &ElementStep, k: usize, name: &OwnedName, attributes: &[OwnedAttribute],
+                counters: &mut HashMap<(usize, usize), usize>) -> bool {
+    if !name_matches(&step.test, name) {
+        return false;
+    }
+    for (j, predicate) in step.predicates.iter().enumerate() {
+        let matches = match *predicate {
+            Predicate::Position(n) => {
+                let counter = counters.entry((k, j)).or_insert(0);
+                *counter += 1;
+                *counter == n
+            }
+            Predicate::HasAttribute(ref test) => attribute_values(attributes, test).next().is_some(),
+            Predicate::AttributeValue(ref test, op, ref value) =>
+                attribute_values(attributes, test).any(|v| (v == value) == (op == CompareOp::Eq)),
+        };
+        if !matches {
+            return false;
+        }
+    }
+    true
+}
+
+fn attribute_values<'a>(attributes: &'a [OwnedAttribute], test: &'a NodeTest) -> impl Iterator<Item=&'a str> {
+    attributes.iter()
+        .filter(move |a| name_matches(test, &a.name))
+        .map(|a| &a.value[..])
+}
+
+impl<'p, R: Read> Iterator for Matches<'p, R> {
+    type Item = reader::Result<Match>;
+
+    fn next(&mut self) -> Option<reader::Result<Match>> {
+        loop {
+            if let Some(m) = self.pending.pop_front() {
+                return Some(Ok(m));
+            }
+            if self.finished {
+                return None;
+            }
+            let event = match self.reader.next() {
+                Ok(XmlEvent::EndDocument) => {
+                    self.finished = true;
+                    continue;
+                }
+                Ok(event) => event,
+                Err(e) => {
+                    self.finished = true;
+                    return Some(Err(e));
+                }
+            };
+            match event {
+                XmlEvent::StartElement { ref name, ref attributes, .. } => self.start_element(name, attributes),
+                XmlEvent::EndElement { .. } => {
+                    for &mut (_, ref mut events) in &mut self.captures {
+                        events.push(event.clone());
+                    }
+                    self.end_element();
+                    continue;
+                }
+                _ => {}
+            }
+            for &mut (_, ref mut events) in &mut self.captures {
+                events.push(event.clone());
+            }
+        }
+    }
+}
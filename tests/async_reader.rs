@@ -0,0 +1,137 @@
+#![forbid(unsafe_code)]
+#![cfg(feature = "async")]
+
+extern crate xml;
+extern crate tokio;
+extern crate futures_core;
+
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+use tokio::io::{AsyncRead, ReadBuf};
+
+use xml::common::Position;
+use xml::reader::{AsyncEventReader, ErrorKind, ParserConfig, XmlEvent};
+
+use common::{block_on, collect_events, configs, pulled_events};
+
+mod common;
+
+/// Resolves to the next item of a stream.
+struct NextItem<'a, S: 'a>(&'a mut S);
+
+impl<'a, S: Stream + Unpin> Future for NextItem<'a, S> {
+    type Output = Option<S::Item>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<S::Item>> {
+        Pin::new(&mut *self.get_mut().0).poll_next(cx)
+    }
+}
+
+/// Returns the data in small chunks, each of them only after the source was polled once
+/// without data, and then optionally fails.
+struct Trickle<'a> {
+    data: &'a [u8],
+    chunk: usize,
+    ready: bool,
+    error: Option<io::Error>,
+}
+
+impl<'a> Trickle<'a> {
+    fn new(data: &'a [u8]) -> Trickle<'a> {
+        Trickle { data, chunk: 0, ready: false, error: None }
+    }
+}
+
+impl<'a> AsyncRead for Trickle<'a> {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context, buf: &mut ReadBuf) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if !this.ready {
+            this.ready = true;
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+        this.ready = false;
+        if this.data.is_empty() {
+            if let Some(e) = this.error.take() {
+                return Poll::Ready(Err(e));
+            }
+        }
+        this.chunk = this.chunk % 5 + 1;
+        let len = this.chunk.min(this.data.len()).min(buf.remaining());
+        buf.put_slice(&this.data[..len]);
+        this.data = &this.data[len..];
+        Poll::Ready(Ok(()))
+    }
+}
+
+fn async_events<R: AsyncRead + Unpin>(source: R, config: ParserConfig) -> Vec<String> {
+    let mut reader = AsyncEventReader::new_with_config(source, config);
+    collect_events(|| {
+        let event = block_on(reader.next());
+        (reader.position(), event)
+    })
+}
+
+#[test]
+fn same_events_as_event_reader() {
+    let documents: &[&[u8]] = &[
+        include_bytes!("documents/sample_1.xml"),
+        include_bytes!("documents/sample_2.xml"),
+        include_bytes!("documents/sample_3.xml"),
+        include_bytes!("documents/sample_4.xml"),
+        include_bytes!("documents/sample_5.xml"),
+        include_bytes!("documents/sample_6.xml"),
+        b"<?xml version='1.0' encoding='ISO-8859-1'?><a b='\xe9'>\xe9t\xe9 &#x1F600;</a>",
+        b"\xff\xfe<\0a\0>\0=\xd8\x00\xde<\0/\0a\0>\0",
+        b"<a>\xf0\x9f\x98\x80<![CDATA[ ]]> ]]></a>",
+        b"<a><b></a>",
+        b"<a>text",
+    ];
+    for &document in documents {
+        for config in configs() {
+            let expected = pulled_events(document, config.clone());
+            assert_eq!(async_events(document, config.clone()), expected);
+            assert_eq!(async_events(Trickle::new(document), config.clone()), expected);
+        }
+    }
+}
+
+#[test]
+fn stream_of_events() {
+    let mut events = AsyncEventReader::new(Trickle::new(b"<a>x<b/></a>")).into_stream();
+    let mut found = Vec::new();
+    while let Some(event) = block_on(NextItem(&mut events)) {
+        found.push(event.unwrap());
+    }
+    assert_eq!(found.len(), 7);
+    assert_eq!(found[2], XmlEvent::Characters("x".into()));
+    assert_eq!(found[6], XmlEvent::EndDocument);
+    assert_eq!(events.position().row, 0);
+    assert!(block_on(NextItem(&mut events)).is_none());
+
+    let mut events = AsyncEventReader::new(&b"<a><b></a>"[..]).into_stream();
+    let errors: Vec<_> = (0..5).filter_map(|_| block_on(NextItem(&mut events))).filter(|e| e.is_err()).collect();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].as_ref().unwrap_err().to_string(), "1:10 Unexpected closing tag: a, expected b");
+    assert_eq!(events.into_inner().into_inner(), &b""[..]);
+}
+
+#[test]
+fn source_errors() {
+    let mut source = Trickle::new(b"<a>text");
+    source.error = Some(io::Error::new(io::ErrorKind::ConnectionReset, "connection reset"));
+    let mut reader = AsyncEventReader::new(source);
+    assert!(block_on(reader.next()).is_ok());  // StartDocument
+    assert!(block_on(reader.next()).is_ok());  // StartElement
+    let error = block_on(reader.next()).unwrap_err();
+    match *error.kind() {
+        ErrorKind::Io(ref e) => assert_eq!(e.kind(), io::ErrorKind::ConnectionReset),
+        ref kind => panic!("Unexpected error: {:?}", kind),
+    }
+    // errors are returned again, like with `EventReader`
+    assert_eq!(block_on(reader.next()).unwrap_err(), error);
+}
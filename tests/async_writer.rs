@@ -0,0 +1,149 @@
+#![forbid(unsafe_code)]
+#![cfg(feature = "async")]
+
+extern crate xml;
+extern crate tokio;
+
+use std::io;
+use std::pin::Pin;
+use std::str;
+use std::task::{Context, Poll};
+
+use tokio::io::AsyncWrite;
+
+use xml::reader::EventReader;
+use xml::writer::{AsyncEventWriter, EmitterConfig, Error, XmlEvent};
+
+use common::block_on;
+
+mod common;
+
+/// Accepts data in small chunks, each of them only after the sink was polled once
+/// without being ready, and optionally fails after accepting the given amount of bytes.
+#[derive(Default)]
+struct Trickle {
+    data: Vec<u8>,
+    chunk: usize,
+    ready: bool,
+    flushes: usize,
+    limit: Option<usize>,
+}
+
+impl Trickle {
+    fn poll_ready(&mut self, cx: &mut Context) -> Poll<()> {
+        if self.ready {
+            self.ready = false;
+            Poll::Ready(())
+        } else {
+            self.ready = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+}
+
+impl AsyncWrite for Trickle {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if this.poll_ready(cx).is_pending() {
+            return Poll::Pending;
+        }
+        if this.limit.map_or(false, |limit| this.data.len() >= limit) {
+            return Poll::Ready(Err(io::Error::new(io::ErrorKind::BrokenPipe, "broken pipe")));
+        }
+        this.chunk = this.chunk % 100 + 1;
+        let len = this.chunk.min(buf.len());
+        this.data.extend_from_slice(&buf[..len]);
+        Poll::Ready(Ok(len))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if this.poll_ready(cx).is_pending() {
+            return Poll::Pending;
+        }
+        this.flushes += 1;
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _: &mut Context) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[test]
+fn same_output_as_event_writer() {
+    let documents: &[&[u8]] = &[
+        include_bytes!("documents/sample_1.xml"),
+        include_bytes!("documents/sample_2.xml"),
+        include_bytes!("documents/sample_4.xml"),
+        include_bytes!("documents/sample_6.xml"),
+    ];
+    let configs = vec![
+        EmitterConfig::new(),
+        EmitterConfig::new().perform_indent(true).normalize_empty_elements(false),
+    ];
+    for &document in documents {
+        let events: Vec<_> = EventReader::new(document).into_iter().map(Result::unwrap).collect();
+        for config in &configs {
+            let mut expected = Vec::new();
+            {
+                let mut w = config.clone().create_writer(&mut expected);
+                for e in events.iter().filter_map(|e| e.as_writer_event()) {
+                    w.write(e).unwrap();
+                }
+            }
+
+            let mut w = AsyncEventWriter::new_with_config(Trickle::default(), config.clone());
+            for e in events.iter().filter_map(|e| e.as_writer_event()) {
+                block_on(w.write(e)).unwrap();
+            }
+            block_on(w.flush()).unwrap();
+            let sink = w.into_inner();
+            assert_eq!(str::from_utf8(&sink.data).unwrap(), str::from_utf8(&expected).unwrap());
+            assert_eq!(sink.flushes, 1);
+        }
+    }
+}
+
+#[test]
+fn buffering_and_flushing() {
+    let mut w = AsyncEventWriter::new_with_config(
+        Trickle::default(), EmitterConfig::new().write_document_declaration(false)
+    );
+    block_on(w.write(XmlEvent::start_element("h:hello").ns("h", "urn:hello-world"))).unwrap();
+    block_on(w.write("hello world")).unwrap();
+    block_on(w.write(XmlEvent::end_element())).unwrap();
+    assert!(w.inner_mut().data.is_empty());
+
+    block_on(w.flush()).unwrap();
+    assert_eq!(
+        str::from_utf8(&w.inner_mut().data).unwrap(),
+        r#"<h:hello xmlns:h="urn:hello-world">hello world</h:hello>"#
+    );
+
+    // large output is written without explicit flushes
+    block_on(w.write(XmlEvent::start_element("text"))).unwrap();
+    for _ in 0..1000 {
+        block_on(w.write("some text ")).unwrap();
+    }
+    assert!(w.inner_mut().data.len() > 8 * 1024);
+    assert_eq!(w.inner_mut().flushes, 1);
+}
+
+#[test]
+fn emitter_and_sink_errors() {
+    let mut w = AsyncEventWriter::new(Trickle::default());
+    block_on(w.write(XmlEvent::start_element("a"))).unwrap();
+    match block_on(w.write(XmlEvent::end_element().name("b"))) {
+        Err(Error::EndElementNameIsNotEqualToLastStartElementName) => {}
+        other => panic!("Unexpected result: {:?}", other),
+    }
+
+    let mut w = AsyncEventWriter::new(Trickle { limit: Some(10), ..Trickle::default() });
+    block_on(w.write(XmlEvent::start_element("a"))).unwrap();
+    match block_on(w.flush()) {
+        Err(Error::Io(ref e)) => assert_eq!(e.kind(), io::ErrorKind::BrokenPipe),
+        other => panic!("Unexpected result: {:?}", other),
+    }
+}
@@ -0,0 +1,300 @@
+#![forbid(unsafe_code)]
+
+extern crate xml;
+
+use std::borrow::Cow;
+
+use xml::common::Position;
+use xml::namespace::NamespaceStack;
+use xml::reader::borrowed::{BorrowedReader, XmlEvent as BorrowedEvent};
+use xml::reader::{ParserConfig, XmlEvent};
+
+use common::{collect_events, configs, pulled_events};
+
+mod common;
+
+/// Converts an event of `BorrowedReader` into the event of `EventReader`, computing the
+/// namespace of elements from the declarations of their ancestors.
+fn to_owned(event: BorrowedEvent, namespaces: &mut NamespaceStack) -> XmlEvent {
+    match event {
+        BorrowedEvent::StartDocument { version, encoding, standalone } =>
+            XmlEvent::StartDocument { version, encoding: encoding.into_owned(), standalone },
+        BorrowedEvent::Doctype(doctype) => XmlEvent::Doctype(doctype),
+        BorrowedEvent::EndDocument => XmlEvent::EndDocument,
+        BorrowedEvent::ProcessingInstruction { name, data } =>
+            XmlEvent::ProcessingInstruction { name: name.into_owned(), data: data.map(Cow::into_owned) },
+        BorrowedEvent::StartElement { name, attributes, namespaces: declared } => {
+            namespaces.push_empty();
+            for (prefix, uri) in declared {
+                namespaces.put(prefix.into_owned(), uri.into_owned());
+            }
+            XmlEvent::StartElement {
+                name: name.to_owned(),
+                attributes: attributes.iter().map(|a| a.to_owned()).collect(),
+                namespace: namespaces.squash(),
+            }
+        }
+        BorrowedEvent::EndElement { name } => {
+            namespaces.pop();
+            XmlEvent::EndElement { name: name.to_owned() }
+        }
+        BorrowedEvent::CData(data) => XmlEvent::CData(data.into_owned()),
+        BorrowedEvent::Comment(data) => XmlEvent::Comment(data.into_owned()),
+        BorrowedEvent::Characters(data) => XmlEvent::Characters(data.into_owned()),
+        BorrowedEvent::Whitespace(data) => XmlEvent::Whitespace(data.into_owned()),
+    }
+}
+
+/// Formats events of `BorrowedReader` in the same way as `pulled_events()`.
+fn borrowed_events(input: &[u8], config: ParserConfig) -> Vec<String> {
+    let mut reader = BorrowedReader::from_bytes_with_config(input, config);
+    let mut namespaces = NamespaceStack::default();
+    collect_events(|| {
+        let event = reader.next().map(|e| to_owned(e, &mut namespaces));
+        (reader.position(), event)
+    })
+}
+
+fn all_configs() -> Vec<ParserConfig> {
+    let mut configs = configs();
+    configs.extend(vec![
+        ParserConfig::new()
+            .ignore_comments(true)
+            .whitespace_to_characters(true)
+            .cdata_to_characters(true)
+            .trim_whitespace(true)
+            .coalesce_characters(true),
+        ParserConfig::new()
+            .ignore_comments(true)
+            .cdata_to_characters(true)
+            .coalesce_characters(false),
+        ParserConfig::new()
+            .ignore_comments(false)
+            .whitespace_to_characters(true)
+            .trim_whitespace(true)
+            .coalesce_characters(false),
+        ParserConfig::new()
+            .validate_dtd(true),
+    ]);
+    configs
+}
+
+/// Checks that both readers produce the same events and errors at the same positions.
+fn check_same<D: AsRef<[u8]> + ?Sized>(input: &D) {
+    let input = input.as_ref();
+    for config in all_configs() {
+        let expected = pulled_events(input, config.clone());
+        let found = borrowed_events(input, config.clone());
+        assert_eq!(found, expected, "\n{}\n{:?}", String::from_utf8_lossy(input), config);
+    }
+}
+
+#[test]
+fn sample_documents() {
+    check_same(include_str!("documents/sample_1.xml"));
+    check_same(include_str!("documents/sample_2.xml"));
+    check_same(include_str!("documents/sample_3.xml"));
+    check_same(include_str!("documents/sample_4.xml"));
+    check_same(include_str!("documents/sample_6.xml"));
+}
+
+#[test]
+fn same_events_as_event_reader() {
+    check_same("<a/>");
+    check_same("<?xml version='1.1' encoding='ISO-8859-1' standalone='no'?><a>x</a>");
+    check_same("<!-- c --><?pi data?>\n<a>\r\n  <b x='1&#x20;2' y=\"\t3\r\n4\"> a &amp; b <![CDATA[ <c> ]]> </b>\r<c/></a>\n<!-- end -->\n");
+    check_same("<a><!----><b>  <!-- c -->  </b>x<!-- c -->y<![CDATA[]]>z</a>");
+    check_same("<?xml version='1.0'?>\n<!DOCTYPE a [\n  <!ELEMENT a ANY>\n]>\n<a>x&#65;&#x42;&lt;</a>");
+    check_same("<a xmlns='urn:a' xmlns:b='urn:b'><b:c b:x='1' x='2'><d xmlns=''/></b:c><e xml:lang='en'/></a>");
+    check_same("<a>\u{1f600} \u{e9}<b \u{e9}='\u{1f600}'/></a>");
+}
+
+#[test]
+fn same_errors_as_event_reader() {
+    check_same("<a><b></a>");
+    check_same("<a>");
+    check_same("");
+    check_same("  text <a/>");
+    check_same("<a x='1' x='2'/>");
+    check_same("<a b:x='1'/>");
+    check_same("<b:a/>");
+    check_same("<a xmlns:p='urn:p' xmlns:q='urn:p' p:x='1' q:x='2'/>");
+    check_same("<a>&unknown;</a>");
+    check_same("<a>&#0;</a>");
+    check_same("<a><!-- a -- b --></a>");
+    check_same("<a><?xml version='1.0'?></a>");
+    check_same("<a x='<b'/>");
+    check_same("<xml:a/>");
+    check_same("<a><!DOC");
+}
+
+#[test]
+fn events_borrow_from_input() {
+    let input = String::from("<a x='plain' y='a&amp;b'>text<!--comment--><![CDATA[data]]>&lt;</a>");
+    let config = ParserConfig::new().ignore_comments(false).coalesce_characters(false);
+    let events: Vec<_> = BorrowedReader::new_with_config(&input, config).into_iter().map(Result::unwrap).collect();
+
+    fn borrowed(s: &Cow<str>) -> bool {
+        match *s { Cow::Borrowed(_) => true, Cow::Owned(_) => false }
+    }
+    match events[1] {
+        BorrowedEvent::StartElement { ref name, ref attributes, .. } => {
+            assert_eq!(name.local_name.as_ptr(), input[1..].as_ptr());
+            assert!(borrowed(&attributes[0].value));
+            assert!(!borrowed(&attributes[1].value));
+            assert_eq!(attributes[1].borrow().value, "a&b");
+            assert_eq!(attributes[1].to_owned().value, "a&b");
+        }
+        ref e => panic!("Unexpected event: {:?}", e),
+    }
+    match (&events[2], &events[3], &events[4], &events[5]) {
+        (&BorrowedEvent::Characters(ref text), &BorrowedEvent::Comment(ref comment),
+         &BorrowedEvent::CData(ref data), &BorrowedEvent::Characters(ref escaped)) => {
+            assert!(borrowed(text) && borrowed(comment) && borrowed(data) && !borrowed(escaped));
+            assert_eq!(escaped, "<");
+        }
+        _ => panic!("Unexpected events: {:?}", events),
+    }
+    assert_eq!(events.len(), 8);
+}
+
+#[test]
+fn documents_with_dtd() {
+    check_same("<!DOCTYPE a [<!ENTITY e 'x'>]><a>&e;</a>");
+    check_same("<!DOCTYPE a [<!ENTITY e '<b c=\"1\">t</b>'>]><a>x&e;y<b c='2'/></a>");
+    check_same("<!DOCTYPE a [<!ENTITY e '<p:b xmlns:p=\"urn:p\"/>'>]><a xmlns:p='urn:q'>&e;<p:c/></a>");
+    check_same("<!DOCTYPE a [<!ATTLIST a b NMTOKENS #IMPLIED c CDATA 'd'>]><a b='  x   y '/>");
+    check_same("<!DOCTYPE a [<!ELEMENT a (b)><!ELEMENT b EMPTY>]><a><c/></a>");
+    check_same("<!DOCTYPE a [<!ENTITY e '<b>'>]><a>&e;</a>");
+    check_same("<!DOCTYPE a [<!ENTITY e 'x;y&amp;'>]><a b='&e;'>&e;</a>");
+
+    let mut reader = BorrowedReader::new("<!DOCTYPE a [<!ENTITY e 'x'>]><a>&e;</a>");
+    assert_eq!(reader.next().unwrap(), BorrowedEvent::StartDocument {
+        version: xml::common::XmlVersion::Version10, encoding: "UTF-8".into(), standalone: None,
+    });
+    match reader.next().unwrap() {
+        BorrowedEvent::Doctype(doctype) => assert_eq!(doctype.name, "a"),
+        e => panic!("Unexpected event: {:?}", e),
+    }
+    match reader.next().unwrap() {
+        BorrowedEvent::StartElement { name, .. } => assert!(match name.local_name { Cow::Borrowed("a") => true, _ => false }),
+        e => panic!("Unexpected event: {:?}", e),
+    }
+    assert_eq!(reader.next().unwrap(), BorrowedEvent::Characters("x".into()));
+}
+
+#[test]
+fn lenient_syntax_and_whitespace() {
+    check_same("<a b='1'c='2'/>");
+    check_same("<a>  <b/>\n\t</a>");
+    check_same("<a xmlns:p='urn:&amp;'><p:b/></a>");
+}
+
+#[test]
+fn encoded_documents() {
+    check_same(&b"<?xml version='1.0' encoding='ISO-8859-1'?><a b='\xe9'>\xe9t\xe9</a>"[..]);
+    check_same(&b"\xff\xfe<\0a\0>\0x\0<\0/\0a\0>\0"[..]);
+    check_same(&b"\xef\xbb\xbf<a>\n<b/></a>"[..]);
+    check_same(&b"<a>abc\xff</a>"[..]);
+}
+
+/// A linear congruential generator, so that generated documents are the same in every run.
+struct Random(u64);
+
+impl Random {
+    fn below(&mut self, n: usize) -> usize {
+        self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (self.0 >> 33) as usize % n
+    }
+
+    /// Picks one of the items, or one of the rare items once in a while.
+    fn pick<'a>(&mut self, items: &[&'a str], rare: &[&'a str]) -> &'a str {
+        if self.below(20) == 0 {
+            rare[self.below(rare.len())]
+        } else {
+            items[self.below(items.len())]
+        }
+    }
+}
+
+/// Generates content of an element out of pieces which are read by the parser in different
+/// ways, including invalid ones.
+fn generate_content(random: &mut Random, depth: usize, out: &mut String) {
+    const NAMES: &[&str] = &["a", "b", "p:c", "x-y", "\u{e9}", "q:d", "b.c1"];
+    const RARE_NAMES: &[&str] = &["a--b", "xml:e", "u:a", "a:", "1"];
+    const ATTRIBUTES: &[&str] = &[
+        " x='1'", " y=\"a&amp;b\"", " z='\t1\n2'", " xmlns='urn:a'", " xmlns:p='urn:p'", " xmlns:q='urn:p'",
+        " p:x='2'", " q:y='3'", " xml:lang='en'", " xmlns=''", " w='&#x20;'", " u = \"\" ", " t='\"'",
+    ];
+    const RARE_ATTRIBUTES: &[&str] = &[" x='1'", "w='1'", " v='<'", " xmlns:p=''", " q:x='3'", " xmlns='xml'", " s=1"];
+    const TEXT: &[&str] = &[
+        "text", " ", "\n  ", "a &amp; b", "&#32;", ";", "x;y", "&lt;", "--", "-->", ">", "]]", "\u{1f600}", "a\tb",
+        "'\"=", "?", "/", "]",
+    ];
+    const RARE_TEXT: &[&str] = &["]]>", "/>", "?>", "&unknown;", "&", "&a--b;", "<", "< a"];
+    const MARKUP: &[&str] = &[
+        "<!-- c -->", "<!---->", "<!-- <!- -->", "<![CDATA[ <c> ]]>", "<![CDATA[]]>", "<![CDATA[ ]]>",
+        "<?pi data?>", "<?pi?>", "<?p:i  d ?>", "<?pi <??>", "<?XML x?>", "<![CDATA[x]]]>", "<![CDATA[]]]]]>",
+    ];
+    const RARE_MARKUP: &[&str] = &[
+        "<!-- a -- b -->", "<!-- <!-- -->", "<?pi <?>", "<?xml version='1.0'?>", "<!DOCTYPE a>",
+        "<!x>", "</>", "<?xml?>",
+    ];
+    for _ in 0..random.below(6) {
+        match random.below(if depth < 5 { 4 } else { 3 }) {
+            0 => out.push_str(random.pick(TEXT, RARE_TEXT)),
+            1 => out.push_str(random.pick(MARKUP, RARE_MARKUP)),
+            2 => out.push_str(random.pick(&["", " ", "\n", "\t"], &["x"])),
+            _ => {
+                let name = random.pick(NAMES, RARE_NAMES);
+                out.push('<');
+                out.push_str(name);
+                for _ in 0..random.below(3) {
+                    out.push_str(random.pick(ATTRIBUTES, RARE_ATTRIBUTES));
+                }
+                if random.below(4) == 0 {
+                    out.push_str(random.pick(&["/>", " />"], &[" / >"]));
+                } else {
+                    out.push('>');
+                    generate_content(random, depth + 1, out);
+                    out.push_str("</");
+                    out.push_str(random.pick(&[name], &["b"]));
+                    out.push_str(random.pick(&[">", " >"], &["/>"]));
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn generated_documents() {
+    const PROLOGS: &[&str] = &[
+        "", "<?xml version='1.0'?>", "<?xml version=\"1.1\" encoding='utf-8' standalone='yes'?>\n",
+        "<?xml version = '1.0' standalone=\"no\" ?>", "<?xml version='1.0' encoding='x-unknown'?>", "\u{feff}",
+        "<!-- c -->\n<?pi?>", " \n", "<?pi?><?xml version='1.0'?>",
+    ];
+    const RARE_PROLOGS: &[&str] = &[
+        "<?xml version='1.0' encoding='ISO-8859-1'?>", "<?xml version='2.0'?>", "<?xml version='1.0'encoding='UTF-8'?>",
+        "<?xml version='1.0' encoding='US-ASCII'?>", "x",
+    ];
+    let mut random = Random(1);
+    for _ in 0..1000 {
+        let mut document = random.pick(PROLOGS, RARE_PROLOGS).to_string();
+        if random.below(5) == 0 {
+            generate_content(&mut random, 1, &mut document);
+        } else {
+            document.push_str("<r>");
+            generate_content(&mut random, 2, &mut document);
+            document.push_str("</r>");
+            document.push_str(random.pick(&["", "\n", "<!-- c -->", "<?pi?> "], &["x", "<r/>"]));
+        }
+        if random.below(20) == 0 {
+            let mut len = random.below(document.len() + 1);
+            while !document.is_char_boundary(len) {
+                len -= 1;
+            }
+            document.truncate(len);
+        }
+        check_same(&document);
+    }
+}
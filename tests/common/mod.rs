@@ -0,0 +1,71 @@
+//! Helpers shared by the tests which compare other readers and writers with `EventReader`
+//! and `EventWriter`.
+
+#![allow(dead_code)]
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::task::{Context, Poll, Wake, Waker};
+
+use xml::common::{Position, TextPosition};
+use xml::reader::{EventReader, ParserConfig, Result, XmlEvent};
+
+/// Configurations which produce different sets of events from the same document.
+pub fn configs() -> Vec<ParserConfig> {
+    vec![
+        ParserConfig::new(),
+        ParserConfig::new().ignore_comments(false).coalesce_characters(false),
+        ParserConfig::new().trim_whitespace(true).cdata_to_characters(true),
+    ]
+}
+
+/// Formats an event with the position of the reader after it.
+pub fn event_line(position: TextPosition, event: &Result<XmlEvent>) -> String {
+    format!("{} {:?}", position, event)
+}
+
+/// Formats events returned by `next` up to the end of the document or the first error.
+pub fn collect_events<F>(mut next: F) -> Vec<String> where F: FnMut() -> (TextPosition, Result<XmlEvent>) {
+    let mut result = Vec::new();
+    loop {
+        let (position, event) = next();
+        result.push(event_line(position, &event));
+        match event {
+            Ok(XmlEvent::EndDocument) | Err(_) => return result,
+            _ => {}
+        }
+    }
+}
+
+/// Formats all events which `EventReader` reads from the document.
+pub fn pulled_events(document: &[u8], config: ParserConfig) -> Vec<String> {
+    let mut reader = EventReader::new_with_config(document, config);
+    collect_events(|| {
+        let event = reader.next();
+        (reader.position(), event)
+    })
+}
+
+struct Flag(AtomicBool);
+
+impl Wake for Flag {
+    fn wake(self: Arc<Self>) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Polls the future until it completes, checking that it wakes up the task before
+/// returning `Poll::Pending`.
+pub fn block_on<F: Future + Unpin>(mut future: F) -> F::Output {
+    let flag = Arc::new(Flag(AtomicBool::new(false)));
+    let waker = Waker::from(flag.clone());
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        match Pin::new(&mut future).poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => assert!(flag.0.swap(false, Ordering::SeqCst), "The task is never woken up"),
+        }
+    }
+}
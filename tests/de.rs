@@ -0,0 +1,172 @@
+#![forbid(unsafe_code)]
+#![cfg(feature = "serde")]
+
+extern crate xml;
+#[macro_use]
+extern crate serde_derive;
+
+use std::collections::BTreeMap;
+
+use xml::common::Position;
+use xml::de::{from_str, ErrorKind};
+
+#[derive(Deserialize, PartialEq, Debug)]
+struct Library {
+    #[serde(rename = "@name")]
+    name: String,
+    open: bool,
+    book: Vec<Book>,
+    magazine: Option<Magazine>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+#[derive(Deserialize, PartialEq, Debug)]
+struct Book {
+    id: u32,
+    format: Format,
+    title: String,
+    author: Vec<String>,
+    rating: Option<f32>,
+}
+
+#[derive(Deserialize, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+enum Format {
+    Hardcover,
+    Paperback,
+    Ebook,
+}
+
+#[derive(Deserialize, PartialEq, Debug)]
+struct Magazine {
+    #[serde(rename = "$value")]
+    title: String,
+}
+
+#[test]
+fn structs_and_sequences() {
+    let library: Library = from_str(r#"<?xml version="1.0"?>
+        <!-- a comment -->
+        <library name="City" open=" 1 " tags="old rare">
+          <book id="1" format="paperback">
+            <title>Dune</title>
+            <author>Frank Herbert</author>
+            <rating> 4.5 </rating>
+          </book>
+          <book id="2">
+            <format>ebook</format>
+            <title><![CDATA[Good]]> Omens</title>
+            <author>Terry Pratchett</author>
+            <author>Neil Gaiman</author>
+          </book>
+          <magazine>Monthly</magazine>
+        </library>"#).unwrap();
+
+    assert_eq!(library, Library {
+        name: "City".into(),
+        open: true,
+        book: vec![
+            Book {
+                id: 1, format: Format::Paperback, title: "Dune".into(),
+                author: vec!["Frank Herbert".into()], rating: Some(4.5),
+            },
+            Book {
+                id: 2, format: Format::Ebook, title: "Good Omens".into(),
+                author: vec!["Terry Pratchett".into(), "Neil Gaiman".into()], rating: None,
+            },
+        ],
+        magazine: Some(Magazine { title: "Monthly".into() }),
+        tags: vec!["old".into(), "rare".into()],
+    });
+}
+
+#[derive(Deserialize, PartialEq, Debug)]
+struct Drawing {
+    #[serde(rename = "@title")]
+    title: Option<String>,
+    background: Shape,
+    #[serde(rename = "$value")]
+    shapes: Vec<Shape>,
+}
+
+#[derive(Deserialize, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+enum Shape {
+    Point,
+    Circle { r: f64 },
+    Label(String),
+    Group(Vec<Shape>),
+}
+
+#[test]
+fn enums() {
+    let drawing: Drawing = from_str(r#"
+        <drawing>
+          <background><point/></background>
+          <circle r="1"/>
+          <label>Hello</label>
+          <group><point/><circle r="2.5"/></group>
+        </drawing>"#).unwrap();
+
+    assert_eq!(drawing, Drawing {
+        title: None,
+        background: Shape::Point,
+        shapes: vec![
+            Shape::Circle { r: 1.0 },
+            Shape::Label("Hello".into()),
+            Shape::Group(vec![Shape::Point, Shape::Circle { r: 2.5 }]),
+        ],
+    });
+
+    let shape: Shape = from_str("<circle><r>3</r></circle>").unwrap();
+    assert_eq!(shape, Shape::Circle { r: 3.0 });
+}
+
+#[test]
+fn maps_and_primitives() {
+    let map: BTreeMap<String, String> = from_str(r#"<m a="1"><b>2</b><c/></m>"#).unwrap();
+    assert_eq!(map.into_iter().collect::<Vec<_>>(), vec![
+        ("a".to_string(), "1".to_string()), ("b".into(), "2".into()), ("c".into(), "".into()),
+    ]);
+
+    assert_eq!(from_str::<i64>("<n> -42 </n>").unwrap(), -42);
+    assert_eq!(from_str::<char>("<c>x</c>").unwrap(), 'x');
+    assert_eq!(from_str::<String>("<s>a &amp; b</s>").unwrap(), "a & b");
+    assert_eq!(from_str::<(u8, u8)>("<a>1</a>").unwrap_err().msg(), "invalid length 1, expected a tuple of size 2");
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct Strict {
+    #[allow(dead_code)]
+    a: Option<u8>,
+}
+
+#[test]
+fn error_positions() {
+    let error = from_str::<Book>("<book id='1' format='paperback'>\n  <title>Dune</title>\n</book>").unwrap_err();
+    assert_eq!(error.to_string(), "3:1 missing field `author`");
+
+    let error = from_str::<Library>("<library name='x'>\n  <open>maybe</open>\n</library>").unwrap_err();
+    assert_eq!(error.to_string(), "2:9 Value 'maybe' is not a valid bool");
+
+    let error = from_str::<Shape>("<x>\n  <square/>\n</x>").unwrap_err();
+    assert_eq!(error.to_string(), "1:1 unknown variant `x`, expected one of `point`, `circle`, `label`, `group`");
+
+    let error = from_str::<Drawing>("<drawing>\n  <background>\n    <point/><point/>\n  </background>\n</drawing>").unwrap_err();
+    assert_eq!(error.to_string(), "3:13 Unexpected element point");
+
+    let error = from_str::<Strict>("<s>\n  <b/>\n</s>").unwrap_err();
+    assert_eq!(error.to_string(), "2:3 unknown field `b`, expected `a`");
+
+    let error = from_str::<Strict>("<s><a>1<b/></a></s>").unwrap_err();
+    assert_eq!(error.to_string(), "1:8 Unexpected element b");
+
+    let error = from_str::<Book>("<book><title>Dune</book>").unwrap_err();
+    assert_eq!(error.to_string(), "1:24 Unexpected closing tag: book, expected title");
+    match *error.kind() {
+        ErrorKind::Reader(ref e) => assert_eq!(e.position(), error.position()),
+        ref other => panic!("Unexpected error kind: {:?}", other),
+    }
+}
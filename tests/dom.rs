@@ -0,0 +1,51 @@
+#![forbid(unsafe_code)]
+
+extern crate xml;
+
+use std::fs::File;
+use std::io::{BufReader, Read};
+
+use xml::dom::{Document, Node};
+use xml::reader::{EventReader, ParserConfig};
+use xml::writer::EmitterConfig;
+
+#[test]
+fn reading_writing_equal_with_namespaces() {
+    let mut source = String::new();
+    File::open("tests/documents/sample_2.xml").unwrap().read_to_string(&mut source).unwrap();
+
+    let document = Document::from_reader(EventReader::new(source.as_bytes())).unwrap();
+    let mut output = Vec::new();
+    document.write_to(&mut EmitterConfig::new().perform_indent(true).create_writer(&mut output)).unwrap();
+
+    assert_eq!(source.trim(), String::from_utf8(output).unwrap().trim());
+}
+
+#[test]
+fn document_structure() {
+    let f = File::open("tests/documents/sample_2.xml").unwrap();
+    let config = ParserConfig::new().trim_whitespace(true);
+    let document = Document::from_reader(EventReader::new_with_config(BufReader::new(f), config)).unwrap();
+
+    assert_eq!(document.encoding, "utf-8");
+    let data = document.root_element().unwrap();
+    let data = document.element(data).unwrap();
+    assert_eq!(data.name.namespace.as_ref().unwrap(), "urn:example:namespace");
+    assert_eq!(data.namespace.get("d"), Some("urn:example:double"));
+
+    let datums = document.children(document.root_element().unwrap());
+    assert!(datums.iter().all(|&id| document.element(id).is_some()));
+    let first = document.element(datums[0]).unwrap();
+    assert_eq!(first.attributes[0].name.local_name, "id");
+    assert_eq!(first.attributes[0].value, "34");
+
+    let name = document.children(datums[0])[0];
+    assert_eq!(document.children(name).iter().map(|&id| document.node(id)).collect::<Vec<_>>(),
+               vec![&Node::Text("Name".into())]);
+}
+
+#[test]
+fn reader_errors() {
+    let error = Document::from_reader(EventReader::from_str("<a><b></a>")).unwrap_err();
+    assert_eq!(error.to_string(), "1:10 Unexpected closing tag: a, expected b");
+}
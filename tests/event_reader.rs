@@ -7,12 +7,12 @@ extern crate lazy_static;
 use std::env;
 use std::fmt;
 use std::fs::File;
-use std::io::{BufRead, BufReader, Write, stderr};
+use std::io::{self, BufRead, BufReader, Read, Write, stderr};
 use std::path::Path;
 
 use xml::name::OwnedName;
-use xml::common::Position;
-use xml::reader::{Result, XmlEvent, ParserConfig, EventReader};
+use xml::common::{Encoding, Position};
+use xml::reader::{Result, XmlEvent, ParserConfig, EventReader, ErrorKind};
 
 /// Dummy function that opens a file, parses it, and returns a `Result`.
 /// There can be IO errors (from `File::open`) and XML errors (from the parser).
@@ -256,6 +256,51 @@ fn issue_83_duplicate_attributes() {
     );
 }
 
+#[test]
+fn duplicate_namespaced_attributes() {
+    test(
+        br#"<a xmlns:p="urn:x" xmlns:q="urn:x"><b p:c="1" q:c="2"/></a>"#,
+        br#"
+            |StartDocument(1.0, UTF-8)
+            |StartElement(a)
+            |1:54 Attributes 'p:c' and 'q:c' have the same local name and namespace
+        "#,
+        ParserConfig::new(),
+        false
+    );
+    test(
+        br#"<a xmlns:p="urn:x" xmlns:q="urn:y"><b p:c="1" q:c="2" c="3"/></a>"#,
+        br#"
+            |StartDocument(1.0, UTF-8)
+            |StartElement(a)
+            |StartElement(b [{urn:x}p:c="1", {urn:y}q:c="2", c="3"])
+            |EndElement(b)
+            |EndElement(a)
+            |EndDocument
+        "#,
+        ParserConfig::new(),
+        false
+    );
+    test(
+        br#"<a xmlns:p="urn:x" xmlns:p="urn:y"/>"#,
+        br#"
+            |StartDocument(1.0, UTF-8)
+            |1:34 Attribute 'xmlns:p' is redefined
+        "#,
+        ParserConfig::new(),
+        false
+    );
+    test(
+        br#"<a xmlns="urn:x" xmlns="urn:x"/>"#,
+        br#"
+            |StartDocument(1.0, UTF-8)
+            |1:30 Attribute 'xmlns' is redefined
+        "#,
+        ParserConfig::new(),
+        false
+    );
+}
+
 #[test]
 fn issue_93_large_characters_in_entity_references() {
     test(
@@ -417,6 +462,677 @@ fn issue_replacement_character_entity_reference() {
     );
 }
 
+#[test]
+fn doctype_with_internal_subset() {
+    test(
+        br#"<!DOCTYPE a [
+  <!ELEMENT a (#PCDATA)>
+  <!-- a comment with a > and a ' -->
+  <!ATTLIST a b CDATA "x > y">
+]><a/>"#,
+        br#"
+            |1:1 StartDocument(1.0, UTF-8)
+            |1:1 Doctype(a)
+            |5:3 StartElement(a)
+            |5:3 EndElement(a)
+            |5:7 EndDocument
+        "#,
+        ParserConfig::new(),
+        true
+    );
+
+    test(
+        br#"<?xml version="1.0"?>
+<!DOCTYPE html PUBLIC "-//W3C//DTD XHTML 1.0 Strict//EN" "xhtml1-strict.dtd">
+<html/>"#,
+        br#"
+            |StartDocument(1.0, UTF-8)
+            |Doctype(html, PUBLIC "-//W3C//DTD XHTML 1.0 Strict//EN" "xhtml1-strict.dtd")
+            |StartElement(html)
+            |EndElement(html)
+            |EndDocument
+        "#,
+        ParserConfig::new(),
+        false
+    );
+}
+
+#[test]
+fn doctype_errors() {
+    test(
+        br#"<!DOCTYPE a [
+  <!ELEMENT a (b, c | d)>
+]><a/>"#,
+        br#"
+            |StartDocument(1.0, UTF-8)
+            |2:21 Unexpected separator inside content model: |
+        "#,
+        ParserConfig::new(),
+        false
+    );
+
+    test(
+        br#"<a><!DOCTYPE a></a>"#,
+        br#"
+            |StartDocument(1.0, UTF-8)
+            |StartElement(a)
+            |1:4 Unexpected token: <!DOCTYPE
+        "#,
+        ParserConfig::new(),
+        false
+    );
+
+    test(
+        br#"<!DOCTYPE a [<!ENTITY e "x"]><a/>"#,
+        br#"
+            |StartDocument(1.0, UTF-8)
+            |1:28 Unexpected character inside entity declaration: ]
+        "#,
+        ParserConfig::new(),
+        false
+    );
+}
+
+#[test]
+fn dtd_entity_expansion() {
+    test(
+        br#"<!DOCTYPE a [
+  <!ENTITY name "world">
+  <!ENTITY greeting "hello, &name;">
+  <!ENTITY markup "<b x='&name;'>&greeting;&#38;amp;</b>">
+]>
+<a title="&greeting;!">&markup; &greeting;</a>"#,
+        br#"
+            |StartDocument(1.0, UTF-8)
+            |Doctype(a)
+            |StartElement(a [title="hello, world!"])
+            |StartElement(b [x="world"])
+            |Characters("hello, world&")
+            |EndElement(b)
+            |Characters(" hello, world")
+            |EndElement(a)
+            |EndDocument
+        "#,
+        ParserConfig::new(),
+        false
+    );
+    test(
+        br#"<!DOCTYPE a [<!ENTITY e "x-y]<b/>">]>
+<a>&e;<c/></a>"#,
+        br#"
+            |1:1 StartDocument(1.0, UTF-8)
+            |1:1 Doctype(a)
+            |2:1 StartElement(a)
+            |2:7 Characters("x-y]")
+            |2:7 StartElement(b)
+            |2:7 EndElement(b)
+            |2:7 StartElement(c)
+            |2:7 EndElement(c)
+            |2:11 EndElement(a)
+            |2:15 EndDocument
+        "#,
+        ParserConfig::new(),
+        true
+    );
+}
+
+#[test]
+fn dtd_entity_with_semicolon_before_reference() {
+    test(
+        br#"<!DOCTYPE a [<!ENTITY e "x;y&amp;">]><a b="&e;">&e;</a>"#,
+        br#"
+            |StartDocument(1.0, UTF-8)
+            |Doctype(a)
+            |StartElement(a [b="x;y&"])
+            |Characters("x;y&")
+            |EndElement(a)
+            |EndDocument
+        "#,
+        ParserConfig::new(),
+        false
+    );
+}
+
+#[test]
+fn dtd_entity_expansion_errors() {
+    test(
+        br#"<!DOCTYPE a [
+  <!ENTITY a "x&b;">
+  <!ENTITY b "y&a;">
+]>
+<a>&a;</a>"#,
+        br#"
+            |StartDocument(1.0, UTF-8)
+            |Doctype(a)
+            |StartElement(a)
+            |5:6 Recursive entity reference: a
+        "#,
+        ParserConfig::new(),
+        false
+    );
+
+    test(
+        br#"<!DOCTYPE a [<!ENTITY lt "<">]><a b="&lt2;"/>"#,
+        br#"
+            |StartDocument(1.0, UTF-8)
+            |Doctype(a)
+            |1:42 Unexpected entity: lt2
+        "#,
+        ParserConfig::new(),
+        false
+    );
+
+    test(
+        br#"<!DOCTYPE a [<!ENTITY e "<b/>">]><a b="&e;"/>"#,
+        br#"
+            |StartDocument(1.0, UTF-8)
+            |Doctype(a)
+            |1:42 Entity e contains < which is not allowed inside attribute values
+        "#,
+        ParserConfig::new(),
+        false
+    );
+
+    // the replacement text of an entity must contain whole elements, see XML 1.0, section 4.3.2
+    test(
+        br#"<!DOCTYPE a [<!ENTITY e "<b>">]><a>&e;</b></a>"#,
+        br#"
+            |StartDocument(1.0, UTF-8)
+            |Doctype(a)
+            |StartElement(a)
+            |1:38 Entity e does not close element b
+        "#,
+        ParserConfig::new(),
+        false
+    );
+
+    test(
+        br#"<!DOCTYPE a [<!ENTITY e "</b><c x='>'>">]><a><b>&e;</c></a>"#,
+        br#"
+            |StartDocument(1.0, UTF-8)
+            |Doctype(a)
+            |StartElement(a)
+            |StartElement(b)
+            |1:51 Entity e closes element b which is not started in it
+        "#,
+        ParserConfig::new(),
+        false
+    );
+
+    test(
+        br#"<!DOCTYPE a [<!ENTITY e "<b/><!-- x">]><a>&e; --></a>"#,
+        br#"
+            |StartDocument(1.0, UTF-8)
+            |Doctype(a)
+            |StartElement(a)
+            |1:45 Entity e contains incomplete markup
+        "#,
+        ParserConfig::new(),
+        false
+    );
+
+    // entities referenced from other entities are checked on their own
+    test(
+        br#"<!DOCTYPE a [<!ENTITY o "<b>"><!ENTITY e "&o;</b>">]><a>&e;</a>"#,
+        br#"
+            |StartDocument(1.0, UTF-8)
+            |Doctype(a)
+            |StartElement(a)
+            |1:59 Entity o does not close element b
+        "#,
+        ParserConfig::new(),
+        false
+    );
+}
+
+#[test]
+fn dtd_entity_expansion_limits() {
+    fn expansion_error(input: &[u8], config: ParserConfig) -> (String, String) {
+        let e = config.create_reader(input).into_iter()
+            .filter_map(|e| e.err())
+            .next()
+            .expect("expected an error");
+        match *e.kind() {
+            ErrorKind::EntityExpansionLimit(_) => {}
+            ref kind => panic!("Unexpected error kind: {:?}", kind)
+        }
+        (e.position().to_string(), e.msg().to_owned())
+    }
+
+    let billion_laughs = br#"<!DOCTYPE lolz [
+  <!ENTITY lol "lol">
+  <!ENTITY lol1 "&lol;&lol;&lol;&lol;&lol;&lol;&lol;&lol;&lol;&lol;">
+  <!ENTITY lol2 "&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;&lol1;">
+  <!ENTITY lol3 "&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;">
+  <!ENTITY lol4 "&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;">
+  <!ENTITY lol5 "&lol4;&lol4;&lol4;&lol4;&lol4;&lol4;&lol4;&lol4;&lol4;&lol4;">
+  <!ENTITY lol6 "&lol5;&lol5;&lol5;&lol5;&lol5;&lol5;&lol5;&lol5;&lol5;&lol5;">
+  <!ENTITY lol7 "&lol6;&lol6;&lol6;&lol6;&lol6;&lol6;&lol6;&lol6;&lol6;&lol6;">
+  <!ENTITY lol8 "&lol7;&lol7;&lol7;&lol7;&lol7;&lol7;&lol7;&lol7;&lol7;&lol7;">
+  <!ENTITY lol9 "&lol8;&lol8;&lol8;&lol8;&lol8;&lol8;&lol8;&lol8;&lol8;&lol8;">
+]>
+<lolz>&lol9;</lolz>"#;
+
+    assert_eq!(
+        expansion_error(billion_laughs, ParserConfig::new()),
+        ("13:7".to_owned(), "Entity expansion exceeds the maximum ratio to the document length".to_owned())
+    );
+    assert_eq!(
+        expansion_error(billion_laughs, ParserConfig::new().max_entity_expansion_ratio(usize::max_value())),
+        ("13:7".to_owned(), "Entity expansion exceeds the maximum length".to_owned())
+    );
+    assert_eq!(
+        expansion_error(billion_laughs, ParserConfig::new().max_entity_expansion_depth(5)),
+        ("13:7".to_owned(), "Entity expansion is nested too deeply in entity lol4".to_owned())
+    );
+
+    // Limits apply to the whole document, not to a single reference
+    assert_eq!(
+        expansion_error(br#"<!DOCTYPE a [<!ENTITY e "0123456789">]><a b="&e;">&e;&e;</a>"#,
+                        ParserConfig::new().max_entity_expansion_length(25)),
+        ("1:54".to_owned(), "Entity expansion exceeds the maximum length".to_owned())
+    );
+
+    test(
+        br#"<!DOCTYPE a [<!ENTITY e "0123456789">]><a b="&e;">&e;&e;</a>"#,
+        br#"
+            |StartDocument(1.0, UTF-8)
+            |Doctype(a)
+            |StartElement(a [b="0123456789"])
+            |Characters("01234567890123456789")
+            |EndElement(a)
+            |EndDocument
+        "#,
+        ParserConfig::new().max_entity_expansion_length(30),
+        false
+    );
+}
+
+#[test]
+fn dtd_validation() {
+    test(
+        br#"<!DOCTYPE book [
+  <!ELEMENT book (title, chapter+, appendix?)>
+  <!ELEMENT title (#PCDATA)>
+  <!ELEMENT chapter (#PCDATA | ref)*>
+  <!ELEMENT appendix ANY>
+  <!ELEMENT ref EMPTY>
+  <!ATTLIST book version CDATA #FIXED "1.0"
+                 lang (en | de) "en">
+  <!ATTLIST chapter id ID #REQUIRED>
+  <!ATTLIST ref to IDREFS #REQUIRED>
+]>
+<book lang="de">
+  <title>Title</title>
+  <chapter id="c1">See <ref to="c2"/>.</chapter>
+  <chapter id="c2">See <ref to=" c1  c2 "/>.</chapter>
+</book>"#,
+        br#"
+            |StartDocument(1.0, UTF-8)
+            |Doctype(book)
+            |StartElement(book [lang="de"])
+            |StartElement(title)
+            |Characters("Title")
+            |EndElement(title)
+            |StartElement(chapter [id="c1"])
+            |Characters("See")
+            |StartElement(ref [to="c2"])
+            |EndElement(ref)
+            |Characters(".")
+            |EndElement(chapter)
+            |StartElement(chapter [id="c2"])
+            |Characters("See")
+            |StartElement(ref [to="c1 c2"])
+            |EndElement(ref)
+            |Characters(".")
+            |EndElement(chapter)
+            |EndElement(book)
+            |EndDocument
+        "#,
+        ParserConfig::new().trim_whitespace(true).validate_dtd(true),
+        false
+    );
+
+    fn validation_error(input: &str) -> String {
+        let e = ParserConfig::new().validate_dtd(true).create_reader(input.as_bytes()).into_iter()
+            .filter_map(|e| e.err())
+            .next()
+            .expect("expected an error");
+        match *e.kind() {
+            ErrorKind::Validation(_) => {}
+            ref kind => panic!("Unexpected error kind: {:?}", kind)
+        }
+        e.to_string()
+    }
+
+    let dtd = r#"<!DOCTYPE a [
+  <!ELEMENT a (b, c*)>
+  <!ELEMENT b EMPTY>
+  <!ELEMENT c (#PCDATA | b)*>
+  <!ATTLIST a id ID #IMPLIED ref IDREF #IMPLIED kind (x | y) #IMPLIED>
+  <!ATTLIST b id ID #IMPLIED fixed CDATA #FIXED "f" req NMTOKEN #REQUIRED>
+]>
+"#;
+    assert_eq!(validation_error("<a/>"), "1:1 Document has no document type declaration");
+
+    let cases = [
+                ("<b req='r'/>", "8:1 Root element b does not match the document type name a"),
+        ("<a><b req='r'/><d/></a>", "8:16 Element d is not declared"),
+        ("<a><c/><b req='r'/></a>", "8:20 Content of element a does not match its declaration"),
+        ("<a>text<b req='r'/></a>", "8:4 Element a cannot contain character data"),
+        ("<a><b req='r'>text</b></a>", "8:15 Element b is declared EMPTY but has content"),
+        ("<a><b req='r'/><c><a/></c></a>", "8:19 Element a is not allowed in the content of element c"),
+        ("<a x='1'><b req='r'/></a>", "8:1 Attribute x of element a is not declared"),
+        ("<a><b/></a>", "8:4 Required attribute req of element b is missing"),
+        ("<a><b req='r' fixed='g'/></a>", "8:4 Attribute fixed of element b must have the fixed value 'f'"),
+        ("<a kind='z'><b req='r'/></a>", "8:1 Value 'z' of attribute kind of element a is not allowed by its type"),
+        ("<a><b req='r s'/></a>", "8:4 Value 'r s' of attribute req of element b is not allowed by its type"),
+        ("<a id='x'><b id='x' req='r'/></a>", "8:11 Duplicate ID value x"),
+        ("<a ref='y'><b id='x' req='r'/></a>", "8:1 IDREF value y does not match any ID"),
+    ];
+    for &(body, expected) in cases.iter() {
+        assert_eq!(validation_error(&format!("{}{}", dtd, body)), expected, "{}", body);
+    }
+}
+
+#[test]
+fn encodings() {
+    fn utf16le(s: &str) -> Vec<u8> {
+        s.encode_utf16().flat_map(|u| u.to_le_bytes().to_vec()).collect()
+    }
+    fn utf16be(s: &str) -> Vec<u8> {
+        s.encode_utf16().flat_map(|u| u.to_be_bytes().to_vec()).collect()
+    }
+
+    test(
+        &utf16le("\u{feff}<?xml version=\"1.0\" encoding=\"UTF-16\"?><a>\u{e9}\u{1f600}</a>"),
+        r#"
+            |StartDocument(1.0, UTF-16)
+            |StartElement(a)
+            |Characters("é😀")
+            |EndElement(a)
+            |EndDocument
+        "#.as_bytes(),
+        ParserConfig::new(),
+        false
+    );
+
+    test(
+        &utf16be("\u{feff}<a b=\"\u{e9}\"/>"),
+        r#"
+            |StartDocument(1.0, UTF-16BE)
+            |StartElement(a [b="é"])
+            |EndElement(a)
+            |EndDocument
+        "#.as_bytes(),
+        ParserConfig::new(),
+        false
+    );
+
+    test(
+        &utf16le("<a/>"),
+        br#"
+            |StartDocument(1.0, UTF-16LE)
+            |StartElement(a)
+            |EndElement(a)
+            |EndDocument
+        "#,
+        ParserConfig::new(),
+        false
+    );
+
+    test(
+        b"<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?><a>caf\xe9 \x80</a>",
+        r#"
+            |StartDocument(1.0, ISO-8859-1)
+            |StartElement(a)
+            |Characters("café \u{80}")
+            |EndElement(a)
+            |EndDocument
+        "#.as_bytes(),
+        ParserConfig::new(),
+        false
+    );
+
+    test(
+        b"<?xml version=\"1.0\" encoding=\"windows-1252\"?><a b=\"\x93q\x94\">\x80</a>",
+        "
+            |StartDocument(1.0, windows-1252)
+            |StartElement(a [b=\"\u{201c}q\u{201d}\"])
+            |Characters(\"\u{20ac}\")
+            |EndElement(a)
+            |EndDocument
+        ".as_bytes(),
+        ParserConfig::new(),
+        false
+    );
+
+    // the document lies about its encoding
+    test(
+        b"<?xml version=\"1.0\" encoding=\"UTF-8\"?><a>caf\xe9</a>",
+        r#"
+            |StartDocument(1.0, UTF-8)
+            |StartElement(a)
+            |Characters("café")
+            |EndElement(a)
+            |EndDocument
+        "#.as_bytes(),
+        ParserConfig::new().override_encoding(Encoding::Latin1),
+        false
+    );
+
+    test(
+        b"<?xml version=\"1.0\" encoding=\"US-ASCII\"?><a>caf\xe9</a>",
+        br#"
+            |1:1 StartDocument(1.0, US-ASCII)
+            |1:42 StartElement(a)
+            |1:42 1:1 Invalid character encoding: invalid ASCII byte
+        "#,
+        ParserConfig::new(),
+        true
+    );
+
+    // unsupported encodings are ignored by default
+    test(
+        b"<?xml version=\"1.0\" encoding=\"ISO-8859-15\"?><a>caf\xc3\xa9</a>",
+        r#"
+            |StartDocument(1.0, ISO-8859-15)
+            |StartElement(a)
+            |Characters("café")
+            |EndElement(a)
+            |EndDocument
+        "#.as_bytes(),
+        ParserConfig::new(),
+        false
+    );
+
+    test(
+        b"<?xml version=\"1.0\" encoding=\"KOI8-R\"?><a/>",
+        br#"
+            |1:1 1:37 Unsupported encoding: KOI8-R
+        "#,
+        ParserConfig::new().reject_unsupported_encoding(true),
+        true
+    );
+}
+
+#[test]
+fn line_endings_normalization() {
+    test(
+        b"<a>\r\n  <b>x\ry\r\n\r\nz</b>\r<![CDATA[\r\n]]>\r\n</a>",
+        br#"
+            |1:1 StartDocument(1.0, UTF-8)
+            |1:1 StartElement(a)
+            |1:4 Whitespace("\n  ")
+            |2:3 StartElement(b)
+            |2:6 Characters("x\ny\n\nz")
+            |5:2 EndElement(b)
+            |5:6 Whitespace("\n")
+            |6:1 CData("\n")
+            |7:4 Whitespace("\n")
+            |8:1 EndElement(a)
+            |8:5 EndDocument
+        "#,
+        ParserConfig::new(),
+        true
+    );
+}
+
+#[test]
+fn attribute_value_normalization() {
+    test(
+        b"<a x=\"1\t2\r\n3\n 4\" y=\"&#9;&#10;&#13;\"/>",
+        br#"
+            |StartDocument(1.0, UTF-8)
+            |StartElement(a [x="1 2 3  4", y="\t\n\r"])
+            |EndElement(a)
+            |EndDocument
+        "#,
+        ParserConfig::new(),
+        false
+    );
+    test(
+        br#"<!DOCTYPE a [
+  <!ATTLIST a id ID #IMPLIED
+              refs IDREFS #IMPLIED
+              title CDATA #IMPLIED
+              p:kind NMTOKEN #IMPLIED>
+  <!ENTITY sp "  x
+  y ">
+]>
+<a id="  one " refs=" two
+  three&sp;" title="  four  five " p:kind=" six " xmlns:p="urn:p"/>"#,
+        br#"
+            |StartDocument(1.0, UTF-8)
+            |Doctype(a)
+            |StartElement(a [id="one", refs="two three x y", title="  four  five ", {urn:p}p:kind="six"])
+            |EndElement(a)
+            |EndDocument
+        "#,
+        ParserConfig::new(),
+        false
+    );
+}
+
+/// Documents for tests which compare events read from different kinds of sources.
+const STREAMED_DOCUMENTS: &[&[u8]] = &[
+    include_bytes!("documents/sample_1.xml"),
+    include_bytes!("documents/sample_2.xml"),
+    include_bytes!("documents/sample_3.xml"),
+    include_bytes!("documents/sample_4.xml"),
+    include_bytes!("documents/sample_5.xml"),
+    include_bytes!("documents/sample_6.xml"),
+    b"<a>\r\n  <b x='1\t2\r\n3' y=\"&lt;&#x20; z\">text\rmore \xc3\xa9\xf0\x9f\x98\x80</b>\r</a>",
+    b"<?xml version='1.0' encoding='ISO-8859-1'?><a b='\xe9\xe8'>\xe9t\xe9</a>",
+    b"\xff\xfe<\0a\0>\0=\xd8\x00\xde \0<\0/\0a\0>\0",
+    b"<!-- comment --><?pi some data?><a><![CDATA[ <x> ]] ]]>  </a>",
+    b"<!DOCTYPE a [<!ENTITY e '<b>&#233;</b>'>]><a>x&e;y</a>",
+    b"<a>abc<b:c/></a>",
+    b"<a>abc def\xff</a>",
+    b"<a x='abc<d'/>",
+    b"<abc!d/>",
+    b"<a:b:c/>",
+    b"<a>text",
+    b"<a><!-- a -- b --></a>",
+    b"<?pi-name?data?><a/>",
+];
+
+/// Returns all events of a document with their positions, skipping errors of the `WouldBlock`
+/// kind after which parsing is resumed.
+fn streamed_events<R: Read>(source: R, config: ParserConfig) -> Vec<String> {
+    let mut reader = EventReader::new_with_config(source, config);
+    let mut result = Vec::new();
+    let mut blocked = 0;
+    loop {
+        let e = reader.next();
+        match e {
+            Err(ref e) if e.is_would_block() => {
+                blocked += 1;
+                assert!(blocked < 100, "Parsing is not resumed after {}", e);
+                continue;
+            }
+            _ => blocked = 0
+        }
+        result.push(format!("{} {}", reader.position(), Event(&e)));
+        match e {
+            Ok(XmlEvent::EndDocument) | Err(_) => return result,
+            _ => {}
+        }
+    }
+}
+
+fn streamed_configs() -> Vec<ParserConfig> {
+    vec![
+        ParserConfig::new(),
+        ParserConfig::new()
+            .ignore_comments(false)
+            .coalesce_characters(false),
+        ParserConfig::new()
+            .trim_whitespace(true)
+            .whitespace_to_characters(true)
+            .cdata_to_characters(true),
+    ]
+}
+
+#[test]
+fn buffered_and_unbuffered_reading() {
+    // Reads one byte at a time, so multibyte characters are assembled from several reads
+    struct ByteByByte<'a>(&'a [u8]);
+
+    impl<'a> Read for ByteByByte<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match (self.0.split_first(), buf.first_mut()) {
+                (Some((&b, rest)), Some(target)) => {
+                    *target = b;
+                    self.0 = rest;
+                    Ok(1)
+                }
+                _ => Ok(0)
+            }
+        }
+    }
+
+    for &document in STREAMED_DOCUMENTS {
+        for config in streamed_configs() {
+            assert_eq!(streamed_events(document, config.clone()), streamed_events(ByteByByte(document), config),
+                       "\n{}", String::from_utf8_lossy(document));
+        }
+    }
+}
+
+#[test]
+fn resuming_after_would_block() {
+    // Returns chunks of growing size, and an error of the `WouldBlock` kind before each of them
+    struct NonBlocking<'a> {
+        data: &'a [u8],
+        chunk: usize,
+        blocked: bool
+    }
+
+    impl<'a> Read for NonBlocking<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if !self.data.is_empty() && !self.blocked {
+                self.blocked = true;
+                return Err(io::Error::new(io::ErrorKind::WouldBlock, "no data yet"));
+            }
+            self.blocked = false;
+            self.chunk = self.chunk % 7 + 1;
+            let len = self.chunk.min(buf.len()).min(self.data.len());
+            buf[..len].copy_from_slice(&self.data[..len]);
+            self.data = &self.data[len..];
+            Ok(len)
+        }
+    }
+
+    for &document in STREAMED_DOCUMENTS {
+        for config in streamed_configs() {
+            let source = NonBlocking { data: document, chunk: 0, blocked: false };
+            assert_eq!(streamed_events(document, config.clone()), streamed_events(source, config),
+                       "\n{}", String::from_utf8_lossy(document));
+        }
+    }
+}
+
 lazy_static! {
     // If PRINT_SPEC env variable is set, print the lines
     // to stderr instead of comparing with the output
@@ -507,6 +1223,10 @@ impl<'a> fmt::Display for Event<'a> {
             Ok(ref e) => match *e {
                 XmlEvent::StartDocument { ref version, ref encoding, .. } =>
                     write!(f, "StartDocument({}, {})", version, encoding),
+                XmlEvent::Doctype(ref doctype) => match doctype.external_id {
+                    Some(ref id) => write!(f, "Doctype({}, {})", doctype.name, id),
+                    None => write!(f, "Doctype({})", doctype.name),
+                },
                 XmlEvent::EndDocument =>
                     write!(f, "EndDocument"),
                 XmlEvent::ProcessingInstruction { ref name, ref data } =>
@@ -214,6 +214,32 @@ fn issue_112_overriding_namepace_prefix() {
     )
 }
 
+#[test]
+fn overriding_namespaces() {
+    use xml::writer::XmlEvent;
+
+    let mut b = Vec::new();
+
+    {
+        let mut w = EmitterConfig::new()
+            .write_document_declaration(false)
+            .create_writer(&mut b);
+
+        unwrap_all! {
+            w.write(XmlEvent::start_element("p:a").ns("p", "urn:1").default_ns("urn:d"));
+            w.write(XmlEvent::start_element("p:b").ns("p", "urn:2"));
+            w.write(XmlEvent::start_element("p:c").ns("p", "urn:1").default_ns(""));
+            w.write(XmlEvent::end_element());
+            w.write(XmlEvent::end_element());
+            w.write(XmlEvent::end_element())
+        }
+    }
+    assert_eq!(
+        str::from_utf8(&b).unwrap(),
+        r#"<p:a xmlns="urn:d" xmlns:p="urn:1"><p:b xmlns:p="urn:2"><p:c xmlns="" xmlns:p="urn:1" /></p:b></p:a>"#
+    );
+}
+
 #[test]
 fn attribute_escaping() {
     use xml::writer::XmlEvent;
@@ -266,4 +292,285 @@ fn attribute_escaping() {
 <hello testNl=\"&#xA;\" testCr=\"&#xD;\" />
 <hello testNl=\"\\n\" testCr=\"\\r\" />"
     );
-}
\ No newline at end of file
+}
+#[test]
+fn writing_in_declared_encoding() {
+    use xml::common::XmlVersion;
+    use xml::writer::XmlEvent;
+
+    let mut b = Vec::new();
+
+    {
+        let mut w = EmitterConfig::new().create_writer(&mut b);
+
+        unwrap_all! {
+            w.write(XmlEvent::StartDocument {
+                version: XmlVersion::Version10,
+                encoding: Some("ISO-8859-1"),
+                standalone: None
+            });
+            w.write(XmlEvent::start_element("caf\u{e9}").attr("a", "\u{e9}\u{20ac}"));
+            w.write("\u{e9} \u{1f600}");
+            w.write(XmlEvent::comment("\u{e9}"));
+            w.write(XmlEvent::end_element())
+        }
+    }
+
+    assert_eq!(
+        b,
+        &b"<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?><caf\xe9 a=\"\xe9&#x20AC;\">\xe9 &#x1F600;<!-- \xe9 --></caf\xe9>"[..]
+    );
+
+    let mut b = Vec::new();
+
+    {
+        let mut w = EmitterConfig::new().create_writer(&mut b);
+
+        unwrap_all! {
+            w.write(XmlEvent::StartDocument {
+                version: XmlVersion::Version10,
+                encoding: Some("US-ASCII"),
+                standalone: None
+            });
+            w.write(XmlEvent::start_element("a").attr("b", "\u{e9}"));
+            w.write("caf\u{e9}");
+            w.write(XmlEvent::end_element())
+        }
+    }
+
+    assert_eq!(
+        b,
+        &b"<?xml version=\"1.0\" encoding=\"US-ASCII\"?><a b=\"&#xE9;\">caf&#xE9;</a>"[..]
+    );
+
+    let mut b = Vec::new();
+
+    {
+        let mut w = EmitterConfig::new().create_writer(&mut b);
+
+        unwrap_all! {
+            w.write(XmlEvent::StartDocument {
+                version: XmlVersion::Version10,
+                encoding: Some("UTF-16"),
+                standalone: None
+            });
+            w.write(XmlEvent::start_element("a"));
+            w.write("\u{e9}\u{1f600}");
+            w.write(XmlEvent::end_element())
+        }
+    }
+
+    let expected: Vec<u8> = "\u{feff}<?xml version=\"1.0\" encoding=\"UTF-16\"?><a>\u{e9}\u{1f600}</a>"
+        .encode_utf16().flat_map(|u| u.to_be_bytes().to_vec()).collect();
+    assert_eq!(b, expected);
+
+    // the written document can be read back
+    let events: Vec<_> = EventReader::new(&b[..]).into_iter().map(|e| e.unwrap()).collect();
+    match events[2] {
+        xml::reader::XmlEvent::Characters(ref s) => assert_eq!(s, "\u{e9}\u{1f600}"),
+        ref e => panic!("Unexpected event: {:?}", e)
+    }
+}
+
+#[test]
+fn writing_unrepresentable_characters() {
+    use xml::common::XmlVersion;
+    use xml::writer::{Error, XmlEvent};
+
+    fn write_in(encoding: &str, event: XmlEvent) -> xml::writer::Result<()> {
+        let mut b = Vec::new();
+        let mut w = EmitterConfig::new().create_writer(&mut b);
+        w.write(XmlEvent::StartDocument {
+            version: XmlVersion::Version10,
+            encoding: Some(encoding),
+            standalone: None
+        })?;
+        w.write(XmlEvent::start_element("a"))?;
+        w.write(event)
+    }
+
+    for &(encoding, c, s) in &[("latin1", '\u{20ac}', "\u{20ac}"), ("ascii", '\u{e9}', "\u{e9}")] {
+        for event in vec![
+            XmlEvent::comment(s),
+            XmlEvent::cdata(s),
+            XmlEvent::processing_instruction("pi", Some(s)),
+            XmlEvent::start_element(s).into(),
+        ] {
+            match write_in(encoding, event) {
+                Err(Error::UnrepresentableCharacter(u)) if u == c => {}
+                r => panic!("Unexpected result: {:?}", r)
+            }
+        }
+    }
+
+    let mut b = Vec::new();
+    let mut w = EmitterConfig::new().create_writer(&mut b);
+    match w.write(XmlEvent::StartDocument {
+        version: XmlVersion::Version10,
+        encoding: Some("KOI8-R"),
+        standalone: None
+    }) {
+        Err(Error::UnsupportedEncoding(ref e)) if e == "KOI8-R" => {}
+        r => panic!("Unexpected result: {:?}", r)
+    }
+}
+
+#[test]
+fn validating_content() {
+    use xml::writer::{Error, XmlEvent};
+
+    fn write(config: EmitterConfig, event: XmlEvent) -> xml::writer::Result<String> {
+        let mut b = Vec::new();
+        {
+            let mut w = config.write_document_declaration(false).create_writer(&mut b);
+            w.write(XmlEvent::start_element("a"))?;
+            w.write(event)?;
+        }
+        Ok(String::from_utf8(b).unwrap())
+    }
+
+    let validating = || EmitterConfig::new().validate_content(true);
+    let cases: Vec<(&dyn Fn() -> XmlEvent<'static>, &str)> = vec![
+        (&|| XmlEvent::comment("a -- b"), "DoubleHyphenInComment"),
+        (&|| XmlEvent::cdata("a ]]> b"), "CDataEndInCData"),
+        (&|| XmlEvent::processing_instruction("pi", Some("a ?> b")), "ProcessingInstructionEndInData"),
+        (&|| XmlEvent::processing_instruction("xml", None), "InvalidProcessingInstructionTarget(\"xml\")"),
+        (&|| XmlEvent::processing_instruction("XmL", Some("a")), "InvalidProcessingInstructionTarget(\"XmL\")"),
+        (&|| XmlEvent::processing_instruction("1pi", None), "InvalidProcessingInstructionTarget(\"1pi\")"),
+        (&|| XmlEvent::processing_instruction("", None), "InvalidProcessingInstructionTarget(\"\")"),
+        (&|| XmlEvent::comment("a\u{0}"), "InvalidCharacter('\\0')"),
+        (&|| XmlEvent::cdata("\u{1b}"), "InvalidCharacter('\\u{1b}')"),
+        (&|| XmlEvent::processing_instruction("pi", Some("\u{fffe}")), "InvalidCharacter('\\u{fffe}')"),
+    ];
+    for (event, expected) in cases {
+        assert_eq!(format!("{:?}", write(validating(), event()).unwrap_err()), expected);
+        // without validation the content is written as is
+        assert!(write(EmitterConfig::new(), event()).is_ok());
+    }
+
+    match write(validating().autopad_comments(false), XmlEvent::comment("a-")) {
+        Err(Error::DoubleHyphenInComment) => {}
+        r => panic!("Unexpected result: {:?}", r)
+    }
+    assert_eq!(write(validating(), XmlEvent::comment("-a-")).unwrap(), "<a><!-- -a- -->");
+    assert_eq!(write(validating(), XmlEvent::processing_instruction("xml-stylesheet", Some("a?b>"))).unwrap(),
+               "<a><?xml-stylesheet a?b>?>");
+    assert_eq!(write(validating(), XmlEvent::cdata("]]\t\u{10000}")).unwrap(),
+               "<a><![CDATA[]]\t\u{10000}]]>");
+}
+
+#[test]
+fn splitting_cdata() {
+    use xml::reader::XmlEvent as ReaderEvent;
+    use xml::writer::XmlEvent;
+
+    for &(ref config, content, expected) in &[
+        (EmitterConfig::new().split_cdata(true), "a]]>b", "<![CDATA[a]]]]><![CDATA[>b]]>"),
+        (EmitterConfig::new().split_cdata(true).validate_content(true), "]]>]]>", "<![CDATA[]]]]><![CDATA[>]]]]><![CDATA[>]]>"),
+        (EmitterConfig::new().split_cdata(true), "a]]b>", "<![CDATA[a]]b>]]>"),
+    ] {
+        let mut b = Vec::new();
+        {
+            let mut w = config.clone().write_document_declaration(false).create_writer(&mut b);
+            w.write(XmlEvent::start_element("a")).unwrap();
+            w.write(XmlEvent::cdata(content)).unwrap();
+            w.write(XmlEvent::end_element()).unwrap();
+        }
+        let output = String::from_utf8(b).unwrap();
+        assert_eq!(output, format!("<a>{}</a>", expected));
+
+        // the sections are read back as the original content
+        let text: String = EventReader::from_str(&output).into_iter().filter_map(|e| match e.unwrap() {
+            ReaderEvent::CData(s) => Some(s),
+            _ => None
+        }).collect();
+        assert_eq!(text, content);
+    }
+}
+
+#[test]
+fn checking_namespaces() {
+    use xml::name::Name;
+    use xml::writer::{Error, XmlEvent};
+
+    fn write(events: Vec<XmlEvent>) -> xml::writer::Result<String> {
+        let mut b = Vec::new();
+        {
+            let mut w = EmitterConfig::new().write_document_declaration(false).create_writer(&mut b);
+            for e in events {
+                w.write(e)?;
+            }
+        }
+        Ok(String::from_utf8(b).unwrap())
+    }
+
+    assert_eq!(
+        write(vec![
+            XmlEvent::start_element("a").ns("x", "urn:x").attr("xml:lang", "en").attr("xmlns:y", "urn:y").into(),
+            XmlEvent::start_element(Name::qualified("b", "urn:x", Some("x"))).attr("x:c", "1").into(),
+            XmlEvent::start_element(Name::qualified("d", "", None)).into(),
+            XmlEvent::start_element(Name::qualified("e", "urn:z", None)).default_ns("urn:z").into(),
+        ]).unwrap(),
+        r#"<a xmlns:x="urn:x" xml:lang="en" xmlns:y="urn:y"><x:b x:c="1"><d><e xmlns="urn:z""#
+    );
+
+    for (events, expected) in vec![
+        (vec![XmlEvent::start_element("x:a").into()], "UnboundNamespacePrefix(\"x\")"),
+        (vec![XmlEvent::start_element("a").attr("x:b", "1").into()], "UnboundNamespacePrefix(\"x\")"),
+        (vec![XmlEvent::start_element(Name::qualified("a", "urn:x", Some("x"))).into()],
+         "UnboundNamespacePrefix(\"x\")"),
+        (vec![
+            XmlEvent::start_element("a").ns("x", "urn:x").into(),
+            XmlEvent::end_element().into(),
+            XmlEvent::start_element("x:b").into(),
+        ], "UnboundNamespacePrefix(\"x\")"),
+        (vec![
+            XmlEvent::start_element("a").ns("x", "urn:x").into(),
+            XmlEvent::start_element(Name::qualified("b", "urn:y", Some("x"))).into(),
+        ], "NamespacePrefixBoundToAnotherUri { prefix: \"x\", uri: \"urn:y\" }"),
+        (vec![XmlEvent::start_element("a").attr(Name::qualified("lang", "urn:x", Some("xml")), "en").into()],
+         "NamespacePrefixBoundToAnotherUri { prefix: \"xml\", uri: \"urn:x\" }"),
+        (vec![XmlEvent::start_element(Name::qualified("a", "urn:x", None)).into()],
+         "NamespacePrefixBoundToAnotherUri { prefix: \"\", uri: \"urn:x\" }"),
+    ] {
+        let error = write(events).unwrap_err();
+        assert_eq!(format!("{:?}", error), expected);
+        match error {
+            Error::NamespacePrefixBoundToAnotherUri { ref prefix, .. } if prefix.is_empty() =>
+                assert_eq!(error.to_string(), "emitter error: default namespace is not bound to 'urn:x'"),
+            _ => {}
+        }
+    }
+}
+
+#[test]
+fn checking_namespaces_declared_as_attributes() {
+    use xml::name::Name;
+    use xml::writer::{Error, XmlEvent};
+
+    let mut b = Vec::new();
+    {
+        let mut w = EmitterConfig::new().write_document_declaration(false).create_writer(&mut b);
+        w.write(XmlEvent::start_element("p:a").attr("xmlns:p", "urn:x").attr("p:b", "1")).unwrap();
+        w.write(XmlEvent::start_element(Name::qualified("c", "urn:d", None)).attr("xmlns", "urn:d")).unwrap();
+        w.write(XmlEvent::end_element()).unwrap();
+        w.write(XmlEvent::end_element()).unwrap();
+
+        // declarations of other elements are not in scope
+        match w.write(XmlEvent::start_element("p:e")) {
+            Err(Error::UnboundNamespacePrefix(ref p)) if p == "p" => {}
+            r => panic!("Unexpected result: {:?}", r)
+        }
+    }
+    assert_eq!(
+        String::from_utf8(b).unwrap(),
+        r#"<p:a xmlns:p="urn:x" p:b="1"><c xmlns="urn:d" /></p:a>"#
+    );
+
+    let mut b = Vec::new();
+    let mut w = EmitterConfig::new().create_writer(&mut b);
+    match w.write(XmlEvent::start_element(Name::qualified("a", "urn:y", Some("p"))).attr("xmlns:p", "urn:x")) {
+        Err(Error::NamespacePrefixBoundToAnotherUri { ref prefix, ref uri }) if prefix == "p" && uri == "urn:y" => {}
+        r => panic!("Unexpected result: {:?}", r)
+    }
+}
@@ -0,0 +1,103 @@
+#![forbid(unsafe_code)]
+
+extern crate xml;
+
+use xml::common::Position;
+use xml::reader::{ParserConfig, PushReader, XmlEvent};
+
+use common::{configs, event_line, pulled_events};
+
+mod common;
+
+const DOCUMENTS: &[&[u8]] = &[
+    include_bytes!("documents/sample_1.xml"),
+    include_bytes!("documents/sample_2.xml"),
+    include_bytes!("documents/sample_3.xml"),
+    include_bytes!("documents/sample_4.xml"),
+    include_bytes!("documents/sample_5.xml"),
+    include_bytes!("documents/sample_6.xml"),
+    b"<a>\r\n  <b x='1\t2\r\n3' y=\"&lt;&#x20; z\">text\rmore \xc3\xa9\xf0\x9f\x98\x80</b>\r</a>",
+    b"<?xml version='1.0' encoding='ISO-8859-1'?><a b='\xe9\xe8'>\xe9t\xe9</a>",
+    b"\xff\xfe<\0a\0>\0=\xd8\x00\xde \0<\0/\0a\0>\0",
+    b"<!-- comment --><?pi some data?><a><![CDATA[ <x> ]] ]]> &amp;&#x1F600;&#233; </a>",
+    b"<!DOCTYPE a [<!ENTITY e '<b>&#233;</b>'>]><a>x&e;y</a>",
+    b"<a>abc def\xff</a>",
+    b"<a x='abc<d'/>",
+    b"<a>text",
+    b"<a/><b/>",
+];
+
+fn pushed_events(document: &[u8], chunk: usize, config: ParserConfig) -> Vec<String> {
+    let mut reader = PushReader::new_with_config(config);
+    let mut result = Vec::new();
+    for data in document.chunks(chunk) {
+        reader.feed(data);
+        while let Some(event) = reader.next() {
+            result.push(event_line(reader.position(), &event));
+        }
+    }
+    reader.finish();
+    let mut events = reader.events();
+    while let Some(event) = events.next() {
+        result.push(event_line(events.position(), &event));
+    }
+    result
+}
+
+#[test]
+fn same_events_as_event_reader() {
+    for &document in DOCUMENTS {
+        for config in configs() {
+            let expected = pulled_events(document, config.clone());
+            for &chunk in &[1, 2, 3, 5, 7, 64, 100000] {
+                assert_eq!(pushed_events(document, chunk, config.clone()), expected,
+                           "\nchunk size {}: {}", chunk, String::from_utf8_lossy(document));
+            }
+        }
+    }
+}
+
+#[test]
+fn draining_complete_events() {
+    let mut reader = PushReader::new();
+    assert!(reader.next().is_none());
+
+    reader.feed(b"<a>te");
+    assert!(reader.next().unwrap().is_ok());  // StartDocument
+    match reader.next() {
+        Some(Ok(XmlEvent::StartElement { ref name, .. })) if name.local_name == "a" => {}
+        other => panic!("Unexpected event: {:?}", other),
+    }
+    assert!(reader.next().is_none());
+
+    // split in the middle of a multibyte character, a reference and the CDATA start
+    reader.feed(b"xt \xc3");
+    assert!(reader.next().is_none());
+    reader.feed(b"\xa9&am");
+    assert!(reader.next().is_none());
+    reader.feed(b"p;<![CD");
+    assert!(reader.next().is_none());
+    reader.feed(b"ATA[<>]]></a>");
+    assert_eq!(reader.next().unwrap().unwrap(), XmlEvent::Characters("text \u{e9}&".into()));
+    assert_eq!(reader.next().unwrap().unwrap(), XmlEvent::CData("<>".into()));
+    assert!(reader.next().unwrap().is_ok());  // EndElement
+    // the document may be followed by comments, so it is not complete yet
+    assert!(reader.next().is_none());
+
+    reader.finish();
+    assert_eq!(reader.next().unwrap().unwrap(), XmlEvent::EndDocument);
+    assert!(reader.next().is_none());
+}
+
+#[test]
+fn errors_are_returned_once() {
+    let mut reader = PushReader::new();
+    reader.feed(b"<a></b>");
+    let events: Vec<_> = reader.events().collect();
+    assert_eq!(events.len(), 3);
+    assert_eq!(events[2].as_ref().unwrap_err().to_string(), "1:7 Unexpected closing tag: b, expected a");
+
+    reader.feed(b"</a>");
+    reader.finish();
+    assert!(reader.next().is_none());
+}
@@ -0,0 +1,128 @@
+#![forbid(unsafe_code)]
+
+extern crate xml;
+
+use xml::attribute::OwnedAttribute;
+use xml::common::{TextPosition, XmlVersion};
+use xml::name::OwnedName;
+use xml::namespace::Namespace;
+use xml::reader::{Error, EventReader, ParserConfig};
+use xml::sax::{self, ContentHandler, Control};
+
+/// Records all callbacks with their positions, and stops after the given number of them.
+#[derive(Default)]
+struct Recorder {
+    calls: Vec<String>,
+    stop_after: Option<usize>,
+}
+
+impl Recorder {
+    fn record(&mut self, position: TextPosition, call: String) -> Control {
+        self.calls.push(format!("{} {}", position, call));
+        if self.stop_after == Some(self.calls.len()) { Control::Stop } else { Control::Continue }
+    }
+}
+
+impl ContentHandler for Recorder {
+    fn start_document(&mut self, version: XmlVersion, encoding: &str, standalone: Option<bool>,
+                      position: TextPosition) -> Control {
+        self.record(position, format!("start_document({}, {}, {:?})", version, encoding, standalone))
+    }
+
+    fn end_document(&mut self, position: TextPosition) -> Control {
+        self.record(position, "end_document".into())
+    }
+
+    fn start_element(&mut self, name: &OwnedName, attributes: &[OwnedAttribute], namespace: &Namespace,
+                     position: TextPosition) -> Control {
+        let attributes: Vec<_> = attributes.iter().map(|a| a.to_string()).collect();
+        self.record(position, format!("start_element({}, [{}], {:?})", name, attributes.join(" "), namespace.get("x")))
+    }
+
+    fn end_element(&mut self, name: &OwnedName, position: TextPosition) -> Control {
+        self.record(position, format!("end_element({})", name))
+    }
+
+    fn characters(&mut self, text: &str, position: TextPosition) -> Control {
+        self.record(position, format!("characters({:?})", text))
+    }
+
+    fn processing_instruction(&mut self, target: &str, data: Option<&str>, position: TextPosition) -> Control {
+        self.record(position, format!("processing_instruction({}, {:?})", target, data))
+    }
+
+    fn comment(&mut self, text: &str, position: TextPosition) -> Control {
+        self.record(position, format!("comment({:?})", text))
+    }
+
+    fn error(&mut self, error: &Error, position: TextPosition) {
+        self.record(position, format!("error({})", error));
+    }
+}
+
+const DOCUMENT: &str = "<?xml version='1.0' standalone='yes'?>\n\
+                        <?pi data?>\n\
+                        <a xmlns:x='urn:x' x:b='1'>\n  <!--c--><x:c>text<![CDATA[<>]]></x:c>\n</a>";
+
+#[test]
+fn callbacks_with_positions() {
+    let config = ParserConfig::new().ignore_comments(false);
+    let mut reader = EventReader::new_with_config(DOCUMENT.as_bytes(), config);
+    let mut recorder = Recorder::default();
+    sax::parse(&mut reader, &mut recorder).unwrap();
+    assert_eq!(recorder.calls, vec![
+        "1:1 start_document(1.0, UTF-8, Some(true))",
+        "2:1 processing_instruction(pi, Some(\"data\"))",
+        "3:1 start_element(a, [{urn:x}x:b=\"1\"], Some(\"urn:x\"))",
+        "3:28 characters(\"\\n  \")",
+        "4:3 comment(\"c\")",
+        "4:11 start_element({urn:x}x:c, [], Some(\"urn:x\"))",
+        "4:16 characters(\"text\")",
+        "4:20 characters(\"<>\")",
+        "4:34 end_element({urn:x}x:c)",
+        "4:40 characters(\"\\n\")",
+        "5:1 end_element(a)",
+        "5:5 end_document",
+    ]);
+}
+
+#[test]
+fn stopping_and_resuming() {
+    let mut reader = EventReader::from_str(DOCUMENT);
+    let mut recorder = Recorder { stop_after: Some(3), ..Recorder::default() };
+    sax::parse(&mut reader, &mut recorder).unwrap();
+    assert_eq!(recorder.calls.len(), 3);
+    assert!(recorder.calls[2].starts_with("3:1 start_element(a,"));
+
+    // the reader is left right after the last reported event
+    sax::parse(&mut reader, &mut recorder).unwrap();
+    assert_eq!(recorder.calls.len(), 11);
+    assert_eq!(recorder.calls[4], "4:11 start_element({urn:x}x:c, [], Some(\"urn:x\"))");
+    assert_eq!(recorder.calls[10], "5:5 end_document");
+}
+
+#[test]
+fn errors() {
+    let mut reader = EventReader::from_str("<a>\n  <b></a>");
+    let mut recorder = Recorder::default();
+    let error = sax::parse(&mut reader, &mut recorder).unwrap_err();
+    assert_eq!(error.to_string(), "2:9 Unexpected closing tag: a, expected b");
+    assert_eq!(recorder.calls.last().unwrap(), "2:9 error(2:9 Unexpected closing tag: a, expected b)");
+}
+
+#[test]
+fn default_callbacks() {
+    struct Text(String);
+
+    impl ContentHandler for Text {
+        fn characters(&mut self, text: &str, _: TextPosition) -> Control {
+            self.0.push_str(text);
+            Control::Continue
+        }
+    }
+
+    let mut reader = EventReader::from_str("<a> <b>x</b><![CDATA[y]]><?pi?></a>");
+    let mut text = Text(String::new());
+    sax::parse(&mut reader, &mut text).unwrap();
+    assert_eq!(text.0, " xy");
+}
@@ -0,0 +1,242 @@
+extern crate xml;
+
+use xml::EventReader;
+use xml::reader::XmlEvent;
+use xml::schema::{Schema, SchemaError, Error, ValidationErrorKind};
+
+const ORDER_SCHEMA: &str = r###"<?xml version="1.0"?>
+<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema"
+           xmlns:o="urn:order"
+           targetNamespace="urn:order"
+           elementFormDefault="qualified">
+  <xs:element name="order" type="o:Order"/>
+  <xs:element name="note" type="xs:string"/>
+
+  <xs:complexType name="Order">
+    <xs:sequence>
+      <xs:element name="customer" type="o:Customer"/>
+      <xs:element name="item" type="o:Item" maxOccurs="unbounded"/>
+      <xs:choice minOccurs="0">
+        <xs:element name="pickup"/>
+        <xs:element name="delivery" type="o:Address"/>
+      </xs:choice>
+      <xs:element ref="o:note" minOccurs="0"/>
+      <xs:any namespace="##other" processContents="skip" minOccurs="0" maxOccurs="unbounded"/>
+    </xs:sequence>
+    <xs:attribute name="id" type="xs:ID" use="required"/>
+    <xs:attribute name="version" type="xs:decimal" fixed="1.0"/>
+    <xs:attributeGroup ref="o:Dates"/>
+  </xs:complexType>
+
+  <xs:attributeGroup name="Dates">
+    <xs:attribute name="created" type="xs:date"/>
+  </xs:attributeGroup>
+
+  <xs:complexType name="Customer">
+    <xs:all>
+      <xs:element name="name" type="xs:token"/>
+      <xs:element name="email" type="o:Email" minOccurs="0"/>
+    </xs:all>
+    <xs:attribute name="ref" type="xs:IDREF"/>
+  </xs:complexType>
+
+  <xs:complexType name="Address">
+    <xs:group ref="o:AddressLines"/>
+  </xs:complexType>
+
+  <xs:group name="AddressLines">
+    <xs:sequence>
+      <xs:element name="line" type="xs:string" minOccurs="1" maxOccurs="3"/>
+    </xs:sequence>
+  </xs:group>
+
+  <xs:complexType name="Item">
+    <xs:simpleContent>
+      <xs:extension base="o:Quantity">
+        <xs:attribute name="sku" type="o:Sku" use="required"/>
+      </xs:extension>
+    </xs:simpleContent>
+  </xs:complexType>
+
+  <xs:complexType name="GiftItem">
+    <xs:complexContent>
+      <xs:extension base="o:Address">
+        <xs:sequence>
+          <xs:element name="message" type="xs:string"/>
+        </xs:sequence>
+      </xs:extension>
+    </xs:complexContent>
+  </xs:complexType>
+  <xs:element name="gift" type="o:GiftItem"/>
+
+  <xs:simpleType name="Quantity">
+    <xs:restriction base="xs:positiveInteger">
+      <xs:maxExclusive value="100"/>
+    </xs:restriction>
+  </xs:simpleType>
+
+  <xs:simpleType name="Sku">
+    <xs:restriction base="xs:string">
+      <xs:pattern value="[A-Z]{3}-\d{4}"/>
+    </xs:restriction>
+  </xs:simpleType>
+
+  <xs:simpleType name="Email">
+    <xs:restriction base="xs:string">
+      <xs:pattern value="[^@ ]+@[^@ ]+"/>
+      <xs:maxLength value="20"/>
+    </xs:restriction>
+  </xs:simpleType>
+
+  <xs:simpleType name="Sizes">
+    <xs:list>
+      <xs:simpleType>
+        <xs:restriction base="xs:token">
+          <xs:enumeration value="S"/>
+          <xs:enumeration value="M"/>
+          <xs:enumeration value="L"/>
+        </xs:restriction>
+      </xs:simpleType>
+    </xs:list>
+  </xs:simpleType>
+  <xs:element name="sizes">
+    <xs:simpleType>
+      <xs:restriction base="o:Sizes">
+        <xs:minLength value="1"/>
+      </xs:restriction>
+    </xs:simpleType>
+  </xs:element>
+</xs:schema>
+"###;
+
+fn load(schema: &str) -> Result<Schema, SchemaError> {
+    Schema::from_reader(EventReader::from_str(schema))
+}
+
+/// Validates the document and returns all events and errors as strings.
+fn validate(schema: &Schema, document: &str) -> (usize, Vec<(ValidationErrorKind, String)>) {
+    let mut events = 0;
+    let mut errors = Vec::new();
+    for item in schema.validate(EventReader::from_str(document).into_iter()) {
+        match item {
+            Ok(_) => events += 1,
+            Err(Error::Validation(e)) => errors.push((e.kind(), e.to_string())),
+            Err(Error::Reader(e)) => panic!("Unexpected reader error: {}", e),
+        }
+    }
+    (events, errors)
+}
+
+fn errors(schema: &Schema, document: &str) -> Vec<(ValidationErrorKind, String)> {
+    validate(schema, document).1
+}
+
+#[test]
+fn valid_documents() {
+    let schema = load(ORDER_SCHEMA).unwrap();
+    assert_eq!(schema.target_namespace(), Some("urn:order"));
+
+    let document = r#"<order xmlns="urn:order" xmlns:x="urn:x" id="o1" version="1.0" created="2020-02-29">
+  <customer ref="o1"><email>a@b.c</email><name> John   Doe </name></customer>
+  <item sku="ABC-1234">5</item>
+  <item sku="XYZ-0001"> 99 </item>
+  <delivery><line>Main St.</line><line>Springfield</line></delivery>
+  <note>Ring twice</note>
+  <x:extra><anything x:y="z"/></x:extra>
+</order>"#;
+    let (events, errs) = validate(&schema, document);
+    assert_eq!(errs, vec![]);
+    // all events are passed through
+    assert_eq!(events, EventReader::from_str(document).into_iter().count());
+
+    assert_eq!(errors(&schema, r#"<gift xmlns="urn:order"><line>A</line><message>Hi</message></gift>"#), vec![]);
+    assert_eq!(errors(&schema, r#"<sizes xmlns="urn:order">S  L</sizes>"#), vec![]);
+}
+
+#[test]
+fn invalid_documents() {
+    let schema = load(ORDER_SCHEMA).unwrap();
+    let cases: &[(&str, &[(ValidationErrorKind, &str)])] = &[
+        (r#"<order/>"#, &[
+            (ValidationErrorKind::UnknownRootElement, "1:1 Element order is not declared in the schema"),
+        ]),
+        (r#"<order xmlns="urn:order"><customer><name>N</name></customer></order>"#, &[
+            (ValidationErrorKind::MissingAttribute, "1:1 Required attribute id is missing in element {urn:order}order"),
+            (ValidationErrorKind::InvalidContent, "1:61 Content of element {urn:order}order does not match its type"),
+        ]),
+        (r#"<order xmlns="urn:order" id="1" version="2.0" created="yesterday" other="x"><customer><name>N</name></customer><item sku="ABC-1234">1</item></order>"#, &[
+            (ValidationErrorKind::InvalidValue, "1:1 Value '1' is not a valid ID in attribute id of element {urn:order}order"),
+            (ValidationErrorKind::InvalidValue, "1:1 Attribute version of element {urn:order}order must have the fixed value '1.0'"),
+            (ValidationErrorKind::InvalidValue, "1:1 Value 'yesterday' is not a valid date in attribute created of element {urn:order}order"),
+            (ValidationErrorKind::UnexpectedAttribute, "1:1 Attribute other is not allowed in element {urn:order}order"),
+        ]),
+        (r#"<order xmlns="urn:order" id="o"><customer><name>N</name><name>M</name></customer><item sku="ABC-1234">1</item></order>"#, &[
+            (ValidationErrorKind::InvalidContent, "1:71 Content of element {urn:order}customer does not match its type"),
+        ]),
+        (r#"<order xmlns="urn:order" id="o"><customer><name>N</name><phone/></customer><item sku="ABC-1234">1</item></order>"#, &[
+            (ValidationErrorKind::UnexpectedElement, "1:57 Element {urn:order}phone is not allowed in element {urn:order}customer"),
+        ]),
+        (r#"<order xmlns="urn:order" id="o"><customer ref="p"><name>N</name></customer><item sku="abc-1234">0</item><item sku="ABC-1234">100</item></order>"#, &[
+            (ValidationErrorKind::InvalidValue, "1:76 Value 'abc-1234' does not satisfy the pattern facet in attribute sku of element {urn:order}item"),
+            (ValidationErrorKind::InvalidValue, "1:98 Value '0' is not a valid positiveInteger in element {urn:order}item"),
+            (ValidationErrorKind::InvalidValue, "1:129 Value '100' does not satisfy the maxExclusive facet in element {urn:order}item"),
+            (ValidationErrorKind::UnknownIdRef, "1:33 IDREF value p does not match any ID"),
+        ]),
+        (r#"<order xmlns="urn:order" id="o">text<customer><name>N</name></customer><item sku="ABC-1234">1<b/></item><pickup/><extra/></order>"#, &[
+            (ValidationErrorKind::UnexpectedCharacters, "1:33 Character data is not allowed in element {urn:order}order"),
+            (ValidationErrorKind::UnexpectedElement, "1:94 Element {urn:order}b is not allowed in element {urn:order}item which cannot have child elements"),
+            (ValidationErrorKind::UnexpectedElement, "1:114 Element {urn:order}extra is not allowed in element {urn:order}order"),
+        ]),
+        (r#"<gift xmlns="urn:order"><message>Hi</message></gift>"#, &[
+            (ValidationErrorKind::InvalidContent, "1:46 Content of element {urn:order}gift does not match its type"),
+        ]),
+        (r#"<sizes xmlns="urn:order">S XL</sizes>"#, &[
+            (ValidationErrorKind::InvalidValue, "1:30 Value 'XL' does not satisfy the enumeration facet in element {urn:order}sizes"),
+        ]),
+        (r#"<sizes xmlns="urn:order"> </sizes>"#, &[
+            (ValidationErrorKind::InvalidValue, "1:27 Value '' does not satisfy the minLength facet in element {urn:order}sizes"),
+        ]),
+    ];
+    for &(document, expected) in cases {
+        let expected: Vec<_> = expected.iter().map(|&(k, m)| (k, m.to_owned())).collect();
+        assert_eq!(errors(&schema, document), expected, "{}", document);
+    }
+}
+
+#[test]
+fn errors_precede_events() {
+    let schema = load(r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">
+  <xs:element name="a" type="xs:int"/>
+</xs:schema>"#).unwrap();
+    let items: Vec<_> = schema.validate(EventReader::from_str("<a>x</a>").into_iter()).collect();
+    match (&items[3], &items[4]) {
+        (&Err(Error::Validation(_)), &Ok(XmlEvent::EndElement { .. })) => {}
+        other => panic!("Unexpected items: {:?}", other)
+    }
+    assert_eq!(items.len(), 6);
+}
+
+#[test]
+fn schema_errors() {
+    fn schema_error(schema: &str) -> String {
+        match load(schema) {
+            Ok(_) => panic!("Schema should be invalid: {}", schema),
+            Err(e) => e.to_string()
+        }
+    }
+    let xs = r#"xmlns:xs="http://www.w3.org/2001/XMLSchema""#;
+    assert_eq!(schema_error("<schema/>"),
+               "1:1 The root element of a schema document must be xs:schema");
+    assert_eq!(schema_error(&format!(r#"<xs:schema {}><xs:element name="a" type="xs:foo"/></xs:schema>"#, xs)),
+               "1:56 Unknown type: {http://www.w3.org/2001/XMLSchema}foo");
+    assert_eq!(schema_error(&format!(r#"<xs:schema {}><xs:element name="a" type="b:foo"/></xs:schema>"#, xs)),
+               "1:56 Namespace prefix b is not bound");
+    assert_eq!(schema_error(&format!(r#"<xs:schema {}><xs:import namespace="urn:x"/></xs:schema>"#, xs)),
+               "1:56 xs:import is not supported");
+    assert_eq!(schema_error(&format!(r#"<xs:schema {}><xs:simpleType name="a"><xs:restriction base="xs:string"><xs:pattern value="(a"/></xs:restriction></xs:simpleType></xs:schema>"#, xs)),
+               "1:113 Unterminated group in regular expression");
+    assert_eq!(schema_error(&format!(r#"<xs:schema {}><xs:complexType name="a"><xs:complexContent><xs:extension base="a"/></xs:complexContent></xs:complexType></xs:schema>"#, xs)),
+               "1:1 Type a is derived from itself");
+    assert_eq!(schema_error(r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema"><xs:element"#),
+               "1:67 Unexpected end of stream: still inside the root element");
+}
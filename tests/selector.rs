@@ -0,0 +1,113 @@
+#![forbid(unsafe_code)]
+
+extern crate xml;
+
+use xml::dom::Document;
+use xml::namespace::Namespace;
+use xml::reader::{EventReader, ParserConfig};
+use xml::selector::Selector;
+
+const DOCUMENT: &'static str = r#"<?xml version="1.0"?>
+<!DOCTYPE library [
+  <!ATTLIST book code ID #IMPLIED>
+]>
+<library xmlns="urn:library" xmlns:x="urn:extra">
+  <book code="b1" lang="en-GB" tags="classic sci-fi"><title>Dune</title><author>Frank Herbert</author><x:rating>5</x:rating></book>
+  <book code="b2" lang="de" x:lang="en"><title>Foundation</title><author>Isaac Asimov</author></book>
+  <magazine id="m1"><title>Monthly</title></magazine>
+  <book code="b3" xml:id="good-omens" tags="fantasy"><title>Good Omens</title><author>Terry Pratchett</author><author>Neil Gaiman</author></book>
+  <x:book code="x1"><x:title>Extra</x:title></x:book>
+  <plain xmlns=""><title>Plain</title></plain>
+</library>"#;
+
+fn document() -> Document {
+    let config = ParserConfig::new().trim_whitespace(true);
+    Document::from_reader(EventReader::new_with_config(DOCUMENT.as_bytes(), config)).unwrap()
+}
+
+fn check_with(namespaces: &Namespace, selector: &str, expected: &[&str]) {
+    let document = document();
+    let compiled = Selector::with_namespaces(selector, namespaces).unwrap_or_else(|e| panic!("{}: {}", selector, e));
+    let result: Vec<_> = compiled.select(&document, document.root()).map(|id| {
+        let element = document.element(id).unwrap();
+        let first = element.attributes.first().map(|a| format!("[{}]", a.value)).unwrap_or_default();
+        format!("{}{}", element.name.local_name, first)
+    }).collect();
+    assert_eq!(result, expected, "{}", selector);
+}
+
+fn check(selector: &str, expected: &[&str]) {
+    check_with(&Namespace::empty(), selector, expected);
+}
+
+#[test]
+fn simple_selectors() {
+    check("book", &["book[b1]", "book[b2]", "book[b3]", "book[x1]"]);
+    check("*|title:first-child", &["title", "title", "title", "title", "title", "title"]);
+    check("#b2", &["book[b2]"]);
+    check("#m1, #good-omens", &["magazine[m1]", "book[b3]"]);
+    check("#x1", &[]);
+    check("[code]", &["book[b1]", "book[b2]", "book[b3]", "book[x1]"]);
+    check("[code=b3]", &["book[b3]"]);
+    check("[lang='de']", &["book[b2]"]);
+    check("[lang|=en]", &["book[b1]"]);
+    check("[tags~=sci-fi]", &["book[b1]"]);
+    check("[tags~=sci]", &[]);
+    check("[tags^=\"class\"]", &["book[b1]"]);
+    check("[tags$=asy]", &["book[b3]"]);
+    check("[tags*='ic s']", &["book[b1]"]);
+    check("[tags*='']", &[]);
+    check("[*|lang=en]", &["book[b2]"]);
+    check("[\\6c ang=de]", &["book[b2]"]);
+}
+
+#[test]
+fn namespaces() {
+    let mut namespaces = Namespace::empty();
+    namespaces.put("l", "urn:library");
+    namespaces.put("ex", "urn:extra");
+    check_with(&namespaces, "l|book", &["book[b1]", "book[b2]", "book[b3]"]);
+    check_with(&namespaces, "ex|*", &["rating", "book[x1]", "title"]);
+    check_with(&namespaces, "|title", &["title"]);
+    check_with(&namespaces, "l|book[ex|lang]", &["book[b2]"]);
+    check_with(&namespaces, "[|lang=en]", &[]);
+
+    namespaces.put("", "urn:library");
+    check_with(&namespaces, "title", &["title", "title", "title", "title"]);
+    check_with(&namespaces, "*", &["library", "book[b1]", "title", "author", "book[b2]", "title", "author",
+                                   "magazine[m1]", "title", "book[b3]", "title", "author", "author"]);
+}
+
+#[test]
+fn combinators() {
+    check("library > title", &[]);
+    check("library title", &["title", "title", "title", "title", "title", "title"]);
+    check("book > author", &["author", "author", "author", "author"]);
+    check("library>book>author", &["author", "author", "author", "author"]);
+    check("title + author", &["author", "author", "author"]);
+    check("author ~ *", &["rating", "author"]);
+    check("book + book", &["book[b2]", "book[x1]"]);
+    check("magazine ~ book", &["book[b3]", "book[x1]"]);
+    check("library book:nth-child(2) > title", &["title"]);
+    check("library > :nth-child(odd)", &["book[b1]", "magazine[m1]", "book[x1]"]);
+    check("library > :nth-last-child(-n+2)", &["book[x1]", "plain"]);
+    check("author:last-child", &["author", "author"]);
+    check("library > *:nth-child(2n+4)", &["book[b3]", "plain"]);
+    check("library:first-child:last-child", &["library"]);
+}
+
+#[test]
+fn matching_nodes() {
+    let document = document();
+    let library = document.root_element().unwrap();
+    let selector = Selector::new("book:nth-child(4) author").unwrap();
+    let first = selector.select_first(&document, library).unwrap();
+    assert_eq!(document.element(first).unwrap().name.local_name, "author");
+    assert!(selector.matches(&document, first));
+    assert!(!selector.matches(&document, library));
+    assert!(!selector.matches(&document, document.root()));
+
+    let book = document.parent(first).unwrap();
+    assert_eq!(Selector::new("library author").unwrap().select(&document, book).count(), 2);
+    assert_eq!(Selector::new("book").unwrap().select_first(&document, book), None);
+}
@@ -0,0 +1,296 @@
+#![forbid(unsafe_code)]
+#![cfg(feature = "serde")]
+
+extern crate xml;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+use xml::{EmitterConfig, EventWriter};
+use xml::ser::{to_string, to_writer, Error, Serializer};
+
+fn write<T: serde::Serialize>(value: &T) -> String {
+    let config = EmitterConfig::new().write_document_declaration(false);
+    let mut writer = EventWriter::new_with_config(Vec::new(), config);
+    to_writer(&mut writer, value).unwrap();
+    String::from_utf8(writer.into_inner()).unwrap()
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(rename = "library")]
+struct Library {
+    #[serde(rename = "@name")]
+    name: String,
+    #[serde(rename = "@tags")]
+    tags: Vec<String>,
+    open: bool,
+    book: Vec<Book>,
+    magazine: Option<Magazine>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+struct Book {
+    #[serde(rename = "@id")]
+    id: u32,
+    format: Format,
+    title: String,
+    author: Vec<String>,
+    rating: Option<f32>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+enum Format {
+    Hardcover,
+    Paperback,
+    Ebook,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+struct Magazine {
+    #[serde(rename = "$value")]
+    title: String,
+}
+
+fn library() -> Library {
+    Library {
+        name: "City & Co".into(),
+        tags: vec!["old".into(), "rare".into()],
+        open: true,
+        book: vec![
+            Book {
+                id: 1, format: Format::Paperback, title: "Dune".into(),
+                author: vec!["Frank Herbert".into()], rating: Some(4.5),
+            },
+            Book {
+                id: 2, format: Format::Ebook, title: "Good <Omens>".into(),
+                author: vec!["Terry Pratchett".into(), "Neil Gaiman".into()], rating: None,
+            },
+        ],
+        magazine: Some(Magazine { title: "Monthly".into() }),
+    }
+}
+
+#[test]
+fn structs_and_sequences() {
+    let library = library();
+    let xml = write(&library);
+    assert_eq!(xml, concat!(
+        r#"<library name="City &amp; Co" tags="old rare"><open>true</open>"#,
+        r#"<book id="1"><format>paperback</format><title>Dune</title><author>Frank Herbert</author><rating>4.5</rating></book>"#,
+        r#"<book id="2"><format>ebook</format><title>Good &lt;Omens></title><author>Terry Pratchett</author><author>Neil Gaiman</author></book>"#,
+        r#"<magazine>Monthly</magazine></library>"#,
+    ));
+    assert_eq!(xml::de::from_str::<Library>(&xml).unwrap(), library);
+
+    assert!(to_string(&library).unwrap().starts_with(r#"<?xml version="1.0" encoding="utf-8"?><library "#));
+}
+
+#[test]
+fn emitter_config() {
+    let mut config = EmitterConfig::new().write_document_declaration(false)
+        .perform_indent(true).indent_string("\t").normalize_empty_elements(false);
+    let mut writer = EventWriter::new_with_config(Vec::new(), config.clone());
+    let book = Book { id: 3, format: Format::Hardcover, title: "<Emma>".into(), author: vec![], rating: None };
+    to_writer(&mut writer, &book).unwrap();
+    assert_eq!(String::from_utf8(writer.into_inner()).unwrap(),
+               "<Book id=\"3\">\n\t<format>hardcover</format>\n\t<title>&lt;Emma></title>\n</Book>");
+
+    config.perform_escaping = false;
+    let mut writer = EventWriter::new_with_config(Vec::new(), config);
+    to_writer(&mut writer, &Magazine { title: "<b>Monthly</b>".into() }).unwrap();
+    assert_eq!(String::from_utf8(writer.into_inner()).unwrap(), "<Magazine><b>Monthly</b></Magazine>");
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(rename = "drawing")]
+struct Drawing {
+    #[serde(rename = "@title")]
+    title: Option<String>,
+    background: Shape,
+    #[serde(rename = "$value")]
+    shapes: Vec<Shape>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+enum Shape {
+    Point,
+    Circle {
+        #[serde(rename = "@r")]
+        r: f64,
+    },
+    Label(String),
+    Group(Vec<Shape>),
+}
+
+#[test]
+fn enums() {
+    let drawing = Drawing {
+        title: None,
+        background: Shape::Point,
+        shapes: vec![
+            Shape::Circle { r: 1.0 },
+            Shape::Label("Hello".into()),
+            Shape::Group(vec![Shape::Point, Shape::Circle { r: 2.5 }]),
+        ],
+    };
+    let xml = write(&drawing);
+    assert_eq!(xml, concat!(
+        r#"<drawing><background>point</background><circle r="1" /><label>Hello</label>"#,
+        r#"<group><point /><circle r="2.5" /></group></drawing>"#,
+    ));
+    assert_eq!(xml::de::from_str::<Drawing>(&xml).unwrap(), drawing);
+
+    assert_eq!(write(&Shape::Group(vec![])), "<group />");
+}
+
+#[derive(Serialize)]
+#[serde(rename = "feed")]
+struct Feed {
+    #[serde(rename = "@xmlns")]
+    xmlns: &'static str,
+    #[serde(rename = "@xmlns:dc")]
+    dc: &'static str,
+    #[serde(rename = "dc:creator")]
+    creator: &'static str,
+    entry: Vec<&'static str>,
+}
+
+#[test]
+fn namespaces_and_root_names() {
+    let feed = Feed { xmlns: "urn:feed", dc: "urn:dc", creator: "Me", entry: vec!["a", "b"] };
+    assert_eq!(write(&feed), concat!(
+        r#"<feed xmlns="urn:feed" xmlns:dc="urn:dc"><dc:creator>Me</dc:creator>"#,
+        r#"<entry>a</entry><entry>b</entry></feed>"#,
+    ));
+
+    let mut writer = EventWriter::new_with_config(Vec::new(), EmitterConfig::new().write_document_declaration(false));
+    {
+        let mut serializer = Serializer::new(&mut writer).root_name("x:shelf").namespace("x", "urn:x");
+        serde::Serialize::serialize(&vec![1, 2], &mut serializer).unwrap();
+    }
+    assert_eq!(String::from_utf8(writer.into_inner()).unwrap(), r#"<x:shelf xmlns:x="urn:x">12</x:shelf>"#);
+
+    let mut map = BTreeMap::new();
+    map.insert("@a", "1");
+    map.insert("b", "2");
+    let mut writer = EventWriter::new_with_config(Vec::new(), EmitterConfig::new().write_document_declaration(false));
+    serde::Serialize::serialize(&map, &mut Serializer::new(&mut writer).root_name("m")).unwrap();
+    assert_eq!(String::from_utf8(writer.into_inner()).unwrap(), r#"<m a="1"><b>2</b></m>"#);
+}
+
+#[derive(Serialize)]
+#[serde(rename = "late")]
+struct Late {
+    #[serde(rename = "$value")]
+    text: &'static str,
+    child: LateChild,
+    #[serde(rename = "@attr")]
+    attr: u8,
+}
+
+#[derive(Serialize)]
+struct LateChild {
+    #[serde(rename = "$value")]
+    shapes: Vec<Shape>,
+    #[serde(rename = "@xmlns:p")]
+    p: &'static str,
+    #[serde(rename = "@p:id")]
+    id: u8,
+}
+
+#[test]
+fn attributes_after_content() {
+    let late = Late {
+        text: "text",
+        child: LateChild { shapes: vec![Shape::Point, Shape::Circle { r: 0.5 }], p: "urn:p", id: 1 },
+        attr: 2,
+    };
+    assert_eq!(write(&late),
+               r#"<late attr="2">text<child xmlns:p="urn:p" p:id="1"><point /><circle r="0.5" /></child></late>"#);
+}
+
+/// Records the output written before it is serialized.
+struct Probe(Rc<RefCell<Vec<u8>>>, RefCell<String>);
+
+impl serde::Serialize for Probe {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        *self.1.borrow_mut() = String::from_utf8(self.0.borrow().clone()).unwrap();
+        serializer.serialize_str("probe")
+    }
+}
+
+/// Appends the output to a shared buffer.
+struct Shared(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Shared {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> { Ok(()) }
+}
+
+#[derive(Serialize)]
+#[serde(rename = "stream")]
+struct Stream<'a> {
+    item: Vec<&'static str>,
+    probe: &'a Probe,
+    #[serde(rename = "@attr")]
+    attr: u8,
+}
+
+#[test]
+fn content_is_written_as_it_is_serialized() {
+    let output = Rc::new(RefCell::new(Vec::new()));
+    let probe = Probe(output.clone(), RefCell::new(String::new()));
+    let config = EmitterConfig::new().write_document_declaration(false);
+    let mut writer = EventWriter::new_with_config(Shared(output.clone()), config);
+    to_writer(&mut writer, &Stream { item: vec!["a", "b"], probe: &probe, attr: 1 }).unwrap();
+    assert_eq!(*probe.1.borrow(), r#"<stream attr="1"><item>a</item><item>b</item>"#);
+    assert_eq!(String::from_utf8(output.borrow().clone()).unwrap(),
+               r#"<stream attr="1"><item>a</item><item>b</item><probe>probe</probe></stream>"#);
+
+    // without `to_writer()` attributes of the root element are only known before its content
+    let mut writer = EventWriter::new(Vec::new());
+    let late = Late { text: "text", child: LateChild { shapes: vec![], p: "urn:p", id: 1 }, attr: 2 };
+    match serde::Serialize::serialize(&late, &mut Serializer::new(&mut writer)) {
+        Err(Error::Data(msg)) =>
+            assert_eq!(msg, "Attribute attr follows content of the element; use Serializer::write() to write it"),
+        other => panic!("Unexpected result: {:?}", other),
+    }
+    let mut writer = EventWriter::new_with_config(Vec::new(), EmitterConfig::new().write_document_declaration(false));
+    Serializer::new(&mut writer).write(&late).unwrap();
+    assert_eq!(String::from_utf8(writer.into_inner()).unwrap(),
+               r#"<late attr="2">text<child xmlns:p="urn:p" p:id="1" /></late>"#);
+}
+
+#[derive(Serialize)]
+struct Nested {
+    #[serde(rename = "@inner")]
+    inner: Book,
+}
+
+#[test]
+fn errors() {
+    fn message<T: serde::Serialize>(value: &T) -> String {
+        let mut writer = EventWriter::new(Vec::new());
+        match to_writer(&mut writer, value).unwrap_err() {
+            Error::Data(msg) => msg,
+            other => panic!("Unexpected error: {:?}", other),
+        }
+    }
+
+    assert_eq!(message(&42), "Cannot serialize a number as the root element without a root name");
+    assert_eq!(message(&vec![Shape::Point]), "Cannot serialize a sequence as the root element without a root name");
+    assert_eq!(message(&Nested { inner: library().book.remove(0) }),
+               "Cannot serialize a struct as an attribute value or a map key");
+    assert_eq!(message(&BTreeMap::<u8, u8>::new()), "Cannot serialize a map as the root element without a root name");
+}
@@ -0,0 +1,311 @@
+#![forbid(unsafe_code)]
+
+extern crate xml;
+
+use std::collections::HashMap;
+
+use xml::dom::{self, Document};
+use xml::namespace::Namespace;
+use xml::reader::{EventReader, ParserConfig};
+use xml::xpath::{XPath, StreamingPath, Match, Value, Node, Error};
+use xml::reader::XmlEvent;
+
+const DOCUMENT: &'static str = r#"<?xml version="1.0"?>
+<!DOCTYPE library [
+  <!ATTLIST book code ID #IMPLIED>
+]>
+<library xmlns:x="urn:extra" xml:lang="en-GB">
+  <?index books?>
+  <book code="b1" year="1965"><title>Dune</title><author>Frank Herbert</author><x:rating>5</x:rating></book>
+  <!-- classics -->
+  <book code="b2" year="1951" xml:lang="de"><title>Foundation</title><author>Isaac Asimov</author></book>
+  <magazine year="2020"><title>Monthly</title>Issue <![CDATA[#7]]></magazine>
+  <book code="b3" x:year="1999"><title>  Good   Omens </title><author>Terry Pratchett</author><author>Neil Gaiman</author></book>
+</library>"#;
+
+fn config() -> ParserConfig {
+    ParserConfig::new()
+        .ignore_comments(false)
+        .trim_whitespace(true)
+        .cdata_to_characters(false)
+}
+
+fn document() -> Document {
+    Document::from_reader(EventReader::new_with_config(DOCUMENT.as_bytes(), config())).unwrap()
+}
+
+fn namespaces() -> Namespace {
+    let mut namespaces = Namespace::empty();
+    namespaces.put("ex", "urn:extra");
+    namespaces
+}
+
+fn evaluate(document: &Document, context: Node, expr: &str) -> Value {
+    let context = match context {
+        Node::Tree(id) => id,
+        _ => panic!("Context node must be a tree node")
+    };
+    XPath::with_namespaces(expr, &namespaces())
+        .and_then(|xpath| xpath.evaluate(document, context))
+        .unwrap_or_else(|e| panic!("{}: {}", expr, e))
+}
+
+/// Describes nodes of a node-set in a compact form.
+fn describe(document: &Document, value: Value) -> Vec<String> {
+    let nodes = match value {
+        Value::NodeSet(nodes) => nodes,
+        other => panic!("Not a node-set: {:?}", other)
+    };
+    nodes.into_iter().map(|node| match node {
+        Node::Tree(id) => match *document.node(id) {
+            dom::Node::Document => "/".into(),
+            dom::Node::Element(ref e) => {
+                let first = e.attributes.first().map(|a| format!("[{}]", a.value)).unwrap_or_default();
+                format!("{}{}", e.name.local_name, first)
+            }
+            dom::Node::Comment(_) => "comment()".into(),
+            dom::Node::ProcessingInstruction { ref name, .. } => format!("pi({})", name),
+            _ => format!("'{}'", node.string_value(document)),
+        },
+        Node::Attribute(..) => format!("@{}", node.string_value(document)),
+        Node::Namespace(..) => format!("ns({})", node.string_value(document)),
+    }).collect()
+}
+
+fn check_nodes(expr: &str, expected: &[&str]) {
+    let document = document();
+    let result = evaluate(&document, Node::Tree(document.root()), expr);
+    assert_eq!(describe(&document, result), expected, "{}", expr);
+}
+
+fn check_value(expr: &str, expected: Value) {
+    let document = document();
+    assert_eq!(evaluate(&document, Node::Tree(document.root()), expr), expected, "{}", expr);
+}
+
+/// Matches a streaming path against the document and checks that the DOM evaluation agrees.
+fn check_streaming(expr: &str, expected: &[&str]) {
+    let path = StreamingPath::with_namespaces(expr, &namespaces()).unwrap_or_else(|e| panic!("{}: {}", expr, e));
+    let reader = EventReader::new_with_config(DOCUMENT.as_bytes(), config());
+    let matches: Vec<_> = path.matches(reader).map(|m| match m.unwrap() {
+        Match::Element(events) => match events[0] {
+            XmlEvent::StartElement { ref name, ref attributes, .. } => {
+                assert!(match events.last() { Some(&XmlEvent::EndElement { .. }) => true, _ => false });
+                let first = attributes.first().map(|a| format!("[{}]", a.value)).unwrap_or_default();
+                format!("{}{}", name.local_name, first)
+            }
+            ref other => panic!("Unexpected event: {:?}", other)
+        },
+        Match::Attribute(attribute) => format!("@{}", attribute.value),
+    }).collect();
+    assert_eq!(matches, expected, "{}", expr);
+    check_nodes(expr, expected);
+}
+
+fn string(s: &str) -> Value { Value::String(s.into()) }
+
+#[test]
+fn location_paths() {
+    check_nodes("/library/book", &["book[b1]", "book[b2]", "book[b3]"]);
+    check_nodes("//title/..", &["book[b1]", "book[b2]", "magazine[2020]", "book[b3]"]);
+    check_nodes("/library/*[2]", &["book[b2]"]);
+    check_nodes("/library/node()[1]", &["pi(index)"]);
+    check_nodes("/library/comment()", &["comment()"]);
+    check_nodes("//processing-instruction('index')", &["pi(index)"]);
+    check_nodes("//processing-instruction('other')", &[]);
+    check_nodes("//book[author][last()]/title", &["title"]);
+    check_nodes("//book[@year > 1960 or @ex:year]/@code", &["@b1", "@b3"]);
+    check_nodes("//ex:*", &["rating"]);
+    check_nodes("//@ex:*", &["@1999"]);
+    check_nodes("//book[3]/author[2]", &["author"]);
+    check_nodes("//book[not(@year)]/author[2]/text()", &["'Neil Gaiman'"]);
+    check_nodes("/", &["/"]);
+    check_nodes("/library/magazine/text()", &["'Issue#7'"]);
+    check_nodes("(//title)[position() >= 3]", &["title", "title"]);
+    check_nodes("(//book | //magazine)[last()]", &["book[b3]"]);
+    check_nodes("//book[1] | //book[1]", &["book[b1]"]);
+    check_nodes("id('b3 b1')", &["book[b1]", "book[b3]"]);
+    check_nodes("id(//book[2]/@code)/title", &["title"]);
+    check_nodes("//*[lang('de')]", &["book[b2]", "title", "author"]);
+    check_nodes("//book[lang('EN')]", &["book[b1]", "book[b3]"]);
+}
+
+#[test]
+fn axes() {
+    check_nodes("//magazine/ancestor::*", &["library[en-GB]"]);
+    check_nodes("//magazine/ancestor-or-self::node()", &["/", "library[en-GB]", "magazine[2020]"]);
+    check_nodes("//magazine/ancestor-or-self::node()[1]", &["magazine[2020]"]);
+    check_nodes("//magazine/following-sibling::*", &["book[b3]"]);
+    check_nodes("//magazine/preceding-sibling::*[1]", &["book[b2]"]);
+    check_nodes("//magazine/preceding-sibling::node()", &["pi(index)", "book[b1]", "comment()", "book[b2]"]);
+    check_nodes("//book[2]/following::title", &["title", "title"]);
+    check_nodes("//book[2]/preceding::*", &["book[b1]", "title", "author", "rating"]);
+    check_nodes("//book[2]/preceding::*[1]", &["rating"]);
+    check_nodes("//book[2]/@year/following::*[1]", &["title"]);
+    check_nodes("//book[2]/@year/preceding::*[1]", &["rating"]);
+    check_nodes("//book[2]/@year/parent::*", &["book[b2]"]);
+    check_nodes("//book[2]/@year/ancestor::*", &["library[en-GB]", "book[b2]"]);
+    check_nodes("//book[1]/descendant::text()", &["'Dune'", "'Frank Herbert'", "'5'"]);
+    check_nodes("//book[1]/descendant-or-self::*[last()]", &["rating"]);
+    check_nodes("//book[1]/self::book/child::title/self::*", &["title"]);
+    check_nodes("//book[1]/attribute::*", &["@b1", "@1965"]);
+    check_nodes("/library/namespace::*", &["ns(urn:extra)", "ns(http://www.w3.org/XML/1998/namespace)"]);
+    check_nodes("/library/namespace::x/parent::*", &["library[en-GB]"]);
+    check_nodes("/parent::node()", &[]);
+}
+
+#[test]
+fn functions() {
+    check_value("count(//author)", Value::Number(4.0));
+    check_value("last()", Value::Number(1.0));
+    check_value("name(//ex:rating)", string("x:rating"));
+    check_value("local-name(//ex:rating)", string("rating"));
+    check_value("namespace-uri(//ex:rating)", string("urn:extra"));
+    check_value("name(//@ex:year)", string("x:year"));
+    check_value("name(/library/namespace::x)", string("x"));
+    check_value("name(//processing-instruction())", string("index"));
+    check_value("name(//nothing)", string(""));
+    check_value("string(//book[2]/@year)", string("1951"));
+    check_value("string(//book)", string("DuneFrank Herbert5"));
+    check_value("concat(//title, ' by ', //author, '!')", string("Dune by Frank Herbert!"));
+    check_value("starts-with('foobar', 'foo')", Value::Boolean(true));
+    check_value("contains('foobar', 'baz')", Value::Boolean(false));
+    check_value("substring-before('1999/04/01', '/')", string("1999"));
+    check_value("substring-after('1999/04/01', '/')", string("04/01"));
+    check_value("substring('12345', 1.5, 2.6)", string("234"));
+    check_value("substring('12345', 0, 3)", string("12"));
+    check_value("substring('12345', 0 div 0, 3)", string(""));
+    check_value("substring('12345', -42, 1 div 0)", string("12345"));
+    check_value("substring('12345', 2)", string("2345"));
+    check_value("string-length(normalize-space(//book[3]/title))", Value::Number(10.0));
+    check_value("normalize-space(//book[3]/title)", string("Good Omens"));
+    check_value("translate('bar', 'abc', 'ABC')", string("BAr"));
+    check_value("translate('--aaa--', 'abc-', 'ABC')", string("AAA"));
+    check_value("boolean(//book) and not(//newspaper) and true() and not(false())", Value::Boolean(true));
+    check_value("boolean('')", Value::Boolean(false));
+    check_value("number('  42 ')", Value::Number(42.0));
+    check_value("sum(//book/@year)", Value::Number(3916.0));
+    check_value("1 div sum(//missing)", Value::Number(f64::INFINITY));
+    check_value("string(sum(//missing))", string("0"));
+    check_value("floor(-1.5) + ceiling(1.2) + round(2.5) + round(-2.5)", Value::Number(1.0));
+    check_value("string(1 div 0)", string("Infinity"));
+    check_value("string(number('x'))", string("NaN"));
+    check_value("string(-0.25 * 4)", string("-1"));
+}
+
+#[test]
+fn operators() {
+    check_value("1 + 2 * 3 - 4 div 2 - 7 mod 3", Value::Number(4.0));
+    check_value("-5 mod 3", Value::Number(-2.0));
+    check_value("--1", Value::Number(1.0));
+    check_value("//book/@year = 1951", Value::Boolean(true));
+    check_value("//book/@year != 1951", Value::Boolean(true));
+    check_value("//book/@year > 2000", Value::Boolean(false));
+    check_value("1951 < //book/@year", Value::Boolean(true));
+    check_value("//title = 'Dune'", Value::Boolean(true));
+    check_value("//title = //author", Value::Boolean(false));
+    check_value("//book[1]/@year = //magazine/@year - 55", Value::Boolean(true));
+    check_value("//nothing = //nothing", Value::Boolean(false));
+    check_value("//nothing != 'x'", Value::Boolean(false));
+    check_value("//nothing = false()", Value::Boolean(true));
+    check_value("true() = 'x'", Value::Boolean(true));
+    check_value("1 = '1.0'", Value::Boolean(true));
+    check_value("'1' = '1.0'", Value::Boolean(false));
+    check_value("'2' > '10'", Value::Boolean(false));
+    check_value("true() > false()", Value::Boolean(true));
+    check_value("0 div 0 = 0 div 0", Value::Boolean(false));
+}
+
+#[test]
+fn context_nodes_and_variables() {
+    let document = document();
+    let library = document.root_element().unwrap();
+    let magazine = document.child_named(library, "magazine").unwrap();
+
+    assert_eq!(describe(&document, evaluate(&document, Node::Tree(magazine), "../book[last()]/title")),
+               vec!["title"]);
+    assert_eq!(evaluate(&document, Node::Tree(magazine), "string(@year)"), string("2020"));
+    assert_eq!(describe(&document, evaluate(&document, Node::Tree(magazine), "/library/magazine")),
+               vec!["magazine[2020]"]);
+
+    // a detached subtree is a tree of its own
+    let mut detached = document.clone();
+    detached.detach(magazine);
+    assert_eq!(describe(&detached, evaluate(&detached, Node::Tree(magazine), "/")), vec!["magazine[2020]"]);
+    assert_eq!(describe(&detached, evaluate(&detached, Node::Tree(magazine), "//title")), vec!["title"]);
+
+    let mut variables = HashMap::new();
+    variables.insert("year".to_string(), Value::Number(1951.0));
+    variables.insert("books".to_string(),
+                     XPath::new("//book").unwrap().evaluate(&document, document.root()).unwrap());
+    let xpath = XPath::new("$books[@year = $year]/title").unwrap();
+    let result = xpath.evaluate_with_variables(&document, document.root(), &variables).unwrap();
+    assert_eq!(result.into_nodes().unwrap()[0].string_value(&document), "Foundation");
+
+    assert_eq!(xpath.evaluate(&document, document.root()),
+               Err(Error::Evaluation("Variable $books is not defined".into())));
+    assert_eq!(XPath::new("'a'/b").unwrap().evaluate(&document, document.root()),
+               Err(Error::Evaluation("Expression does not evaluate to a node-set".into())));
+    assert_eq!(XPath::new("1 | //a").unwrap().evaluate(&document, document.root()),
+               Err(Error::Evaluation("Expression does not evaluate to a node-set".into())));
+}
+
+#[test]
+fn value_conversions() {
+    let document = document();
+    let books = XPath::new("//book/@year").unwrap().evaluate(&document, document.root()).unwrap();
+    assert_eq!(books.to_string_value(&document), "1965");
+    assert_eq!(books.to_number(&document), 1965.0);
+    assert!(books.to_boolean());
+    assert_eq!(Value::Number(2.5).to_string_value(&document), "2.5");
+    assert_eq!(Value::Boolean(true).to_number(&document), 1.0);
+    assert!(Value::String(" NaN".into()).to_number(&document).is_nan());
+    assert!(!Value::NodeSet(Vec::new()).to_boolean());
+}
+
+#[test]
+fn streaming_paths() {
+    check_streaming("/library/book", &["book[b1]", "book[b2]", "book[b3]"]);
+    check_streaming("library/*[2]", &["book[b2]"]);
+    check_streaming("//title", &["title", "title", "title", "title"]);
+    check_streaming("/library//author[2]", &["author"]);
+    check_streaming("//book[@year]/@code", &["@b1", "@b2"]);
+    check_streaming("//book[@year = '1951']/title", &["title"]);
+    check_streaming("//*['1951' != @year]/@year", &["@1965", "@2020"]);
+    check_streaming("/library/*[@code][3]/@code", &["@b3"]);
+    check_streaming("/library/*[3][@code]", &[]);
+    check_streaming("//ex:*", &["rating"]);
+    check_streaming("//book[@ex:year]/@*", &["@b3", "@1999"]);
+    check_streaming("//@ex:*", &["@1999"]);
+    check_streaming("/library/book[2]//@*", &["@b2", "@1951", "@de"]);
+    check_streaming("/library/book[4]", &[]);
+
+    let path = StreamingPath::new("//a").unwrap();
+    let matches: Vec<_> = path.matches(EventReader::from_str("<a><a>x</a></a>")).map(Result::unwrap).collect();
+    assert_eq!(matches.len(), 2);
+    assert_eq!(matches[0], Match::Element(EventReader::from_str("<a>x</a>").into_iter()
+        .map(Result::unwrap)
+        .filter(|e| match *e { XmlEvent::StartDocument { .. } | XmlEvent::EndDocument => false, _ => true })
+        .collect()));
+    match matches[1] {
+        Match::Element(ref events) => assert_eq!(events.len(), 5),
+        _ => panic!("Not an element: {:?}", matches[1])
+    }
+
+    let mut matches = path.matches(EventReader::from_str("<b><a/><a></b>"));
+    assert!(matches.next().unwrap().is_ok());
+    assert_eq!(matches.next().unwrap().unwrap_err().to_string(), "1:14 Unexpected closing tag: b, expected a");
+    assert!(matches.next().is_none());
+}
+
+#[test]
+fn streaming_unsupported() {
+    for expr in &["count(//a)", "/", "//a/..", "//a[last()]", "//a[b]", "//a[@b = 1]", "//a[0]",
+                  "/a/@b/c", "//text()", "/@a", "(//a)[1]"] {
+        match StreamingPath::new(expr) {
+            Err(Error::Unsupported(_)) => {}
+            other => panic!("{}: {:?}", expr, other)
+        }
+    }
+    assert_eq!(StreamingPath::new("/a[").unwrap_err(), Error::Syntax { pos: 3, msg: "Expected an expression".into() });
+}